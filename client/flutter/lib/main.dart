@@ -134,7 +134,10 @@ class _PairingPageWrapperState extends State<_PairingPageWrapper> {
   void initState() {
     super.initState();
     _currentDomain = widget.initialDomain;
-    _backend = HttpSignalingBackend(_currentDomain);
+    _backend = HttpSignalingBackend(
+      _currentDomain,
+      identitySecret: widget.settings.deviceIdentitySecret(),
+    );
   }
 
   @override
@@ -148,7 +151,10 @@ class _PairingPageWrapperState extends State<_PairingPageWrapper> {
       final oldBackend = _backend;
       setState(() {
         _currentDomain = newDomain;
-        _backend = HttpSignalingBackend(_currentDomain);
+        _backend = HttpSignalingBackend(
+          _currentDomain,
+          identitySecret: widget.settings.deviceIdentitySecret(),
+        );
       });
       oldBackend.dispose();
     }
@@ -1,6 +1,8 @@
 import 'package:application/src/rust/api/connection.dart' as rust_connection;
+import '../domain/models.dart';
 import 'package:http/http.dart' as http;
 import 'package:logging/logging.dart';
+import 'package:web_socket_channel/io.dart';
 import 'package:web_socket_channel/web_socket_channel.dart';
 import 'dart:convert';
 import 'dart:async';
@@ -15,29 +17,53 @@ class ConnectionService {
     : httpClient = httpClient ?? http.Client();
 
   /// Step 1: Initialize a connection locally (Client A)
-  /// Generates a high-entropy secret and derives encryption keys
+  /// Generates an ephemeral X25519 key; `secret` holds the private key and the
+  /// session keys stay empty until [completeExchange]
   /// Does not communicate with server
   Future<ConnectionInitResult> initializeConnectionLocally() async {
     final result = rust_connection.connectionInitLocal();
-    return ConnectionInitResult(
-      rendezvousId: result.rendezvousId,
-      mailboxId: result.mailboxId,
-      secret: result.secret,
-      kSig: result.kSig,
-      kMac: result.kMac,
-      sas: result.sas,
-    );
+    return _initResult(result);
   }
 
   /// Generate a shareable connection link
-  String generateConnectionLink(String rendezvousId, String secret) {
+  /// Only the public key goes into the link, so a leaked link alone does not
+  /// give away the session keys
+  String generateConnectionLink(String rendezvousId, String publicKey) {
     return rust_connection.generateConnectionLink(
       baseUrl: signalingBaseUrl,
       rendezvousId: rendezvousId,
-      secret: secret,
+      publicKey: publicKey,
     );
   }
 
+  /// Client B: answer the initiator's public key from the link fragment
+  /// Send `publicKey` of the result as the first mailbox message, unencrypted,
+  /// and have the user confirm [formatSas] of `sas` before signaling
+  ConnectionInitResult joinExchange(String initiatorPublicKey) {
+    final result = rust_connection.connectionJoinExchange(
+      initiatorPublicKey: initiatorPublicKey,
+    );
+    return _initResult(result);
+  }
+
+  /// Client A: session keys from the responder's public key
+  ConnectionInitResult completeExchange({
+    required String secret,
+    required String responderPublicKey,
+  }) {
+    final result = rust_connection.connectionCompleteExchange(
+      secretHex: secret,
+      responderPublicKey: responderPublicKey,
+    );
+    return _initResult(result);
+  }
+
+  /// Six digits for users to compare, e.g. `123 456`; a mismatch means
+  /// someone swapped the keys in transit
+  String formatSas(String sasHex) {
+    return rust_connection.connectionFormatSas(sasHex: sasHex);
+  }
+
   /// Step 2: Send connection init to server (Client A)
   /// Server creates a mailbox and stores the rendezvous token
   Future<Map<String, dynamic>> sendConnectionInit({
@@ -59,7 +85,10 @@ class ConnectionService {
       _log.warning(
         'Connection Init Failed: ${response.statusCode} - ${response.body}',
       );
-      throw Exception('Failed to init connection: ${response.statusCode}');
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
     }
 
     return jsonDecode(response.body) as Map<String, dynamic>;
@@ -77,7 +106,176 @@ class ConnectionService {
     );
 
     if (response.statusCode != 200) {
-      throw Exception('Failed to join connection: ${response.statusCode}');
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
+    }
+
+    return jsonDecode(response.body) as Map<String, dynamic>;
+  }
+
+  /// Host session (both sides): start SPAKE2 keyed by the host ID and
+  /// password once the viewer has joined the host's mailbox
+  /// Send `message` to the peer as the ciphertext of a mailbox message, and
+  /// pass `exchangeId` to [finishHostExchange]
+  ({String exchangeId, String message}) startHostExchange({
+    required String hostId,
+    required String password,
+  }) {
+    final exchangeId = rust_connection.connectionHostPakeStart(
+      hostId: hostId,
+      password: password,
+    );
+    final message = rust_connection.connectionPakeMessage(
+      exchangeId: exchangeId,
+    );
+    return (exchangeId: exchangeId, message: message);
+  }
+
+  /// Host session (both sides): fresh keys from the peer's SPAKE2 message
+  /// If the peer's messages fail to decrypt, it did not know the password
+  ConnectionInitResult finishHostExchange({
+    required String exchangeId,
+    required String peerMessageB64,
+  }) {
+    final result = rust_connection.connectionPakeFinish(
+      exchangeId: exchangeId,
+      peerMessageB64: peerMessageB64,
+    );
+    return _initResult(result);
+  }
+
+  /// Unattended access (host): claim this device's persistent host ID, or
+  /// change its password. Returns the nine-digit host ID
+  Future<String> registerHost({
+    required String clientId,
+    required String sessionToken,
+    required String password,
+  }) async {
+    final data = await _postJson('/hosts/register', {
+      'client_id': clientId,
+      'session_token': sessionToken,
+      'password': password,
+    });
+    return data['host_id'] as String;
+  }
+
+  /// Unattended access (host): open the mailbox the next viewer joins
+  /// Call again once it is joined or before `expires_at_epoch_ms`
+  Future<Map<String, dynamic>> listenAsHost({
+    required String clientId,
+    required String sessionToken,
+  }) {
+    return _postJson('/hosts/listen', {
+      'client_id': clientId,
+      'session_token': sessionToken,
+    });
+  }
+
+  /// Unattended access (host): give up the host ID
+  Future<void> unregisterHost({
+    required String clientId,
+    required String sessionToken,
+  }) async {
+    final response = await httpClient.post(
+      Uri.parse('$signalingBaseUrl/hosts/unregister'),
+      headers: {'Content-Type': 'application/json'},
+      body: jsonEncode({'client_id': clientId, 'session_token': sessionToken}),
+    );
+    if (response.statusCode != 202) {
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
+    }
+  }
+
+  /// Unattended access (viewer): join the host's mailbox by ID and password
+  /// Fails with `host_offline` when the host is not listening, and with
+  /// `host_locked` after too many wrong passwords
+  Future<Map<String, dynamic>> connectToHost({
+    required String hostId,
+    required String password,
+  }) {
+    return _postJson('/hosts/connect', {
+      'host_id': hostId,
+      'password': password,
+    });
+  }
+
+  /// Short code (initiator): open a mailbox behind a nameplate and turn it
+  /// into a code such as `7-guitar-orbit` to read out to the peer
+  /// The result is the server response plus `code`
+  Future<Map<String, dynamic>> openShortCode({
+    required String clientId,
+    required String sessionToken,
+  }) async {
+    final data = await _postJson('/connection/nameplate', {
+      'client_id': clientId,
+      'session_token': sessionToken,
+    });
+    final code = rust_connection.connectionCodeGenerate(
+      nameplate: data['nameplate'] as String,
+    );
+    return {...data, 'code': code};
+  }
+
+  /// Short code (joiner): join the mailbox behind a typed code
+  /// Only the nameplate is sent; each code can be tried once
+  Future<Map<String, dynamic>> joinShortCode({
+    required String clientId,
+    required String sessionToken,
+    required String code,
+  }) {
+    final nameplate = rust_connection.connectionCodeNameplate(code: code);
+    return _postJson('/connection/nameplate/join', {
+      'client_id': clientId,
+      'session_token': sessionToken,
+      'nameplate': nameplate,
+    });
+  }
+
+  /// Short code (both sides): start SPAKE2 once the mailboxes are paired
+  /// Send `message` to the peer as the ciphertext of a mailbox message, and
+  /// pass `exchangeId` to [finishShortCodeExchange]
+  ({String exchangeId, String message}) startShortCodeExchange(String code) {
+    final exchangeId = rust_connection.connectionPakeStart(code: code);
+    final message = rust_connection.connectionPakeMessage(
+      exchangeId: exchangeId,
+    );
+    return (exchangeId: exchangeId, message: message);
+  }
+
+  /// Short code (both sides): keys from the peer's SPAKE2 message
+  /// With a wrong code the peer's messages fail to decrypt; close the
+  /// connection then instead of retrying, the code is used up
+  ConnectionInitResult finishShortCodeExchange({
+    required String exchangeId,
+    required String peerMessageB64,
+  }) {
+    final result = rust_connection.connectionPakeFinish(
+      exchangeId: exchangeId,
+      peerMessageB64: peerMessageB64,
+    );
+    return _initResult(result);
+  }
+
+  Future<Map<String, dynamic>> _postJson(
+    String path,
+    Map<String, dynamic> body,
+  ) async {
+    final response = await httpClient.post(
+      Uri.parse('$signalingBaseUrl$path'),
+      headers: {'Content-Type': 'application/json'},
+      body: jsonEncode(body),
+    );
+
+    if (response.statusCode != 200) {
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
     }
 
     return jsonDecode(response.body) as Map<String, dynamic>;
@@ -86,6 +284,7 @@ class ConnectionService {
   /// Send an encrypted signal through the mailbox
   Future<void> sendSignal({
     required String mailboxId,
+    required String mailboxToken,
     required String ciphertextB64,
     int retries = 3,
   }) async {
@@ -98,27 +297,32 @@ class ConnectionService {
           headers: {'Content-Type': 'application/json'},
           body: jsonEncode({
             'mailbox_id': mailboxId,
+            'mailbox_token': mailboxToken,
             'ciphertext_b64': ciphertextB64,
           }),
         );
 
         if (response.statusCode == 202) return; // Success
 
-        if (response.statusCode == 429 && attempt < retries) {
-          _log.info('Send signal 429 (Attempt $attempt), throttling...');
-          await Future.delayed(
-            Duration(milliseconds: 1000 * attempt),
-          ); // Longer backoff
-          continue;
-        }
-
-        if (response.statusCode == 500 && attempt < retries) {
-          _log.info('Send signal 500 (Attempt $attempt), retrying...');
-          await Future.delayed(Duration(milliseconds: 500 * attempt));
+        final error = SignalingApiException.fromResponse(
+          response.statusCode,
+          response.body,
+        );
+        // Only failures the server marked as retryable are worth repeating,
+        // and no sooner than it asked for
+        final retryAfter = error.retryAfter;
+        if (retryAfter != null && attempt < retries) {
+          _log.info(
+            'Send signal ${error.code} (Attempt $attempt), '
+            'retrying in ${retryAfter.inMilliseconds}ms...',
+          );
+          await Future.delayed(retryAfter);
           continue;
         }
 
-        throw Exception('Failed to send signal: ${response.statusCode}');
+        throw error;
+      } on SignalingApiException {
+        rethrow;
       } catch (e) {
         if (attempt >= retries) rethrow;
         _log.warning('Send signal error (Attempt $attempt): $e, retrying...');
@@ -128,21 +332,27 @@ class ConnectionService {
   }
 
   /// Fetch messages from mailbox
+  /// Only messages with a sequence above [sinceSequence] are returned
   Future<List<Map<String, dynamic>>> fetchMessages({
     required String mailboxId,
+    required String mailboxToken,
+    int sinceSequence = 0,
   }) async {
     final response = await httpClient.post(
       Uri.parse('$signalingBaseUrl/connection/recv'),
       headers: {'Content-Type': 'application/json'},
       body: jsonEncode({
         'mailbox_id': mailboxId,
-        // server expects same shape as MailboxSendRequest
-        'ciphertext_b64': '',
+        'mailbox_token': mailboxToken,
+        'since_sequence': sinceSequence,
       }),
     );
 
     if (response.statusCode != 200) {
-      throw Exception('Failed to fetch messages: ${response.statusCode}');
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
     }
 
     final data = jsonDecode(response.body) as Map<String, dynamic>;
@@ -151,45 +361,76 @@ class ConnectionService {
     return messagesList.map((msg) => msg as Map<String, dynamic>).toList();
   }
 
+  /// Acknowledge delivered messages so the server can trim them
+  Future<void> ackMessages({
+    required String mailboxId,
+    required String mailboxToken,
+    required int upToSequence,
+  }) async {
+    final response = await httpClient.post(
+      Uri.parse('$signalingBaseUrl/connection/ack'),
+      headers: {'Content-Type': 'application/json'},
+      body: jsonEncode({
+        'mailbox_id': mailboxId,
+        'mailbox_token': mailboxToken,
+        'up_to_sequence': upToSequence,
+      }),
+    );
+
+    if (response.statusCode != 202) {
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
+    }
+  }
+
   /// Subscribe to mailbox messages using WebSockets
-  Stream<Map<String, dynamic>> subscribeMailbox({required String mailboxId}) {
+  /// The mailbox token is sent as a bearer header so it stays out of URLs.
+  /// The server replays stored messages after [sinceSequence] first, and every
+  /// reconnect resumes after the last sequence seen, so nothing is missed.
+  Stream<Map<String, dynamic>> subscribeMailbox({
+    required String mailboxId,
+    required String mailboxToken,
+    int sinceSequence = 0,
+  }) {
     final wsBaseUrl = signalingBaseUrl
         .replaceFirst('https://', 'wss://')
         .replaceFirst('http://', 'ws://');
-    final wsUrl = '$wsBaseUrl/ws/$mailboxId';
-
-    _log.info('Connecting to WebSocket: $wsUrl');
 
     final controller = StreamController<Map<String, dynamic>>.broadcast();
     WebSocketChannel? channel;
     bool isDisposed = false;
-
-    // Initial fetch to ensure no messages are missed
-    fetchMessages(mailboxId: mailboxId)
-        .then((messages) {
-          for (final msg in messages) {
-            if (!controller.isClosed) {
-              controller.add(msg);
-            }
-          }
-        })
-        .catchError((e, st) {
-          _log.warning('Initial fetch error', e, st);
-          return null;
-        });
+    int lastSequence = sinceSequence;
 
     void connect() {
       if (isDisposed) return;
 
+      final wsUrl = '$wsBaseUrl/ws/$mailboxId?since=$lastSequence';
+      _log.info('Connecting to WebSocket: $wsUrl');
+
       try {
-        channel = WebSocketChannel.connect(Uri.parse(wsUrl));
+        channel = IOWebSocketChannel.connect(
+          Uri.parse(wsUrl),
+          headers: {'Authorization': 'Bearer $mailboxToken'},
+        );
 
         channel!.stream.listen(
           (data) {
             try {
-              final msg = jsonDecode(data as String);
+              final msg = jsonDecode(data as String) as Map<String, dynamic>;
+              // Typed frames are server replies, not mailbox messages
+              if (msg.containsKey('type')) {
+                _log.info('WS frame: $msg');
+                return;
+              }
+              final sequence = (msg['sequence'] as num?)?.toInt();
+              if (sequence != null) {
+                if (sequence <= lastSequence) return;
+                lastSequence = sequence;
+              }
               if (!controller.isClosed) {
-                controller.add(msg as Map<String, dynamic>);
+                controller.add(msg);
               }
             } catch (e) {
               _log.warning('WS message decode error: $e');
@@ -228,31 +469,55 @@ class ConnectionService {
   }
 
   /// Close and delete mailbox data on the server
-  Future<void> closeConnection({required String mailboxId}) async {
+  Future<void> closeConnection({
+    required String mailboxId,
+    required String mailboxToken,
+  }) async {
     final response = await httpClient.post(
       Uri.parse('$signalingBaseUrl/connection/close'),
       headers: {'Content-Type': 'application/json'},
-      body: jsonEncode({'mailbox_id': mailboxId}),
+      body: jsonEncode({
+        'mailbox_id': mailboxId,
+        'mailbox_token': mailboxToken,
+      }),
     );
 
     if (response.statusCode != 202) {
-      throw Exception('Failed to close connection: ${response.statusCode}');
+      throw SignalingApiException.fromResponse(
+        response.statusCode,
+        response.body,
+      );
     }
   }
 
   void dispose() {
     httpClient.close();
   }
+
+  static ConnectionInitResult _initResult(
+    rust_connection.ConnectionInitLocalResult result,
+  ) {
+    return ConnectionInitResult(
+      rendezvousId: result.rendezvousId,
+      mailboxId: result.mailboxId,
+      secret: result.secret,
+      kSig: result.kSig,
+      kMac: result.kMac,
+      sas: result.sas,
+      publicKey: result.publicKey,
+    );
+  }
 }
 
 /// Local result from connection initialization
 class ConnectionInitResult {
   final String rendezvousId; // Share via link
   final String mailboxId; // Keep private
-  final String secret; // Shared secret (hex)
+  final String secret; // Shared secret, or the X25519 key until exchanged (hex)
   final String kSig; // Encryption key (hex)
   final String kMac; // MAC key (hex)
   final String sas; // Short auth string (hex)
+  final String publicKey; // Own X25519 public key (base64url), may be empty
 
   ConnectionInitResult({
     required this.rendezvousId,
@@ -261,5 +526,6 @@ class ConnectionInitResult {
     required this.kSig,
     required this.kMac,
     required this.sas,
+    required this.publicKey,
   });
 }
@@ -3,12 +3,19 @@ import 'dart:convert';
 
 import 'package:http/http.dart' as http;
 
+import '../../../../rust/api/identity.dart';
 import '../../domain/models.dart';
 import '../../domain/signaling_backend.dart';
 
+/// Renew the session token this long before the server expires it.
+const Duration _sessionRenewMargin = Duration(minutes: 1);
+
 /// HTTP client for the signaling server implementing the domain interface.
+/// Registers with the device identity in [identitySecret] by signing a
+/// server challenge, and re-registers before the session token expires.
 class HttpSignalingBackend implements SignalingBackend {
   final String baseUrl;
+  final String identitySecret;
   final http.Client _client;
   final bool _ownsClient;
 
@@ -16,9 +23,16 @@ class HttpSignalingBackend implements SignalingBackend {
   String? _sessionToken;
   int _heartbeatIntervalSecs = 30;
   Timer? _heartbeatTimer;
+  Timer? _renewTimer;
+  String? _deviceLabel;
   String? _displayName;
+  List<Map<String, dynamic>> _iceServers = const [];
 
-  HttpSignalingBackend(this.baseUrl, {http.Client? client})
+  HttpSignalingBackend(
+    this.baseUrl, {
+    required this.identitySecret,
+    http.Client? client,
+  })
     : _client = client ?? http.Client(),
       _ownsClient = client == null;
 
@@ -33,24 +47,72 @@ class HttpSignalingBackend implements SignalingBackend {
 
   @override
   Future<RegisterResponse> register({required String deviceLabel}) async {
+    final nonce = await _fetchChallenge();
     final uri = Uri.parse('$baseUrl/register');
     final resp = await _client.post(
       uri,
       headers: {'Content-Type': 'application/json'},
-      body: jsonEncode({'device_label': deviceLabel}),
+      body: jsonEncode({
+        'device_label': deviceLabel,
+        'public_key_b64': identityPublicKey(secretHex: identitySecret),
+        'nonce': nonce,
+        'signature_b64': identitySignRegistration(
+          secretHex: identitySecret,
+          nonce: nonce,
+        ),
+      }),
     );
     if (resp.statusCode != 200) {
-      throw Exception('Register failed: ${resp.statusCode} ${resp.body}');
+      throw SignalingApiException.fromResponse(resp.statusCode, resp.body);
     }
     final data = RegisterResponse.fromJson(jsonDecode(resp.body));
+    _deviceLabel = deviceLabel;
     _clientId = data.clientId;
     _sessionToken = data.sessionToken;
     _heartbeatIntervalSecs = data.heartbeatIntervalSecs;
     _displayName = data.displayName;
+    _iceServers = data.iceServers;
     _scheduleNextHeartbeat(_heartbeatIntervalSecs);
+    _scheduleRenewal(data.sessionExpiresAtEpochMs);
     return data;
   }
 
+  /// Single-use nonce to sign for the next registration
+  Future<String> _fetchChallenge() async {
+    final uri = Uri.parse('$baseUrl/register/challenge');
+    final resp = await _client.post(uri);
+    if (resp.statusCode != 200) {
+      throw SignalingApiException.fromResponse(resp.statusCode, resp.body);
+    }
+    final data = jsonDecode(resp.body) as Map<String, dynamic>;
+    return data['nonce'] as String;
+  }
+
+  void _scheduleRenewal(int expiresAtEpochMs) {
+    _renewTimer?.cancel();
+    if (expiresAtEpochMs <= 0) return;
+    final remaining = DateTime.fromMillisecondsSinceEpoch(
+      expiresAtEpochMs,
+    ).difference(DateTime.now());
+    final delay = remaining > _sessionRenewMargin * 2
+        ? remaining - _sessionRenewMargin
+        : remaining ~/ 2;
+    _renewTimer = Timer(delay, _renew);
+  }
+
+  /// Register again with the same identity; the device keeps its client ID
+  Future<void> _renew() async {
+    final deviceLabel = _deviceLabel;
+    if (deviceLabel == null) return;
+    try {
+      await register(deviceLabel: deviceLabel);
+    } catch (_) {
+      // Retry shortly; heartbeats keep failing until this succeeds
+      _renewTimer?.cancel();
+      _renewTimer = Timer(const Duration(seconds: 5), _renew);
+    }
+  }
+
   void _scheduleNextHeartbeat([int? seconds]) {
     _heartbeatTimer?.cancel();
     if (!isRegistered) return;
@@ -88,12 +150,45 @@ class HttpSignalingBackend implements SignalingBackend {
       _heartbeatIntervalSecs = hb.nextHeartbeatSecs;
       return hb;
     }
+    final error = SignalingApiException.fromResponse(
+      resp.statusCode,
+      resp.body,
+    );
+    if (error.code == 'session_expired' || error.code == 'invalid_session_token') {
+      // The token lapsed or was replaced; get a fresh one
+      unawaited(_renew());
+    }
     return null;
   }
 
+  @override
+  Future<List<Map<String, dynamic>>> fetchIceServers() async {
+    if (!isRegistered) return _iceServers;
+    final uri = Uri.parse('$baseUrl/ice-servers');
+    try {
+      final resp = await _client.post(
+        uri,
+        headers: {'Content-Type': 'application/json'},
+        body: jsonEncode({
+          'client_id': _clientId,
+          'session_token': _sessionToken,
+        }),
+      );
+      if (resp.statusCode == 200) {
+        final data = jsonDecode(resp.body) as Map<String, dynamic>;
+        _iceServers = parseIceServers(data['ice_servers']);
+      }
+    } catch (_) {
+      // Fall back to the servers handed out at registration
+    }
+    return _iceServers;
+  }
+
   @override
   Future<void> dispose() async {
     _heartbeatTimer?.cancel();
+    _renewTimer?.cancel();
+    _deviceLabel = null;
     if (_ownsClient) {
       _client.close();
     }
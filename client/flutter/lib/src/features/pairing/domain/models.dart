@@ -1,14 +1,22 @@
+import 'dart:convert';
+
 class RegisterResponse {
   final String clientId; // UUID
   final String sessionToken;
   final int heartbeatIntervalSecs;
   final String displayName;
+  // RTCPeerConnection `iceServers` entries; TURN entries carry credentials
+  final List<Map<String, dynamic>> iceServers;
+  // The session token stops working at this time; register again before it
+  final int sessionExpiresAtEpochMs;
 
   RegisterResponse({
     required this.clientId,
     required this.sessionToken,
     required this.heartbeatIntervalSecs,
     required this.displayName,
+    this.iceServers = const [],
+    this.sessionExpiresAtEpochMs = 0,
   });
 
   factory RegisterResponse.fromJson(Map<String, dynamic> json) =>
@@ -17,12 +25,66 @@ class RegisterResponse {
         sessionToken: json['session_token'] as String,
         heartbeatIntervalSecs: json['heartbeat_interval_secs'] as int,
         displayName: json['display_name'] as String? ?? 'Client',
+        iceServers: parseIceServers(json['ice_servers']),
+        sessionExpiresAtEpochMs:
+            (json['session_expires_at_epoch_ms'] as num?)?.toInt() ?? 0,
       );
 }
 
+List<Map<String, dynamic>> parseIceServers(dynamic raw) =>
+    (raw as List<dynamic>? ?? const [])
+        .map((entry) => Map<String, dynamic>.from(entry as Map))
+        .toList();
+
 class HeartbeatResponse {
   final int nextHeartbeatSecs;
   HeartbeatResponse(this.nextHeartbeatSecs);
   factory HeartbeatResponse.fromJson(Map<String, dynamic> json) =>
       HeartbeatResponse(json['next_heartbeat_secs'] as int);
 }
+
+/// A non-2xx reply from the signaling server. [code] is the server's stable
+/// `ErrorResponse.code` (e.g. `session_already_paired`) and is what callers
+/// should branch on; [message] is only for logs.
+class SignalingApiException implements Exception {
+  final int statusCode;
+  final String code;
+  final String message;
+  final Duration? retryAfter;
+  final String requestId;
+
+  SignalingApiException({
+    required this.statusCode,
+    required this.code,
+    required this.message,
+    this.retryAfter,
+    this.requestId = '',
+  });
+
+  /// Parses an `ErrorResponse` body; bodies from older servers or proxies
+  /// that are not JSON yield code `unknown`.
+  factory SignalingApiException.fromResponse(int statusCode, String body) {
+    Map<String, dynamic>? json;
+    try {
+      final decoded = jsonDecode(body);
+      if (decoded is Map<String, dynamic>) json = decoded;
+    } on FormatException {
+      // Not JSON; keep the raw body as the message
+    }
+    final retryAfterMs = (json?['retry_after_ms'] as num?)?.toInt();
+    return SignalingApiException(
+      statusCode: statusCode,
+      code: json?['code'] as String? ?? 'unknown',
+      message: json?['message'] as String? ?? body,
+      retryAfter: retryAfterMs == null
+          ? null
+          : Duration(milliseconds: retryAfterMs),
+      requestId: json?['request_id'] as String? ?? '',
+    );
+  }
+
+  @override
+  String toString() =>
+      'SignalingApiException($statusCode $code: $message'
+      '${requestId.isEmpty ? '' : ', request $requestId'})';
+}
@@ -3,6 +3,8 @@ import 'models.dart';
 abstract class SignalingBackend {
   Future<RegisterResponse> register({required String deviceLabel});
   Future<HeartbeatResponse?> heartbeat();
+  // Fresh ICE servers (with unexpired TURN credentials) for a new peer connection
+  Future<List<Map<String, dynamic>>> fetchIceServers();
   Future<void> dispose();
   bool get isRegistered;
   String? get clientId;
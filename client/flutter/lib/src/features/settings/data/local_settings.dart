@@ -1,9 +1,12 @@
 import 'package:shared_preferences/shared_preferences.dart';
 
+import '../../../rust/api/identity.dart';
+
 /// Local settings storage for domain and onboarding state
 class LocalSettings {
   static const String _keyDomain = 'signaling_domain';
   static const String _keyWelcomeShown = 'welcome_shown';
+  static const String _keyDeviceIdentity = 'device_identity_secret';
   static const String _defaultDomain = 'http://127.0.0.1:8080';
 
   final SharedPreferences _prefs;
@@ -43,9 +46,23 @@ class LocalSettings {
     await _prefs.setBool(_keyWelcomeShown, true);
   }
 
+  /// Secret key of this device's identity, generated on first use.
+  /// The server derives the device ID from it, so it must not change between
+  /// launches. Kept in SharedPreferences, which is not secure storage.
+  String deviceIdentitySecret() {
+    final existing = _prefs.getString(_keyDeviceIdentity);
+    if (existing != null) {
+      return existing;
+    }
+    final secret = identityGenerate();
+    _prefs.setString(_keyDeviceIdentity, secret);
+    return secret;
+  }
+
   /// Reset all settings (for testing or app reset)
   Future<void> reset() async {
     await _prefs.remove(_keyDomain);
     await _prefs.remove(_keyWelcomeShown);
+    await _prefs.remove(_keyDeviceIdentity);
   }
 }
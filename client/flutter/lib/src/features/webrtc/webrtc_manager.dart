@@ -8,6 +8,16 @@ import 'package:logging/logging.dart';
 class WebRTCManager {
   static final Logger _log = Logger('WebRTCManager');
 
+  // Used only when the signaling server hands out no ICE servers
+  static const List<Map<String, dynamic>> _fallbackIceServers = [
+    {'urls': 'stun:stun.l.google.com:19302'},
+  ];
+
+  final List<Map<String, dynamic>> _iceServers;
+
+  WebRTCManager({List<Map<String, dynamic>> iceServers = const []})
+    : _iceServers = iceServers;
+
   // ─── Magic number constants ────────────────────────────────────────────
   static const int _KBPS_TO_BPS_MULTIPLIER = 1000;
   static const int _DEFAULT_BITRATE_KBPS = 2000;
@@ -114,9 +124,7 @@ class WebRTCManager {
   Future<void> initialize() async {
     _log.info('WebRTC: Initializing...');
     final config = {
-      'iceServers': [
-        {'urls': 'stun:stun.l.google.com:19302'},
-      ],
+      'iceServers': _iceServers.isEmpty ? _fallbackIceServers : _iceServers,
       'sdpSemantics': 'unified-plan',
     };
 
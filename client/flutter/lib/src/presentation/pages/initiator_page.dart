@@ -47,6 +47,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
   static const double _sessionTitleFontSize = 22;
   static const double _linkFontSize = 12;
   static const double _dialogDetailFontSize = 12;
+  static const double _sasFontSize = 28;
   static const double _dropdownItemFontSize = 14;
   static const double _advancedOptionsFontSize = 13;
   static const double _maxPairingBodyWidth = 600;
@@ -79,6 +80,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
   ConnectionInitResult? _initiatorResult;
   String? _connectionLink;
   String? _initiatorServerMailboxId;
+  String? _initiatorServerMailboxToken;
   bool _generatingLink = false;
   bool _pollingPeer = false;
 
@@ -159,7 +161,9 @@ class _InitiatorPageState extends State<InitiatorPage> {
   Future<void> _startWebRTCHandshake() async {
     try {
       _log.info('Initiator: Starting WebRTC Handshake...');
-      _webrtcManager = WebRTCManager();
+      _webrtcManager = WebRTCManager(
+        iceServers: await widget.backend.fetchIceServers(),
+      );
       _log.info('Initiator: Initializing WebRTCManager...');
       await _webrtcManager!.initialize();
       _log.info('Initiator: WebRTCManager initialized.');
@@ -198,6 +202,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
       _log.info('Initiator: Sending Offer...');
       await _connectionService.sendSignal(
         mailboxId: _initiatorServerMailboxId!,
+        mailboxToken: _initiatorServerMailboxToken!,
         ciphertextB64: offerB64,
       );
     } catch (e) {
@@ -437,6 +442,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
     );
     await _connectionService.sendSignal(
       mailboxId: _initiatorServerMailboxId!,
+      mailboxToken: _initiatorServerMailboxToken!,
       ciphertextB64: iceB64,
     );
   }
@@ -461,6 +467,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
     }
 
     final previousMailboxId = _initiatorServerMailboxId;
+    final previousMailboxToken = _initiatorServerMailboxToken;
 
     setState(() => _generatingLink = true);
     try {
@@ -471,7 +478,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
       final initResult = await _connectionService.initializeConnectionLocally();
       final link = _connectionService.generateConnectionLink(
         initResult.rendezvousId,
-        initResult.secret,
+        initResult.publicKey,
       );
 
       final initResp = await _connectionService.sendConnectionInit(
@@ -480,12 +487,18 @@ class _InitiatorPageState extends State<InitiatorPage> {
         rendezvousId: initResult.rendezvousId,
       );
       final serverMailboxId = initResp['mailbox_id'] as String?;
+      final serverMailboxToken = initResp['mailbox_token'] as String?;
       final expiresAtEpochMs = (initResp['expires_at_epoch_ms'] as num?)
           ?.toInt();
 
-      if (serverMailboxId != null) {
+      if (serverMailboxId != null && serverMailboxToken != null) {
         _initiatorServerMailboxId = serverMailboxId;
-        _startListeningForPeer(serverMailboxId);
+        _initiatorServerMailboxToken = serverMailboxToken;
+        _startListeningForPeer(
+          serverMailboxId,
+          serverMailboxToken,
+          initResult,
+        );
       }
 
       setState(() {
@@ -502,10 +515,13 @@ class _InitiatorPageState extends State<InitiatorPage> {
 
       _startMailboxCountdown();
 
-      if (previousMailboxId != null && previousMailboxId != serverMailboxId) {
+      if (previousMailboxId != null &&
+          previousMailboxToken != null &&
+          previousMailboxId != serverMailboxId) {
         try {
           await _connectionService.closeConnection(
             mailboxId: previousMailboxId,
+            mailboxToken: previousMailboxToken,
           );
         } catch (_) {}
       }
@@ -562,22 +578,39 @@ class _InitiatorPageState extends State<InitiatorPage> {
     return (remainingMs / totalMs).clamp(0.0, 1.0);
   }
 
-  void _startListeningForPeer(String mailboxId) {
+  void _startListeningForPeer(
+    String mailboxId,
+    String mailboxToken,
+    ConnectionInitResult initResult,
+  ) {
     _mailboxSubscription?.cancel();
     setState(() => _pollingPeer = true);
 
     _mailboxSubscription = _connectionService
-        .subscribeMailbox(mailboxId: mailboxId)
+        .subscribeMailbox(mailboxId: mailboxId, mailboxToken: mailboxToken)
         .listen(
           (evt) {
-            if (!_peerAccepted && _incomingRequestFrom == null) {
+            if (_peerAccepted) {
+              _signalQueue.enqueue(evt);
+              return;
+            }
+            if (_incomingRequestFrom != null) return;
+            // The server's join notice is empty; the responder's public key
+            // follows as its first message
+            final peerKey = evt['ciphertext_b64'] as String?;
+            if (peerKey == null || peerKey.isEmpty) return;
+            try {
+              final completed = _connectionService.completeExchange(
+                secret: initResult.secret,
+                responderPublicKey: peerKey,
+              );
               setState(() {
                 _pollingPeer = false;
                 _incomingRequestFrom = evt['from_mailbox_id'] as String?;
               });
-              _showIncomingDialog();
-            } else if (_peerAccepted) {
-              _signalQueue.enqueue(evt);
+              _showIncomingDialog(completed);
+            } catch (e) {
+              _log.warning('Initiator: Invalid key from peer: $e');
             }
           },
           onError: (_) {
@@ -586,8 +619,11 @@ class _InitiatorPageState extends State<InitiatorPage> {
         );
   }
 
-  void _showIncomingDialog() {
+  /// Accepting counts as confirming the SAS, so the dialog insists on
+  /// comparing it with the peer first
+  void _showIncomingDialog(ConnectionInitResult completed) {
     if (_incomingRequestFrom == null) return;
+    final sas = _connectionService.formatSas(completed.sas);
     showDialog(
       context: context,
       barrierDismissible: false,
@@ -607,6 +643,14 @@ class _InitiatorPageState extends State<InitiatorPage> {
                   color: AppColors.textMuted,
                 ),
               ),
+              SizedBox(height: _dialogSpacing),
+              Text(sas, style: AppTypography.mono(size: _sasFontSize)),
+              const SizedBox(height: AppSpacing.sm),
+              const Text(
+                'Only accept if the peer sees the same code. A different '
+                'code means someone is intercepting the connection.',
+                textAlign: TextAlign.center,
+              ),
             ],
           ),
           actions: [
@@ -624,6 +668,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
               onPressed: () {
                 Navigator.of(ctx).pop();
                 setState(() {
+                  _initiatorResult = completed;
                   _peerAccepted = true;
                   _incomingRequestFrom = null;
                 });
@@ -633,7 +678,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
                 backgroundColor: AppColors.primary,
                 foregroundColor: AppColors.onPrimary,
               ),
-              child: const Text('Accept'),
+              child: const Text('Codes match'),
             ),
           ],
         );
@@ -711,7 +756,8 @@ class _InitiatorPageState extends State<InitiatorPage> {
   Future<void> _closeSignalingAfterConnect() async {
     if (_signalingClosed) return;
     final mailboxId = _initiatorServerMailboxId;
-    if (mailboxId == null) return;
+    final mailboxToken = _initiatorServerMailboxToken;
+    if (mailboxId == null || mailboxToken == null) return;
 
     _signalingClosed = true;
     _iceCandidateQueue.clear();
@@ -719,7 +765,10 @@ class _InitiatorPageState extends State<InitiatorPage> {
     _mailboxSubscription = null;
 
     try {
-      await _connectionService.closeConnection(mailboxId: mailboxId);
+      await _connectionService.closeConnection(
+        mailboxId: mailboxId,
+        mailboxToken: mailboxToken,
+      );
     } catch (e) {
       _log.warning('Failed to close signaling mailbox: $e');
     }
@@ -763,6 +812,7 @@ class _InitiatorPageState extends State<InitiatorPage> {
       );
       await _connectionService.sendSignal(
         mailboxId: _initiatorServerMailboxId!,
+        mailboxToken: _initiatorServerMailboxToken!,
         ciphertextB64: encryptedB64,
       );
     } catch (e) {
@@ -15,6 +15,7 @@ import 'package:application/src/presentation/widgets/session_status_views.dart';
 import 'package:flutter/material.dart';
 import 'package:logging/logging.dart';
 import 'package:application/src/features/pairing/data/connection_service.dart';
+import 'package:application/src/features/pairing/domain/models.dart';
 import 'package:application/src/features/pairing/domain/signaling_backend.dart';
 import 'package:application/src/features/webrtc/webrtc_manager.dart';
 import 'package:application/src/rust/api/connection.dart' as rust_connection;
@@ -54,6 +55,7 @@ class _ResponderPageState extends State<ResponderPage> {
   static const double _menuHandleClosedTop = 0;
   static const double _menuHandleOpenTop = 108;
   static const double _menuOverlayHeight = 170;
+  static const double _sasFontSize = 28;
 
   late ConnectionService _connectionService;
   WebRTCManager? _webrtcManager;
@@ -64,6 +66,8 @@ class _ResponderPageState extends State<ResponderPage> {
 
   final TextEditingController _tokenController = TextEditingController();
   String? _responderMailboxId;
+  String? _responderMailboxToken;
+  int _fetchedUpToSequence = 0;
   String? _kSig; // Session encryption key
   bool _joiningConnection = false;
   String? _joinError;
@@ -141,7 +145,9 @@ class _ResponderPageState extends State<ResponderPage> {
 
   Future<void> _startWebRTCHandshake() async {
     try {
-      _webrtcManager = WebRTCManager();
+      _webrtcManager = WebRTCManager(
+        iceServers: await widget.backend.fetchIceServers(),
+      );
       await _webrtcManager!.initialize();
 
       _remoteStreamSubscription?.cancel();
@@ -210,23 +216,36 @@ class _ResponderPageState extends State<ResponderPage> {
     }
 
     String token = input;
-    String? secret;
+    String? initiatorKey;
 
     try {
       final uri = Uri.parse(input);
       if (uri.hasQuery && uri.queryParameters.containsKey('token')) {
         token = uri.queryParameters['token']!;
       }
-      // Extract secret from fragment (e.g. #secret_hex)
+      // The fragment carries the initiator's X25519 public key
       if (uri.hasFragment && uri.fragment.isNotEmpty) {
-        secret = uri.fragment;
+        initiatorKey = uri.fragment;
       }
     } catch (_) {}
 
-    // If we have no secret, we cannot derive keys for E2EE
-    if (secret == null) {
+    // Without the initiator's key we cannot agree on keys for E2EE
+    if (initiatorKey == null) {
+      setState(() {
+        _joinError = 'Invalid link: Missing public key (fragment)';
+      });
+      return;
+    }
+
+    // Checked before joining, so a bad link does not use up the token
+    final ConnectionInitResult keys;
+    try {
+      keys = _connectionService.joinExchange(initiatorKey);
+    } catch (_) {
       setState(() {
-        _joinError = 'Invalid link: Missing security key (fragment)';
+        _joinError =
+            'This link is from an older version or damaged. '
+            'Ask for a new one.';
       });
       return;
     }
@@ -237,14 +256,39 @@ class _ResponderPageState extends State<ResponderPage> {
     });
 
     try {
-      // Derive keys locally
-      final keys = rust_connection.connectionDeriveKeys(secretHex: secret);
-      _kSig = keys.kSig;
-
       final joinResult = await _connectionService.joinConnection(
         tokenB64: token,
       );
       final mailboxId = joinResult['mailbox_id'] as String;
+      final mailboxToken = joinResult['mailbox_token'] as String;
+
+      // Our public key goes first and in the clear; the initiator needs it
+      // to derive the keys everything after is encrypted with
+      await _connectionService.sendSignal(
+        mailboxId: mailboxId,
+        mailboxToken: mailboxToken,
+        ciphertextB64: keys.publicKey,
+      );
+
+      setState(() {
+        _responderMailboxId = mailboxId;
+        _responderMailboxToken = mailboxToken;
+      });
+
+      if (!await _confirmSas(keys.sas)) {
+        await _connectionService.closeConnection(
+          mailboxId: mailboxId,
+          mailboxToken: mailboxToken,
+        );
+        setState(() {
+          _responderMailboxId = null;
+          _responderMailboxToken = null;
+          _joiningConnection = false;
+          _joinError = 'Connection cancelled: the codes did not match.';
+        });
+        return;
+      }
+      _kSig = keys.kSig;
 
       final hello = jsonEncode({
         'type': 'connect_request',
@@ -258,11 +302,11 @@ class _ResponderPageState extends State<ResponderPage> {
 
       await _connectionService.sendSignal(
         mailboxId: mailboxId,
+        mailboxToken: mailboxToken,
         ciphertextB64: helloB64,
       );
 
       setState(() {
-        _responderMailboxId = mailboxId;
         _joiningConnection = false;
         _joined = true;
       });
@@ -271,18 +315,77 @@ class _ResponderPageState extends State<ResponderPage> {
     } catch (e) {
       setState(() {
         _joiningConnection = false;
-        _joinError = e.toString();
+        _joinError = _describeJoinError(e);
       });
     }
   }
 
+  /// The SAS must match what the initiator sees; a mismatch means the keys
+  /// were swapped in transit, so nothing is signaled until the user confirms
+  Future<bool> _confirmSas(String sasHex) async {
+    final sas = _connectionService.formatSas(sasHex);
+    final confirmed = await showDialog<bool>(
+      context: context,
+      barrierDismissible: false,
+      builder: (ctx) => AlertDialog(
+        title: const Text('Compare Codes'),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          children: [
+            Text(sas, style: AppTypography.mono(size: _sasFontSize)),
+            const SizedBox(height: AppSpacing.sm),
+            const Text(
+              'Check that the other device shows the same code. A different '
+              'code means someone is intercepting the connection.',
+              textAlign: TextAlign.center,
+            ),
+          ],
+        ),
+        actions: [
+          TextButton(
+            onPressed: () => Navigator.of(ctx).pop(false),
+            style: TextButton.styleFrom(foregroundColor: AppColors.textMuted),
+            child: const Text('Codes differ'),
+          ),
+          ElevatedButton(
+            onPressed: () => Navigator.of(ctx).pop(true),
+            style: ElevatedButton.styleFrom(
+              backgroundColor: AppColors.primary,
+              foregroundColor: Colors.white,
+            ),
+            child: const Text('Codes match'),
+          ),
+        ],
+      ),
+    );
+    return confirmed ?? false;
+  }
+
+  String _describeJoinError(Object error) {
+    if (error is! SignalingApiException) return error.toString();
+    switch (error.code) {
+      case 'session_already_paired':
+        return 'This link has already been used. Ask for a new one.';
+      case 'invalid_rendezvous_token':
+        return 'This link has expired or was revoked. Ask for a new one.';
+      case 'rate_limited':
+        final seconds = error.retryAfter?.inSeconds ?? 0;
+        return 'Too many attempts. Try again in ${seconds < 1 ? 1 : seconds}s.';
+      default:
+        return error.toString();
+    }
+  }
+
   Future<void> _fetchAndProcessExistingMessages() async {
     try {
       final messages = await _connectionService.fetchMessages(
         mailboxId: _responderMailboxId!,
+        mailboxToken: _responderMailboxToken!,
       );
       for (final msg in messages) {
         await _handleIncomingSignal(msg);
+        final sequence = (msg['sequence'] as num?)?.toInt() ?? 0;
+        if (sequence > _fetchedUpToSequence) _fetchedUpToSequence = sequence;
       }
     } catch (e) {
       _log.warning('Failed to fetch existing messages: $e');
@@ -292,7 +395,12 @@ class _ResponderPageState extends State<ResponderPage> {
   void _startListeningForSignals() {
     _mailboxSubscription?.cancel();
     _mailboxSubscription = _connectionService
-        .subscribeMailbox(mailboxId: _responderMailboxId!)
+        .subscribeMailbox(
+          mailboxId: _responderMailboxId!,
+          mailboxToken: _responderMailboxToken!,
+          // Messages handled by the initial fetch are not replayed again
+          sinceSequence: _fetchedUpToSequence,
+        )
         .listen((msg) {
           _signalQueue.enqueue(msg);
         });
@@ -333,6 +441,7 @@ class _ResponderPageState extends State<ResponderPage> {
         );
         await _connectionService.sendSignal(
           mailboxId: _responderMailboxId!,
+          mailboxToken: _responderMailboxToken!,
           ciphertextB64: answerB64,
         );
         _log.info('Responder: Sent Answer');
@@ -378,6 +487,7 @@ class _ResponderPageState extends State<ResponderPage> {
     );
     await _connectionService.sendSignal(
       mailboxId: _responderMailboxId!,
+      mailboxToken: _responderMailboxToken!,
       ciphertextB64: iceB64,
     );
   }
@@ -395,7 +505,8 @@ class _ResponderPageState extends State<ResponderPage> {
   Future<void> _closeSignalingAfterConnect() async {
     if (_signalingClosed) return;
     final mailboxId = _responderMailboxId;
-    if (mailboxId == null) return;
+    final mailboxToken = _responderMailboxToken;
+    if (mailboxId == null || mailboxToken == null) return;
 
     _signalingClosed = true;
     _iceCandidateQueue.clear();
@@ -403,7 +514,10 @@ class _ResponderPageState extends State<ResponderPage> {
     _mailboxSubscription = null;
 
     try {
-      await _connectionService.closeConnection(mailboxId: mailboxId);
+      await _connectionService.closeConnection(
+        mailboxId: mailboxId,
+        mailboxToken: mailboxToken,
+      );
     } catch (e) {
       _log.warning('Failed to close signaling mailbox: $e');
     }
@@ -447,6 +561,7 @@ class _ResponderPageState extends State<ResponderPage> {
       );
       await _connectionService.sendSignal(
         mailboxId: _responderMailboxId!,
+        mailboxToken: _responderMailboxToken!,
         ciphertextB64: encryptedB64,
       );
     } catch (e) {
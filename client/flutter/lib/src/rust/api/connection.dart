@@ -9,25 +9,93 @@ import 'package:flutter_rust_bridge/flutter_rust_bridge_for_generated.dart';
 // These function are ignored because they are on traits that is not defined in current crate (put an empty `#[frb]` on it to unignore): `clone`, `fmt`
 
 /// Initialize a connection link (Client A)
-/// Returns a mailbox ID and generates a rendezvous token
+/// Returns a mailbox ID, a rendezvous token and an ephemeral X25519 key; the
+/// session keys are only known once the responder answers, see
+/// `connection_complete_exchange`
 ConnectionInitLocalResult connectionInitLocal() =>
     RustLib.instance.api.crateApiConnectionConnectionInitLocal();
 
-/// Derive keys from a shared secret (Client B)
+/// Answer a connection link with a fresh X25519 key (Client B)
+/// Send `public_key` to the initiator as the first mailbox message, then have
+/// both users compare `connection_format_sas` before signaling
+ConnectionInitLocalResult connectionJoinExchange({
+  required String initiatorPublicKey,
+}) => RustLib.instance.api.crateApiConnectionConnectionJoinExchange(
+  initiatorPublicKey: initiatorPublicKey,
+);
+
+/// Finish the key exchange with the responder's public key (Client A)
+ConnectionInitLocalResult connectionCompleteExchange({
+  required String secretHex,
+  required String responderPublicKey,
+}) => RustLib.instance.api.crateApiConnectionConnectionCompleteExchange(
+  secretHex: secretHex,
+  responderPublicKey: responderPublicKey,
+);
+
+/// Six-digit code from a hex-encoded SAS, for users to compare out of band
+String connectionFormatSas({required String sasHex}) =>
+    RustLib.instance.api.crateApiConnectionConnectionFormatSas(sasHex: sasHex);
+
+/// Derive keys from a shared secret
 ConnectionInitLocalResult connectionDeriveKeys({required String secretHex}) =>
     RustLib.instance.api.crateApiConnectionConnectionDeriveKeys(
       secretHex: secretHex,
     );
 
 /// Generate a connection link URL
+/// The fragment carries the initiator's public key, never a secret
 String generateConnectionLink({
   required String baseUrl,
   required String rendezvousId,
-  required String secret,
+  required String publicKey,
 }) => RustLib.instance.api.crateApiConnectionGenerateConnectionLink(
   baseUrl: baseUrl,
   rendezvousId: rendezvousId,
-  secret: secret,
+  publicKey: publicKey,
+);
+
+/// Short code for a nameplate from `/connection/nameplate`, e.g. `7-guitar-orbit`
+String connectionCodeGenerate({required String nameplate}) => RustLib
+    .instance
+    .api
+    .crateApiConnectionConnectionCodeGenerate(nameplate: nameplate);
+
+/// Nameplate to join with, from a code as the user typed it
+String connectionCodeNameplate({required String code}) =>
+    RustLib.instance.api.crateApiConnectionConnectionCodeNameplate(code: code);
+
+/// Start a SPAKE2 exchange for a short code (both sides)
+/// Returns the exchange ID for `connection_pake_message` and `connection_pake_finish`;
+/// the exchange's secret never leaves Rust
+String connectionPakeStart({required String code}) =>
+    RustLib.instance.api.crateApiConnectionConnectionPakeStart(code: code);
+
+/// Start the SPAKE2 exchange for a host session, keyed by the host ID and access
+/// password (host and viewer both run one); returns the exchange ID
+String connectionHostPakeStart({
+  required String hostId,
+  required String password,
+}) => RustLib.instance.api.crateApiConnectionConnectionHostPakeStart(
+  hostId: hostId,
+  password: password,
+);
+
+/// SPAKE2 message to send through the mailbox, base64
+String connectionPakeMessage({required String exchangeId}) => RustLib
+    .instance
+    .api
+    .crateApiConnectionConnectionPakeMessage(exchangeId: exchangeId);
+
+/// Finish a SPAKE2 exchange with the peer's message and derive the session keys
+/// (a wrong code or password yields keys the peer's messages fail to decrypt with);
+/// the exchange is gone afterwards, whether or not it succeeded
+ConnectionInitLocalResult connectionPakeFinish({
+  required String exchangeId,
+  required String peerMessageB64,
+}) => RustLib.instance.api.crateApiConnectionConnectionPakeFinish(
+  exchangeId: exchangeId,
+  peerMessageB64: peerMessageB64,
 );
 
 /// Encrypt signaling payload using the shared session key (AES-GCM)
@@ -56,6 +124,9 @@ class ConnectionInitLocalResult {
   final String kMac;
   final String sas;
 
+  /// Own X25519 public key, URL-safe base64; empty if there was no exchange
+  final String publicKey;
+
   const ConnectionInitLocalResult({
     required this.rendezvousId,
     required this.mailboxId,
@@ -63,6 +134,7 @@ class ConnectionInitLocalResult {
     required this.kSig,
     required this.kMac,
     required this.sas,
+    required this.publicKey,
   });
 
   @override
@@ -72,7 +144,8 @@ class ConnectionInitLocalResult {
       secret.hashCode ^
       kSig.hashCode ^
       kMac.hashCode ^
-      sas.hashCode;
+      sas.hashCode ^
+      publicKey.hashCode;
 
   @override
   bool operator ==(Object other) =>
@@ -84,5 +157,6 @@ class ConnectionInitLocalResult {
           secret == other.secret &&
           kSig == other.kSig &&
           kMac == other.kMac &&
-          sas == other.sas;
+          sas == other.sas &&
+          publicKey == other.publicKey;
 }
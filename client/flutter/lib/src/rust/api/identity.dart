@@ -0,0 +1,29 @@
+// This file is automatically generated, so please do not edit it.
+// @generated by `flutter_rust_bridge`@ 2.11.1.
+
+// ignore_for_file: invalid_use_of_internal_member, unused_import, unnecessary_import
+
+import '../frb_generated.dart';
+import 'package:flutter_rust_bridge/flutter_rust_bridge_for_generated.dart';
+
+// These functions are ignored because they are not marked as `pub`: `parse_identity`
+
+/// Generate a new device identity key (Ed25519)
+/// Returns the hex-encoded secret; store it to keep the same device ID
+String identityGenerate() =>
+    RustLib.instance.api.crateApiIdentityIdentityGenerate();
+
+/// Public key to register with, URL-safe base64
+String identityPublicKey({required String secretHex}) =>
+    RustLib.instance.api.crateApiIdentityIdentityPublicKey(
+      secretHex: secretHex,
+    );
+
+/// Sign a registration challenge nonce from the signaling server
+String identitySignRegistration({
+  required String secretHex,
+  required String nonce,
+}) => RustLib.instance.api.crateApiIdentityIdentitySignRegistration(
+  secretHex: secretHex,
+  nonce: nonce,
+);
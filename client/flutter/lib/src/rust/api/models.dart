@@ -8,17 +8,40 @@ import 'package:flutter_rust_bridge/flutter_rust_bridge_for_generated.dart';
 
 // These functions have error during generation (see debug logs or enable `stop_on_error: true` for more details): `new`
 
+class IceServerDto {
+  final List<String> urls;
+  final String? username;
+  final String? credential;
+
+  const IceServerDto({required this.urls, this.username, this.credential});
+
+  @override
+  int get hashCode => urls.hashCode ^ username.hashCode ^ credential.hashCode;
+
+  @override
+  bool operator ==(Object other) =>
+      identical(this, other) ||
+      other is IceServerDto &&
+          runtimeType == other.runtimeType &&
+          urls == other.urls &&
+          username == other.username &&
+          credential == other.credential;
+}
+
 class SignalingClientConfigDto {
   final String baseUrl;
   final BigInt heartbeatIntervalSecs;
+  final List<IceServerDto> iceServers;
 
   const SignalingClientConfigDto({
     required this.baseUrl,
     required this.heartbeatIntervalSecs,
+    required this.iceServers,
   });
 
   @override
-  int get hashCode => baseUrl.hashCode ^ heartbeatIntervalSecs.hashCode;
+  int get hashCode =>
+      baseUrl.hashCode ^ heartbeatIntervalSecs.hashCode ^ iceServers.hashCode;
 
   @override
   bool operator ==(Object other) =>
@@ -26,5 +49,6 @@ class SignalingClientConfigDto {
       other is SignalingClientConfigDto &&
           runtimeType == other.runtimeType &&
           baseUrl == other.baseUrl &&
-          heartbeatIntervalSecs == other.heartbeatIntervalSecs;
+          heartbeatIntervalSecs == other.heartbeatIntervalSecs &&
+          iceServers == other.iceServers;
 }
@@ -5,6 +5,7 @@
 
 import 'api/client.dart';
 import 'api/connection.dart';
+import 'api/identity.dart';
 import 'api/models.dart';
 import 'api/share.dart';
 import 'api/simple.dart';
@@ -107,6 +108,30 @@ abstract class RustLibApi extends BaseApi {
     required ArcRtcDataChannel dc,
   });
 
+  ConnectionInitLocalResult crateApiConnectionConnectionCompleteExchange({
+    required String secretHex,
+    required String responderPublicKey,
+  });
+
+  String crateApiConnectionConnectionFormatSas({required String sasHex});
+
+  ConnectionInitLocalResult crateApiConnectionConnectionJoinExchange({
+    required String initiatorPublicKey,
+  });
+
+  String crateApiConnectionConnectionCodeGenerate({required String nameplate});
+
+  String crateApiConnectionConnectionCodeNameplate({required String code});
+
+  ConnectionInitLocalResult crateApiConnectionConnectionPakeFinish({
+    required String exchangeId,
+    required String peerMessageB64,
+  });
+
+  String crateApiConnectionConnectionPakeStart({required String code});
+
+  String crateApiConnectionConnectionPakeMessage({required String exchangeId});
+
   Uint8List crateApiConnectionConnectionDecrypt({
     required String keyHex,
     required String ciphertextB64,
@@ -121,16 +146,30 @@ abstract class RustLibApi extends BaseApi {
     required List<int> plaintext,
   });
 
+  String crateApiConnectionConnectionHostPakeStart({
+    required String hostId,
+    required String password,
+  });
+
   ConnectionInitLocalResult crateApiConnectionConnectionInitLocal();
 
   String crateApiConnectionGenerateConnectionLink({
     required String baseUrl,
     required String rendezvousId,
-    required String secret,
+    required String publicKey,
   });
 
   String crateApiSimpleGreet({required String name});
 
+  String crateApiIdentityIdentityGenerate();
+
+  String crateApiIdentityIdentityPublicKey({required String secretHex});
+
+  String crateApiIdentityIdentitySignRegistration({
+    required String secretHex,
+    required String nonce,
+  });
+
   void crateApiShareInit();
 
   Future<void> crateApiSimpleInitApp();
@@ -382,6 +421,221 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         argNames: ["connectionId", "label", "dc"],
       );
 
+  @override
+  ConnectionInitLocalResult crateApiConnectionConnectionCompleteExchange({
+    required String secretHex,
+    required String responderPublicKey,
+  }) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(secretHex, serializer);
+          sse_encode_String(responderPublicKey, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 32)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_connection_init_local_result,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionCompleteExchangeConstMeta,
+        argValues: [secretHex, responderPublicKey],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionCompleteExchangeConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_complete_exchange",
+        argNames: ["secretHex", "responderPublicKey"],
+      );
+
+  @override
+  String crateApiConnectionConnectionFormatSas({required String sasHex}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(sasHex, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 33)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionFormatSasConstMeta,
+        argValues: [sasHex],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionFormatSasConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_format_sas",
+        argNames: ["sasHex"],
+      );
+
+  @override
+  ConnectionInitLocalResult crateApiConnectionConnectionJoinExchange({
+    required String initiatorPublicKey,
+  }) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(initiatorPublicKey, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 31)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_connection_init_local_result,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionJoinExchangeConstMeta,
+        argValues: [initiatorPublicKey],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionJoinExchangeConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_join_exchange",
+        argNames: ["initiatorPublicKey"],
+      );
+
+  @override
+  String crateApiConnectionConnectionCodeGenerate({required String nameplate}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(nameplate, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 26)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionCodeGenerateConstMeta,
+        argValues: [nameplate],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionCodeGenerateConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_code_generate",
+        argNames: ["nameplate"],
+      );
+
+  @override
+  String crateApiConnectionConnectionCodeNameplate({required String code}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(code, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 27)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionCodeNameplateConstMeta,
+        argValues: [code],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionCodeNameplateConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_code_nameplate",
+        argNames: ["code"],
+      );
+
+  @override
+  ConnectionInitLocalResult crateApiConnectionConnectionPakeFinish({
+    required String exchangeId,
+    required String peerMessageB64,
+  }) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(exchangeId, serializer);
+          sse_encode_String(peerMessageB64, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 30)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_connection_init_local_result,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionPakeFinishConstMeta,
+        argValues: [exchangeId, peerMessageB64],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionPakeFinishConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_pake_finish",
+        argNames: ["exchangeId", "peerMessageB64"],
+      );
+
+  @override
+  String crateApiConnectionConnectionPakeStart({required String code}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(code, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 28)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionPakeStartConstMeta,
+        argValues: [code],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionPakeStartConstMeta =>
+      const TaskConstMeta(debugName: "connection_pake_start", argNames: ["code"]);
+
+  @override
+  String crateApiConnectionConnectionPakeMessage({required String exchangeId}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(exchangeId, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 29)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionPakeMessageConstMeta,
+        argValues: [exchangeId],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionPakeMessageConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_pake_message",
+        argNames: ["exchangeId"],
+      );
+
   @override
   Uint8List crateApiConnectionConnectionDecrypt({
     required String keyHex,
@@ -470,6 +724,36 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
         argNames: ["keyHex", "plaintext"],
       );
 
+  @override
+  String crateApiConnectionConnectionHostPakeStart({
+    required String hostId,
+    required String password,
+  }) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(hostId, serializer);
+          sse_encode_String(password, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 25)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiConnectionConnectionHostPakeStartConstMeta,
+        argValues: [hostId, password],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiConnectionConnectionHostPakeStartConstMeta =>
+      const TaskConstMeta(
+        debugName: "connection_host_pake_start",
+        argNames: ["hostId", "password"],
+      );
+
   @override
   ConnectionInitLocalResult crateApiConnectionConnectionInitLocal() {
     return handler.executeSync(
@@ -496,7 +780,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   String crateApiConnectionGenerateConnectionLink({
     required String baseUrl,
     required String rendezvousId,
-    required String secret,
+    required String publicKey,
   }) {
     return handler.executeSync(
       SyncTask(
@@ -504,7 +788,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
           final serializer = SseSerializer(generalizedFrbRustBinding);
           sse_encode_String(baseUrl, serializer);
           sse_encode_String(rendezvousId, serializer);
-          sse_encode_String(secret, serializer);
+          sse_encode_String(publicKey, serializer);
           return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 10)!;
         },
         codec: SseCodec(
@@ -512,7 +796,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
           decodeErrorData: null,
         ),
         constMeta: kCrateApiConnectionGenerateConnectionLinkConstMeta,
-        argValues: [baseUrl, rendezvousId, secret],
+        argValues: [baseUrl, rendezvousId, publicKey],
         apiImpl: this,
       ),
     );
@@ -521,7 +805,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   TaskConstMeta get kCrateApiConnectionGenerateConnectionLinkConstMeta =>
       const TaskConstMeta(
         debugName: "generate_connection_link",
-        argNames: ["baseUrl", "rendezvousId", "secret"],
+        argNames: ["baseUrl", "rendezvousId", "publicKey"],
       );
 
   @override
@@ -547,6 +831,84 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   TaskConstMeta get kCrateApiSimpleGreetConstMeta =>
       const TaskConstMeta(debugName: "greet", argNames: ["name"]);
 
+  @override
+  String crateApiIdentityIdentityGenerate() {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 22)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: null,
+        ),
+        constMeta: kCrateApiIdentityIdentityGenerateConstMeta,
+        argValues: [],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiIdentityIdentityGenerateConstMeta =>
+      const TaskConstMeta(debugName: "identity_generate", argNames: []);
+
+  @override
+  String crateApiIdentityIdentityPublicKey({required String secretHex}) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(secretHex, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 23)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiIdentityIdentityPublicKeyConstMeta,
+        argValues: [secretHex],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiIdentityIdentityPublicKeyConstMeta =>
+      const TaskConstMeta(
+        debugName: "identity_public_key",
+        argNames: ["secretHex"],
+      );
+
+  @override
+  String crateApiIdentityIdentitySignRegistration({
+    required String secretHex,
+    required String nonce,
+  }) {
+    return handler.executeSync(
+      SyncTask(
+        callFfi: () {
+          final serializer = SseSerializer(generalizedFrbRustBinding);
+          sse_encode_String(secretHex, serializer);
+          sse_encode_String(nonce, serializer);
+          return pdeCallFfi(generalizedFrbRustBinding, serializer, funcId: 24)!;
+        },
+        codec: SseCodec(
+          decodeSuccessData: sse_decode_String,
+          decodeErrorData: sse_decode_AnyhowException,
+        ),
+        constMeta: kCrateApiIdentityIdentitySignRegistrationConstMeta,
+        argValues: [secretHex, nonce],
+        apiImpl: this,
+      ),
+    );
+  }
+
+  TaskConstMeta get kCrateApiIdentityIdentitySignRegistrationConstMeta =>
+      const TaskConstMeta(
+        debugName: "identity_sign_registration",
+        argNames: ["secretHex", "nonce"],
+      );
+
   @override
   void crateApiShareInit() {
     return handler.executeSync(
@@ -976,8 +1338,8 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   ) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
     final arr = raw as List<dynamic>;
-    if (arr.length != 6)
-      throw Exception('unexpected arr length: expect 6 but see ${arr.length}');
+    if (arr.length != 7)
+      throw Exception('unexpected arr length: expect 7 but see ${arr.length}');
     return ConnectionInitLocalResult(
       rendezvousId: dco_decode_String(arr[0]),
       mailboxId: dco_decode_String(arr[1]),
@@ -985,6 +1347,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
       kSig: dco_decode_String(arr[3]),
       kMac: dco_decode_String(arr[4]),
       sas: dco_decode_String(arr[5]),
+      publicKey: dco_decode_String(arr[6]),
     );
   }
 
@@ -994,6 +1357,31 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return raw as int;
   }
 
+  @protected
+  IceServerDto dco_decode_ice_server_dto(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    final arr = raw as List<dynamic>;
+    if (arr.length != 3)
+      throw Exception('unexpected arr length: expect 3 but see ${arr.length}');
+    return IceServerDto(
+      urls: dco_decode_list_String(arr[0]),
+      username: dco_decode_opt_String(arr[1]),
+      credential: dco_decode_opt_String(arr[2]),
+    );
+  }
+
+  @protected
+  List<String> dco_decode_list_String(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_String).toList();
+  }
+
+  @protected
+  List<IceServerDto> dco_decode_list_ice_server_dto(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return (raw as List<dynamic>).map(dco_decode_ice_server_dto).toList();
+  }
+
   @protected
   List<int> dco_decode_list_prim_u_8_loose(dynamic raw) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
@@ -1025,6 +1413,12 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return (raw as List<dynamic>).map(dco_decode_source_descriptor).toList();
   }
 
+  @protected
+  String? dco_decode_opt_String(dynamic raw) {
+    // Codec=Dco (DartCObject based), see doc to use other codecs
+    return raw == null ? null : dco_decode_String(raw);
+  }
+
   @protected
   int? dco_decode_opt_box_autoadd_u_32(dynamic raw) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
@@ -1078,11 +1472,12 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
   SignalingClientConfigDto dco_decode_signaling_client_config_dto(dynamic raw) {
     // Codec=Dco (DartCObject based), see doc to use other codecs
     final arr = raw as List<dynamic>;
-    if (arr.length != 2)
-      throw Exception('unexpected arr length: expect 2 but see ${arr.length}');
+    if (arr.length != 3)
+      throw Exception('unexpected arr length: expect 3 but see ${arr.length}');
     return SignalingClientConfigDto(
       baseUrl: dco_decode_String(arr[0]),
       heartbeatIntervalSecs: dco_decode_u_64(arr[1]),
+      iceServers: dco_decode_list_ice_server_dto(arr[2]),
     );
   }
 
@@ -1298,6 +1693,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     var var_kSig = sse_decode_String(deserializer);
     var var_kMac = sse_decode_String(deserializer);
     var var_sas = sse_decode_String(deserializer);
+    var var_publicKey = sse_decode_String(deserializer);
     return ConnectionInitLocalResult(
       rendezvousId: var_rendezvousId,
       mailboxId: var_mailboxId,
@@ -1305,6 +1701,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
       kSig: var_kSig,
       kMac: var_kMac,
       sas: var_sas,
+      publicKey: var_publicKey,
     );
   }
 
@@ -1314,6 +1711,45 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return deserializer.buffer.getInt32();
   }
 
+  @protected
+  IceServerDto sse_decode_ice_server_dto(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    var var_urls = sse_decode_list_String(deserializer);
+    var var_username = sse_decode_opt_String(deserializer);
+    var var_credential = sse_decode_opt_String(deserializer);
+    return IceServerDto(
+      urls: var_urls,
+      username: var_username,
+      credential: var_credential,
+    );
+  }
+
+  @protected
+  List<String> sse_decode_list_String(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <String>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_String(deserializer));
+    }
+    return ans_;
+  }
+
+  @protected
+  List<IceServerDto> sse_decode_list_ice_server_dto(
+    SseDeserializer deserializer,
+  ) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    var len_ = sse_decode_i_32(deserializer);
+    var ans_ = <IceServerDto>[];
+    for (var idx_ = 0; idx_ < len_; ++idx_) {
+      ans_.add(sse_decode_ice_server_dto(deserializer));
+    }
+    return ans_;
+  }
+
   @protected
   List<int> sse_decode_list_prim_u_8_loose(SseDeserializer deserializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -1361,6 +1797,17 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     return ans_;
   }
 
+  @protected
+  String? sse_decode_opt_String(SseDeserializer deserializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    if (sse_decode_bool(deserializer)) {
+      return (sse_decode_String(deserializer));
+    } else {
+      return null;
+    }
+  }
+
   @protected
   int? sse_decode_opt_box_autoadd_u_32(SseDeserializer deserializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -1414,9 +1861,11 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     // Codec=Sse (Serialization based), see doc to use other codecs
     var var_baseUrl = sse_decode_String(deserializer);
     var var_heartbeatIntervalSecs = sse_decode_u_64(deserializer);
+    var var_iceServers = sse_decode_list_ice_server_dto(deserializer);
     return SignalingClientConfigDto(
       baseUrl: var_baseUrl,
       heartbeatIntervalSecs: var_heartbeatIntervalSecs,
+      iceServers: var_iceServers,
     );
   }
 
@@ -1644,6 +2093,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     sse_encode_String(self.kSig, serializer);
     sse_encode_String(self.kMac, serializer);
     sse_encode_String(self.sas, serializer);
+    sse_encode_String(self.publicKey, serializer);
   }
 
   @protected
@@ -1652,6 +2102,35 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     serializer.buffer.putInt32(self);
   }
 
+  @protected
+  void sse_encode_ice_server_dto(IceServerDto self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_list_String(self.urls, serializer);
+    sse_encode_opt_String(self.username, serializer);
+    sse_encode_opt_String(self.credential, serializer);
+  }
+
+  @protected
+  void sse_encode_list_String(List<String> self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_String(item, serializer);
+    }
+  }
+
+  @protected
+  void sse_encode_list_ice_server_dto(
+    List<IceServerDto> self,
+    SseSerializer serializer,
+  ) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+    sse_encode_i_32(self.length, serializer);
+    for (final item in self) {
+      sse_encode_ice_server_dto(item, serializer);
+    }
+  }
+
   @protected
   void sse_encode_list_prim_u_8_loose(
     List<int> self,
@@ -1702,6 +2181,16 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     }
   }
 
+  @protected
+  void sse_encode_opt_String(String? self, SseSerializer serializer) {
+    // Codec=Sse (Serialization based), see doc to use other codecs
+
+    sse_encode_bool(self != null, serializer);
+    if (self != null) {
+      sse_encode_String(self, serializer);
+    }
+  }
+
   @protected
   void sse_encode_opt_box_autoadd_u_32(int? self, SseSerializer serializer) {
     // Codec=Sse (Serialization based), see doc to use other codecs
@@ -1752,6 +2241,7 @@ class RustLibApiImpl extends RustLibApiImplPlatform implements RustLibApi {
     // Codec=Sse (Serialization based), see doc to use other codecs
     sse_encode_String(self.baseUrl, serializer);
     sse_encode_u_64(self.heartbeatIntervalSecs, serializer);
+    sse_encode_list_ice_server_dto(self.iceServers, serializer);
   }
 
   @protected
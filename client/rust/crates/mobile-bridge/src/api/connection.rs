@@ -1,72 +1,203 @@
-use shared::connection;
+use base64::Engine as _;
+use once_cell::sync::Lazy;
+use shared::pake::Spake2;
+use shared::{code, connection, host, token};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 /// Initialize a connection link (Client A)
-/// Returns a mailbox ID and generates a rendezvous token
+/// Returns a mailbox ID, a rendezvous token and an ephemeral X25519 key; the
+/// session keys are only known once the responder answers, see
+/// `connection_complete_exchange`
 #[flutter_rust_bridge::frb(sync)]
 pub fn connection_init_local() -> ConnectionInitLocalResult {
     // Generate high-entropy rendezvous ID locally (will be shared via link)
-    let rendezvous_id = connection::gen_rendezvous_id();
+    let rendezvous_id = token::gen_token();
 
     // Generate mailbox ID locally
     let mailbox_id = connection::gen_mailbox_id();
 
-    // Generate a secret (in real client, user would generate this)
-    // This secret is kept local and NOT sent to server
-    let mut secret = [0u8; 32];
-    let mut rng = rand::rng();
-    use rand::RngCore;
-    rng.fill_bytes(&mut secret);
-
-    // Derive keys from secret
-    let keys = connection::derive_keys(&secret).expect("key derivation failed");
+    // Only the public half goes into the link
+    let exchange_key = connection::ExchangeKey::generate();
 
     ConnectionInitLocalResult {
         rendezvous_id,
         mailbox_id,
-        secret: hex::encode(secret),
-        k_sig: hex::encode(keys.k_sig),
-        k_mac: hex::encode(keys.k_mac),
-        sas: hex::encode(keys.sas),
+        secret: hex::encode(exchange_key.secret()),
+        public_key: exchange_key.public_key_b64(),
+        k_sig: "".to_string(),
+        k_mac: "".to_string(),
+        sas: "".to_string(),
     }
 }
 
-/// Derive keys from a shared secret (Client B)
+/// Answer a connection link with a fresh X25519 key (Client B)
+/// Send `public_key` to the initiator as the first mailbox message, then have
+/// both users compare `connection_format_sas` before signaling
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_join_exchange(
+    initiator_public_key: String,
+) -> anyhow::Result<ConnectionInitLocalResult> {
+    let initiator_public = connection::decode_public_key(&initiator_public_key)?;
+    let exchange_key = connection::ExchangeKey::generate();
+    let secret = exchange_key.agree_as_responder(&initiator_public)?;
+    keys_result(&secret, exchange_key.public_key_b64())
+}
+
+/// Finish the key exchange with the responder's public key (Client A)
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_complete_exchange(
+    secret_hex: String,
+    responder_public_key: String,
+) -> anyhow::Result<ConnectionInitLocalResult> {
+    let exchange_key = connection::ExchangeKey::from_secret(&parse_secret(&secret_hex)?);
+    let responder_public = connection::decode_public_key(&responder_public_key)?;
+    let secret = exchange_key.agree_as_initiator(&responder_public)?;
+    keys_result(&secret, exchange_key.public_key_b64())
+}
+
+/// Six-digit code from a hex-encoded SAS, for users to compare out of band
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_format_sas(sas_hex: String) -> anyhow::Result<String> {
+    let sas: [u8; 32] = hex::decode(sas_hex)
+        .map_err(|e| anyhow::anyhow!("Invalid SAS hex: {}", e))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid SAS length"))?;
+    Ok(connection::format_sas(&sas))
+}
+
+/// Derive keys from a shared secret
 #[flutter_rust_bridge::frb(sync)]
 pub fn connection_derive_keys(secret_hex: String) -> anyhow::Result<ConnectionInitLocalResult> {
+    keys_result(&parse_secret(&secret_hex)?, "".to_string())
+}
+
+fn parse_secret(secret_hex: &str) -> anyhow::Result<[u8; 32]> {
     let secret_bytes =
         hex::decode(secret_hex).map_err(|e| anyhow::anyhow!("Invalid secret hex: {}", e))?;
-    let secret: [u8; 32] = secret_bytes
+    secret_bytes
         .try_into()
-        .map_err(|_| anyhow::anyhow!("Invalid secret length"))?;
+        .map_err(|_| anyhow::anyhow!("Invalid secret length"))
+}
 
-    let keys = connection::derive_keys(&secret)?;
+fn keys_result(secret: &[u8; 32], public_key: String) -> anyhow::Result<ConnectionInitLocalResult> {
+    let keys = connection::derive_keys(secret)?;
 
     Ok(ConnectionInitLocalResult {
         rendezvous_id: "".to_string(), // Not needed for derivation
         mailbox_id: "".to_string(),    // Not needed for derivation
         secret: hex::encode(secret),
+        public_key,
         k_sig: hex::encode(keys.k_sig),
         k_mac: hex::encode(keys.k_mac),
         sas: hex::encode(keys.sas),
     })
 }
 
+/// Short code for a nameplate from `/connection/nameplate`, e.g. `7-guitar-orbit`
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_code_generate(nameplate: String) -> anyhow::Result<String> {
+    code::gen_code(&nameplate)
+}
+
+/// Nameplate to join with, from a code as the user typed it
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_code_nameplate(code: String) -> anyhow::Result<String> {
+    code::code_nameplate(&code).ok_or_else(|| anyhow::anyhow!("Invalid code: {}", code))
+}
+
+/// SPAKE2 exchanges waiting for the peer's message, by exchange ID
+static PENDING_PAKES: Lazy<Mutex<HashMap<String, (Instant, Spake2)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How long an exchange waits for the peer before it is dropped
+const PAKE_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Start a SPAKE2 exchange for a short code (both sides)
+/// Returns the exchange ID for `connection_pake_message` and `connection_pake_finish`;
+/// the exchange's secret never leaves Rust
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_pake_start(code: String) -> anyhow::Result<String> {
+    let normalized =
+        code::normalize_code(&code).ok_or_else(|| anyhow::anyhow!("Invalid code: {}", code))?;
+    Ok(hold_pake(Spake2::start(&normalized)))
+}
+
+/// Start the SPAKE2 exchange for a host session, keyed by the host ID and access
+/// password (host and viewer both run one); returns the exchange ID
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_host_pake_start(host_id: String, password: String) -> anyhow::Result<String> {
+    let normalized = host::normalize_host_id(&host_id)
+        .ok_or_else(|| anyhow::anyhow!("Invalid host ID: {}", host_id))?;
+    Ok(hold_pake(host::host_pake(&normalized, &password)))
+}
+
+/// SPAKE2 message to send through the mailbox, base64
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_pake_message(exchange_id: String) -> anyhow::Result<String> {
+    let pending = pending_pakes();
+    let (_, pake) = pending
+        .get(&exchange_id)
+        .ok_or_else(|| anyhow::anyhow!("Unknown SPAKE2 exchange"))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(pake.message()))
+}
+
+/// Finish a SPAKE2 exchange with the peer's message and derive the session keys
+/// (a wrong code or password yields keys the peer's messages fail to decrypt with);
+/// the exchange is gone afterwards, whether or not it succeeded
+#[flutter_rust_bridge::frb(sync)]
+pub fn connection_pake_finish(
+    exchange_id: String,
+    peer_message_b64: String,
+) -> anyhow::Result<ConnectionInitLocalResult> {
+    let (_, pake) = pending_pakes()
+        .remove(&exchange_id)
+        .ok_or_else(|| anyhow::anyhow!("Unknown SPAKE2 exchange"))?;
+    let peer_message = base64::engine::general_purpose::STANDARD
+        .decode(peer_message_b64)
+        .map_err(|e| anyhow::anyhow!("Invalid SPAKE2 message: {}", e))?;
+    let secret = pake.finish(&peer_message)?;
+    connection_derive_keys(hex::encode(secret))
+}
+
+fn pending_pakes() -> MutexGuard<'static, HashMap<String, (Instant, Spake2)>> {
+    PENDING_PAKES.lock().expect("pending SPAKE2 mutex poisoned")
+}
+
+fn hold_pake(pake: Spake2) -> String {
+    let exchange_id = token::gen_token();
+    let now = Instant::now();
+    let mut pending = pending_pakes();
+    // Exchanges whose peer never answered
+    pending.retain(|_, (started, _)| now.duration_since(*started) < PAKE_TIMEOUT);
+    pending.insert(exchange_id.clone(), (now, pake));
+    exchange_id
+}
+
 #[derive(Debug, Clone)]
 pub struct ConnectionInitLocalResult {
     pub rendezvous_id: String,
     pub mailbox_id: String,
-    pub secret: String, // Hex-encoded shared secret
+    pub secret: String, // Hex-encoded shared secret, or the X25519 key until exchanged
     pub k_sig: String,  // Hex-encoded signaling key
     pub k_mac: String,  // Hex-encoded MAC key
     pub sas: String,    // Hex-encoded short auth string
+    /// Own X25519 public key, URL-safe base64; empty if there was no exchange
+    pub public_key: String,
 }
 
 /// Generate a connection link URL
+/// The fragment carries the initiator's public key, never a secret
 #[flutter_rust_bridge::frb(sync)]
-pub fn generate_connection_link(base_url: String, rendezvous_id: String, secret: String) -> String {
+pub fn generate_connection_link(
+    base_url: String,
+    rendezvous_id: String,
+    public_key: String,
+) -> String {
     format!(
         "{}/connection/join?token={}#{}",
-        base_url, rendezvous_id, secret
+        base_url, rendezvous_id, public_key
     )
 }
 
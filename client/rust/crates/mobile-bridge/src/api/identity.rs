@@ -0,0 +1,28 @@
+use shared::identity::DeviceIdentity;
+
+/// Generate a new device identity key (Ed25519)
+/// Returns the hex-encoded secret; store it to keep the same device ID
+#[flutter_rust_bridge::frb(sync)]
+pub fn identity_generate() -> String {
+    hex::encode(DeviceIdentity::generate().secret())
+}
+
+/// Public key to register with, URL-safe base64
+#[flutter_rust_bridge::frb(sync)]
+pub fn identity_public_key(secret_hex: String) -> anyhow::Result<String> {
+    Ok(parse_identity(&secret_hex)?.public_key_b64())
+}
+
+/// Sign a registration challenge nonce from the signaling server
+#[flutter_rust_bridge::frb(sync)]
+pub fn identity_sign_registration(secret_hex: String, nonce: String) -> anyhow::Result<String> {
+    Ok(parse_identity(&secret_hex)?.sign_registration(&nonce))
+}
+
+fn parse_identity(secret_hex: &str) -> anyhow::Result<DeviceIdentity> {
+    let secret: [u8; 32] = hex::decode(secret_hex)
+        .map_err(|e| anyhow::anyhow!("Invalid identity hex: {}", e))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid identity length"))?;
+    Ok(DeviceIdentity::from_secret(&secret))
+}
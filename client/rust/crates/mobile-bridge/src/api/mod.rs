@@ -1,5 +1,6 @@
 pub mod client;
 pub mod connection;
+pub mod identity;
 pub mod models;
 pub mod share;
 pub mod simple;
@@ -6,6 +6,7 @@ use std::time::Duration;
 pub struct SignalingClientConfigDto {
     pub base_url: String,
     pub heartbeat_interval_secs: u64,
+    pub ice_servers: Vec<IceServerDto>,
 }
 
 impl SignalingClientConfigDto {
@@ -13,6 +14,7 @@ impl SignalingClientConfigDto {
         Self {
             base_url: base_url.into(),
             heartbeat_interval_secs: heartbeat_interval.as_secs(),
+            ice_servers: Vec::new(),
         }
     }
 }
@@ -22,6 +24,7 @@ impl From<shared::models::SignalingClientConfigDto> for SignalingClientConfigDto
         Self {
             base_url: s.base_url,
             heartbeat_interval_secs: s.heartbeat_interval_secs,
+            ice_servers: s.ice_servers.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -29,5 +32,34 @@ impl From<shared::models::SignalingClientConfigDto> for SignalingClientConfigDto
 impl From<SignalingClientConfigDto> for shared::models::SignalingClientConfigDto {
     fn from(s: SignalingClientConfigDto) -> Self {
         Self::new(s.base_url, Duration::from_secs(s.heartbeat_interval_secs))
+            .with_ice_servers(s.ice_servers.into_iter().map(Into::into).collect())
+    }
+}
+
+// Keep in sync with shared::models::IceServerDto.
+#[derive(Debug, Clone)]
+pub struct IceServerDto {
+    pub urls: Vec<String>,
+    pub username: Option<String>,
+    pub credential: Option<String>,
+}
+
+impl From<shared::models::IceServerDto> for IceServerDto {
+    fn from(s: shared::models::IceServerDto) -> Self {
+        Self {
+            urls: s.urls,
+            username: s.username,
+            credential: s.credential,
+        }
+    }
+}
+
+impl From<IceServerDto> for shared::models::IceServerDto {
+    fn from(s: IceServerDto) -> Self {
+        Self {
+            urls: s.urls,
+            username: s.username,
+            credential: s.credential,
+        }
     }
 }
@@ -0,0 +1,84 @@
+use anyhow::{bail, Context};
+use clap::{Args, Subcommand};
+use reqwest::{Method, StatusCode};
+use shared::models::ErrorResponse;
+
+/// Talks to a running server's `/admin` API.
+#[derive(Debug, Args)]
+pub struct AdminArgs {
+    /// Base URL of the server to manage
+    #[arg(
+        long,
+        env = "SIGNALING_ADMIN_URL",
+        default_value = "http://127.0.0.1:8080"
+    )]
+    url: String,
+
+    /// Bearer token the server was started with
+    #[arg(long, env = "SIGNALING_ADMIN_TOKEN", hide_env_values = true)]
+    token: String,
+
+    #[command(subcommand)]
+    command: AdminCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum AdminCommand {
+    /// Aggregate counts of clients, mailboxes and WebSockets
+    Stats,
+    /// List registered clients
+    Clients,
+    /// Drop a client and its queued signals
+    KickClient { client_id: String },
+    /// List live mailboxes with pairing state and expiry
+    Mailboxes,
+    /// Close a mailbox and its peer, disconnecting their WebSockets
+    CloseMailbox { mailbox_id: String },
+    /// Invalidate a rendezvous token that has not been joined yet
+    RevokeRendezvous { token: String },
+}
+
+pub async fn run(args: AdminArgs) -> anyhow::Result<()> {
+    let (method, path) = match &args.command {
+        AdminCommand::Stats => (Method::GET, "stats".to_string()),
+        AdminCommand::Clients => (Method::GET, "clients".to_string()),
+        AdminCommand::KickClient { client_id } => (Method::DELETE, format!("clients/{client_id}")),
+        AdminCommand::Mailboxes => (Method::GET, "mailboxes".to_string()),
+        AdminCommand::CloseMailbox { mailbox_id } => {
+            (Method::DELETE, format!("mailboxes/{mailbox_id}"))
+        }
+        AdminCommand::RevokeRendezvous { token } => (Method::DELETE, format!("rendezvous/{token}")),
+    };
+    let url = format!("{}/admin/{path}", args.url.trim_end_matches('/'));
+
+    let response = reqwest::Client::new()
+        .request(method, &url)
+        .bearer_auth(&args.token)
+        .send()
+        .await
+        .with_context(|| format!("request to {url} failed"))?;
+    let status = response.status();
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        // Fall back to the raw text for bodies that are not an `ErrorResponse`,
+        // e.g. from a proxy in front of the server
+        match serde_json::from_str::<ErrorResponse>(&body) {
+            Ok(error) => bail!(
+                "{status} {:?}: {} (request {})",
+                error.code,
+                error.message,
+                error.request_id
+            ),
+            Err(_) => bail!("{status}: {body}"),
+        }
+    }
+    if status == StatusCode::NO_CONTENT {
+        println!("done");
+        return Ok(());
+    }
+    let value: serde_json::Value =
+        serde_json::from_str(&body).context("server returned invalid JSON")?;
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
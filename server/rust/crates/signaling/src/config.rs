@@ -1,8 +1,20 @@
+mod sources;
+
+pub use sources::{ConfigError, ConfigSources, CONFIG_PATH_VAR};
+
+use crate::rate_limit::RateLimitPolicies;
+use crate::repository::mailbox_store::MailboxQuota;
+use crate::repository::rate_limit_store::RateLimitPolicy;
+use crate::services::host_service::HostLockoutPolicy;
 use base64::Engine as _;
-use shared::models::SignalingClientConfigDto;
+use ipnet::IpNet;
+use shared::models::{IceServerDto, SignalingClientConfigDto};
+use sources::Loader;
 use std::{
-    env,
+    env, fmt,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     time::Duration,
 };
 
@@ -10,6 +22,7 @@ const DEFAULT_LISTEN_PORT: u16 = 8080;
 const DEFAULT_PUBLIC_URL: &str = "http://127.0.0.1:8080";
 const DEFAULT_SESSION_TTL_SECS: u64 = 30;
 const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 30;
+const DEFAULT_SESSION_TOKEN_TTL_SECS: u64 = 3600;
 const DEFAULT_REDIS_URL: &str = "redis://127.0.0.1/";
 const DEFAULT_MAILBOX_TTL_SECS: u64 = 30;
 const DEFAULT_REDIS_REQUIRE_TLS: bool = true;
@@ -17,13 +30,187 @@ const DEFAULT_REDIS_KEY_PREFIX: &str = "sig";
 const DEFAULT_JOINED_FLAG_TTL_SECS: u64 = 60;
 const DEFAULT_RENDEZVOUS_TTL_SECS: u64 = 30;
 const DEFAULT_REDIS_ENCRYPT: bool = false;
+const DEFAULT_REDIS_ENC_KEY_ID: &str = "primary";
+const DEFAULT_REDIS_ENC_ALLOW_PLAINTEXT: bool = false;
 const DEFAULT_WS_PUSH_BUFFER_CAPACITY: usize = 100;
+const DEFAULT_WS_PING_INTERVAL_SECS: u64 = 20;
+const DEFAULT_WS_MAX_SUBSCRIBERS_PER_MAILBOX: usize = 4;
+const DEFAULT_WS_IDLE_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+const DEFAULT_MAILBOX_MAX_MESSAGES: u64 = 256;
+const DEFAULT_MAILBOX_MAX_BYTES: u64 = 1024 * 1024;
+const DEFAULT_MAILBOX_STORE: StoreBackend = StoreBackend::Redis;
+const DEFAULT_SESSION_STORE: StoreBackend = StoreBackend::Memory;
+const DEFAULT_RATE_LIMIT_ENABLED: bool = true;
+const DEFAULT_METRICS_ENABLED: bool = true;
+const DEFAULT_SHUTDOWN_GRACE_SECS: u64 = 10;
+const MIN_ADMIN_TOKEN_LEN: usize = 32;
+const MIN_TURN_SECRET_LEN: usize = 16;
+const DEFAULT_TURN_CREDENTIAL_TTL_SECS: u64 = 24 * 60 * 60;
+const DEFAULT_TURN_REALM: &str = "signaling";
+const DEFAULT_TURN_MAX_ALLOCATIONS: usize = 4;
+const DEFAULT_TURN_BANDWIDTH_BYTES_PER_SEC: u64 = 2 * 1024 * 1024;
+/// Private networks behind the relay: RFC 1918 and IPv6 unique local addresses.
+const DEFAULT_TURN_DENIED_PEER_RANGES: &[&str] =
+    &["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"];
+const DEFAULT_HOST_MAX_FAILED_ATTEMPTS: u32 = 5;
+const DEFAULT_HOST_LOCKOUT_SECS: u64 = 15 * 60;
+const DEFAULT_RATE_LIMIT_REGISTER: RateLimitPolicy = RateLimitPolicy {
+    burst: 10,
+    period: Duration::from_secs(60),
+};
+const DEFAULT_RATE_LIMIT_INIT: RateLimitPolicy = RateLimitPolicy {
+    burst: 30,
+    period: Duration::from_secs(60),
+};
+const DEFAULT_RATE_LIMIT_JOIN: RateLimitPolicy = RateLimitPolicy {
+    burst: 10,
+    period: Duration::from_secs(60),
+};
+const DEFAULT_RATE_LIMIT_NAMEPLATE_JOIN: RateLimitPolicy = RateLimitPolicy {
+    burst: 5,
+    period: Duration::from_secs(300),
+};
+const DEFAULT_RATE_LIMIT_HOST_CONNECT: RateLimitPolicy = RateLimitPolicy {
+    burst: 10,
+    period: Duration::from_secs(60),
+};
+
+/// Where a server-side store (mailboxes, sessions) keeps its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreBackend {
+    /// Shared Redis instance; required when running more than one replica.
+    Redis,
+    /// Process-local maps with TTL expiry; no Redis needed.
+    Memory,
+}
+
+impl std::str::FromStr for StoreBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        match raw.to_lowercase().as_str() {
+            "redis" => Ok(Self::Redis),
+            "memory" | "in-memory" | "inmemory" => Ok(Self::Memory),
+            other => anyhow::bail!("unknown store backend: {other}"),
+        }
+    }
+}
+
+/// PEM files for serving HTTPS directly instead of behind a reverse proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsSettings {
+    /// Leaf certificate followed by any intermediates.
+    pub cert_path: PathBuf,
+    /// PKCS#8, PKCS#1 or SEC1 private key.
+    pub key_path: PathBuf,
+}
+
+/// The embedded TURN relay (RFC 5766 allocations over UDP).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnRelaySettings {
+    /// UDP address clients send Allocate requests and ChannelData to.
+    pub listen_addr: SocketAddr,
+    /// Address written into XOR-RELAYED-ADDRESS; peers send to it.
+    pub relay_ip: IpAddr,
+    /// Ports relay sockets are bound from; any free port when `None`.
+    pub relay_ports: Option<RangeInclusive<u16>>,
+    pub realm: String,
+    /// Live allocations one registered client may hold at once.
+    pub max_allocations_per_session: usize,
+    /// Relayed bytes per second, both directions, across a client's allocations.
+    pub bandwidth_bytes_per_sec: u64,
+    /// Peers refused on top of the loopback, unspecified, multicast and
+    /// link-local addresses the relay never sends to.
+    pub denied_peer_ranges: Vec<IpNet>,
+}
+
+/// Bearer token for the `/admin` API. Kept out of `Debug` output so it never
+/// reaches the logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminToken(String);
+
+impl AdminToken {
+    pub fn new(token: &str) -> Result<Self, String> {
+        if token.len() < MIN_ADMIN_TOKEN_LEN {
+            return Err(format!(
+                "must be at least {MIN_ADMIN_TOKEN_LEN} characters (e.g. `openssl rand -hex 32`)"
+            ));
+        }
+        Ok(Self(token.to_string()))
+    }
+
+    pub fn matches(&self, presented: &str) -> bool {
+        shared::connection::constant_time_eq(self.0.as_bytes(), presented.as_bytes())
+    }
+}
+
+impl fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AdminToken(<redacted>)")
+    }
+}
+
+/// Shared secret for TURN REST credentials (coturn's `static-auth-secret`).
+/// Kept out of `Debug` output so it never reaches the logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TurnSecret(String);
+
+impl TurnSecret {
+    pub fn new(secret: &str) -> Result<Self, String> {
+        if secret.len() < MIN_TURN_SECRET_LEN {
+            return Err(format!("must be at least {MIN_TURN_SECRET_LEN} characters"));
+        }
+        Ok(Self(secret.to_string()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl fmt::Debug for TurnSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TurnSecret(<redacted>)")
+    }
+}
+
+/// AES-256 key for payloads stored in Redis. Kept out of `Debug` output so it
+/// never reaches the logs.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+
+    pub fn bytes(&self) -> [u8; 32] {
+        self.0
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+/// A validated configuration plus warnings about settings that were ignored.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: SignalingServerConfig,
+    pub warnings: Vec<String>,
+}
 
 #[derive(Debug, Clone)]
 pub struct SignalingServerConfig {
     pub listen_addr: SocketAddr,
     pub public_base_url: String,
     pub session_ttl: Duration,
+    /// Lifetime of a session token; devices register again with their key to
+    /// get a new one.
+    pub session_token_ttl: Duration,
     pub heartbeat_interval: Duration,
     pub redis_url: String,
     pub mailbox_ttl: Duration,
@@ -32,101 +219,410 @@ pub struct SignalingServerConfig {
     pub joined_flag_ttl: Duration,
     pub rendezvous_ttl: Duration,
     pub redis_encrypt_payloads: bool,
-    pub redis_encryption_key: Option<[u8; 32]>,
+    pub redis_encryption_key: Option<EncryptionKey>,
+    pub redis_encryption_key_id: String,
+    pub redis_decryption_keys: Vec<(String, EncryptionKey)>,
+    /// Migration aid: read values written before encryption was switched on.
+    pub redis_encrypt_allow_plaintext: bool,
     pub ws_push_buffer_capacity: usize,
+    pub ws_ping_interval: Duration,
+    pub ws_idle_timeout: Duration,
+    pub ws_max_subscribers_per_mailbox: usize,
+    pub mailbox_store: StoreBackend,
+    pub session_store: StoreBackend,
+    pub push_backend: StoreBackend,
+    pub max_message_bytes: u64,
+    pub mailbox_max_messages: u64,
+    pub mailbox_max_bytes: u64,
+    pub rate_limit_enabled: bool,
+    pub rate_limit_store: StoreBackend,
+    pub rate_limit_register: RateLimitPolicy,
+    pub rate_limit_init: RateLimitPolicy,
+    pub rate_limit_join: RateLimitPolicy,
+    pub rate_limit_nameplate_join: RateLimitPolicy,
+    pub rate_limit_host_connect: RateLimitPolicy,
+    pub trusted_proxies: Vec<IpNet>,
+    pub metrics_enabled: bool,
+    pub metrics_addr: Option<SocketAddr>,
+    pub shutdown_grace_period: Duration,
+    pub tls: Option<TlsSettings>,
+    pub admin_token: Option<AdminToken>,
+    pub stun_urls: Vec<String>,
+    pub stun_addr: Option<SocketAddr>,
+    pub turn_urls: Vec<String>,
+    pub turn_secret: Option<TurnSecret>,
+    pub turn_credential_ttl: Duration,
+    pub turn_relay: Option<TurnRelaySettings>,
+    /// Wrong passwords that lock a host ID, counted over `host_lockout`.
+    pub host_max_failed_attempts: u32,
+    pub host_lockout: Duration,
 }
 
 impl SignalingServerConfig {
-    pub fn from_env() -> anyhow::Result<Self> {
-        let listen_port = env::var("SIGNALING_PORT")
-            .ok()
-            .and_then(|raw| raw.parse::<u16>().ok())
-            .unwrap_or(DEFAULT_LISTEN_PORT);
+    /// Loads the configuration from the `SIGNALING_*` environment variables and
+    /// the file named by `SIGNALING_CONFIG`, if any, logging any warnings.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let path = env::var_os(CONFIG_PATH_VAR).map(PathBuf::from);
+        let loaded = Self::load(path.as_deref())?;
+        for warning in &loaded.warnings {
+            tracing::warn!("{warning}");
+        }
+        Ok(loaded.config)
+    }
 
-        let listen_addr = env::var("SIGNALING_ADDR")
-            .ok()
-            .and_then(|raw| raw.parse::<IpAddr>().ok())
-            .map(|ip| SocketAddr::new(ip, listen_port))
-            .unwrap_or(SocketAddr::new(
-                IpAddr::V4(Ipv4Addr::LOCALHOST),
-                listen_port,
-            ));
+    /// Loads the configuration from `path` (if given) overridden by the
+    /// process's `SIGNALING_*` environment variables.
+    pub fn load(path: Option<&Path>) -> Result<LoadedConfig, ConfigError> {
+        Self::from_sources(&ConfigSources::from_process(path)?)
+    }
 
-        let public_base_url =
-            env::var("SIGNALING_PUBLIC_URL").unwrap_or_else(|_| DEFAULT_PUBLIC_URL.to_string());
+    /// Builds and validates the configuration. Every invalid or unknown setting
+    /// is reported in the error, not just the first.
+    pub fn from_sources(sources: &ConfigSources) -> Result<LoadedConfig, ConfigError> {
+        let mut settings = Loader::new(sources);
 
-        let session_ttl = env::var("SIGNALING_SESSION_TTL_SECS")
-            .ok()
-            .and_then(|raw| raw.parse::<u64>().ok())
-            .map(Duration::from_secs)
+        let listen_port = settings
+            .parsed::<u16>("port")
+            .unwrap_or(DEFAULT_LISTEN_PORT);
+        let listen_ip = settings
+            .parsed::<IpAddr>("addr")
+            .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let listen_addr = SocketAddr::new(listen_ip, listen_port);
+
+        let public_base_url = settings
+            .value("public_url", |raw| {
+                if raw.starts_with("http://") || raw.starts_with("https://") {
+                    Ok(raw.trim_end_matches('/').to_string())
+                } else {
+                    Err("expected an http:// or https:// URL".to_string())
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_PUBLIC_URL.to_string());
+
+        let session_ttl = settings
+            .positive_seconds("session_ttl_secs")
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_SESSION_TTL_SECS));
 
-        let heartbeat_interval = env::var("SIGNALING_HEARTBEAT_SECS")
-            .ok()
-            .and_then(|raw| raw.parse::<u64>().ok())
-            .map(Duration::from_secs)
+        let session_token_ttl = settings
+            .positive_seconds("session_token_ttl_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_SESSION_TOKEN_TTL_SECS));
+
+        let heartbeat_interval = settings
+            .positive_seconds("heartbeat_secs")
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL_SECS));
 
-        let redis_url =
-            env::var("SIGNALING_REDIS_URL").unwrap_or_else(|_| DEFAULT_REDIS_URL.to_string());
-        let mailbox_ttl = env::var("SIGNALING_MAILBOX_TTL_SECS")
-            .ok()
-            .and_then(|raw| raw.parse::<u64>().ok())
-            .map(Duration::from_secs)
+        let redis_url = settings
+            .value("redis_url", |raw| {
+                if raw.starts_with("redis://") || raw.starts_with("rediss://") {
+                    Ok(raw.to_string())
+                } else {
+                    Err("expected a redis:// or rediss:// URL".to_string())
+                }
+            })
+            .unwrap_or_else(|| DEFAULT_REDIS_URL.to_string());
+
+        let mailbox_ttl = settings
+            .positive_seconds("mailbox_ttl_secs")
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_MAILBOX_TTL_SECS));
 
         // SECURITY: Require TLS for Redis by default. Can be disabled for local dev only.
-        let redis_require_tls = env::var("SIGNALING_REDIS_REQUIRE_TLS")
-            .ok()
-            .and_then(|raw| match raw.to_lowercase().as_str() {
-                "1" | "true" | "yes" => Some(true),
-                "0" | "false" | "no" => Some(false),
-                _ => None,
-            })
+        let redis_require_tls = settings
+            .flag("redis_require_tls")
             .unwrap_or(DEFAULT_REDIS_REQUIRE_TLS);
 
         // Namespace all keys so Redis ACLs can be scoped safely
-        let redis_key_prefix = env::var("SIGNALING_REDIS_KEY_PREFIX")
-            .unwrap_or_else(|_| DEFAULT_REDIS_KEY_PREFIX.to_string());
-
-        // TTL for the short-lived "joined" flag
-        let joined_flag_ttl = env::var("SIGNALING_JOINED_FLAG_TTL_SECS")
-            .ok()
-            .and_then(|raw| raw.parse::<u64>().ok())
-            .map(Duration::from_secs)
+        let redis_key_prefix = settings
+            .string("redis_key_prefix")
+            .unwrap_or_else(|| DEFAULT_REDIS_KEY_PREFIX.to_string());
+
+        // How long a redeemed rendezvous token is remembered as used
+        let joined_flag_ttl = settings
+            .positive_seconds("joined_flag_ttl_secs")
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_JOINED_FLAG_TTL_SECS));
 
-        let rendezvous_ttl = env::var("SIGNALING_RENDEZVOUS_TTL_SECS")
-            .ok()
-            .and_then(|raw| raw.parse::<u64>().ok())
-            .map(Duration::from_secs)
+        let rendezvous_ttl = settings
+            .positive_seconds("rendezvous_ttl_secs")
             .unwrap_or_else(|| Duration::from_secs(DEFAULT_RENDEZVOUS_TTL_SECS));
 
         // Application-level encryption (optional)
-        let redis_encrypt_payloads = env::var("SIGNALING_REDIS_ENCRYPT")
-            .ok()
-            .and_then(|raw| match raw.to_lowercase().as_str() {
-                "1" | "true" | "yes" => Some(true),
-                "0" | "false" | "no" => Some(false),
-                _ => None,
-            })
+        let redis_encrypt_payloads = settings
+            .flag("redis_encrypt")
             .unwrap_or(DEFAULT_REDIS_ENCRYPT);
 
-        let redis_encryption_key = env::var("SIGNALING_REDIS_ENC_KEY_B64")
-            .ok()
-            .and_then(|b64| base64::engine::general_purpose::STANDARD.decode(b64).ok())
-            .and_then(|bytes| bytes.try_into().ok());
+        let redis_encryption_key = settings.secret("redis_enc_key_b64", decode_encryption_key);
+
+        // Stored values are tagged with the key ID so keys can be rotated: move the
+        // old key into SIGNALING_REDIS_ENC_PREVIOUS_KEYS as `id:base64` until its
+        // mailboxes have expired.
+        let redis_encryption_key_id = settings
+            .string("redis_enc_key_id")
+            .unwrap_or_else(|| DEFAULT_REDIS_ENC_KEY_ID.to_string());
 
-        let ws_push_buffer_capacity = env::var("SIGNALING_WS_PUSH_BUFFER_CAPACITY")
-            .ok()
-            .and_then(|raw| raw.parse::<usize>().ok())
-            .filter(|value| *value > 0)
+        let redis_decryption_keys = settings
+            .secret_list("redis_enc_previous_keys", |entry| {
+                let (key_id, b64) = entry
+                    .split_once(':')
+                    .ok_or_else(|| "expected `id:base64`".to_string())?;
+                Ok((key_id.to_string(), decode_encryption_key(b64)?))
+            })
+            .unwrap_or_default();
+
+        // Only while switching encryption on for a store that still holds
+        // plaintext mailboxes; otherwise anything unsealed is rejected
+        let redis_encrypt_allow_plaintext = settings
+            .flag("redis_enc_allow_plaintext")
+            .unwrap_or(DEFAULT_REDIS_ENC_ALLOW_PLAINTEXT);
+
+        let ws_push_buffer_capacity = settings
+            .positive::<usize>("ws_push_buffer_capacity")
             .unwrap_or(DEFAULT_WS_PUSH_BUFFER_CAPACITY);
 
-        Ok(Self {
+        // Sockets that answer nothing (not even a pong) for two ping intervals are
+        // treated as dead; live sockets with no traffic are closed after the idle timeout
+        let ws_ping_interval = settings
+            .positive_seconds("ws_ping_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_WS_PING_INTERVAL_SECS));
+
+        let ws_idle_timeout = settings
+            .positive_seconds("ws_idle_timeout_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_WS_IDLE_TIMEOUT_SECS));
+
+        // A mailbox has one legitimate owner; the headroom covers reconnect overlap
+        // and a second device, not a crowd of watchers holding a leaked ID
+        let ws_max_subscribers_per_mailbox = settings
+            .positive::<usize>("ws_max_subscribers_per_mailbox")
+            .unwrap_or(DEFAULT_WS_MAX_SUBSCRIBERS_PER_MAILBOX);
+
+        let mailbox_store = settings
+            .parsed::<StoreBackend>("mailbox_store")
+            .unwrap_or(DEFAULT_MAILBOX_STORE);
+
+        // Client records must live in Redis when running several replicas
+        let session_store = settings
+            .parsed::<StoreBackend>("session_store")
+            .unwrap_or(DEFAULT_SESSION_STORE);
+
+        // WebSocket push follows the mailbox store unless set explicitly, so
+        // Redis-backed mailboxes get cross-instance delivery by default
+        let push_backend = settings
+            .parsed::<StoreBackend>("push_backend")
+            .unwrap_or(mailbox_store);
+
+        // Per-mailbox quotas keep one mailbox from filling Redis (and, with
+        // allkeys-lru, evicting everyone else's sessions)
+        let max_message_bytes = settings
+            .positive::<u64>("max_message_bytes")
+            .unwrap_or(DEFAULT_MAX_MESSAGE_BYTES);
+
+        let mailbox_max_messages = settings
+            .positive::<u64>("mailbox_max_messages")
+            .unwrap_or(DEFAULT_MAILBOX_MAX_MESSAGES);
+
+        let mailbox_max_bytes = settings
+            .positive::<u64>("mailbox_max_bytes")
+            .unwrap_or(DEFAULT_MAILBOX_MAX_BYTES);
+
+        let rate_limit_enabled = settings
+            .flag("rate_limit_enabled")
+            .unwrap_or(DEFAULT_RATE_LIMIT_ENABLED);
+
+        // Buckets must be shared for limits to hold across replicas
+        let rate_limit_store = settings
+            .parsed::<StoreBackend>("rate_limit_store")
+            .unwrap_or(session_store);
+
+        // Limits are `<requests>/<seconds>`: a burst of that many, refilled evenly
+        let rate_limit_register = settings
+            .parsed::<RateLimitPolicy>("rate_limit_register")
+            .unwrap_or(DEFAULT_RATE_LIMIT_REGISTER);
+
+        let rate_limit_init = settings
+            .parsed::<RateLimitPolicy>("rate_limit_init")
+            .unwrap_or(DEFAULT_RATE_LIMIT_INIT);
+
+        let rate_limit_join = settings
+            .parsed::<RateLimitPolicy>("rate_limit_join")
+            .unwrap_or(DEFAULT_RATE_LIMIT_JOIN);
+
+        let rate_limit_nameplate_join = settings
+            .parsed::<RateLimitPolicy>("rate_limit_nameplate_join")
+            .unwrap_or(DEFAULT_RATE_LIMIT_NAMEPLATE_JOIN);
+
+        // Per IP, across host IDs; the per-host lockout below covers guesses
+        // spread over many IPs
+        let rate_limit_host_connect = settings
+            .parsed::<RateLimitPolicy>("rate_limit_host_connect")
+            .unwrap_or(DEFAULT_RATE_LIMIT_HOST_CONNECT);
+
+        // SECURITY: X-Forwarded-For is ignored unless the direct peer is listed
+        // here, e.g. the Caddy container network
+        let trusted_proxies = settings
+            .list("trusted_proxies", parse_ip_net)
+            .unwrap_or_default();
+
+        let metrics_enabled = settings
+            .flag("metrics_enabled")
+            .unwrap_or(DEFAULT_METRICS_ENABLED);
+
+        // When set, /metrics is only served on this address (e.g. an internal
+        // interface) instead of alongside the public API
+        let metrics_addr = settings.parsed::<SocketAddr>("metrics_addr");
+
+        // How long in-flight requests get to finish after SIGTERM/SIGINT; keep it
+        // below the orchestrator's kill timeout (10s for `docker stop`)
+        let shutdown_grace_period = settings
+            .seconds("shutdown_grace_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_SHUTDOWN_GRACE_SECS));
+
+        // TLS is optional: the Docker deployment terminates it in Caddy, single-binary
+        // installs can point these at a certificate and key
+        let tls = match (
+            settings.string("tls_cert_path"),
+            settings.string("tls_key_path"),
+        ) {
+            (Some(cert_path), Some(key_path)) => Some(TlsSettings {
+                cert_path: PathBuf::from(cert_path),
+                key_path: PathBuf::from(key_path),
+            }),
+            (None, None) => None,
+            _ => {
+                settings.problem(
+                    "SIGNALING_TLS_CERT_PATH and SIGNALING_TLS_KEY_PATH must be set together",
+                );
+                None
+            }
+        };
+
+        // The /admin API is only mounted when a token is configured
+        let admin_token = settings.secret("admin_token", AdminToken::new);
+
+        // Handed to clients as their ICE server list. TURN entries get coturn-style
+        // REST credentials (`use-auth-secret`) derived from the shared secret
+        let mut stun_urls = settings
+            .list("stun_urls", |entry| ice_url(entry, &["stun:", "stuns:"]))
+            .unwrap_or_default();
+
+        // Built-in STUN responder on UDP, advertised to clients at the public
+        // URL's host so a single binary works without any third-party server
+        let stun_addr = settings.parsed::<SocketAddr>("stun_addr");
+        if let Some(addr) = stun_addr {
+            if addr.port() == 0 {
+                settings.problem("SIGNALING_STUN_ADDR needs a fixed port to be advertised");
+            } else if let Some(host) = url_host(&public_base_url) {
+                let advertised = format!("stun:{host}:{}", addr.port());
+                if !stun_urls.contains(&advertised) {
+                    stun_urls.insert(0, advertised);
+                }
+            }
+        }
+
+        let mut turn_urls = settings
+            .list("turn_urls", |entry| ice_url(entry, &["turn:", "turns:"]))
+            .unwrap_or_default();
+
+        let turn_secret = settings.secret("turn_secret", TurnSecret::new);
+
+        // Long enough to outlast a session: coturn checks the expiry again on
+        // every allocation refresh
+        let turn_credential_ttl = settings
+            .positive_seconds("turn_credential_ttl_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_TURN_CREDENTIAL_TTL_SECS));
+
+        if !turn_urls.is_empty() && turn_secret.is_none() {
+            settings.problem("SIGNALING_TURN_URLS is set but SIGNALING_TURN_SECRET is not");
+        }
+
+        // Embedded TURN relay, so symmetric NATs on both ends still connect
+        // without running coturn. It accepts the same REST credentials as above
+        let turn_addr = settings.parsed::<SocketAddr>("turn_addr");
+        let turn_external_ip = settings.parsed::<IpAddr>("turn_external_ip");
+        let turn_relay_ports = settings.value("turn_relay_ports", parse_port_range);
+        let turn_realm = settings.string("turn_realm");
+        let turn_max_allocations = settings
+            .positive::<usize>("turn_max_allocations")
+            .unwrap_or(DEFAULT_TURN_MAX_ALLOCATIONS);
+        let turn_bandwidth_bytes_per_sec = settings
+            .positive::<u64>("turn_bandwidth_bytes_per_sec")
+            .unwrap_or(DEFAULT_TURN_BANDWIDTH_BYTES_PER_SEC);
+        // SECURITY: keeps clients from using the relay to reach the host's own
+        // network; `none` allows every peer that is not a special address
+        let turn_denied_peer_ranges: Vec<IpNet> = settings
+            .list("turn_denied_peer_ranges", |entry| match entry {
+                "none" => Ok(None),
+                _ => parse_ip_net(entry).map(Some),
+            })
+            .map(|ranges| ranges.into_iter().flatten().collect())
+            .unwrap_or_else(|| {
+                DEFAULT_TURN_DENIED_PEER_RANGES
+                    .iter()
+                    .map(|range| range.parse().expect("default ranges are valid"))
+                    .collect()
+            });
+
+        let turn_relay = turn_addr.and_then(|addr| {
+            if turn_secret.is_none() {
+                settings.problem("SIGNALING_TURN_ADDR is set but SIGNALING_TURN_SECRET is not");
+            }
+            if addr.port() == 0 {
+                settings.problem("SIGNALING_TURN_ADDR needs a fixed port to be advertised");
+            }
+            if stun_addr.is_some_and(|stun| stun.port() == addr.port()) {
+                settings.problem(
+                    "SIGNALING_STUN_ADDR and SIGNALING_TURN_ADDR must use different ports; the TURN listener answers Binding requests too",
+                );
+            }
+            let relay_ip = match turn_external_ip {
+                Some(ip) => ip,
+                None if addr.ip().is_unspecified() => {
+                    settings.problem(
+                        "SIGNALING_TURN_EXTERNAL_IP is required when SIGNALING_TURN_ADDR listens on all interfaces",
+                    );
+                    return None;
+                }
+                None => addr.ip(),
+            };
+            if let Some(host) = url_host(&public_base_url) {
+                let advertised = format!("turn:{host}:{}?transport=udp", addr.port());
+                if !turn_urls.contains(&advertised) {
+                    turn_urls.insert(0, advertised);
+                }
+            }
+            Some(TurnRelaySettings {
+                listen_addr: addr,
+                relay_ip,
+                relay_ports: turn_relay_ports,
+                realm: turn_realm.unwrap_or_else(|| DEFAULT_TURN_REALM.to_string()),
+                max_allocations_per_session: turn_max_allocations,
+                bandwidth_bytes_per_sec: turn_bandwidth_bytes_per_sec,
+                denied_peer_ranges: turn_denied_peer_ranges,
+            })
+        });
+
+        if redis_encrypt_payloads && redis_encryption_key.is_none() {
+            settings.problem(
+                "SIGNALING_REDIS_ENCRYPT is enabled but SIGNALING_REDIS_ENC_KEY_B64 is not set to a valid key",
+            );
+        }
+        if metrics_addr.is_some_and(|addr| addr == listen_addr) {
+            settings.problem("SIGNALING_METRICS_ADDR must differ from the main listen address");
+        }
+
+        // Unattended hosts: this many wrong passwords within the lockout period
+        // lock the host ID for the rest of it
+        let host_max_failed_attempts = settings
+            .positive::<u32>("host_max_failed_attempts")
+            .unwrap_or(DEFAULT_HOST_MAX_FAILED_ATTEMPTS);
+
+        let host_lockout = settings
+            .positive_seconds("host_lockout_secs")
+            .unwrap_or_else(|| Duration::from_secs(DEFAULT_HOST_LOCKOUT_SECS));
+
+        let warnings = settings.finish()?;
+        let config = Self {
             listen_addr,
             public_base_url,
             session_ttl,
+            session_token_ttl,
             heartbeat_interval,
             redis_url,
             mailbox_ttl,
@@ -136,31 +632,134 @@ impl SignalingServerConfig {
             rendezvous_ttl,
             redis_encrypt_payloads,
             redis_encryption_key,
+            redis_encryption_key_id,
+            redis_decryption_keys,
+            redis_encrypt_allow_plaintext,
             ws_push_buffer_capacity,
-        })
+            ws_ping_interval,
+            ws_idle_timeout,
+            ws_max_subscribers_per_mailbox,
+            mailbox_store,
+            session_store,
+            push_backend,
+            max_message_bytes,
+            mailbox_max_messages,
+            mailbox_max_bytes,
+            rate_limit_enabled,
+            rate_limit_store,
+            rate_limit_register,
+            rate_limit_init,
+            rate_limit_join,
+            rate_limit_nameplate_join,
+            rate_limit_host_connect,
+            trusted_proxies,
+            metrics_enabled,
+            metrics_addr,
+            shutdown_grace_period,
+            tls,
+            admin_token,
+            stun_urls,
+            stun_addr,
+            turn_urls,
+            turn_secret,
+            turn_credential_ttl,
+            turn_relay,
+            host_max_failed_attempts,
+            host_lockout,
+        };
+        Ok(LoadedConfig { config, warnings })
     }
 
     pub fn client_config(&self) -> SignalingClientConfigDto {
+        let ice_servers = if self.stun_urls.is_empty() {
+            Vec::new()
+        } else {
+            vec![IceServerDto::without_credentials(self.stun_urls.clone())]
+        };
         SignalingClientConfigDto::new(&self.public_base_url, self.heartbeat_interval)
+            .with_ice_servers(ice_servers)
+    }
+
+    pub fn mailbox_quota(&self) -> MailboxQuota {
+        MailboxQuota {
+            max_message_bytes: self.max_message_bytes,
+            max_messages: self.mailbox_max_messages,
+            max_bytes: self.mailbox_max_bytes,
+        }
+    }
+
+    pub fn rate_limit_policies(&self) -> RateLimitPolicies {
+        RateLimitPolicies {
+            register: self.rate_limit_register,
+            connection_init: self.rate_limit_init,
+            connection_join: self.rate_limit_join,
+            nameplate_join: self.rate_limit_nameplate_join,
+            host_connect: self.rate_limit_host_connect,
+        }
+    }
+
+    pub fn host_lockout_policy(&self) -> HostLockoutPolicy {
+        HostLockoutPolicy {
+            max_failed_attempts: self.host_max_failed_attempts,
+            lockout: self.host_lockout,
+        }
+    }
+}
+
+/// The host part of an `http(s)://host[:port][/path]` URL, IPv6 brackets kept.
+fn url_host(url: &str) -> Option<&str> {
+    let authority = url.split_once("://")?.1.split('/').next()?;
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => &authority[..rest.find(']')? + 2],
+        None => authority.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+fn ice_url(entry: &str, schemes: &[&str]) -> Result<String, String> {
+    if schemes.iter().any(|scheme| entry.starts_with(scheme)) {
+        Ok(entry.to_string())
+    } else {
+        Err(format!("expected a {} URL", schemes.join(" or ")))
     }
 }
 
+/// `low-high`, both inclusive, e.g. `49152-65535`.
+fn parse_port_range(raw: &str) -> Result<RangeInclusive<u16>, String> {
+    let (low, high) = raw
+        .split_once('-')
+        .ok_or_else(|| "expected a range like 49152-65535".to_string())?;
+    let low: u16 = low.trim().parse().map_err(|err| format!("{err}"))?;
+    let high: u16 = high.trim().parse().map_err(|err| format!("{err}"))?;
+    if low == 0 || low > high {
+        return Err("expected a non-empty range of non-zero ports".to_string());
+    }
+    Ok(low..=high)
+}
+
+fn decode_encryption_key(b64: &str) -> Result<EncryptionKey, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64.trim())
+        // The decoder's message names the offending character, so leave it out
+        .map_err(|_| "expected standard base64".to_string())?;
+    bytes
+        .try_into()
+        .map(EncryptionKey)
+        .map_err(|bytes: Vec<u8>| format!("expected 32 bytes, got {}", bytes.len()))
+}
+
+fn parse_ip_net(entry: &str) -> Result<IpNet, String> {
+    entry
+        .parse::<IpNet>()
+        .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| "expected an IP or CIDR".to_string())
+}
+
+/// The built-in defaults, ignoring the environment.
 impl Default for SignalingServerConfig {
     fn default() -> Self {
-        Self::from_env().unwrap_or_else(|_| Self {
-            listen_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), DEFAULT_LISTEN_PORT),
-            public_base_url: DEFAULT_PUBLIC_URL.to_string(),
-            session_ttl: Duration::from_secs(DEFAULT_SESSION_TTL_SECS),
-            heartbeat_interval: Duration::from_secs(DEFAULT_HEARTBEAT_INTERVAL_SECS),
-            redis_url: DEFAULT_REDIS_URL.to_string(),
-            mailbox_ttl: Duration::from_secs(DEFAULT_MAILBOX_TTL_SECS),
-            redis_require_tls: DEFAULT_REDIS_REQUIRE_TLS,
-            redis_key_prefix: DEFAULT_REDIS_KEY_PREFIX.to_string(),
-            joined_flag_ttl: Duration::from_secs(DEFAULT_JOINED_FLAG_TTL_SECS),
-            rendezvous_ttl: Duration::from_secs(DEFAULT_RENDEZVOUS_TTL_SECS),
-            redis_encrypt_payloads: DEFAULT_REDIS_ENCRYPT,
-            redis_encryption_key: None,
-            ws_push_buffer_capacity: DEFAULT_WS_PUSH_BUFFER_CAPACITY,
-        })
+        Self::from_sources(&ConfigSources::default())
+            .expect("built-in defaults are valid")
+            .config
     }
 }
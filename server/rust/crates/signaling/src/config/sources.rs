@@ -0,0 +1,379 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Prefix shared by every environment variable the server reads.
+const ENV_PREFIX: &str = "SIGNALING_";
+
+/// Names the config file itself rather than a setting inside it.
+pub const CONFIG_PATH_VAR: &str = "SIGNALING_CONFIG";
+
+/// Variables older deployments set that no longer do anything, with what replaced them.
+const LEGACY_VARIABLES: &[(&str, &str)] = &[(
+    "SIGNALING_ROOM_TTL_SECS",
+    "rooms were replaced by mailboxes; use SIGNALING_MAILBOX_TTL_SECS and SIGNALING_RENDEZVOUS_TTL_SECS",
+)];
+
+/// Every problem found while loading the configuration, reported together so a
+/// deployment can be fixed in one pass.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid configuration:\n  - {}", .problems.join("\n  - "))]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl ConfigError {
+    fn single(problem: String) -> Self {
+        Self {
+            problems: vec![problem],
+        }
+    }
+}
+
+/// The raw layers a configuration is built from: an optional TOML or YAML file,
+/// overridden by `SIGNALING_*` environment variables.
+///
+/// File keys are the variable names without the prefix, in lower case. Tables
+/// are flattened with `_`, so `[redis] url = ...` is the same setting as
+/// `SIGNALING_REDIS_URL`, and arrays become comma-separated lists.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    file: Option<ConfigFile>,
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+struct ConfigFile {
+    path: PathBuf,
+    values: BTreeMap<String, String>,
+}
+
+impl ConfigSources {
+    /// Reads `SIGNALING_*` variables from the process and, if given, the file at `path`.
+    pub fn from_process(path: Option<&Path>) -> Result<Self, ConfigError> {
+        let sources = Self {
+            file: None,
+            env: std::env::vars()
+                .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+                .collect(),
+        };
+        match path {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path).map_err(|err| {
+                    ConfigError::single(format!("{}: cannot read: {err}", path.display()))
+                })?;
+                sources.with_file(path, &contents)
+            }
+            None => Ok(sources),
+        }
+    }
+
+    /// Adds a config file layer; the format is picked from the extension
+    /// (`.toml`, `.yaml` or `.yml`).
+    pub fn with_file(
+        mut self,
+        path: impl Into<PathBuf>,
+        contents: &str,
+    ) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        let mut values = BTreeMap::new();
+        let parsed = match extension.as_deref() {
+            Some("toml") => toml::from_str::<toml::Table>(contents)
+                .map_err(|err| err.to_string())
+                .and_then(|table| flatten_toml("", &toml::Value::Table(table), &mut values)),
+            Some("yaml" | "yml") => serde_yaml_ng::from_str::<serde_yaml_ng::Value>(contents)
+                .map_err(|err| err.to_string())
+                .and_then(|value| flatten_yaml("", &value, &mut values)),
+            _ => Err("expected a .toml, .yaml or .yml file".to_string()),
+        };
+        parsed.map_err(|err| ConfigError::single(format!("{}: {err}", path.display())))?;
+        self.file = Some(ConfigFile { path, values });
+        Ok(self)
+    }
+
+    /// Sets an environment variable layer value, e.g. `SIGNALING_PORT`.
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+}
+
+fn flatten_toml(
+    prefix: &str,
+    value: &toml::Value,
+    out: &mut BTreeMap<String, String>,
+) -> Result<(), String> {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                flatten_toml(&join_key(prefix, key), value, out)?;
+            }
+        }
+        toml::Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| match item {
+                    toml::Value::Table(_) | toml::Value::Array(_) => {
+                        Err(format!("{prefix}: lists may only contain plain values"))
+                    }
+                    toml::Value::String(text) => Ok(text.clone()),
+                    other => Ok(other.to_string()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            out.insert(prefix.to_string(), items.join(","));
+        }
+        toml::Value::String(text) => {
+            out.insert(prefix.to_string(), text.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+    Ok(())
+}
+
+fn flatten_yaml(
+    prefix: &str,
+    value: &serde_yaml_ng::Value,
+    out: &mut BTreeMap<String, String>,
+) -> Result<(), String> {
+    use serde_yaml_ng::Value;
+
+    fn scalar(prefix: &str, value: &Value) -> Result<Option<String>, String> {
+        Ok(match value {
+            Value::Null => None,
+            Value::Bool(flag) => Some(flag.to_string()),
+            Value::Number(number) => Some(number.to_string()),
+            Value::String(text) => Some(text.clone()),
+            _ => return Err(format!("{prefix}: expected a plain value")),
+        })
+    }
+
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let Some(key) = key.as_str() else {
+                    return Err(format!("{prefix}: keys must be strings"));
+                };
+                flatten_yaml(&join_key(prefix, key), value, out)?;
+            }
+        }
+        Value::Sequence(items) => {
+            let items = items
+                .iter()
+                .map(|item| scalar(prefix, item))
+                .collect::<Result<Vec<_>, _>>()?;
+            out.insert(
+                prefix.to_string(),
+                items.into_iter().flatten().collect::<Vec<_>>().join(","),
+            );
+        }
+        Value::Tagged(tagged) => flatten_yaml(prefix, &tagged.value, out)?,
+        other => {
+            if let Some(text) = scalar(prefix, other)? {
+                out.insert(prefix.to_string(), text);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    let key = key.to_lowercase().replace('-', "_");
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{prefix}_{key}")
+    }
+}
+
+/// Reads typed settings out of [`ConfigSources`], collecting every invalid value
+/// instead of stopping at the first one.
+pub(super) struct Loader<'a> {
+    sources: &'a ConfigSources,
+    read: BTreeSet<&'static str>,
+    problems: Vec<String>,
+}
+
+impl<'a> Loader<'a> {
+    pub(super) fn new(sources: &'a ConfigSources) -> Self {
+        Self {
+            sources,
+            read: BTreeSet::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// The raw value for `key` (e.g. `redis_url`) and where it came from.
+    /// Empty values count as unset, which is what `VAR=${VAR:-}` in compose produces.
+    fn raw(&mut self, key: &'static str) -> Option<(String, String)> {
+        self.read.insert(key);
+        let var = env_name(key);
+        if let Some(value) = self.sources.env.get(&var).filter(|v| !v.trim().is_empty()) {
+            return Some((var, value.trim().to_string()));
+        }
+        let file = self.sources.file.as_ref()?;
+        let value = file.values.get(key).filter(|v| !v.trim().is_empty())?;
+        Some((
+            format!("`{key}` in {}", file.path.display()),
+            value.trim().to_string(),
+        ))
+    }
+
+    /// Parses `key` with `parse`, recording a problem (and returning `None`) if it fails.
+    pub(super) fn value<T>(
+        &mut self,
+        key: &'static str,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Option<T> {
+        let (origin, raw) = self.raw(key)?;
+        match parse(&raw) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.problems.push(format!("{origin}: {err} (got {raw:?})"));
+                None
+            }
+        }
+    }
+
+    /// Like [`Loader::value`], but problems never quote the raw value.
+    pub(super) fn secret<T>(
+        &mut self,
+        key: &'static str,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> Option<T> {
+        let (origin, raw) = self.raw(key)?;
+        match parse(&raw) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.problems.push(format!("{origin}: {err}"));
+                None
+            }
+        }
+    }
+
+    pub(super) fn string(&mut self, key: &'static str) -> Option<String> {
+        self.value(key, |raw| Ok(raw.to_string()))
+    }
+
+    pub(super) fn parsed<T>(&mut self, key: &'static str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(key, |raw| raw.parse::<T>().map_err(|err| err.to_string()))
+    }
+
+    /// A number that must be greater than zero.
+    pub(super) fn positive<T>(&mut self, key: &'static str) -> Option<T>
+    where
+        T: FromStr + Default + PartialOrd,
+        T::Err: Display,
+    {
+        self.value(key, |raw| {
+            let value = raw.parse::<T>().map_err(|err| err.to_string())?;
+            if value > T::default() {
+                Ok(value)
+            } else {
+                Err("must be greater than zero".to_string())
+            }
+        })
+    }
+
+    pub(super) fn seconds(&mut self, key: &'static str) -> Option<Duration> {
+        self.parsed::<u64>(key).map(Duration::from_secs)
+    }
+
+    pub(super) fn positive_seconds(&mut self, key: &'static str) -> Option<Duration> {
+        self.positive::<u64>(key).map(Duration::from_secs)
+    }
+
+    pub(super) fn flag(&mut self, key: &'static str) -> Option<bool> {
+        self.value(key, |raw| match raw.to_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" => Ok(false),
+            _ => Err("expected true or false".to_string()),
+        })
+    }
+
+    /// A comma-separated list; each entry is parsed with `parse`.
+    pub(super) fn list<T>(
+        &mut self,
+        key: &'static str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Option<Vec<T>> {
+        self.value(key, |raw| {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| parse(entry).map_err(|err| format!("{entry:?}: {err}")))
+                .collect()
+        })
+    }
+
+    /// Like [`Loader::list`], but problems name the entry by position instead
+    /// of quoting it.
+    pub(super) fn secret_list<T>(
+        &mut self,
+        key: &'static str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Option<Vec<T>> {
+        self.secret(key, |raw| {
+            raw.split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .enumerate()
+                .map(|(index, entry)| {
+                    parse(entry).map_err(|err| format!("entry {}: {err}", index + 1))
+                })
+                .collect()
+        })
+    }
+
+    /// Records a problem that involves more than one setting.
+    pub(super) fn problem(&mut self, problem: impl Into<String>) {
+        self.problems.push(problem.into());
+    }
+
+    /// Checks for file keys nothing read and returns the warnings for ignored
+    /// variables, or every problem found.
+    pub(super) fn finish(mut self) -> Result<Vec<String>, ConfigError> {
+        if let Some(file) = &self.sources.file {
+            for key in file.values.keys() {
+                if !self.read.iter().any(|read| read == key) {
+                    self.problems.push(format!(
+                        "`{key}` in {}: unknown setting",
+                        file.path.display()
+                    ));
+                }
+            }
+        }
+        if !self.problems.is_empty() {
+            return Err(ConfigError {
+                problems: self.problems,
+            });
+        }
+
+        let mut warnings = Vec::new();
+        for var in self.sources.env.keys() {
+            if var == CONFIG_PATH_VAR || self.read.iter().any(|key| env_name(key) == *var) {
+                continue;
+            }
+            match LEGACY_VARIABLES.iter().find(|(legacy, _)| legacy == var) {
+                Some((_, hint)) => warnings.push(format!("{var} is no longer used: {hint}")),
+                None => warnings.push(format!("{var} is not a recognized setting and is ignored")),
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+fn env_name(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.to_uppercase())
+}
@@ -0,0 +1,85 @@
+use crate::config::{SignalingServerConfig, TurnSecret};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use shared::models::{ClientId, IceServerDto, IceServersResponse};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Builds the ICE server list handed to each client, minting TURN REST
+/// credentials in coturn's `use-auth-secret` format.
+#[derive(Debug, Clone)]
+pub struct IceServerProvider {
+    stun_urls: Vec<String>,
+    turn: Option<TurnRest>,
+}
+
+#[derive(Debug, Clone)]
+struct TurnRest {
+    urls: Vec<String>,
+    secret: TurnSecret,
+    ttl: Duration,
+}
+
+impl IceServerProvider {
+    pub fn new(config: &SignalingServerConfig) -> Self {
+        let turn = match (&config.turn_secret, config.turn_urls.is_empty()) {
+            (Some(secret), false) => Some(TurnRest {
+                urls: config.turn_urls.clone(),
+                secret: secret.clone(),
+                ttl: config.turn_credential_ttl,
+            }),
+            _ => None,
+        };
+        Self {
+            stun_urls: config.stun_urls.clone(),
+            turn,
+        }
+    }
+
+    /// STUN servers plus, if configured, TURN servers with credentials naming
+    /// `client_id` that expire one TTL after `now`.
+    pub fn servers_for(&self, client_id: &ClientId, now: SystemTime) -> IceServersResponse {
+        let mut ice_servers = Vec::new();
+        if !self.stun_urls.is_empty() {
+            ice_servers.push(IceServerDto::without_credentials(self.stun_urls.clone()));
+        }
+        let mut expires_at_epoch_ms = None;
+        if let Some(turn) = &self.turn {
+            let expires_at = now + turn.ttl;
+            let expires_at_secs = expires_at
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let (username, credential) = turn_rest_credentials(
+                turn.secret.as_bytes(),
+                &client_id.to_string(),
+                expires_at_secs,
+            );
+            ice_servers.push(IceServerDto {
+                urls: turn.urls.clone(),
+                username: Some(username),
+                credential: Some(credential),
+            });
+            expires_at_epoch_ms = Some(u128::from(expires_at_secs) * 1000);
+        }
+        IceServersResponse {
+            ice_servers,
+            expires_at_epoch_ms,
+        }
+    }
+}
+
+/// TURN REST API credentials: the username is `<unix expiry>:<user>` and the
+/// password is base64(HMAC-SHA1(secret, username)), as coturn verifies them.
+pub fn turn_rest_credentials(secret: &[u8], user: &str, expires_at_secs: u64) -> (String, String) {
+    let username = format!("{expires_at_secs}:{user}");
+    let credential = turn_rest_password(secret, &username);
+    (username, credential)
+}
+
+/// The password that goes with a TURN REST `username`.
+pub fn turn_rest_password(secret: &[u8], username: &str) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(username.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
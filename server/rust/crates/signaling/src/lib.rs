@@ -1,8 +1,16 @@
 pub mod config;
+pub mod ice;
+pub mod metrics;
+pub mod push_hub;
+pub mod rate_limit;
 pub mod registry;
 pub mod repository;
 pub mod server;
 pub mod services;
+pub mod stun;
+mod sync;
+pub mod tls;
+pub mod turn;
 
 pub use config::SignalingServerConfig;
-pub use server::run_server;
+pub use server::{run_server, run_server_until};
@@ -1,11 +1,52 @@
+mod admin_cli;
+
+use clap::{Parser, Subcommand};
+use signaling_server::config::CONFIG_PATH_VAR;
 use signaling_server::{run_server, SignalingServerConfig};
-use tracing::info;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use tracing::{info, warn};
 use tracing_subscriber::EnvFilter;
 
+/// Blind rendezvous signaling server.
+///
+/// Settings come from an optional TOML or YAML file, overridden by `SIGNALING_*`
+/// environment variables.
+#[derive(Debug, Parser)]
+#[command(version)]
+struct Cli {
+    /// Config file (`.toml`, `.yaml` or `.yml`)
+    #[arg(long, short, env = CONFIG_PATH_VAR)]
+    config: Option<PathBuf>,
+
+    /// Validate the configuration, report any problems and exit
+    #[arg(long)]
+    check_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Inspect or intervene in a running server through its `/admin` API
+    Admin(admin_cli::AdminArgs),
+}
+
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> anyhow::Result<ExitCode> {
     // Load environment variables from a .env file if present (local dev)
     let _ = dotenvy::dotenv();
+    let cli = Cli::parse();
+
+    if let Some(Command::Admin(args)) = cli.command {
+        admin_cli::run(args).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    if cli.check_config {
+        return Ok(check_config(&cli));
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(
             EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
@@ -14,7 +55,27 @@ async fn main() -> anyhow::Result<()> {
         .compact()
         .init();
 
-    let config = SignalingServerConfig::from_env()?;
-    info!(configuration = ?config, "Loaded signaling server configuration");
-    run_server(config).await
+    let loaded = SignalingServerConfig::load(cli.config.as_deref())?;
+    for warning in &loaded.warnings {
+        warn!("{warning}");
+    }
+    info!(configuration = ?loaded.config, "Loaded signaling server configuration");
+    run_server(loaded.config).await?;
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check_config(cli: &Cli) -> ExitCode {
+    match SignalingServerConfig::load(cli.config.as_deref()) {
+        Ok(loaded) => {
+            for warning in &loaded.warnings {
+                eprintln!("warning: {warning}");
+            }
+            println!("configuration OK");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,229 @@
+use crate::registry::RegistryError;
+use crate::services::host_service::HostError;
+use crate::services::rendezvous_service::RendezvousError;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Upper bounds for Redis round trips, in seconds: sub-millisecond on a healthy
+/// local instance, up to a second when something is badly wrong.
+const REDIS_LATENCY_BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+/// Process-wide metrics, exported in Prometheus text format on `/metrics`.
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+pub struct Metrics {
+    registry: Registry,
+    pub registrations: IntCounterVec,
+    pub heartbeats: IntCounterVec,
+    pub active_clients: IntGauge,
+    pub rendezvous_operations: IntCounterVec,
+    pub mailbox_messages: IntCounterVec,
+    pub host_operations: IntCounterVec,
+    pub ws_connections: IntCounter,
+    pub ws_active: IntGauge,
+    pub ws_lagged: IntCounter,
+    pub redis_command_duration: HistogramVec,
+    pub stun_requests: IntCounterVec,
+    pub turn_requests: IntCounterVec,
+    pub turn_allocations: IntGauge,
+    pub turn_relayed_bytes: IntCounterVec,
+    pub turn_dropped: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("signaling".to_string()), None)
+            .expect("metric namespace is valid");
+
+        let registrations = IntCounterVec::new(
+            Opts::new("registrations_total", "Client registrations by outcome"),
+            &["outcome"],
+        )
+        .expect("metric options are valid");
+        let heartbeats = IntCounterVec::new(
+            Opts::new("heartbeats_total", "Client heartbeats by outcome"),
+            &["outcome"],
+        )
+        .expect("metric options are valid");
+        let active_clients = IntGauge::new("active_clients", "Registered clients not yet expired")
+            .expect("metric options are valid");
+        let rendezvous_operations = IntCounterVec::new(
+            Opts::new(
+                "rendezvous_operations_total",
+                "Rendezvous init, join and close calls by outcome",
+            ),
+            &["operation", "outcome"],
+        )
+        .expect("metric options are valid");
+        let mailbox_messages = IntCounterVec::new(
+            Opts::new(
+                "mailbox_messages_total",
+                "Mailbox sends and receives by outcome",
+            ),
+            &["operation", "outcome"],
+        )
+        .expect("metric options are valid");
+        let host_operations = IntCounterVec::new(
+            Opts::new(
+                "host_operations_total",
+                "Unattended host register, listen, connect and unregister calls by outcome",
+            ),
+            &["operation", "outcome"],
+        )
+        .expect("metric options are valid");
+        let ws_connections =
+            IntCounter::new("websocket_connections_total", "Mailbox WebSockets accepted")
+                .expect("metric options are valid");
+        let ws_active = IntGauge::new("websocket_active", "Mailbox WebSockets currently open")
+            .expect("metric options are valid");
+        let ws_lagged = IntCounter::new(
+            "websocket_lag_events_total",
+            "Times a WebSocket fell behind its push channel and was resynced from storage",
+        )
+        .expect("metric options are valid");
+        let redis_command_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "redis_command_duration_seconds",
+                "Redis round-trip latency by command",
+            )
+            .buckets(REDIS_LATENCY_BUCKETS.to_vec()),
+            &["command", "outcome"],
+        )
+        .expect("metric options are valid");
+        let stun_requests = IntCounterVec::new(
+            Opts::new(
+                "stun_requests_total",
+                "Datagrams received by the built-in STUN responder by outcome",
+            ),
+            &["outcome"],
+        )
+        .expect("metric options are valid");
+        let turn_requests = IntCounterVec::new(
+            Opts::new("turn_requests_total", "TURN requests by method and outcome"),
+            &["method", "outcome"],
+        )
+        .expect("metric options are valid");
+        let turn_allocations =
+            IntGauge::new("turn_allocations", "TURN relay allocations currently live")
+                .expect("metric options are valid");
+        let turn_relayed_bytes = IntCounterVec::new(
+            Opts::new(
+                "turn_relayed_bytes_total",
+                "Application bytes relayed by TURN, to_peer or to_client",
+            ),
+            &["direction"],
+        )
+        .expect("metric options are valid");
+        let turn_dropped = IntCounterVec::new(
+            Opts::new(
+                "turn_dropped_datagrams_total",
+                "Datagrams the TURN relay did not forward by reason",
+            ),
+            &["reason"],
+        )
+        .expect("metric options are valid");
+
+        for collector in [
+            Box::new(registrations.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(heartbeats.clone()),
+            Box::new(active_clients.clone()),
+            Box::new(rendezvous_operations.clone()),
+            Box::new(mailbox_messages.clone()),
+            Box::new(host_operations.clone()),
+            Box::new(ws_connections.clone()),
+            Box::new(ws_active.clone()),
+            Box::new(ws_lagged.clone()),
+            Box::new(redis_command_duration.clone()),
+            Box::new(stun_requests.clone()),
+            Box::new(turn_requests.clone()),
+            Box::new(turn_allocations.clone()),
+            Box::new(turn_relayed_bytes.clone()),
+            Box::new(turn_dropped.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            registrations,
+            heartbeats,
+            active_clients,
+            rendezvous_operations,
+            mailbox_messages,
+            host_operations,
+            ws_connections,
+            ws_active,
+            ws_lagged,
+            redis_command_duration,
+            stun_requests,
+            turn_requests,
+            turn_allocations,
+            turn_relayed_bytes,
+            turn_dropped,
+        }
+    }
+
+    pub fn record_registry<T>(&self, counter: &IntCounterVec, result: &Result<T, RegistryError>) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(err) => err.label(),
+        };
+        counter.with_label_values(&[outcome]).inc();
+    }
+
+    pub fn record_rendezvous<T>(&self, operation: &str, result: &Result<T, RendezvousError>) {
+        Self::record_operation(&self.rendezvous_operations, operation, result);
+    }
+
+    pub fn record_mailbox<T>(&self, operation: &str, result: &Result<T, RendezvousError>) {
+        Self::record_operation(&self.mailbox_messages, operation, result);
+    }
+
+    pub fn record_host<T>(&self, operation: &str, result: &Result<T, HostError>) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(err) => err.label(),
+        };
+        self.host_operations
+            .with_label_values(&[operation, outcome])
+            .inc();
+    }
+
+    fn record_operation<T>(
+        counter: &IntCounterVec,
+        operation: &str,
+        result: &Result<T, RendezvousError>,
+    ) {
+        let outcome = match result {
+            Ok(_) => "ok",
+            Err(err) => err.label(),
+        };
+        counter.with_label_values(&[operation, outcome]).inc();
+    }
+
+    pub fn observe_redis(&self, command: &str, elapsed: Duration, ok: bool) {
+        self.redis_command_duration
+            .with_label_values(&[command, if ok { "ok" } else { "error" }])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::warn!(error = %err, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
@@ -0,0 +1,211 @@
+use crate::repository::timed_connection::TimedConnection;
+use crate::sync::lock;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+const REDIS_RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Fans mailbox notifications out to WebSocket subscribers.
+///
+/// Without Redis every notification is delivered to local subscribers only. With
+/// Redis, notifications are published on `{prefix}:push:{mailbox_id}` and every
+/// instance (this one included) delivers them to its own sockets from a pattern
+/// subscription, so a peer can be connected to any replica.
+///
+/// A mailbox only has a channel while someone is subscribed to it: the channel is
+/// removed when its last [`PushSubscription`] is dropped or the mailbox is closed.
+#[derive(Clone)]
+pub struct PushHub {
+    buffer_capacity: usize,
+    max_subscribers_per_mailbox: usize,
+    inner: Arc<Mutex<HashMap<String, broadcast::Sender<String>>>>,
+    active_subscribers: Arc<AtomicUsize>,
+    redis: Option<RedisPublisher>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PushError {
+    #[error("Too many subscribers for this mailbox")]
+    SubscriberLimit,
+}
+
+/// Snapshot of the hub's bookkeeping.
+#[derive(Debug, Clone, Copy)]
+pub struct PushStats {
+    /// Mailboxes with at least one local subscriber.
+    pub mailboxes: usize,
+    /// Open local subscriptions, i.e. connected WebSockets.
+    pub subscribers: usize,
+}
+
+/// Live notifications for one mailbox. Dropping it releases the subscriber slot.
+pub struct PushSubscription {
+    // Declared before `_guard` so the receiver is gone by the time the guard
+    // checks whether it was the last one.
+    receiver: broadcast::Receiver<String>,
+    _guard: SubscriptionGuard,
+}
+
+impl PushSubscription {
+    pub async fn recv(&mut self) -> Result<String, broadcast::error::RecvError> {
+        self.receiver.recv().await
+    }
+}
+
+struct SubscriptionGuard {
+    mailbox_id: String,
+    inner: Arc<Mutex<HashMap<String, broadcast::Sender<String>>>>,
+    active_subscribers: Arc<AtomicUsize>,
+}
+
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        self.active_subscribers.fetch_sub(1, Ordering::Relaxed);
+        let mut guard = lock(&self.inner);
+        if guard
+            .get(&self.mailbox_id)
+            .is_some_and(|tx| tx.receiver_count() == 0)
+        {
+            guard.remove(&self.mailbox_id);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RedisPublisher {
+    conn_manager: TimedConnection,
+    channel_prefix: String,
+}
+
+impl PushHub {
+    pub fn new(buffer_capacity: usize, max_subscribers_per_mailbox: usize) -> Self {
+        Self {
+            buffer_capacity,
+            max_subscribers_per_mailbox,
+            inner: Arc::new(Mutex::new(HashMap::new())),
+            active_subscribers: Arc::new(AtomicUsize::new(0)),
+            redis: None,
+        }
+    }
+
+    pub fn with_redis(
+        buffer_capacity: usize,
+        max_subscribers_per_mailbox: usize,
+        conn_manager: redis::aio::ConnectionManager,
+        key_prefix: &str,
+    ) -> Self {
+        Self {
+            redis: Some(RedisPublisher {
+                conn_manager: TimedConnection::new(conn_manager),
+                channel_prefix: format!("{}:push:", key_prefix),
+            }),
+            ..Self::new(buffer_capacity, max_subscribers_per_mailbox)
+        }
+    }
+
+    pub fn subscribe(&self, mailbox_id: &str) -> Result<PushSubscription, PushError> {
+        let mut guard = lock(&self.inner);
+        let tx = guard.entry(mailbox_id.to_string()).or_insert_with(|| {
+            let (tx, _rx) = broadcast::channel(self.buffer_capacity);
+            tx
+        });
+        if tx.receiver_count() >= self.max_subscribers_per_mailbox {
+            return Err(PushError::SubscriberLimit);
+        }
+        let receiver = tx.subscribe();
+        self.active_subscribers.fetch_add(1, Ordering::Relaxed);
+        Ok(PushSubscription {
+            receiver,
+            _guard: SubscriptionGuard {
+                mailbox_id: mailbox_id.to_string(),
+                inner: Arc::clone(&self.inner),
+                active_subscribers: Arc::clone(&self.active_subscribers),
+            },
+        })
+    }
+
+    /// Drops the mailbox's channel; its subscribers see the stream end and close.
+    pub fn close_mailbox(&self, mailbox_id: &str) {
+        lock(&self.inner).remove(mailbox_id);
+    }
+
+    /// Local WebSockets currently subscribed to `mailbox_id`.
+    pub fn subscriber_count(&self, mailbox_id: &str) -> usize {
+        lock(&self.inner)
+            .get(mailbox_id)
+            .map_or(0, |tx| tx.receiver_count())
+    }
+
+    pub fn stats(&self) -> PushStats {
+        PushStats {
+            mailboxes: lock(&self.inner).len(),
+            subscribers: self.active_subscribers.load(Ordering::Relaxed),
+        }
+    }
+
+    pub async fn notify(&self, mailbox_id: &str, msg: String) {
+        if let Some(redis) = &self.redis {
+            let mut conn = redis.conn_manager.clone();
+            let channel = format!("{}{}", redis.channel_prefix, mailbox_id);
+            match redis::cmd("PUBLISH")
+                .arg(&channel)
+                .arg(&msg)
+                .query_async::<()>(&mut conn)
+                .await
+            {
+                Ok(()) => return,
+                Err(err) => {
+                    warn!(%mailbox_id, error = %err, "Redis publish failed, delivering locally only");
+                }
+            }
+        }
+        self.deliver_local(mailbox_id, msg);
+    }
+
+    fn deliver_local(&self, mailbox_id: &str, msg: String) {
+        let guard = lock(&self.inner);
+        if let Some(tx) = guard.get(mailbox_id) {
+            let _ = tx.send(msg);
+        }
+    }
+
+    /// Starts the pattern subscription that feeds notifications published by any
+    /// instance into local subscribers. No-op for a hub without Redis.
+    pub fn spawn_redis_listener(&self, client: redis::Client) {
+        let Some(redis) = self.redis.clone() else {
+            return;
+        };
+        let hub = self.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(err) = hub.listen(&client, &redis.channel_prefix).await {
+                    warn!(error = %err, "Redis push subscription lost, resubscribing");
+                }
+                tokio::time::sleep(REDIS_RESUBSCRIBE_DELAY).await;
+            }
+        });
+    }
+
+    async fn listen(&self, client: &redis::Client, channel_prefix: &str) -> anyhow::Result<()> {
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.psubscribe(format!("{}*", channel_prefix)).await?;
+        info!(pattern = %format!("{}*", channel_prefix), "Subscribed to Redis push channel");
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(message) = messages.next().await {
+            let Some(mailbox_id) = message.get_channel_name().strip_prefix(channel_prefix) else {
+                continue;
+            };
+            match message.get_payload::<String>() {
+                Ok(payload) => self.deliver_local(mailbox_id, payload),
+                Err(err) => debug!(%mailbox_id, error = %err, "Dropping malformed push payload"),
+            }
+        }
+        anyhow::bail!("Redis push stream ended")
+    }
+}
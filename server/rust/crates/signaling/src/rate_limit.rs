@@ -0,0 +1,176 @@
+use crate::repository::rate_limit_store::{RateDecision, RateLimitPolicy, RateLimitStore};
+use crate::server::ApiError;
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use ipnet::IpNet;
+use shared::models::ErrorCode;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+
+/// Endpoints that get their own bucket per client IP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitScope {
+    Register,
+    ConnectionInit,
+    ConnectionJoin,
+    /// Nameplates are short, so joins by nameplate get a tighter budget than
+    /// joins by link token.
+    NameplateJoin,
+    HostConnect,
+}
+
+impl RateLimitScope {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::ConnectionInit => "init",
+            Self::ConnectionJoin => "join",
+            Self::NameplateJoin => "nameplate_join",
+            Self::HostConnect => "host_connect",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitPolicies {
+    pub register: RateLimitPolicy,
+    pub connection_init: RateLimitPolicy,
+    pub connection_join: RateLimitPolicy,
+    pub nameplate_join: RateLimitPolicy,
+    pub host_connect: RateLimitPolicy,
+}
+
+impl RateLimitPolicies {
+    fn for_scope(&self, scope: RateLimitScope) -> &RateLimitPolicy {
+        match scope {
+            RateLimitScope::Register => &self.register,
+            RateLimitScope::ConnectionInit => &self.connection_init,
+            RateLimitScope::ConnectionJoin => &self.connection_join,
+            RateLimitScope::NameplateJoin => &self.nameplate_join,
+            RateLimitScope::HostConnect => &self.host_connect,
+        }
+    }
+}
+
+/// Per-IP token buckets in front of the endpoints that create state or accept
+/// guessable tokens.
+///
+/// `X-Forwarded-For` is only consulted when the direct peer is one of
+/// `trusted_proxies`; otherwise any client could pick its own bucket.
+pub struct RateLimiter {
+    store: Arc<dyn RateLimitStore>,
+    policies: RateLimitPolicies,
+    trusted_proxies: Vec<IpNet>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        store: Arc<dyn RateLimitStore>,
+        policies: RateLimitPolicies,
+        trusted_proxies: Vec<IpNet>,
+    ) -> Self {
+        Self {
+            store,
+            policies,
+            trusted_proxies,
+        }
+    }
+
+    pub async fn check(&self, scope: RateLimitScope, client_ip: IpAddr) -> RateDecision {
+        let key = format!("{}:{}", scope.as_str(), bucket_ip(client_ip));
+        match self
+            .store
+            .try_acquire(&key, self.policies.for_scope(scope))
+            .await
+        {
+            Ok(decision) => decision,
+            Err(e) => {
+                // Fail open: a storage outage should not lock every client out
+                warn!(error = %e, scope = scope.as_str(), "Rate limit check failed");
+                RateDecision::Allowed
+            }
+        }
+    }
+
+    /// Resolves the address requests are counted against. Walks `X-Forwarded-For`
+    /// from the right and returns the first hop that is not a trusted proxy.
+    /// Hops left of that are client-controlled, so if every hop is trusted, or one
+    /// cannot be parsed before an untrusted one is found, the peer is used.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.is_trusted(peer) {
+            return peer;
+        }
+        let hops: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        for hop in hops.into_iter().rev() {
+            match hop.parse::<IpAddr>() {
+                Ok(ip) if self.is_trusted(ip) => continue,
+                Ok(ip) => return ip,
+                Err(_) => break,
+            }
+        }
+        peer
+    }
+
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.trusted_proxies.iter().any(|net| net.contains(&ip))
+    }
+}
+
+/// IPv6 clients usually control a whole /64, so they share one bucket.
+fn bucket_ip(ip: IpAddr) -> String {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => v4.to_string(),
+        IpAddr::V6(v6) => {
+            let prefix = u128::from(v6) & !((1u128 << 64) - 1);
+            format!("{}/64", std::net::Ipv6Addr::from(prefix))
+        }
+    }
+}
+
+/// Middleware state: the shared limiter plus the bucket this route draws from.
+#[derive(Clone)]
+pub struct ScopedRateLimiter {
+    pub limiter: Arc<RateLimiter>,
+    pub scope: RateLimitScope,
+}
+
+pub async fn enforce_rate_limit(
+    State(scoped): State<ScopedRateLimiter>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(peer) = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+    else {
+        return next.run(request).await;
+    };
+    let client_ip = scoped.limiter.client_ip(peer, request.headers());
+
+    match scoped.limiter.check(scoped.scope, client_ip).await {
+        RateDecision::Allowed => next.run(request).await,
+        RateDecision::Limited { retry_after } => {
+            warn!(client_ip = %client_ip, scope = scoped.scope.as_str(), "Rate limit exceeded");
+            ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::RateLimited,
+                "Too many requests",
+            )
+            .with_retry_after(retry_after)
+            .into_response()
+        }
+    }
+}
@@ -1,12 +1,18 @@
-use crate::repository::session_repository::{ClientRecord, InMemorySessionRepository};
+use crate::metrics::metrics;
+use crate::repository::session_store::{ClientRecord, SessionStore};
+use shared::connection::constant_time_eq;
 use shared::models::{
-    ClientId, HeartbeatRequest, HeartbeatResponse, RegisterRequest, RegisterResponse,
-    SignalFetchRequest, SignalFetchResponse, SignalSubmitRequest,
+    ClientId, HeartbeatRequest, HeartbeatResponse, RegisterChallengeResponse, RegisterRequest,
+    RegisterResponse, SignalFetchRequest, SignalFetchResponse, SignalSubmitRequest,
 };
-use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use shared::{identity, token};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tracing::debug;
-use uuid::Uuid;
+
+/// How long a device has to answer a registration challenge.
+const REGISTER_CHALLENGE_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Error)]
 pub enum RegistryError {
@@ -14,20 +20,48 @@ pub enum RegistryError {
     ClientNotFound,
     #[error("session token rejected")]
     InvalidToken,
+    #[error("session token expired; register again")]
+    SessionExpired,
+    #[error("registration challenge unknown, expired or already used")]
+    InvalidChallenge,
+    #[error("registration signature rejected")]
+    InvalidSignature,
+    #[error("storage error: {0}")]
+    Storage(#[from] anyhow::Error),
+}
+
+impl RegistryError {
+    /// Short, stable name used as a metrics label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::ClientNotFound => "client_not_found",
+            Self::InvalidToken => "invalid_token",
+            Self::SessionExpired => "session_expired",
+            Self::InvalidChallenge => "invalid_challenge",
+            Self::InvalidSignature => "invalid_signature",
+            Self::Storage(_) => "storage",
+        }
+    }
 }
 
-#[derive(Debug)]
 pub struct SessionRegistry {
-    repository: InMemorySessionRepository,
+    repository: Arc<dyn SessionStore>,
     session_ttl: Duration,
+    session_token_ttl: Duration,
     heartbeat_interval: Duration,
 }
 
 impl SessionRegistry {
-    pub fn new(session_ttl: Duration, heartbeat_interval: Duration) -> Self {
+    pub fn new(
+        repository: Arc<dyn SessionStore>,
+        session_ttl: Duration,
+        session_token_ttl: Duration,
+        heartbeat_interval: Duration,
+    ) -> Self {
         Self {
-            repository: InMemorySessionRepository::new(),
+            repository,
             session_ttl,
+            session_token_ttl,
             heartbeat_interval,
         }
     }
@@ -40,46 +74,109 @@ impl SessionRegistry {
         let record = self
             .repository
             .get_client(client_id)
-            .await
+            .await?
             .ok_or(RegistryError::ClientNotFound)?;
-        if record.session_token != session_token {
+        let presented = token::hash_token(session_token);
+        if !constant_time_eq(presented.as_bytes(), record.session_token_hash.as_bytes()) {
             return Err(RegistryError::InvalidToken);
         }
+        if record.session_expires_at_epoch_ms <= now_epoch_ms() {
+            return Err(RegistryError::SessionExpired);
+        }
         Ok(record)
     }
 
-    pub async fn register(&self, request: RegisterRequest) -> RegisterResponse {
-        self.prune_expired().await;
+    /// A single-use nonce for the device to sign in its `RegisterRequest`.
+    pub async fn issue_challenge(&self) -> Result<RegisterChallengeResponse, RegistryError> {
+        let nonce = token::gen_token();
+        self.repository
+            .save_challenge(&nonce, REGISTER_CHALLENGE_TTL)
+            .await?;
+        Ok(RegisterChallengeResponse {
+            nonce,
+            expires_at_epoch_ms: now_epoch_ms() + REGISTER_CHALLENGE_TTL.as_millis(),
+        })
+    }
 
-        let client_id = Uuid::new_v4();
-        let session_token = Uuid::new_v4().to_string();
+    pub async fn register(
+        &self,
+        request: RegisterRequest,
+    ) -> Result<RegisterResponse, RegistryError> {
+        let result = self.register_client(request).await;
+        metrics().record_registry(&metrics().registrations, &result);
+        result
+    }
+
+    async fn register_client(
+        &self,
+        request: RegisterRequest,
+    ) -> Result<RegisterResponse, RegistryError> {
+        self.prune_expired().await?;
+
+        // The nonce is spent even if the signature turns out to be bad, so every
+        // attempt needs a fresh challenge
+        if !self.repository.take_challenge(&request.nonce).await? {
+            return Err(RegistryError::InvalidChallenge);
+        }
+        let client_id = identity::verify_registration(
+            &request.public_key_b64,
+            &request.nonce,
+            &request.signature_b64,
+        )
+        .map_err(|err| {
+            debug!(error = %err, "registration signature rejected");
+            RegistryError::InvalidSignature
+        })?;
+
+        // Registering again replaces the session token but keeps the device's
+        // original registration time
+        let existing = self.repository.get_client(&client_id).await?;
         // Assign incremental display name based on current active clients count + 1
         let display_name = {
-            let count = self.repository.get_client_count().await;
-            format!("Client {}", count + 1)
+            let count = self.repository.get_client_count().await?;
+            format!("Client {}", count + usize::from(existing.is_none()))
         };
+        let session_token = token::gen_token();
+        let now_ms = now_epoch_ms();
+        let session_expires_at_epoch_ms = now_ms + self.session_token_ttl.as_millis();
         let new_record = ClientRecord {
             device_label: request.device_label,
-            session_token: session_token.clone(),
-            registered_at: Instant::now(),
-            last_heartbeat: Instant::now(),
+            public_key_b64: request.public_key_b64,
+            session_token_hash: token::hash_token(&session_token),
+            session_expires_at_epoch_ms,
+            registered_at_epoch_ms: existing.map_or(now_ms, |record| record.registered_at_epoch_ms),
+            last_heartbeat_epoch_ms: now_ms,
         };
 
-        self.repository.insert_client(client_id, new_record).await;
+        self.repository
+            .insert_client(client_id, new_record, self.session_ttl)
+            .await?;
 
-        RegisterResponse {
+        Ok(RegisterResponse {
             client_id,
             session_token,
+            session_expires_at_epoch_ms,
             heartbeat_interval_secs: self.heartbeat_interval.as_secs(),
             display_name,
-        }
+            // Credentials are minted per request by the HTTP layer
+            ice_servers: Vec::new(),
+        })
     }
 
     pub async fn heartbeat(
         &self,
         request: HeartbeatRequest,
     ) -> Result<HeartbeatResponse, RegistryError> {
-        self.prune_expired().await;
+        let result = self.record_heartbeat(request).await;
+        metrics().record_registry(&metrics().heartbeats, &result);
+        result
+    }
+
+    async fn record_heartbeat(
+        &self,
+        request: HeartbeatRequest,
+    ) -> Result<HeartbeatResponse, RegistryError> {
+        self.prune_expired().await?;
 
         // Verify first
         self.verify_client(&request.client_id, &request.session_token)
@@ -88,8 +185,8 @@ impl SessionRegistry {
         // Update
         if !self
             .repository
-            .update_client_heartbeat(&request.client_id, Instant::now())
-            .await
+            .update_client_heartbeat(&request.client_id, now_epoch_ms(), self.session_ttl)
+            .await?
         {
             return Err(RegistryError::ClientNotFound);
         }
@@ -100,18 +197,17 @@ impl SessionRegistry {
     }
 
     pub async fn enqueue_signal(&self, submit: SignalSubmitRequest) -> Result<(), RegistryError> {
-        self.prune_expired().await;
+        self.prune_expired().await?;
 
         self.verify_client(&submit.envelope.from, &submit.session_token)
             .await?;
 
         let mut envelope = submit.envelope;
-        envelope.created_at_epoch_ms = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_millis())
-            .unwrap_or_default();
+        envelope.created_at_epoch_ms = now_epoch_ms();
 
-        self.repository.add_message(envelope).await;
+        self.repository
+            .add_message(envelope, self.session_ttl)
+            .await?;
         Ok(())
     }
 
@@ -119,7 +215,7 @@ impl SessionRegistry {
         &self,
         request: SignalFetchRequest,
     ) -> Result<SignalFetchResponse, RegistryError> {
-        self.prune_expired().await;
+        self.prune_expired().await?;
 
         self.verify_client(&request.client_id, &request.session_token)
             .await?;
@@ -127,7 +223,7 @@ impl SessionRegistry {
         let collected = self
             .repository
             .get_messages_for_client(&request.client_id)
-            .await;
+            .await?;
 
         Ok(SignalFetchResponse {
             messages: collected,
@@ -143,12 +239,43 @@ impl SessionRegistry {
         Ok(())
     }
 
-    async fn prune_expired(&self) {
+    /// Whether `client_id` is registered and has not expired.
+    pub async fn is_registered(&self, client_id: &ClientId) -> Result<bool, RegistryError> {
+        self.prune_expired().await?;
+        Ok(self.repository.get_client(client_id).await?.is_some())
+    }
+
+    /// Clients currently registered, after dropping expired ones.
+    pub async fn active_client_count(&self) -> Result<usize, RegistryError> {
+        self.prune_expired().await?;
+        Ok(self.repository.get_client_count().await?)
+    }
+
+    /// Clients currently registered, with their records, after dropping expired ones.
+    pub async fn list_clients(&self) -> Result<Vec<(ClientId, ClientRecord)>, RegistryError> {
+        self.prune_expired().await?;
+        Ok(self.repository.list_clients().await?)
+    }
+
+    /// Drops a client and its queued signals; its session token stops working
+    /// immediately.
+    pub async fn remove_client(&self, client_id: &ClientId) -> Result<(), RegistryError> {
+        if self.repository.get_client(client_id).await?.is_none() {
+            return Err(RegistryError::ClientNotFound);
+        }
+        self.repository.remove_client(client_id).await?;
+        self.repository
+            .prune_messages_for_clients(std::slice::from_ref(client_id))
+            .await?;
+        Ok(())
+    }
+
+    async fn prune_expired(&self) -> Result<(), RegistryError> {
         let expiration_threshold = self.session_ttl;
         let stale_clients = self
             .repository
             .prune_stale_clients(expiration_threshold)
-            .await;
+            .await?;
 
         if !stale_clients.is_empty() {
             debug!(?stale_clients, "pruned expired clients");
@@ -157,7 +284,15 @@ impl SessionRegistry {
             // I added `prune_messages_for_clients` to the repository.
             self.repository
                 .prune_messages_for_clients(&stale_clients)
-                .await;
+                .await?;
         }
+        Ok(())
     }
 }
+
+fn now_epoch_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
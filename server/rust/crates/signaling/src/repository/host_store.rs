@@ -0,0 +1,392 @@
+use super::timed_connection::TimedConnection;
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use shared::models::ClientId;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// A persistent host ID and the device it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostRecord {
+    pub host_id: String,
+    /// Only this device may listen on the ID or change its password.
+    pub owner: ClientId,
+    /// Argon2id PHC string of the access password.
+    pub password_hash: String,
+    pub created_at_epoch_ms: u128,
+}
+
+/// Wrong passwords counted against one host ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FailedAttempts {
+    pub count: u32,
+    /// Until the counter is forgotten; the host stays locked this long once
+    /// `count` reaches the limit.
+    pub resets_in: Duration,
+}
+
+/// Outcome of [`HostStore::reserve_attempt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptReservation {
+    /// The attempt is counted as a failure until it is refunded; `count`
+    /// includes it.
+    Reserved(FailedAttempts),
+    /// The host is locked; nothing was counted.
+    Locked { resets_in: Duration },
+}
+
+/// Storage for host IDs, the rendezvous each host is listening on and the
+/// failed password attempts against it.
+///
+/// Host records never expire; listening rendezvous and failure counters do.
+#[async_trait]
+pub trait HostStore: Send + Sync {
+    /// Stores a new host unless its ID is already taken. Returns `false` if it is.
+    async fn create_host(&self, record: &HostRecord) -> Result<bool>;
+
+    /// Replaces an existing host record, e.g. after a password change.
+    async fn update_host(&self, record: &HostRecord) -> Result<()>;
+
+    async fn get_host(&self, host_id: &str) -> Result<Option<HostRecord>>;
+
+    async fn host_id_for_owner(&self, owner: &ClientId) -> Result<Option<String>>;
+
+    /// Removes the host together with its rendezvous and failure counter.
+    async fn delete_host(&self, record: &HostRecord) -> Result<()>;
+
+    /// Points the host at the rendezvous its next viewer joins, for `ttl`.
+    /// Returns the rendezvous it replaces, if that had not expired yet.
+    async fn set_listening(
+        &self,
+        host_id: &str,
+        rendezvous_id: &str,
+        ttl: Duration,
+    ) -> Result<Option<String>>;
+
+    /// Removes and returns the host's rendezvous, so of two concurrent viewers
+    /// only one gets it.
+    async fn take_listening(&self, host_id: &str) -> Result<Option<String>>;
+
+    /// Counts a password attempt as a failure before it is checked, unless
+    /// `max_attempts` have already failed. Checking and counting happen in one
+    /// step, so concurrent guesses cannot all slip in under the limit. The
+    /// counter lives for `lockout` from its first attempt, and again from the
+    /// one that brings it to `max_attempts`.
+    async fn reserve_attempt(
+        &self,
+        host_id: &str,
+        max_attempts: u32,
+        lockout: Duration,
+    ) -> Result<AttemptReservation>;
+
+    /// Takes back a reserved attempt that could not be checked.
+    async fn refund_attempt(&self, host_id: &str) -> Result<()>;
+
+    async fn clear_failures(&self, host_id: &str) -> Result<()>;
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    hosts: HashMap<String, HostRecord>,
+    owners: HashMap<ClientId, String>,
+    listening: HashMap<String, (String, Instant)>,
+    failures: HashMap<String, (u32, Instant)>,
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryHostRepository {
+    state: Mutex<HostState>,
+}
+
+impl InMemoryHostRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl HostStore for InMemoryHostRepository {
+    async fn create_host(&self, record: &HostRecord) -> Result<bool> {
+        let mut state = self.state.lock().await;
+        if state.hosts.contains_key(&record.host_id) {
+            return Ok(false);
+        }
+        state.hosts.insert(record.host_id.clone(), record.clone());
+        state.owners.insert(record.owner, record.host_id.clone());
+        Ok(true)
+    }
+
+    async fn update_host(&self, record: &HostRecord) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.hosts.insert(record.host_id.clone(), record.clone());
+        Ok(())
+    }
+
+    async fn get_host(&self, host_id: &str) -> Result<Option<HostRecord>> {
+        Ok(self.state.lock().await.hosts.get(host_id).cloned())
+    }
+
+    async fn host_id_for_owner(&self, owner: &ClientId) -> Result<Option<String>> {
+        Ok(self.state.lock().await.owners.get(owner).cloned())
+    }
+
+    async fn delete_host(&self, record: &HostRecord) -> Result<()> {
+        let mut state = self.state.lock().await;
+        state.hosts.remove(&record.host_id);
+        state.owners.remove(&record.owner);
+        state.listening.remove(&record.host_id);
+        state.failures.remove(&record.host_id);
+        Ok(())
+    }
+
+    async fn set_listening(
+        &self,
+        host_id: &str,
+        rendezvous_id: &str,
+        ttl: Duration,
+    ) -> Result<Option<String>> {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        state
+            .listening
+            .retain(|_, (_, expires_at)| *expires_at > now);
+        Ok(state
+            .listening
+            .insert(host_id.to_string(), (rendezvous_id.to_string(), now + ttl))
+            .map(|(previous, _)| previous))
+    }
+
+    async fn take_listening(&self, host_id: &str) -> Result<Option<String>> {
+        let now = Instant::now();
+        Ok(self
+            .state
+            .lock()
+            .await
+            .listening
+            .remove(host_id)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(rendezvous_id, _)| rendezvous_id))
+    }
+
+    async fn reserve_attempt(
+        &self,
+        host_id: &str,
+        max_attempts: u32,
+        lockout: Duration,
+    ) -> Result<AttemptReservation> {
+        let now = Instant::now();
+        let mut state = self.state.lock().await;
+        // Unknown IDs are counted too, so drop lapsed counters as they pile up
+        state.failures.retain(|_, (_, resets_at)| *resets_at > now);
+        let (count, resets_at) = state
+            .failures
+            .entry(host_id.to_string())
+            .or_insert((0, now + lockout));
+        if *count >= max_attempts {
+            return Ok(AttemptReservation::Locked {
+                resets_in: resets_at.saturating_duration_since(now),
+            });
+        }
+        *count += 1;
+        if *count == max_attempts {
+            *resets_at = now + lockout;
+        }
+        Ok(AttemptReservation::Reserved(FailedAttempts {
+            count: *count,
+            resets_in: resets_at.saturating_duration_since(now),
+        }))
+    }
+
+    async fn refund_attempt(&self, host_id: &str) -> Result<()> {
+        let mut state = self.state.lock().await;
+        if let Some((count, _)) = state.failures.get_mut(host_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.failures.remove(host_id);
+            }
+        }
+        Ok(())
+    }
+
+    async fn clear_failures(&self, host_id: &str) -> Result<()> {
+        self.state.lock().await.failures.remove(host_id);
+        Ok(())
+    }
+}
+
+/// Increments a failure counter unless it already reached the limit, starting
+/// its lifetime on the first attempt and restarting it on the one that reaches
+/// the limit.
+/// KEYS: 1 = counter. ARGV: 1 = lockout ms, 2 = limit.
+/// Returns `{count, remaining ms}`, with a count of 0 if the host is locked.
+static RESERVE_ATTEMPT_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local limit = tonumber(ARGV[2])
+if tonumber(redis.call('GET', KEYS[1]) or '0') >= limit then
+    return {0, redis.call('PTTL', KEYS[1])}
+end
+local count = redis.call('INCR', KEYS[1])
+if count == 1 or count == limit then
+    redis.call('PEXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('PTTL', KEYS[1])}
+",
+    )
+});
+
+/// Decrements a failure counter that exists, deleting it at zero.
+/// KEYS: 1 = counter.
+static REFUND_ATTEMPT_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+if redis.call('EXISTS', KEYS[1]) == 1 and redis.call('DECR', KEYS[1]) <= 0 then
+    redis.call('DEL', KEYS[1])
+end
+return 0
+",
+    )
+});
+
+#[derive(Clone)]
+pub struct RedisHostRepository {
+    conn_manager: TimedConnection,
+    key_prefix: String,
+}
+
+impl RedisHostRepository {
+    pub fn new(conn_manager: redis::aio::ConnectionManager, key_prefix: String) -> Self {
+        Self {
+            conn_manager: TimedConnection::new(conn_manager),
+            key_prefix,
+        }
+    }
+
+    fn host_key(&self, host_id: &str) -> String {
+        format!("{}:host:{}", self.key_prefix, host_id)
+    }
+
+    fn owner_key(&self, owner: &ClientId) -> String {
+        format!("{}:host_owner:{}", self.key_prefix, owner)
+    }
+
+    fn listening_key(&self, host_id: &str) -> String {
+        format!("{}:host_listening:{}", self.key_prefix, host_id)
+    }
+
+    fn failures_key(&self, host_id: &str) -> String {
+        format!("{}:host_failures:{}", self.key_prefix, host_id)
+    }
+}
+
+#[async_trait]
+impl HostStore for RedisHostRepository {
+    async fn create_host(&self, record: &HostRecord) -> Result<bool> {
+        let mut conn = self.conn_manager.clone();
+        let json = serde_json::to_string(record)?;
+        let created: bool = conn.set_nx(self.host_key(&record.host_id), json).await?;
+        if created {
+            conn.set::<_, _, ()>(self.owner_key(&record.owner), &record.host_id)
+                .await?;
+        }
+        Ok(created)
+    }
+
+    async fn update_host(&self, record: &HostRecord) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        let json = serde_json::to_string(record)?;
+        conn.set::<_, _, ()>(self.host_key(&record.host_id), json)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_host(&self, host_id: &str) -> Result<Option<HostRecord>> {
+        let mut conn = self.conn_manager.clone();
+        let json: Option<String> = conn.get(self.host_key(host_id)).await?;
+        match json {
+            Some(s) => Ok(Some(serde_json::from_str(&s)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn host_id_for_owner(&self, owner: &ClientId) -> Result<Option<String>> {
+        let mut conn = self.conn_manager.clone();
+        Ok(conn.get(self.owner_key(owner)).await?)
+    }
+
+    async fn delete_host(&self, record: &HostRecord) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        conn.del::<_, ()>(&[
+            self.host_key(&record.host_id),
+            self.owner_key(&record.owner),
+            self.listening_key(&record.host_id),
+            self.failures_key(&record.host_id),
+        ])
+        .await?;
+        Ok(())
+    }
+
+    async fn set_listening(
+        &self,
+        host_id: &str,
+        rendezvous_id: &str,
+        ttl: Duration,
+    ) -> Result<Option<String>> {
+        let mut conn = self.conn_manager.clone();
+        let previous: Option<String> = redis::cmd("SET")
+            .arg(self.listening_key(host_id))
+            .arg(rendezvous_id)
+            .arg("EX")
+            .arg(ttl.as_secs())
+            .arg("GET")
+            .query_async(&mut conn)
+            .await?;
+        Ok(previous)
+    }
+
+    async fn take_listening(&self, host_id: &str) -> Result<Option<String>> {
+        let mut conn = self.conn_manager.clone();
+        Ok(conn.get_del(self.listening_key(host_id)).await?)
+    }
+
+    async fn reserve_attempt(
+        &self,
+        host_id: &str,
+        max_attempts: u32,
+        lockout: Duration,
+    ) -> Result<AttemptReservation> {
+        let mut conn = self.conn_manager.clone();
+        let (count, remaining_ms): (u32, i64) = RESERVE_ATTEMPT_SCRIPT
+            .key(self.failures_key(host_id))
+            .arg(lockout.as_millis() as u64)
+            .arg(max_attempts)
+            .invoke_async(&mut conn)
+            .await?;
+        // PTTL is negative if the counter somehow lost its expiry
+        let resets_in = Duration::from_millis(remaining_ms.max(0) as u64);
+        Ok(if count == 0 {
+            AttemptReservation::Locked { resets_in }
+        } else {
+            AttemptReservation::Reserved(FailedAttempts { count, resets_in })
+        })
+    }
+
+    async fn refund_attempt(&self, host_id: &str) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        REFUND_ATTEMPT_SCRIPT
+            .key(self.failures_key(host_id))
+            .invoke_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn clear_failures(&self, host_id: &str) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        conn.del::<_, ()>(self.failures_key(host_id)).await?;
+        Ok(())
+    }
+}
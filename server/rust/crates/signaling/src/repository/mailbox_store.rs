@@ -0,0 +1,137 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MailboxState {
+    pub mailbox_id: String,
+    pub peer_mailbox_id: Option<String>,
+    /// SHA-256 of the mailbox access token; the token itself is never stored.
+    #[serde(default)]
+    pub access_token_hash: String,
+    pub created_at_epoch_ms: u128,
+    pub expires_at_epoch_ms: u128,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct MailboxMessageStored {
+    pub from_mailbox_id: String,
+    pub ciphertext_b64: String,
+    pub sequence: u64,
+    pub timestamp_epoch_ms: u128,
+}
+
+/// Size limits for a single mailbox. Message count and total bytes are checked by the
+/// store together with the append, so concurrent senders cannot overshoot them.
+#[derive(Debug, Clone, Copy)]
+pub struct MailboxQuota {
+    /// Largest accepted `ciphertext_b64`, in bytes.
+    pub max_message_bytes: u64,
+    /// Most messages a mailbox may hold before older ones are acknowledged.
+    pub max_messages: u64,
+    /// Most `ciphertext_b64` bytes a mailbox may hold in total.
+    pub max_bytes: u64,
+}
+
+/// Result of [`MailboxStore::append_message`].
+#[derive(Debug, Clone)]
+pub enum MailboxAppend {
+    Appended(MailboxMessageStored),
+    QuotaExceeded,
+}
+
+/// Result of [`MailboxStore::redeem_rendezvous`] and [`MailboxStore::redeem_nameplate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RendezvousRedemption {
+    /// The token was live; it now points at nothing and this is its mailbox.
+    Redeemed(String),
+    /// The token was redeemed earlier and is still remembered as used.
+    AlreadyRedeemed,
+    /// The token never existed, expired, or was revoked.
+    Unknown,
+}
+
+/// Storage backend for rendezvous tokens, mailbox metadata and mailbox message lists.
+///
+/// Every write takes the TTL the entry should live for; backends are expected to
+/// expire entries on their own so the service never has to sweep them.
+#[async_trait]
+pub trait MailboxStore: Send + Sync {
+    async fn save_mailbox_meta(&self, state: &MailboxState, ttl_secs: u64) -> Result<()>;
+
+    async fn get_mailbox_meta(&self, mailbox_id: &str) -> Result<Option<MailboxState>>;
+
+    /// Metadata of every live mailbox, for operator tooling. May be slow on large
+    /// deployments; not for request paths.
+    async fn list_mailboxes(&self) -> Result<Vec<MailboxState>>;
+
+    /// Drops all messages and resets the sequence counter and byte usage.
+    async fn clear_mailbox_messages(&self, mailbox_id: &str) -> Result<()>;
+
+    async fn delete_mailbox_meta(&self, mailbox_id: &str) -> Result<()>;
+
+    /// Removes the metadata, messages, sequence counter and byte usage of a mailbox.
+    async fn delete_mailbox(&self, mailbox_id: &str) -> Result<()>;
+
+    async fn save_rendezvous(&self, token: &str, mailbox_id: &str, ttl_secs: u64) -> Result<()>;
+
+    /// Consumes a rendezvous token, so each token can be redeemed at most once.
+    /// The token is remembered as used for `used_ttl_secs`, so a second attempt
+    /// can be told apart from one with an unknown or expired token.
+    async fn redeem_rendezvous(
+        &self,
+        token: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption>;
+
+    /// Deletes a rendezvous token nobody has redeemed yet and returns its mailbox.
+    async fn get_and_delete_rendezvous(&self, token: &str) -> Result<Option<String>>;
+
+    /// Points a nameplate at a mailbox unless the nameplate is live or still
+    /// remembered as redeemed. Returns `false` if it is taken.
+    async fn claim_nameplate(
+        &self,
+        nameplate: &str,
+        mailbox_id: &str,
+        ttl_secs: u64,
+    ) -> Result<bool>;
+
+    /// Consumes a nameplate the way [`MailboxStore::redeem_rendezvous`] consumes
+    /// a token. Nameplates live apart from tokens, so neither can shadow the other.
+    async fn redeem_nameplate(
+        &self,
+        nameplate: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption>;
+
+    /// Allocates the next sequence number for `mailbox_id` and appends the message
+    /// in one atomic step, refreshing the TTL of the message log. Nothing is
+    /// written if the message would take the mailbox past `quota`.
+    ///
+    /// Sequences start at 1 and increase by one per message, so 0 always means
+    /// "nothing delivered yet".
+    async fn append_message(
+        &self,
+        mailbox_id: &str,
+        from_mailbox_id: &str,
+        ciphertext_b64: &str,
+        timestamp_epoch_ms: u128,
+        ttl_secs: u64,
+        quota: &MailboxQuota,
+    ) -> Result<MailboxAppend>;
+
+    /// Highest sequence allocated for `mailbox_id`, or 0 if none.
+    async fn get_last_sequence(&self, mailbox_id: &str) -> Result<u64>;
+
+    /// Messages with a sequence greater than `since_sequence`, in sequence order.
+    async fn get_messages(
+        &self,
+        mailbox_id: &str,
+        since_sequence: u64,
+    ) -> Result<Vec<MailboxMessageStored>>;
+
+    /// Drops messages with a sequence up to and including `up_to_sequence` and
+    /// releases their bytes from the quota. The sequence counter is left
+    /// untouched, so later messages keep counting up.
+    async fn trim_messages(&self, mailbox_id: &str, up_to_sequence: u64) -> Result<()>;
+}
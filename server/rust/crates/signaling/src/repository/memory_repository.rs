@@ -0,0 +1,310 @@
+use super::mailbox_store::{
+    MailboxAppend, MailboxMessageStored, MailboxQuota, MailboxState, MailboxStore,
+    RendezvousRedemption,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+#[derive(Debug, Clone)]
+struct Expiring<T> {
+    value: T,
+    expires_at: Instant,
+}
+
+impl<T> Expiring<T> {
+    fn new(value: T, ttl_secs: u64) -> Self {
+        Self {
+            value,
+            expires_at: Instant::now() + Duration::from_secs(ttl_secs),
+        }
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        now >= self.expires_at
+    }
+}
+
+#[derive(Debug, Default)]
+struct MessageLog {
+    last_sequence: u64,
+    bytes_used: u64,
+    messages: Vec<MailboxMessageStored>,
+}
+
+#[derive(Debug, Default)]
+struct MailboxTables {
+    meta: HashMap<String, Expiring<MailboxState>>,
+    messages: HashMap<String, Expiring<MessageLog>>,
+    rendezvous: HashMap<String, Expiring<String>>,
+    redeemed_rendezvous: HashMap<String, Expiring<()>>,
+    /// `None` once redeemed.
+    nameplates: HashMap<String, Expiring<Option<String>>>,
+}
+
+impl MailboxTables {
+    fn purge_expired(&mut self) {
+        let now = Instant::now();
+        self.meta.retain(|_, entry| !entry.is_expired(now));
+        self.messages.retain(|_, entry| !entry.is_expired(now));
+        self.rendezvous.retain(|_, entry| !entry.is_expired(now));
+        self.redeemed_rendezvous
+            .retain(|_, entry| !entry.is_expired(now));
+        self.nameplates.retain(|_, entry| !entry.is_expired(now));
+    }
+}
+
+/// Process-local mailbox store for single-node deployments and tests.
+///
+/// Mirrors the Redis key semantics, including per-entry TTLs. Expired entries are
+/// ignored on read and dropped whenever the store is written to.
+#[derive(Debug)]
+pub struct InMemoryMailboxRepository {
+    tables: RwLock<MailboxTables>,
+}
+
+impl InMemoryMailboxRepository {
+    pub fn new() -> Self {
+        Self {
+            tables: RwLock::new(MailboxTables::default()),
+        }
+    }
+}
+
+impl Default for InMemoryMailboxRepository {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl MailboxStore for InMemoryMailboxRepository {
+    async fn save_mailbox_meta(&self, state: &MailboxState, ttl_secs: u64) -> Result<()> {
+        let mut tables = self.tables.write().await;
+        tables.purge_expired();
+        tables.meta.insert(
+            state.mailbox_id.clone(),
+            Expiring::new(state.clone(), ttl_secs),
+        );
+        Ok(())
+    }
+
+    async fn get_mailbox_meta(&self, mailbox_id: &str) -> Result<Option<MailboxState>> {
+        let now = Instant::now();
+        Ok(self
+            .tables
+            .read()
+            .await
+            .meta
+            .get(mailbox_id)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value.clone()))
+    }
+
+    async fn list_mailboxes(&self) -> Result<Vec<MailboxState>> {
+        let now = Instant::now();
+        Ok(self
+            .tables
+            .read()
+            .await
+            .meta
+            .values()
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value.clone())
+            .collect())
+    }
+
+    async fn clear_mailbox_messages(&self, mailbox_id: &str) -> Result<()> {
+        self.tables.write().await.messages.remove(mailbox_id);
+        Ok(())
+    }
+
+    async fn delete_mailbox_meta(&self, mailbox_id: &str) -> Result<()> {
+        self.tables.write().await.meta.remove(mailbox_id);
+        Ok(())
+    }
+
+    async fn delete_mailbox(&self, mailbox_id: &str) -> Result<()> {
+        let mut tables = self.tables.write().await;
+        tables.meta.remove(mailbox_id);
+        tables.messages.remove(mailbox_id);
+        Ok(())
+    }
+
+    async fn save_rendezvous(&self, token: &str, mailbox_id: &str, ttl_secs: u64) -> Result<()> {
+        let mut tables = self.tables.write().await;
+        tables.purge_expired();
+        tables.rendezvous.insert(
+            token.to_string(),
+            Expiring::new(mailbox_id.to_string(), ttl_secs),
+        );
+        Ok(())
+    }
+
+    async fn redeem_rendezvous(
+        &self,
+        token: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption> {
+        let now = Instant::now();
+        let mut tables = self.tables.write().await;
+        if let Some(entry) = tables.rendezvous.remove(token) {
+            if !entry.is_expired(now) {
+                tables
+                    .redeemed_rendezvous
+                    .insert(token.to_string(), Expiring::new((), used_ttl_secs));
+                return Ok(RendezvousRedemption::Redeemed(entry.value));
+            }
+        }
+        let redeemed = tables
+            .redeemed_rendezvous
+            .get(token)
+            .is_some_and(|entry| !entry.is_expired(now));
+        Ok(if redeemed {
+            RendezvousRedemption::AlreadyRedeemed
+        } else {
+            RendezvousRedemption::Unknown
+        })
+    }
+
+    async fn get_and_delete_rendezvous(&self, token: &str) -> Result<Option<String>> {
+        let now = Instant::now();
+        Ok(self
+            .tables
+            .write()
+            .await
+            .rendezvous
+            .remove(token)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value))
+    }
+
+    async fn claim_nameplate(
+        &self,
+        nameplate: &str,
+        mailbox_id: &str,
+        ttl_secs: u64,
+    ) -> Result<bool> {
+        let mut tables = self.tables.write().await;
+        tables.purge_expired();
+        if tables.nameplates.contains_key(nameplate) {
+            return Ok(false);
+        }
+        tables.nameplates.insert(
+            nameplate.to_string(),
+            Expiring::new(Some(mailbox_id.to_string()), ttl_secs),
+        );
+        Ok(true)
+    }
+
+    async fn redeem_nameplate(
+        &self,
+        nameplate: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption> {
+        let now = Instant::now();
+        let mut tables = self.tables.write().await;
+        let Some(entry) = tables
+            .nameplates
+            .get_mut(nameplate)
+            .filter(|entry| !entry.is_expired(now))
+        else {
+            return Ok(RendezvousRedemption::Unknown);
+        };
+        Ok(match entry.value.take() {
+            Some(mailbox_id) => {
+                *entry = Expiring::new(None, used_ttl_secs);
+                RendezvousRedemption::Redeemed(mailbox_id)
+            }
+            None => RendezvousRedemption::AlreadyRedeemed,
+        })
+    }
+
+    async fn append_message(
+        &self,
+        mailbox_id: &str,
+        from_mailbox_id: &str,
+        ciphertext_b64: &str,
+        timestamp_epoch_ms: u128,
+        ttl_secs: u64,
+        quota: &MailboxQuota,
+    ) -> Result<MailboxAppend> {
+        let mut tables = self.tables.write().await;
+        tables.purge_expired();
+        let entry = tables
+            .messages
+            .entry(mailbox_id.to_string())
+            .or_insert_with(|| Expiring::new(MessageLog::default(), ttl_secs));
+        let size = ciphertext_b64.len() as u64;
+        if entry.value.messages.len() as u64 >= quota.max_messages
+            || entry.value.bytes_used + size > quota.max_bytes
+        {
+            return Ok(MailboxAppend::QuotaExceeded);
+        }
+        entry.value.last_sequence += 1;
+        entry.value.bytes_used += size;
+        let message = MailboxMessageStored {
+            from_mailbox_id: from_mailbox_id.to_string(),
+            ciphertext_b64: ciphertext_b64.to_string(),
+            sequence: entry.value.last_sequence,
+            timestamp_epoch_ms,
+        };
+        entry.value.messages.push(message.clone());
+        entry.expires_at = Instant::now() + Duration::from_secs(ttl_secs);
+        Ok(MailboxAppend::Appended(message))
+    }
+
+    async fn get_last_sequence(&self, mailbox_id: &str) -> Result<u64> {
+        let now = Instant::now();
+        Ok(self
+            .tables
+            .read()
+            .await
+            .messages
+            .get(mailbox_id)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value.last_sequence)
+            .unwrap_or(0))
+    }
+
+    async fn get_messages(
+        &self,
+        mailbox_id: &str,
+        since_sequence: u64,
+    ) -> Result<Vec<MailboxMessageStored>> {
+        let now = Instant::now();
+        Ok(self
+            .tables
+            .read()
+            .await
+            .messages
+            .get(mailbox_id)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| {
+                entry
+                    .value
+                    .messages
+                    .iter()
+                    .filter(|message| message.sequence > since_sequence)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn trim_messages(&self, mailbox_id: &str, up_to_sequence: u64) -> Result<()> {
+        if let Some(entry) = self.tables.write().await.messages.get_mut(mailbox_id) {
+            let log = &mut entry.value;
+            log.messages
+                .retain(|message| message.sequence > up_to_sequence);
+            log.bytes_used = log
+                .messages
+                .iter()
+                .map(|message| message.ciphertext_b64.len() as u64)
+                .sum();
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,10 @@
+pub mod host_store;
+pub mod mailbox_store;
+pub mod memory_repository;
+pub mod payload_cipher;
+pub mod rate_limit_store;
 pub mod redis_repository;
+pub mod redis_session_repository;
 pub mod session_repository;
+pub mod session_store;
+pub mod timed_connection;
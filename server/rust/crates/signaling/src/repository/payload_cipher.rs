@@ -0,0 +1,86 @@
+use anyhow::{anyhow, bail, Result};
+use shared::connection;
+use std::collections::HashMap;
+
+/// Marker for values sealed by [`PayloadCipher`]: `enc1:{key_id}:{base64}`.
+const SEALED_PREFIX: &str = "enc1:";
+
+/// AES-256-GCM envelope for values written to Redis.
+///
+/// New values are always sealed with the active key. Each sealed value names the
+/// key it was sealed with, so retired keys can stay configured for decryption
+/// until every mailbox written under them has expired. Values without the
+/// marker are rejected unless [`PayloadCipher::allow_plaintext`] is set, so
+/// encryption can be switched on without flushing existing mailboxes but an
+/// unsealed value is never accepted by accident.
+pub struct PayloadCipher {
+    active_key_id: String,
+    keys: HashMap<String, [u8; 32]>,
+    allow_plaintext: bool,
+}
+
+impl PayloadCipher {
+    pub fn new(
+        active_key_id: impl Into<String>,
+        active_key: [u8; 32],
+        previous_keys: impl IntoIterator<Item = (String, [u8; 32])>,
+    ) -> Result<Self> {
+        let active_key_id = active_key_id.into();
+        validate_key_id(&active_key_id)?;
+
+        let mut keys = HashMap::new();
+        for (key_id, key) in previous_keys {
+            validate_key_id(&key_id)?;
+            keys.insert(key_id, key);
+        }
+        keys.insert(active_key_id.clone(), active_key);
+
+        Ok(Self {
+            active_key_id,
+            keys,
+            allow_plaintext: false,
+        })
+    }
+
+    /// Passes values without the marker through unchanged. Only meant for the
+    /// mailbox TTL after encryption is first enabled.
+    pub fn allow_plaintext(mut self, allow: bool) -> Self {
+        self.allow_plaintext = allow;
+        self
+    }
+
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let key = &self.keys[&self.active_key_id];
+        let sealed = connection::encrypt_payload(key, plaintext.as_bytes())?;
+        Ok(format!(
+            "{}{}:{}",
+            SEALED_PREFIX, self.active_key_id, sealed
+        ))
+    }
+
+    pub fn open(&self, stored: &str) -> Result<String> {
+        let Some(rest) = stored.strip_prefix(SEALED_PREFIX) else {
+            if self.allow_plaintext {
+                return Ok(stored.to_string());
+            }
+            bail!("payload is not sealed and plaintext is not allowed");
+        };
+        let (key_id, sealed) = rest
+            .split_once(':')
+            .ok_or_else(|| anyhow!("malformed sealed payload"))?;
+        let key = self
+            .keys
+            .get(key_id)
+            .ok_or_else(|| anyhow!("unknown payload encryption key id: {key_id}"))?;
+        let plaintext = connection::decrypt_payload(key, sealed)
+            .map_err(|err| anyhow!("payload sealed with key id {key_id}: {err}"))?;
+        Ok(String::from_utf8(plaintext)?)
+    }
+}
+
+fn validate_key_id(key_id: &str) -> Result<()> {
+    if key_id.is_empty() || key_id.contains(':') {
+        bail!("invalid payload encryption key id {key_id:?}: must be non-empty and contain no ':'");
+    }
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use super::timed_connection::TimedConnection;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Buckets that have refilled completely carry no state worth keeping; the
+/// in-memory store sweeps them out once it holds more than this many.
+const MEMORY_PRUNE_THRESHOLD: usize = 4096;
+
+/// Sweeps above the threshold run at most this often, since each one walks
+/// every bucket.
+const MEMORY_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Hard cap on in-memory buckets. When sweeping leaves no room, the bucket
+/// closest to full is dropped, which forgives at most the tokens it lacks.
+const MEMORY_MAX_BUCKETS: usize = 65_536;
+
+/// Token bucket: up to `burst` requests at once, refilled at `burst` per `period`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub burst: u32,
+    pub period: Duration,
+}
+
+impl RateLimitPolicy {
+    fn refill_per_ms(&self) -> f64 {
+        f64::from(self.burst) / self.period.as_millis().max(1) as f64
+    }
+}
+
+impl std::str::FromStr for RateLimitPolicy {
+    type Err = anyhow::Error;
+
+    /// Parses `<requests>/<seconds>`, e.g. `10/60`.
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let (burst, secs) = raw
+            .split_once('/')
+            .ok_or_else(|| anyhow::anyhow!("expected <requests>/<seconds>"))?;
+        let burst: u32 = burst.trim().parse()?;
+        let secs: u64 = secs.trim().parse()?;
+        if burst == 0 || secs == 0 {
+            anyhow::bail!("rate limit requests and seconds must be positive");
+        }
+        Ok(Self {
+            burst,
+            period: Duration::from_secs(secs),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    Limited { retry_after: Duration },
+}
+
+/// Storage for token buckets, keyed by an opaque string (scope plus client IP).
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Takes one token from the bucket for `key`, creating a full bucket if needed.
+    async fn try_acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateDecision>;
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// When the bucket is back to `burst` under its own policy, so buckets of
+    /// different scopes are only forgotten once they have nothing to remember.
+    full_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct MemoryBuckets {
+    buckets: HashMap<String, Bucket>,
+    last_sweep: Option<Instant>,
+}
+
+impl MemoryBuckets {
+    /// Drops full buckets once the map grows past the threshold, and makes
+    /// room for `key` if it would go past the cap.
+    fn make_room(&mut self, key: &str, now: Instant) {
+        let due = self
+            .last_sweep
+            .is_none_or(|last| now.duration_since(last) >= MEMORY_SWEEP_INTERVAL);
+        if self.buckets.len() > MEMORY_PRUNE_THRESHOLD && due {
+            self.buckets.retain(|_, bucket| bucket.full_at > now);
+            self.last_sweep = Some(now);
+        }
+        if self.buckets.len() >= MEMORY_MAX_BUCKETS && !self.buckets.contains_key(key) {
+            let fullest = self
+                .buckets
+                .iter()
+                .min_by_key(|(_, bucket)| bucket.full_at)
+                .map(|(key, _)| key.clone());
+            if let Some(fullest) = fullest {
+                self.buckets.remove(&fullest);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitRepository {
+    state: Mutex<MemoryBuckets>,
+}
+
+impl InMemoryRateLimitRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for InMemoryRateLimitRepository {
+    async fn try_acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateDecision> {
+        let now = Instant::now();
+        let capacity = f64::from(policy.burst);
+        let refill_per_ms = policy.refill_per_ms();
+
+        let mut state = self.state.lock().await;
+        state.make_room(key, now);
+
+        let bucket = state.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            full_at: now,
+        });
+        let elapsed_ms = now.duration_since(bucket.updated).as_millis() as f64;
+        bucket.tokens = (bucket.tokens + elapsed_ms * refill_per_ms).min(capacity);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+        let refill_ms = ((capacity - bucket.tokens) / refill_per_ms).ceil() as u64;
+        bucket.full_at = now + Duration::from_millis(refill_ms);
+
+        if allowed {
+            Ok(RateDecision::Allowed)
+        } else {
+            let wait_ms = ((1.0 - bucket.tokens) / refill_per_ms).ceil() as u64;
+            Ok(RateDecision::Limited {
+                retry_after: Duration::from_millis(wait_ms),
+            })
+        }
+    }
+}
+
+/// Token bucket evaluated inside Redis using the server clock, so every replica
+/// shares one bucket per key. Returns 0 when allowed, otherwise the wait in ms.
+/// KEYS: 1 = bucket hash. ARGV: 1 = capacity, 2 = refill per ms.
+static TOKEN_BUCKET_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local capacity = tonumber(ARGV[1])
+local refill = tonumber(ARGV[2])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(state[1]) or capacity
+local ts = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill)
+local wait = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+else
+    wait = math.ceil((1 - tokens) / refill)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill))
+return wait
+",
+    )
+});
+
+#[derive(Clone)]
+pub struct RedisRateLimitRepository {
+    conn_manager: TimedConnection,
+    key_prefix: String,
+}
+
+impl RedisRateLimitRepository {
+    pub fn new(conn_manager: redis::aio::ConnectionManager, key_prefix: String) -> Self {
+        Self {
+            conn_manager: TimedConnection::new(conn_manager),
+            key_prefix,
+        }
+    }
+
+    fn bucket_key(&self, key: &str) -> String {
+        format!("{}:ratelimit:{}", self.key_prefix, key)
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for RedisRateLimitRepository {
+    async fn try_acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateDecision> {
+        let mut conn = self.conn_manager.clone();
+        let wait_ms: u64 = TOKEN_BUCKET_SCRIPT
+            .key(self.bucket_key(key))
+            .arg(policy.burst)
+            .arg(policy.refill_per_ms())
+            .invoke_async(&mut conn)
+            .await?;
+        if wait_ms == 0 {
+            Ok(RateDecision::Allowed)
+        } else {
+            Ok(RateDecision::Limited {
+                retry_after: Duration::from_millis(wait_ms),
+            })
+        }
+    }
+}
@@ -1,139 +1,371 @@
+use super::mailbox_store::{
+    MailboxAppend, MailboxMessageStored, MailboxQuota, MailboxState, MailboxStore,
+    RendezvousRedemption,
+};
+use super::payload_cipher::PayloadCipher;
+use super::timed_connection::TimedConnection;
 use anyhow::Result;
+use async_trait::async_trait;
 use redis::AsyncCommands;
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct MailboxState {
-    pub mailbox_id: String,
-    pub peer_mailbox_id: Option<String>,
-    pub created_at_epoch_ms: u128,
-    pub expires_at_epoch_ms: u128,
-}
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
+use tracing::warn;
+
+/// Allocates the next sequence and appends the message under it in one step,
+/// unless the mailbox is already at its message or byte quota (returns -1).
+/// KEYS: 1 = message log (sorted set scored by sequence), 2 = sequence counter,
+/// 3 = bytes in use. ARGV: 1 = body, 2 = TTL, 3 = ciphertext bytes,
+/// 4 = max messages, 5 = max bytes.
+/// Members are `{sequence}:{ciphertext bytes}:{body}` so identical bodies never
+/// collapse and trimming can release bytes without decrypting.
+static APPEND_MESSAGE_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local size = tonumber(ARGV[3])
+local used = tonumber(redis.call('GET', KEYS[3]) or '0')
+if redis.call('ZCARD', KEYS[1]) >= tonumber(ARGV[4]) or used + size > tonumber(ARGV[5]) then
+    return -1
+end
+local seq = redis.call('INCR', KEYS[2])
+redis.call('ZADD', KEYS[1], seq, seq .. ':' .. size .. ':' .. ARGV[1])
+redis.call('INCRBY', KEYS[3], size)
+redis.call('EXPIRE', KEYS[1], ARGV[2])
+redis.call('EXPIRE', KEYS[2], ARGV[2])
+redis.call('EXPIRE', KEYS[3], ARGV[2])
+return seq
+",
+    )
+});
+
+/// Removes acknowledged messages and gives their bytes back to the quota.
+/// KEYS: 1 = message log, 2 = bytes in use. ARGV: 1 = highest sequence to drop.
+static TRIM_MESSAGES_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local removed = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+if #removed == 0 then
+    return 0
+end
+local freed = 0
+for _, member in ipairs(removed) do
+    freed = freed + tonumber(string.match(member, '^%d+:(%d+):') or '0')
+end
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+if redis.call('EXISTS', KEYS[2]) == 1 then
+    redis.call('DECRBY', KEYS[2], freed)
+end
+return #removed
+",
+    )
+});
+
+/// Value a rendezvous key is overwritten with once redeemed. Mailbox IDs are
+/// never empty, so it cannot be mistaken for one.
+const REDEEMED_RENDEZVOUS: &str = "";
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct MailboxMessageStored {
-    pub from_mailbox_id: String,
-    pub ciphertext_b64: String,
-    pub sequence: u64,
-    pub timestamp_epoch_ms: u128,
+/// Keys fetched per MGET when listing mailboxes.
+const LIST_BATCH_SIZE: usize = 500;
+
+/// Message fields stored in Redis; the sequence lives in the member prefix and score.
+#[derive(Deserialize, Serialize)]
+struct StoredMessageBody {
+    from_mailbox_id: String,
+    ciphertext_b64: String,
+    timestamp_epoch_ms: u128,
 }
 
 #[derive(Clone)]
 pub struct RedisRepository {
-    conn_manager: redis::aio::ConnectionManager,
+    conn_manager: TimedConnection,
     key_prefix: String,
+    cipher: Option<Arc<PayloadCipher>>,
 }
 
 impl RedisRepository {
     pub fn new(conn_manager: redis::aio::ConnectionManager, key_prefix: String) -> Self {
         Self {
-            conn_manager,
+            conn_manager: TimedConnection::new(conn_manager),
             key_prefix,
+            cipher: None,
         }
     }
 
+    /// Seals mailbox metadata and messages before they are written to Redis.
+    pub fn with_cipher(mut self, cipher: PayloadCipher) -> Self {
+        self.cipher = Some(Arc::new(cipher));
+        self
+    }
+
+    fn encode<T: Serialize>(&self, value: &T) -> Result<String> {
+        let json = serde_json::to_string(value)?;
+        match &self.cipher {
+            Some(cipher) => cipher.seal(&json),
+            None => Ok(json),
+        }
+    }
+
+    fn decode<T: DeserializeOwned>(&self, stored: &str) -> Result<T> {
+        match &self.cipher {
+            Some(cipher) => Ok(serde_json::from_str(&cipher.open(stored)?)?),
+            None => Ok(serde_json::from_str(stored)?),
+        }
+    }
+
+    fn decode_message(&self, member: &str) -> Result<MailboxMessageStored> {
+        let mut parts = member.splitn(3, ':');
+        let (Some(sequence), Some(_size), Some(body)) = (parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("malformed mailbox log entry");
+        };
+        let body: StoredMessageBody = self.decode(body)?;
+        Ok(MailboxMessageStored {
+            from_mailbox_id: body.from_mailbox_id,
+            ciphertext_b64: body.ciphertext_b64,
+            sequence: sequence.parse()?,
+            timestamp_epoch_ms: body.timestamp_epoch_ms,
+        })
+    }
+
     fn meta_key(&self, mailbox_id: &str) -> String {
         format!("{}:mailbox_meta:{}", self.key_prefix, mailbox_id)
     }
 
-    fn list_key(&self, mailbox_id: &str) -> String {
-        format!("{}:mailbox_msgs:{}", self.key_prefix, mailbox_id)
+    fn log_key(&self, mailbox_id: &str) -> String {
+        format!("{}:mailbox_log:{}", self.key_prefix, mailbox_id)
+    }
+
+    fn sequence_key(&self, mailbox_id: &str) -> String {
+        format!("{}:mailbox_seq:{}", self.key_prefix, mailbox_id)
+    }
+
+    fn bytes_key(&self, mailbox_id: &str) -> String {
+        format!("{}:mailbox_bytes:{}", self.key_prefix, mailbox_id)
     }
 
     fn rendezvous_key(&self, token: &str) -> String {
         format!("{}:rendezvous:{}", self.key_prefix, token)
     }
 
-    pub async fn save_mailbox_meta(&self, state: &MailboxState, ttl_secs: u64) -> Result<()> {
+    fn nameplate_key(&self, nameplate: &str) -> String {
+        format!("{}:nameplate:{}", self.key_prefix, nameplate)
+    }
+
+    /// Swaps the redeemed marker into `key` if it exists. Of two concurrent
+    /// joins, exactly one sees the mailbox ID.
+    async fn redeem_key(&self, key: String, used_ttl_secs: u64) -> Result<RendezvousRedemption> {
+        let mut conn = self.conn_manager.clone();
+        let previous: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(REDEEMED_RENDEZVOUS)
+            .arg("XX")
+            .arg("GET")
+            .arg("EX")
+            .arg(used_ttl_secs)
+            .query_async(&mut conn)
+            .await?;
+        Ok(match previous {
+            None => RendezvousRedemption::Unknown,
+            Some(value) if value == REDEEMED_RENDEZVOUS => RendezvousRedemption::AlreadyRedeemed,
+            Some(mailbox_id) => RendezvousRedemption::Redeemed(mailbox_id),
+        })
+    }
+}
+
+#[async_trait]
+impl MailboxStore for RedisRepository {
+    async fn save_mailbox_meta(&self, state: &MailboxState, ttl_secs: u64) -> Result<()> {
         let mut conn = self.conn_manager.clone();
         let key = self.meta_key(&state.mailbox_id);
-        let json = serde_json::to_string(state)?;
-        conn.set_ex::<_, _, ()>(key, json, ttl_secs).await?;
+        let value = self.encode(state)?;
+        conn.set_ex::<_, _, ()>(key, value, ttl_secs).await?;
         Ok(())
     }
 
-    pub async fn get_mailbox_meta(&self, mailbox_id: &str) -> Result<Option<MailboxState>> {
+    async fn get_mailbox_meta(&self, mailbox_id: &str) -> Result<Option<MailboxState>> {
         let mut conn = self.conn_manager.clone();
         let key = self.meta_key(mailbox_id);
-        let json: Option<String> = conn.get(key).await?;
-        match json {
-            Some(s) => Ok(Some(serde_json::from_str(&s)?)),
+        let stored: Option<String> = conn.get(key).await?;
+        match stored {
+            Some(s) => Ok(Some(self.decode(&s)?)),
             None => Ok(None),
         }
     }
 
-    pub async fn clear_mailbox_messages(&self, mailbox_id: &str) -> Result<()> {
+    async fn list_mailboxes(&self) -> Result<Vec<MailboxState>> {
         let mut conn = self.conn_manager.clone();
-        let key = self.list_key(mailbox_id);
-        conn.del::<_, ()>(key).await?;
+        let pattern = self.meta_key("*");
+        let mut keys: Vec<String> = Vec::new();
+        {
+            let mut iter = conn.scan_match::<_, String>(pattern).await?;
+            while let Some(key) = iter.next_item().await {
+                keys.push(key?);
+            }
+        }
+        let mut mailboxes = Vec::with_capacity(keys.len());
+        for chunk in keys.chunks(LIST_BATCH_SIZE) {
+            let stored: Vec<Option<String>> = conn.mget(chunk).await?;
+            // One unreadable mailbox should not hide the rest from the admin API
+            mailboxes.extend(stored.into_iter().flatten().filter_map(|s| {
+                self.decode::<MailboxState>(&s)
+                    .map_err(|err| warn!("Skipping unreadable mailbox state: {err:#}"))
+                    .ok()
+            }));
+        }
+        Ok(mailboxes)
+    }
+
+    async fn clear_mailbox_messages(&self, mailbox_id: &str) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        let keys = [
+            self.log_key(mailbox_id),
+            self.sequence_key(mailbox_id),
+            self.bytes_key(mailbox_id),
+        ];
+        conn.del::<_, ()>(&keys).await?;
         Ok(())
     }
 
-    pub async fn delete_mailbox_meta(&self, mailbox_id: &str) -> Result<()> {
+    async fn delete_mailbox_meta(&self, mailbox_id: &str) -> Result<()> {
         let mut conn = self.conn_manager.clone();
         let key = self.meta_key(mailbox_id);
         conn.del::<_, ()>(key).await?;
         Ok(())
     }
 
-    pub async fn delete_mailbox(&self, mailbox_id: &str) -> Result<()> {
+    async fn delete_mailbox(&self, mailbox_id: &str) -> Result<()> {
         let mut conn = self.conn_manager.clone();
-        let meta_key = self.meta_key(mailbox_id);
-        let list_key = self.list_key(mailbox_id);
-        let _: () = conn.del(&[meta_key, list_key]).await?;
+        let keys = [
+            self.meta_key(mailbox_id),
+            self.log_key(mailbox_id),
+            self.sequence_key(mailbox_id),
+            self.bytes_key(mailbox_id),
+        ];
+        let _: () = conn.del(&keys).await?;
         Ok(())
     }
 
-    pub async fn save_rendezvous(
-        &self,
-        token: &str,
-        mailbox_id: &str,
-        ttl_secs: u64,
-    ) -> Result<()> {
+    async fn save_rendezvous(&self, token: &str, mailbox_id: &str, ttl_secs: u64) -> Result<()> {
         let mut conn = self.conn_manager.clone();
         let key = self.rendezvous_key(token);
         conn.set_ex::<_, _, ()>(key, mailbox_id, ttl_secs).await?;
         Ok(())
     }
 
-    pub async fn get_and_delete_rendezvous(&self, token: &str) -> Result<Option<String>> {
+    async fn redeem_rendezvous(
+        &self,
+        token: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption> {
+        self.redeem_key(self.rendezvous_key(token), used_ttl_secs)
+            .await
+    }
+
+    async fn get_and_delete_rendezvous(&self, token: &str) -> Result<Option<String>> {
         let mut conn = self.conn_manager.clone();
-        let key = self.rendezvous_key(token);
-        let val: Option<String> = conn.get(&key).await?;
-        if val.is_some() {
-            conn.del::<_, ()>(&key).await?;
-        }
-        Ok(val)
+        // One command, so a join redeeming the token concurrently either wins
+        // and leaves its marker here, or finds the token gone
+        let val: Option<String> = conn.get_del(self.rendezvous_key(token)).await?;
+        Ok(val.filter(|value| value != REDEEMED_RENDEZVOUS))
     }
 
-    pub async fn get_message_count(&self, mailbox_id: &str) -> Result<u64> {
+    async fn claim_nameplate(
+        &self,
+        nameplate: &str,
+        mailbox_id: &str,
+        ttl_secs: u64,
+    ) -> Result<bool> {
         let mut conn = self.conn_manager.clone();
-        let key = self.list_key(mailbox_id);
-        Ok(conn.llen(key).await?)
+        // A redeemed marker still occupies the key, so the nameplate is not
+        // handed out again while late joiners may still try it
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(self.nameplate_key(nameplate))
+            .arg(mailbox_id)
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs)
+            .query_async(&mut conn)
+            .await?;
+        Ok(claimed.is_some())
     }
 
-    pub async fn push_message(
+    async fn redeem_nameplate(
+        &self,
+        nameplate: &str,
+        used_ttl_secs: u64,
+    ) -> Result<RendezvousRedemption> {
+        self.redeem_key(self.nameplate_key(nameplate), used_ttl_secs)
+            .await
+    }
+
+    async fn append_message(
         &self,
         mailbox_id: &str,
-        message: &MailboxMessageStored,
+        from_mailbox_id: &str,
+        ciphertext_b64: &str,
+        timestamp_epoch_ms: u128,
         ttl_secs: u64,
-    ) -> Result<()> {
+        quota: &MailboxQuota,
+    ) -> Result<MailboxAppend> {
         let mut conn = self.conn_manager.clone();
-        let key = self.list_key(mailbox_id);
-        let json = serde_json::to_string(message)?;
-        conn.rpush::<_, _, ()>(&key, &json).await?;
-        conn.expire::<_, ()>(&key, ttl_secs as i64).await?;
-        Ok(())
+        let body = self.encode(&StoredMessageBody {
+            from_mailbox_id: from_mailbox_id.to_string(),
+            ciphertext_b64: ciphertext_b64.to_string(),
+            timestamp_epoch_ms,
+        })?;
+        let sequence: i64 = APPEND_MESSAGE_SCRIPT
+            .key(self.log_key(mailbox_id))
+            .key(self.sequence_key(mailbox_id))
+            .key(self.bytes_key(mailbox_id))
+            .arg(body)
+            .arg(ttl_secs)
+            .arg(ciphertext_b64.len())
+            .arg(quota.max_messages)
+            .arg(quota.max_bytes)
+            .invoke_async(&mut conn)
+            .await?;
+        if sequence < 0 {
+            return Ok(MailboxAppend::QuotaExceeded);
+        }
+        Ok(MailboxAppend::Appended(MailboxMessageStored {
+            from_mailbox_id: from_mailbox_id.to_string(),
+            ciphertext_b64: ciphertext_b64.to_string(),
+            sequence: sequence as u64,
+            timestamp_epoch_ms,
+        }))
+    }
+
+    async fn get_last_sequence(&self, mailbox_id: &str) -> Result<u64> {
+        let mut conn = self.conn_manager.clone();
+        let sequence: Option<u64> = conn.get(self.sequence_key(mailbox_id)).await?;
+        Ok(sequence.unwrap_or(0))
     }
 
-    pub async fn get_messages(&self, mailbox_id: &str) -> Result<Vec<MailboxMessageStored>> {
+    async fn get_messages(
+        &self,
+        mailbox_id: &str,
+        since_sequence: u64,
+    ) -> Result<Vec<MailboxMessageStored>> {
         let mut conn = self.conn_manager.clone();
-        let key = self.list_key(mailbox_id);
-        let jsons: Vec<String> = conn.lrange(key, 0, -1).await?;
-        let messages = jsons
+        let key = self.log_key(mailbox_id);
+        let stored: Vec<String> = conn
+            .zrangebyscore(key, format!("({since_sequence}"), "+inf")
+            .await?;
+        // A message that cannot be read (e.g. sealed with a key that was rotated
+        // away) fails the fetch rather than leaving a silent gap in the sequence
+        stored
             .into_iter()
-            .filter_map(|j| serde_json::from_str(&j).ok())
-            .collect();
-        Ok(messages)
+            .map(|s| self.decode_message(&s))
+            .collect()
+    }
+
+    async fn trim_messages(&self, mailbox_id: &str, up_to_sequence: u64) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        TRIM_MESSAGES_SCRIPT
+            .key(self.log_key(mailbox_id))
+            .key(self.bytes_key(mailbox_id))
+            .arg(up_to_sequence)
+            .invoke_async::<()>(&mut conn)
+            .await?;
+        Ok(())
     }
 }
@@ -0,0 +1,276 @@
+use super::session_store::{ClientRecord, SessionStore};
+use super::timed_connection::TimedConnection;
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::AsyncCommands;
+use shared::models::{ClientId, SignalEnvelope};
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Replaces a client record only if it still holds the value the heartbeat
+/// read, so a client removed or re-registered in between is not written back.
+/// KEYS: 1 = client record, 2 = clients index, 3 = signal queue.
+/// ARGV: 1 = expected record, 2 = new record, 3 = TTL secs, 4 = client ID,
+/// 5 = heartbeat ms.
+/// Returns 1 when updated, 0 when the record is gone, -1 when it changed.
+static UPDATE_HEARTBEAT_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local current = redis.call('GET', KEYS[1])
+if not current then
+    return 0
+end
+if current ~= ARGV[1] then
+    return -1
+end
+redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[3])
+redis.call('ZADD', KEYS[2], ARGV[5], ARGV[4])
+redis.call('EXPIRE', KEYS[3], ARGV[3])
+return 1
+",
+    )
+});
+
+/// Removes every client whose last heartbeat is at or before the cutoff, in one
+/// step so a heartbeat cannot refresh a client between selecting and deleting
+/// it.
+/// KEYS: 1 = clients index. ARGV: 1 = cutoff ms, 2 = client record key prefix.
+/// Returns the removed client IDs.
+static PRUNE_STALE_CLIENTS_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r"
+local stale = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1])
+for _, client_id in ipairs(stale) do
+    redis.call('ZREM', KEYS[1], client_id)
+    redis.call('DEL', ARGV[2] .. client_id)
+end
+return stale
+",
+    )
+});
+
+/// Attempts before a heartbeat gives up on a record that keeps changing.
+const HEARTBEAT_ATTEMPTS: usize = 3;
+
+/// Session store shared by every signaling replica.
+///
+/// Client records and signal queues carry a Redis TTL of `session_ttl` that is
+/// refreshed on each heartbeat, while a sorted set indexed by last heartbeat
+/// keeps client counts and stale-client pruning cheap.
+#[derive(Clone)]
+pub struct RedisSessionRepository {
+    conn_manager: TimedConnection,
+    key_prefix: String,
+}
+
+impl RedisSessionRepository {
+    pub fn new(conn_manager: redis::aio::ConnectionManager, key_prefix: String) -> Self {
+        Self {
+            conn_manager: TimedConnection::new(conn_manager),
+            key_prefix,
+        }
+    }
+
+    fn client_key(&self, client_id: &ClientId) -> String {
+        format!("{}{}", self.client_key_prefix(), client_id)
+    }
+
+    fn client_key_prefix(&self) -> String {
+        format!("{}:client:", self.key_prefix)
+    }
+
+    fn clients_index_key(&self) -> String {
+        format!("{}:clients", self.key_prefix)
+    }
+
+    fn signals_key(&self, client_id: &ClientId) -> String {
+        format!("{}:client_signals:{}", self.key_prefix, client_id)
+    }
+
+    fn challenge_key(&self, nonce: &str) -> String {
+        format!("{}:register_challenge:{}", self.key_prefix, nonce)
+    }
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionRepository {
+    async fn insert_client(
+        &self,
+        client_id: ClientId,
+        record: ClientRecord,
+        session_ttl: Duration,
+    ) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        let json = serde_json::to_string(&record)?;
+        redis::pipe()
+            .atomic()
+            .set_ex(self.client_key(&client_id), json, session_ttl.as_secs())
+            .ignore()
+            .zadd(
+                self.clients_index_key(),
+                client_id.to_string(),
+                record.last_heartbeat_epoch_ms as u64,
+            )
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &ClientId) -> Result<Option<ClientRecord>> {
+        let mut conn = self.conn_manager.clone();
+        let json: Option<String> = conn.get(self.client_key(client_id)).await?;
+        match json {
+            Some(s) => Ok(Some(serde_json::from_str(&s)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_client_heartbeat(
+        &self,
+        client_id: &ClientId,
+        timestamp_epoch_ms: u128,
+        session_ttl: Duration,
+    ) -> Result<bool> {
+        let mut conn = self.conn_manager.clone();
+        for _ in 0..HEARTBEAT_ATTEMPTS {
+            let Some(current) = conn
+                .get::<_, Option<String>>(self.client_key(client_id))
+                .await?
+            else {
+                return Ok(false);
+            };
+            let mut record: ClientRecord = serde_json::from_str(&current)?;
+            record.last_heartbeat_epoch_ms = timestamp_epoch_ms;
+
+            let updated: i64 = UPDATE_HEARTBEAT_SCRIPT
+                .key(self.client_key(client_id))
+                .key(self.clients_index_key())
+                .key(self.signals_key(client_id))
+                .arg(&current)
+                .arg(serde_json::to_string(&record)?)
+                .arg(session_ttl.as_secs())
+                .arg(client_id.to_string())
+                .arg(timestamp_epoch_ms as u64)
+                .invoke_async(&mut conn)
+                .await?;
+            match updated {
+                1 => return Ok(true),
+                0 => return Ok(false),
+                // Re-registered concurrently; heartbeat the new record
+                _ => continue,
+            }
+        }
+        anyhow::bail!("client record for {client_id} kept changing during heartbeat")
+    }
+
+    async fn remove_client(&self, client_id: &ClientId) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        redis::pipe()
+            .atomic()
+            .del(self.client_key(client_id))
+            .ignore()
+            .zrem(self.clients_index_key(), client_id.to_string())
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_client_count(&self) -> Result<usize> {
+        let mut conn = self.conn_manager.clone();
+        Ok(conn.zcard(self.clients_index_key()).await?)
+    }
+
+    async fn list_clients(&self) -> Result<Vec<(ClientId, ClientRecord)>> {
+        let mut conn = self.conn_manager.clone();
+        let ids: Vec<String> = conn.zrange(self.clients_index_key(), 0, -1).await?;
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let client_ids: Vec<ClientId> = ids.iter().filter_map(|id| id.parse().ok()).collect();
+        let keys: Vec<String> = client_ids.iter().map(|id| self.client_key(id)).collect();
+        let records: Vec<Option<String>> = conn.mget(&keys).await?;
+        // Index entries whose record already expired are skipped; the next prune drops them
+        Ok(client_ids
+            .into_iter()
+            .zip(records)
+            .filter_map(|(client_id, json)| {
+                let record = serde_json::from_str(&json?).ok()?;
+                Some((client_id, record))
+            })
+            .collect())
+    }
+
+    async fn add_message(&self, message: SignalEnvelope, session_ttl: Duration) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        let key = self.signals_key(&message.to);
+        let json = serde_json::to_string(&message)?;
+        redis::pipe()
+            .atomic()
+            .rpush(&key, json)
+            .ignore()
+            .expire(&key, session_ttl.as_secs() as i64)
+            .ignore()
+            .query_async::<()>(&mut conn)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_messages_for_client(&self, client_id: &ClientId) -> Result<Vec<SignalEnvelope>> {
+        let mut conn = self.conn_manager.clone();
+        let key = self.signals_key(client_id);
+        let (jsons,): (Vec<String>,) = redis::pipe()
+            .atomic()
+            .lrange(&key, 0, -1)
+            .del(&key)
+            .ignore()
+            .query_async(&mut conn)
+            .await?;
+        Ok(jsons
+            .into_iter()
+            .filter_map(|j| serde_json::from_str(&j).ok())
+            .collect())
+    }
+
+    async fn prune_stale_clients(&self, expiration_threshold: Duration) -> Result<Vec<ClientId>> {
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let cutoff_ms = now_ms.saturating_sub(expiration_threshold.as_millis()) as u64;
+
+        let mut conn = self.conn_manager.clone();
+        let stale: Vec<String> = PRUNE_STALE_CLIENTS_SCRIPT
+            .key(self.clients_index_key())
+            .arg(cutoff_ms)
+            .arg(self.client_key_prefix())
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(stale.iter().filter_map(|id| id.parse().ok()).collect())
+    }
+
+    async fn prune_messages_for_clients(&self, client_ids: &[ClientId]) -> Result<()> {
+        if client_ids.is_empty() {
+            return Ok(());
+        }
+        // Messages already queued by a stale client for a live peer stay put;
+        // unlike the in-memory store they are only dropped by the peer's TTL.
+        let mut conn = self.conn_manager.clone();
+        let keys: Vec<String> = client_ids.iter().map(|id| self.signals_key(id)).collect();
+        conn.del::<_, ()>(keys).await?;
+        Ok(())
+    }
+
+    async fn save_challenge(&self, nonce: &str, ttl: Duration) -> Result<()> {
+        let mut conn = self.conn_manager.clone();
+        conn.set_ex::<_, _, ()>(self.challenge_key(nonce), 1, ttl.as_secs())
+            .await?;
+        Ok(())
+    }
+
+    async fn take_challenge(&self, nonce: &str) -> Result<bool> {
+        // DEL reports whether the key existed, so of two concurrent attempts
+        // only one sees the nonce
+        let mut conn = self.conn_manager.clone();
+        let removed: u64 = conn.del(self.challenge_key(nonce)).await?;
+        Ok(removed == 1)
+    }
+}
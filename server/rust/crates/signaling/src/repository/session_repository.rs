@@ -1,20 +1,16 @@
+use super::session_store::{ClientRecord, SessionStore};
+use anyhow::Result;
+use async_trait::async_trait;
 use shared::models::{ClientId, SignalEnvelope};
 use std::collections::HashMap;
-use std::time::Instant;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 
-#[derive(Debug, Clone)]
-pub struct ClientRecord {
-    pub device_label: String,
-    pub session_token: String,
-    pub registered_at: Instant,
-    pub last_heartbeat: Instant,
-}
-
 #[derive(Debug)]
 pub struct InMemorySessionRepository {
     clients: RwLock<HashMap<ClientId, ClientRecord>>,
     messages: RwLock<Vec<SignalEnvelope>>,
+    challenges: RwLock<HashMap<String, Instant>>,
 }
 
 impl InMemorySessionRepository {
@@ -22,39 +18,66 @@ impl InMemorySessionRepository {
         Self {
             clients: RwLock::new(HashMap::new()),
             messages: RwLock::new(Vec::new()),
+            challenges: RwLock::new(HashMap::new()),
         }
     }
+}
 
-    pub async fn insert_client(&self, client_id: ClientId, record: ClientRecord) {
+#[async_trait]
+impl SessionStore for InMemorySessionRepository {
+    async fn insert_client(
+        &self,
+        client_id: ClientId,
+        record: ClientRecord,
+        _session_ttl: Duration,
+    ) -> Result<()> {
         self.clients.write().await.insert(client_id, record);
+        Ok(())
     }
 
-    pub async fn get_client(&self, client_id: &ClientId) -> Option<ClientRecord> {
-        self.clients.read().await.get(client_id).cloned()
+    async fn get_client(&self, client_id: &ClientId) -> Result<Option<ClientRecord>> {
+        Ok(self.clients.read().await.get(client_id).cloned())
     }
 
-    pub async fn update_client_heartbeat(&self, client_id: &ClientId, timestamp: Instant) -> bool {
+    async fn update_client_heartbeat(
+        &self,
+        client_id: &ClientId,
+        timestamp_epoch_ms: u128,
+        _session_ttl: Duration,
+    ) -> Result<bool> {
         if let Some(record) = self.clients.write().await.get_mut(client_id) {
-            record.last_heartbeat = timestamp;
-            true
+            record.last_heartbeat_epoch_ms = timestamp_epoch_ms;
+            Ok(true)
         } else {
-            false
+            Ok(false)
         }
     }
 
-    pub async fn remove_client(&self, client_id: &ClientId) {
+    async fn remove_client(&self, client_id: &ClientId) -> Result<()> {
         self.clients.write().await.remove(client_id);
+        Ok(())
+    }
+
+    async fn get_client_count(&self) -> Result<usize> {
+        Ok(self.clients.read().await.len())
     }
 
-    pub async fn get_client_count(&self) -> usize {
-        self.clients.read().await.len()
+    async fn list_clients(&self) -> Result<Vec<(ClientId, ClientRecord)>> {
+        Ok(self
+            .clients
+            .read()
+            .await
+            .iter()
+            .map(|(client_id, record)| (*client_id, record.clone()))
+            .collect())
     }
 
-    pub async fn add_message(&self, message: SignalEnvelope) {
+    async fn add_message(&self, message: SignalEnvelope, _session_ttl: Duration) -> Result<()> {
         self.messages.write().await.push(message);
+        Ok(())
     }
 
-    pub async fn get_messages_for_client(&self, client_id: &ClientId) -> Vec<SignalEnvelope> {
+    async fn get_messages_for_client(&self, client_id: &ClientId) -> Result<Vec<SignalEnvelope>> {
         let mut messages = self.messages.write().await;
         let mut collected = Vec::new();
         messages.retain(|msg| {
@@ -65,17 +88,20 @@ impl InMemorySessionRepository {
                 true
             }
         });
-        collected
+        Ok(collected)
     }
 
-    pub async fn prune_stale_clients(
-        &self,
-        expiration_threshold: std::time::Duration,
-    ) -> Vec<ClientId> {
+    async fn prune_stale_clients(&self, expiration_threshold: Duration) -> Result<Vec<ClientId>> {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
         let mut clients = self.clients.write().await;
         let mut stale_clients = Vec::new();
         for (client_id, record) in clients.iter() {
-            if record.last_heartbeat.elapsed() >= expiration_threshold {
+            if now_ms.saturating_sub(record.last_heartbeat_epoch_ms)
+                >= expiration_threshold.as_millis()
+            {
                 stale_clients.push(*client_id);
             }
         }
@@ -83,15 +109,33 @@ impl InMemorySessionRepository {
         for client_id in &stale_clients {
             clients.remove(client_id);
         }
-        stale_clients
+        Ok(stale_clients)
     }
 
-    pub async fn prune_messages_for_clients(&self, client_ids: &[ClientId]) {
+    async fn prune_messages_for_clients(&self, client_ids: &[ClientId]) -> Result<()> {
         if client_ids.is_empty() {
-            return;
+            return Ok(());
         }
         let mut messages = self.messages.write().await;
         messages.retain(|msg| !client_ids.contains(&msg.from) && !client_ids.contains(&msg.to));
+        Ok(())
+    }
+
+    async fn save_challenge(&self, nonce: &str, ttl: Duration) -> Result<()> {
+        let now = Instant::now();
+        let mut challenges = self.challenges.write().await;
+        challenges.retain(|_, expires_at| *expires_at > now);
+        challenges.insert(nonce.to_string(), now + ttl);
+        Ok(())
+    }
+
+    async fn take_challenge(&self, nonce: &str) -> Result<bool> {
+        Ok(self
+            .challenges
+            .write()
+            .await
+            .remove(nonce)
+            .is_some_and(|expires_at| expires_at > Instant::now()))
     }
 }
 
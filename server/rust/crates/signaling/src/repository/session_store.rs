@@ -0,0 +1,70 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use shared::models::{ClientId, SignalEnvelope};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientRecord {
+    pub device_label: String,
+    /// Ed25519 public key the device proved it holds; the client ID is derived from it.
+    #[serde(default)]
+    pub public_key_b64: String,
+    /// SHA-256 of the current session token; the token itself is never stored.
+    #[serde(default)]
+    pub session_token_hash: String,
+    #[serde(default)]
+    pub session_expires_at_epoch_ms: u128,
+    pub registered_at_epoch_ms: u128,
+    pub last_heartbeat_epoch_ms: u128,
+}
+
+/// Storage backend for registered clients and their queued `SignalEnvelope`s.
+///
+/// `session_ttl` is passed on every write so that backends with native expiry
+/// can let idle clients and their queues lapse without an explicit sweep.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn insert_client(
+        &self,
+        client_id: ClientId,
+        record: ClientRecord,
+        session_ttl: Duration,
+    ) -> Result<()>;
+
+    async fn get_client(&self, client_id: &ClientId) -> Result<Option<ClientRecord>>;
+
+    /// Records a heartbeat and extends the client's lifetime.
+    /// Returns `false` when the client is no longer known.
+    async fn update_client_heartbeat(
+        &self,
+        client_id: &ClientId,
+        timestamp_epoch_ms: u128,
+        session_ttl: Duration,
+    ) -> Result<bool>;
+
+    async fn remove_client(&self, client_id: &ClientId) -> Result<()>;
+
+    async fn get_client_count(&self) -> Result<usize>;
+
+    /// Every registered client, for operator tooling.
+    async fn list_clients(&self) -> Result<Vec<(ClientId, ClientRecord)>>;
+
+    async fn add_message(&self, message: SignalEnvelope, session_ttl: Duration) -> Result<()>;
+
+    /// Drains every queued message addressed to `client_id`.
+    async fn get_messages_for_client(&self, client_id: &ClientId) -> Result<Vec<SignalEnvelope>>;
+
+    /// Removes clients whose last heartbeat is older than `expiration_threshold`
+    /// and returns their IDs.
+    async fn prune_stale_clients(&self, expiration_threshold: Duration) -> Result<Vec<ClientId>>;
+
+    async fn prune_messages_for_clients(&self, client_ids: &[ClientId]) -> Result<()>;
+
+    /// Remembers a registration nonce for `ttl`.
+    async fn save_challenge(&self, nonce: &str, ttl: Duration) -> Result<()>;
+
+    /// Consumes a registration nonce, so each one can be answered at most once.
+    /// Returns `false` when it is unknown, expired or already used.
+    async fn take_challenge(&self, nonce: &str) -> Result<bool>;
+}
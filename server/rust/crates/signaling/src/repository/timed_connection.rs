@@ -0,0 +1,55 @@
+use crate::metrics::metrics;
+use redis::aio::{ConnectionLike, ConnectionManager};
+use redis::{Arg, Cmd, Pipeline, RedisFuture, Value};
+use std::time::Instant;
+
+/// `ConnectionManager` that records every round trip in the Redis latency histogram.
+///
+/// Commands are labeled by name (`GET`, `EVALSHA`, ...) and pipelines as
+/// `PIPELINE`; only names the server itself issues ever reach the label.
+#[derive(Clone)]
+pub struct TimedConnection {
+    inner: ConnectionManager,
+}
+
+impl TimedConnection {
+    pub fn new(inner: ConnectionManager) -> Self {
+        Self { inner }
+    }
+}
+
+impl ConnectionLike for TimedConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = self.inner.req_packed_command(cmd).await;
+            metrics().observe_redis(&command_name(cmd), started.elapsed(), result.is_ok());
+            result
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = self.inner.req_packed_commands(cmd, offset, count).await;
+            metrics().observe_redis("PIPELINE", started.elapsed(), result.is_ok());
+            result
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.inner.get_db()
+    }
+}
+
+fn command_name(cmd: &Cmd) -> String {
+    match cmd.args_iter().next() {
+        Some(Arg::Simple(name)) => String::from_utf8_lossy(name).to_uppercase(),
+        _ => "UNKNOWN".to_string(),
+    }
+}
@@ -1,63 +1,70 @@
-use crate::config::SignalingServerConfig;
+mod admin;
+mod error;
+
+use crate::config::{SignalingServerConfig, StoreBackend};
+use crate::ice::IceServerProvider;
+use crate::metrics::metrics;
+use crate::push_hub::{PushError, PushHub, PushSubscription};
+use crate::rate_limit::{enforce_rate_limit, RateLimitScope, RateLimiter, ScopedRateLimiter};
 use crate::registry::{RegistryError, SessionRegistry};
+use crate::repository::host_store::{HostStore, InMemoryHostRepository, RedisHostRepository};
+use crate::repository::mailbox_store::MailboxStore;
+use crate::repository::memory_repository::InMemoryMailboxRepository;
+use crate::repository::payload_cipher::PayloadCipher;
+use crate::repository::rate_limit_store::{
+    InMemoryRateLimitRepository, RateLimitStore, RedisRateLimitRepository,
+};
 use crate::repository::redis_repository::RedisRepository;
+use crate::repository::redis_session_repository::RedisSessionRepository;
+use crate::repository::session_repository::InMemorySessionRepository;
+use crate::repository::session_store::SessionStore;
+use crate::services::host_service::{HostError, HostService};
 use crate::services::rendezvous_service::{RendezvousError, RendezvousService};
+use crate::stun;
+use crate::tls;
+use crate::turn;
+pub(crate) use error::ApiError;
+use error::{JsonBody, PathParam, QueryParams};
 use shared::models::{
     ConnectionCloseRequest, ConnectionInitRequest, ConnectionInitResponse, ConnectionJoinRequest,
-    ConnectionJoinResponse, HeartbeatRequest, MailboxRecvResponse, MailboxSendRequest,
-    RegisterRequest, SignalFetchRequest, SignalFetchResponse, SignalSubmitRequest,
+    ConnectionJoinResponse, ErrorCode, HeartbeatRequest, HostConnectRequest, HostListenRequest,
+    HostRegisterRequest, HostRegisterResponse, HostUnregisterRequest, IceServersRequest,
+    MailboxAckRequest, MailboxMessage, MailboxRecvRequest, MailboxRecvResponse, MailboxSendRequest,
+    MailboxWsClientFrame, MailboxWsServerFrame, NameplateJoinRequest, NameplateOpenRequest,
+    NameplateOpenResponse, RegisterRequest, SignalFetchRequest, SignalFetchResponse,
+    SignalSubmitRequest,
 };
 
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use axum::{
-    extract::{Path, State, WebSocketUpgrade},
-    http::StatusCode,
+    extract::{State, WebSocketUpgrade},
+    http::{header, HeaderMap, StatusCode},
+    middleware,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{get, post, MethodRouter},
     Json, Router,
 };
-use serde::Serialize;
+use axum_server::tls_rustls::RustlsConfig;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::net::TcpListener;
-use tracing::{info, instrument};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, instrument, warn};
 
-#[derive(Clone)]
-struct PushHub {
-    buffer_capacity: usize,
-    inner: Arc<
-        tokio::sync::Mutex<
-            std::collections::HashMap<String, tokio::sync::broadcast::Sender<String>>,
-        >,
-    >,
-}
-
-impl PushHub {
-    fn new(buffer_capacity: usize) -> Self {
-        Self {
-            buffer_capacity,
-            inner: Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
-        }
-    }
-    async fn subscribe(&self, mailbox_id: &str) -> tokio::sync::broadcast::Receiver<String> {
-        let mut guard = self.inner.lock().await;
-        let tx = guard.entry(mailbox_id.to_string()).or_insert_with(|| {
-            let (tx, _rx) = tokio::sync::broadcast::channel(self.buffer_capacity);
-            tx
-        });
-        tx.subscribe()
-    }
-    async fn notify(&self, mailbox_id: &str, msg: String) {
-        let guard = self.inner.lock().await;
-        if let Some(tx) = guard.get(mailbox_id) {
-            let _ = tx.send(msg);
-        }
-    }
-}
+/// Suggested wait before retrying a request that failed on storage.
+const STORAGE_RETRY_AFTER: Duration = Duration::from_secs(1);
 
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    message: String,
-}
+/// Suggested wait before asking for a nameplate again when all are taken; they
+/// free up as codes are used or expire.
+const NAMEPLATE_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// How often the TLS certificate files are checked for changes.
+const TLS_RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Clone)]
 struct AppState {
@@ -65,94 +72,536 @@ struct AppState {
     config: Arc<SignalingServerConfig>,
     push: Arc<PushHub>,
     rendezvous_service: Arc<RendezvousService>,
+    hosts: Arc<HostService>,
+    ice_servers: Arc<IceServerProvider>,
+    /// Flips to `true` once shutdown starts; WebSocket tasks watch it to say goodbye.
+    shutdown: watch::Receiver<bool>,
+    /// Flips to `true` once the grace period is over; WebSocket tasks still
+    /// running then are dropped.
+    abort: watch::Receiver<bool>,
 }
 
 pub async fn run_server(config: SignalingServerConfig) -> anyhow::Result<()> {
+    run_server_until(config, shutdown_signal()).await
+}
+
+/// Like `run_server`, but drains and stops once `shutdown` resolves instead of
+/// on SIGINT or SIGTERM.
+pub async fn run_server_until(
+    config: SignalingServerConfig,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    let payload_cipher = build_payload_cipher(&config)?;
+
+    let redis_client = if config.mailbox_store == StoreBackend::Redis
+        || config.session_store == StoreBackend::Redis
+        || config.push_backend == StoreBackend::Redis
+        || (config.rate_limit_enabled && config.rate_limit_store == StoreBackend::Redis)
+    {
+        Some(connect_redis(&config)?)
+    } else {
+        None
+    };
+    let redis_conn = match &redis_client {
+        Some(client) => Some(client.get_connection_manager().await?),
+        None => None,
+    };
+
+    let session_store = build_session_store(&config, redis_conn.clone());
+    let host_store = build_host_store(&config, redis_conn.clone());
     let registry = Arc::new(SessionRegistry::new(
+        session_store,
         config.session_ttl,
+        config.session_token_ttl,
         config.heartbeat_interval,
     ));
 
-    if config.redis_require_tls && !config.redis_url.starts_with("rediss://") {
-        anyhow::bail!(
-            "Redis TLS required but URL is not rediss:// (got: {}). Set SIGNALING_REDIS_REQUIRE_TLS=false only for local development.",
-            config.redis_url
-        );
-    }
-
-    let redis_client = redis::Client::open(config.redis_url.clone())?;
-    let redis_conn = redis_client.get_connection_manager().await?;
-    let redis_repo = RedisRepository::new(redis_conn, config.redis_key_prefix.clone());
+    let mailbox_store = build_mailbox_store(&config, redis_conn.clone(), payload_cipher);
     let rendezvous_service = Arc::new(RendezvousService::new(
-        redis_repo,
+        mailbox_store,
         config.mailbox_ttl,
         config.rendezvous_ttl,
+        config.joined_flag_ttl,
+        config.mailbox_quota(),
+    ));
+    let hosts = Arc::new(HostService::new(
+        host_store,
+        rendezvous_service.clone(),
+        config.rendezvous_ttl,
+        config.host_lockout_policy(),
     ));
-    let ws_push_buffer_capacity = config.ws_push_buffer_capacity;
+    let rate_limiter = build_rate_limiter(&config, redis_conn.clone());
+    let push = build_push_hub(&config, redis_client, redis_conn);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (abort_tx, abort_rx) = watch::channel(false);
+
+    let ice_servers = Arc::new(IceServerProvider::new(&config));
+    if let Some(stun_addr) = config.stun_addr {
+        stun::spawn(stun_addr, shutdown_rx.clone()).await?;
+    }
+    if let (Some(relay), Some(secret)) = (&config.turn_relay, &config.turn_secret) {
+        turn::spawn(
+            relay.clone(),
+            secret.clone(),
+            registry.clone(),
+            shutdown_rx.clone(),
+        )
+        .await?;
+    }
 
     let state = AppState {
         registry,
         config: Arc::new(config),
-        push: Arc::new(PushHub::new(ws_push_buffer_capacity)),
+        push: Arc::new(push),
         rendezvous_service,
+        hosts,
+        ice_servers,
+        shutdown: shutdown_rx,
+        abort: abort_rx,
     };
 
     let router = Router::new()
         .route("/", get(root))
         .route("/health", get(healthcheck))
+        // Every registration spends a challenge, so limiting challenges limits
+        // registrations without charging each one twice
+        .route(
+            "/register/challenge",
+            rate_limited(
+                post(register_challenge),
+                &rate_limiter,
+                RateLimitScope::Register,
+            ),
+        )
         .route("/register", post(register))
         .route("/heartbeat", post(heartbeat))
+        .route("/ice-servers", post(fetch_ice_servers))
         .route("/signal", post(send_signal))
         .route("/signal/fetch", post(fetch_signal))
         // connection-based blind rendezvous endpoints
-        .route("/connection/init", post(connection_init))
-        .route("/connection/join", post(connection_join))
+        .route(
+            "/connection/init",
+            rate_limited(
+                post(connection_init),
+                &rate_limiter,
+                RateLimitScope::ConnectionInit,
+            ),
+        )
+        .route(
+            "/connection/join",
+            rate_limited(
+                post(connection_join),
+                &rate_limiter,
+                RateLimitScope::ConnectionJoin,
+            ),
+        )
+        // short codes: the nameplate stands in for the link token
+        .route(
+            "/connection/nameplate",
+            rate_limited(
+                post(nameplate_open),
+                &rate_limiter,
+                RateLimitScope::ConnectionInit,
+            ),
+        )
+        .route(
+            "/connection/nameplate/join",
+            rate_limited(
+                post(nameplate_join),
+                &rate_limiter,
+                RateLimitScope::NameplateJoin,
+            ),
+        )
         .route("/connection/send", post(mailbox_send))
         .route("/connection/recv", post(mailbox_recv))
+        .route("/connection/ack", post(mailbox_ack))
         .route("/connection/close", post(connection_close))
+        // unattended access by host ID and password
+        .route("/hosts/register", post(host_register))
+        .route("/hosts/listen", post(host_listen))
+        .route("/hosts/unregister", post(host_unregister))
+        .route(
+            "/hosts/connect",
+            rate_limited(
+                post(host_connect),
+                &rate_limiter,
+                RateLimitScope::HostConnect,
+            ),
+        )
         // websocket push for mailbox
         .route("/ws/:mailbox_id", get(ws_upgrade))
-        .with_state(state.clone());
+        .fallback(error::not_found);
+
+    let router = match state.config.admin_token.clone() {
+        Some(token) => {
+            info!("Admin API enabled at /admin");
+            router.nest("/admin", admin::router(token))
+        }
+        None => router,
+    };
+
+    let router = match (state.config.metrics_enabled, state.config.metrics_addr) {
+        (true, Some(metrics_addr)) => {
+            let metrics_router = Router::new()
+                .route("/metrics", get(metrics_handler))
+                .with_state(state.clone());
+            let metrics_listener = TcpListener::bind(metrics_addr).await?;
+            info!(address = %metrics_addr, "Serving metrics");
+            let stopping = wait_for_shutdown(state.shutdown.clone());
+            tokio::spawn(async move {
+                if let Err(err) = axum::serve(metrics_listener, metrics_router)
+                    .with_graceful_shutdown(stopping)
+                    .await
+                {
+                    tracing::error!(error = %err, "Metrics listener stopped");
+                }
+            });
+            router
+        }
+        (true, None) => router.route("/metrics", get(metrics_handler)),
+        (false, _) => router,
+    }
+    .layer(middleware::from_fn(error::assign_request_id))
+    .with_state(state.clone());
 
     let listen_addr = state.config.listen_addr;
-    let listener = TcpListener::bind(listen_addr).await?;
-    info!(address = %listen_addr, "Starting signaling server");
-    axum::serve(
-        listener,
-        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-    )
-    .await?;
+    let grace_period = state.config.shutdown_grace_period;
+    let app = router.into_make_service_with_connect_info::<SocketAddr>();
+    let stopping = wait_for_shutdown(state.shutdown.clone());
+    // Both listeners are served through one handle, so connections still open
+    // after the grace period can be cut
+    let handle = axum_server::Handle::new();
+    let on_stop = handle.clone();
+    tokio::spawn(async move {
+        stopping.await;
+        // The grace period is enforced below, together with the WebSocket drain
+        on_stop.graceful_shutdown(None);
+    });
+    let mut server = match &state.config.tls {
+        Some(settings) => {
+            let tls = RustlsConfig::from_config(tls::load_server_config(settings)?);
+            tls::spawn_reloader(tls.clone(), settings.clone(), TLS_RELOAD_POLL_INTERVAL);
+            info!(address = %listen_addr, "Starting signaling server with TLS");
+            tokio::spawn(
+                axum_server::bind_rustls(listen_addr, tls)
+                    .handle(handle.clone())
+                    .serve(app),
+            )
+        }
+        None => {
+            let listener = std::net::TcpListener::bind(listen_addr)?;
+            listener.set_nonblocking(true)?;
+            info!(address = %listen_addr, "Starting signaling server");
+            tokio::spawn(
+                axum_server::from_tcp(listener)
+                    .handle(handle.clone())
+                    .serve(app),
+            )
+        }
+    };
+
+    tokio::select! {
+        served = &mut server => return Ok(served??),
+        () = shutdown => {}
+    }
+
+    // Stop accepting, tell every WebSocket to reconnect elsewhere, then wait for
+    // the last clone of the state (held by in-flight handlers and socket tasks)
+    // to go away, or give up when the grace period runs out
+    info!(grace_period = ?grace_period, "Shutting down, draining connections");
+    let _ = shutdown_tx.send(true);
+    drop(state);
+    let drained = async {
+        let served = (&mut server).await;
+        shutdown_tx.closed().await;
+        served
+    };
+    match tokio::time::timeout(grace_period, drained).await {
+        Ok(served) => served??,
+        Err(_) => {
+            warn!("Grace period elapsed, dropping remaining connections");
+            handle.shutdown();
+            server.abort();
+            let _ = abort_tx.send(true);
+        }
+    }
+    info!("Signaling server stopped");
     Ok(())
 }
 
+/// Resolves on SIGINT (Ctrl-C) or, on Unix, SIGTERM as sent by `docker stop`.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            warn!(error = %err, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(err) => {
+                warn!(error = %err, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => {}
+        () = terminate => {}
+    }
+}
+
+async fn wait_for_shutdown(mut shutdown: watch::Receiver<bool>) {
+    // An error means the sender is gone, which only happens once we are stopping
+    let _ = shutdown.wait_for(|stopping| *stopping).await;
+}
+
+fn connect_redis(config: &SignalingServerConfig) -> anyhow::Result<redis::Client> {
+    if config.redis_require_tls && !config.redis_url.starts_with("rediss://") {
+        anyhow::bail!(
+            "Redis TLS required but URL is not rediss:// (got: {}). Set SIGNALING_REDIS_REQUIRE_TLS=false only for local development.",
+            config.redis_url
+        );
+    }
+
+    Ok(redis::Client::open(config.redis_url.clone())?)
+}
+
+/// Refuses to start with encryption enabled but no usable key, rather than
+/// silently writing plaintext.
+fn build_payload_cipher(config: &SignalingServerConfig) -> anyhow::Result<Option<PayloadCipher>> {
+    if !config.redis_encrypt_payloads {
+        return Ok(None);
+    }
+    let Some(key) = config.redis_encryption_key else {
+        anyhow::bail!(
+            "Redis payload encryption is enabled but no valid key is configured (SIGNALING_REDIS_ENC_KEY_B64)"
+        );
+    };
+    let cipher = PayloadCipher::new(
+        config.redis_encryption_key_id.clone(),
+        key.bytes(),
+        config
+            .redis_decryption_keys
+            .iter()
+            .map(|(key_id, key)| (key_id.clone(), key.bytes())),
+    )?
+    .allow_plaintext(config.redis_encrypt_allow_plaintext);
+    if config.redis_encrypt_allow_plaintext {
+        warn!("Reading unencrypted Redis payloads (SIGNALING_REDIS_ENC_ALLOW_PLAINTEXT); turn it off once older mailboxes have expired");
+    }
+    info!(key_id = %config.redis_encryption_key_id, "Redis payload encryption enabled");
+    Ok(Some(cipher))
+}
+
+fn build_mailbox_store(
+    config: &SignalingServerConfig,
+    redis_conn: Option<redis::aio::ConnectionManager>,
+    payload_cipher: Option<PayloadCipher>,
+) -> Arc<dyn MailboxStore> {
+    match (config.mailbox_store, redis_conn) {
+        (StoreBackend::Redis, Some(conn)) => {
+            let repo = RedisRepository::new(conn, config.redis_key_prefix.clone());
+            match payload_cipher {
+                Some(cipher) => Arc::new(repo.with_cipher(cipher)),
+                None => Arc::new(repo),
+            }
+        }
+        _ => {
+            info!("Using in-memory mailbox store");
+            Arc::new(InMemoryMailboxRepository::new())
+        }
+    }
+}
+
+fn build_session_store(
+    config: &SignalingServerConfig,
+    redis_conn: Option<redis::aio::ConnectionManager>,
+) -> Arc<dyn SessionStore> {
+    match (config.session_store, redis_conn) {
+        (StoreBackend::Redis, Some(conn)) => Arc::new(RedisSessionRepository::new(
+            conn,
+            config.redis_key_prefix.clone(),
+        )),
+        _ => {
+            info!("Using in-memory session store");
+            Arc::new(InMemorySessionRepository::new())
+        }
+    }
+}
+
+/// Host IDs live next to the client records they belong to, so they survive
+/// restarts exactly when sessions do.
+fn build_host_store(
+    config: &SignalingServerConfig,
+    redis_conn: Option<redis::aio::ConnectionManager>,
+) -> Arc<dyn HostStore> {
+    match (config.session_store, redis_conn) {
+        (StoreBackend::Redis, Some(conn)) => Arc::new(RedisHostRepository::new(
+            conn,
+            config.redis_key_prefix.clone(),
+        )),
+        _ => Arc::new(InMemoryHostRepository::new()),
+    }
+}
+
+fn build_rate_limiter(
+    config: &SignalingServerConfig,
+    redis_conn: Option<redis::aio::ConnectionManager>,
+) -> Option<Arc<RateLimiter>> {
+    if !config.rate_limit_enabled {
+        info!("Rate limiting disabled");
+        return None;
+    }
+    let store: Arc<dyn RateLimitStore> = match (config.rate_limit_store, redis_conn) {
+        (StoreBackend::Redis, Some(conn)) => Arc::new(RedisRateLimitRepository::new(
+            conn,
+            config.redis_key_prefix.clone(),
+        )),
+        _ => {
+            info!("Using in-memory rate limit store");
+            Arc::new(InMemoryRateLimitRepository::new())
+        }
+    };
+    Some(Arc::new(RateLimiter::new(
+        store,
+        config.rate_limit_policies(),
+        config.trusted_proxies.clone(),
+    )))
+}
+
+fn rate_limited(
+    route: MethodRouter<AppState>,
+    limiter: &Option<Arc<RateLimiter>>,
+    scope: RateLimitScope,
+) -> MethodRouter<AppState> {
+    match limiter {
+        Some(limiter) => route.layer(middleware::from_fn_with_state(
+            ScopedRateLimiter {
+                limiter: limiter.clone(),
+                scope,
+            },
+            enforce_rate_limit,
+        )),
+        None => route,
+    }
+}
+
+fn build_push_hub(
+    config: &SignalingServerConfig,
+    redis_client: Option<redis::Client>,
+    redis_conn: Option<redis::aio::ConnectionManager>,
+) -> PushHub {
+    match (config.push_backend, redis_client, redis_conn) {
+        (StoreBackend::Redis, Some(client), Some(conn)) => {
+            let hub = PushHub::with_redis(
+                config.ws_push_buffer_capacity,
+                config.ws_max_subscribers_per_mailbox,
+                conn,
+                &config.redis_key_prefix,
+            );
+            hub.spawn_redis_listener(client);
+            hub
+        }
+        _ => {
+            info!("Using process-local WebSocket push");
+            PushHub::new(
+                config.ws_push_buffer_capacity,
+                config.ws_max_subscribers_per_mailbox,
+            )
+        }
+    }
+}
+
 async fn healthcheck(State(state): State<AppState>) -> impl IntoResponse {
     let body = SignalingServerInfo {
         public_base_url: state.config.public_base_url.clone(),
         heartbeat_interval_secs: state.config.heartbeat_interval.as_secs(),
+        active_websockets: state.push.stats().subscribers,
     };
     (StatusCode::OK, Json(body))
 }
 
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    // Gauges are sampled at scrape time rather than tracked on every change
+    match state.registry.active_client_count().await {
+        Ok(count) => metrics().active_clients.set(count as i64),
+        Err(err) => tracing::warn!(error = %err, "Failed to count active clients"),
+    }
+    metrics()
+        .ws_active
+        .set(state.push.stats().subscribers as i64);
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics().render(),
+    )
+}
+
 #[derive(Serialize)]
 struct SignalingServerInfo {
     public_base_url: String,
     heartbeat_interval_secs: u64,
+    active_websockets: usize,
+}
+
+#[instrument(skip(state))]
+async fn register_challenge(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let challenge = state
+        .registry
+        .issue_challenge()
+        .await
+        .map_err(registry_err)?;
+    Ok((StatusCode::OK, Json(challenge)))
 }
 
 #[instrument(skip(state, payload))]
 async fn register(
     State(state): State<AppState>,
-    Json(payload): Json<RegisterRequest>,
-) -> impl IntoResponse {
-    let response = state.registry.register(payload).await;
-    (StatusCode::OK, Json(response))
+    JsonBody(payload): JsonBody<RegisterRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut response = state
+        .registry
+        .register(payload)
+        .await
+        .map_err(registry_err)?;
+    response.ice_servers = state
+        .ice_servers
+        .servers_for(&response.client_id, SystemTime::now())
+        .ice_servers;
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Fresh ICE servers for a registered client, e.g. before each new peer
+/// connection so TURN credentials never go stale.
+#[instrument(skip(state, payload))]
+async fn fetch_ice_servers(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<IceServersRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+    let response = state
+        .ice_servers
+        .servers_for(&payload.client_id, SystemTime::now());
+    Ok((StatusCode::OK, Json(response)))
 }
 
 #[instrument(skip(state, payload))]
 async fn heartbeat(
     State(state): State<AppState>,
-    Json(payload): Json<HeartbeatRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<HeartbeatRequest>,
+) -> Result<impl IntoResponse, ApiError> {
     state
         .registry
         .heartbeat(payload)
@@ -164,8 +613,8 @@ async fn heartbeat(
 #[instrument(skip(state, payload))]
 async fn send_signal(
     State(state): State<AppState>,
-    Json(payload): Json<SignalSubmitRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<SignalSubmitRequest>,
+) -> Result<impl IntoResponse, ApiError> {
     state
         .registry
         .enqueue_signal(payload)
@@ -177,8 +626,8 @@ async fn send_signal(
 #[instrument(skip(state, payload))]
 async fn fetch_signal(
     State(state): State<AppState>,
-    Json(payload): Json<SignalFetchRequest>,
-) -> Result<(StatusCode, Json<SignalFetchResponse>), (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<SignalFetchRequest>,
+) -> Result<(StatusCode, Json<SignalFetchResponse>), ApiError> {
     state
         .registry
         .fetch_signals(payload)
@@ -187,34 +636,97 @@ async fn fetch_signal(
         .map_err(registry_err)
 }
 
-fn registry_err(err: RegistryError) -> (StatusCode, Json<ErrorResponse>) {
-    let status = match err {
-        RegistryError::ClientNotFound => StatusCode::NOT_FOUND,
-        RegistryError::InvalidToken => StatusCode::UNAUTHORIZED,
+fn registry_err(err: RegistryError) -> ApiError {
+    let (status, code) = match err {
+        RegistryError::ClientNotFound => (StatusCode::NOT_FOUND, ErrorCode::ClientNotFound),
+        RegistryError::InvalidToken => (StatusCode::UNAUTHORIZED, ErrorCode::InvalidSessionToken),
+        RegistryError::SessionExpired => (StatusCode::UNAUTHORIZED, ErrorCode::SessionExpired),
+        RegistryError::InvalidChallenge => (StatusCode::UNAUTHORIZED, ErrorCode::InvalidChallenge),
+        RegistryError::InvalidSignature => (StatusCode::UNAUTHORIZED, ErrorCode::InvalidSignature),
+        RegistryError::Storage(_) => return storage_err(err),
     };
-    (
-        status,
-        Json(ErrorResponse {
-            message: err.to_string(),
-        }),
-    )
+    ApiError::new(status, code, err.to_string())
+}
+
+fn rendezvous_err(err: RendezvousError) -> ApiError {
+    if let RendezvousError::Storage(_) = err {
+        return storage_err(err);
+    }
+    let exhausted = matches!(err, RendezvousError::NameplatesExhausted);
+    let api_err = ApiError::new(
+        rendezvous_status(&err),
+        rendezvous_code(&err),
+        err.to_string(),
+    );
+    if exhausted {
+        return api_err.with_retry_after(NAMEPLATE_RETRY_AFTER);
+    }
+    api_err
 }
 
-fn rendezvous_err(err: RendezvousError) -> (StatusCode, Json<ErrorResponse>) {
-    let status = match err {
+fn rendezvous_status(err: &RendezvousError) -> StatusCode {
+    match err {
         RendezvousError::MailboxNotFound => StatusCode::NOT_FOUND,
+        RendezvousError::InvalidMailboxToken => StatusCode::UNAUTHORIZED,
         RendezvousError::SessionExpired => StatusCode::GONE,
         RendezvousError::InvalidToken => StatusCode::NOT_FOUND,
         RendezvousError::SessionAlreadyPaired => StatusCode::CONFLICT,
         RendezvousError::NoPeerConnected => StatusCode::CONFLICT,
-        RendezvousError::Redis(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        RendezvousError::MessageTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+        RendezvousError::MailboxQuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+        RendezvousError::NameplatesExhausted => StatusCode::SERVICE_UNAVAILABLE,
+        RendezvousError::Storage(_) => StatusCode::SERVICE_UNAVAILABLE,
+    }
+}
+
+/// Shared by the HTTP and WebSocket paths so both report the same code.
+fn rendezvous_code(err: &RendezvousError) -> ErrorCode {
+    match err {
+        RendezvousError::MailboxNotFound => ErrorCode::MailboxNotFound,
+        RendezvousError::InvalidMailboxToken => ErrorCode::InvalidMailboxToken,
+        RendezvousError::SessionExpired => ErrorCode::SessionExpired,
+        RendezvousError::InvalidToken => ErrorCode::InvalidRendezvousToken,
+        RendezvousError::SessionAlreadyPaired => ErrorCode::SessionAlreadyPaired,
+        RendezvousError::NoPeerConnected => ErrorCode::NoPeerConnected,
+        RendezvousError::MessageTooLarge { .. } => ErrorCode::MessageTooLarge,
+        RendezvousError::MailboxQuotaExceeded => ErrorCode::MailboxFull,
+        RendezvousError::NameplatesExhausted => ErrorCode::NameplatesExhausted,
+        RendezvousError::Storage(_) => ErrorCode::StorageUnavailable,
+    }
+}
+
+fn host_err(err: HostError) -> ApiError {
+    let (status, code) = match err {
+        HostError::InvalidPassword => (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest),
+        HostError::HostNotFound => (StatusCode::NOT_FOUND, ErrorCode::HostNotFound),
+        HostError::InvalidCredentials => {
+            (StatusCode::UNAUTHORIZED, ErrorCode::InvalidHostCredentials)
+        }
+        HostError::Locked { retry_after } => {
+            return ApiError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ErrorCode::HostLocked,
+                err.to_string(),
+            )
+            .with_retry_after(retry_after)
+        }
+        HostError::HostOffline => (StatusCode::CONFLICT, ErrorCode::HostOffline),
+        HostError::Rendezvous(err) => return rendezvous_err(err),
+        HostError::Storage(_) => return storage_err(err),
     };
-    (
-        status,
-        Json(ErrorResponse {
-            message: err.to_string(),
-        }),
+    ApiError::new(status, code, err.to_string())
+}
+
+/// Storage failures are logged in full but only reported as retryable, so
+/// backend details never reach clients.
+fn storage_err(err: impl std::fmt::Display) -> ApiError {
+    tracing::error!(error = %err, "Storage error");
+    ApiError::new(
+        StatusCode::SERVICE_UNAVAILABLE,
+        ErrorCode::StorageUnavailable,
+        "Storage temporarily unavailable",
     )
+    .with_retry_after(STORAGE_RETRY_AFTER)
 }
 
 // -------- Connection Link Handlers (Blind Rendezvous) --------
@@ -222,8 +734,8 @@ fn rendezvous_err(err: RendezvousError) -> (StatusCode, Json<ErrorResponse>) {
 #[instrument(skip(state, payload))]
 async fn connection_init(
     State(state): State<AppState>,
-    Json(payload): Json<ConnectionInitRequest>,
-) -> Result<(StatusCode, Json<ConnectionInitResponse>), (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<ConnectionInitRequest>,
+) -> Result<(StatusCode, Json<ConnectionInitResponse>), ApiError> {
     // verify client/session
     state
         .registry
@@ -243,8 +755,8 @@ async fn connection_init(
 #[instrument(skip(state, payload))]
 async fn connection_join(
     State(state): State<AppState>,
-    Json(payload): Json<ConnectionJoinRequest>,
-) -> Result<(StatusCode, Json<ConnectionJoinResponse>), (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<ConnectionJoinRequest>,
+) -> Result<(StatusCode, Json<ConnectionJoinResponse>), ApiError> {
     let (response, initiator_mailbox_id, join_json) = state
         .rendezvous_service
         .join_connection(payload.token_b64)
@@ -257,14 +769,60 @@ async fn connection_join(
     Ok((StatusCode::OK, Json(response)))
 }
 
+#[instrument(skip(state, payload))]
+async fn nameplate_open(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<NameplateOpenRequest>,
+) -> Result<(StatusCode, Json<NameplateOpenResponse>), ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+
+    let response = state
+        .rendezvous_service
+        .open_nameplate()
+        .await
+        .map_err(rendezvous_err)?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[instrument(skip(state, payload))]
+async fn nameplate_join(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<NameplateJoinRequest>,
+) -> Result<(StatusCode, Json<ConnectionJoinResponse>), ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+
+    let (response, initiator_mailbox_id, join_json) = state
+        .rendezvous_service
+        .join_nameplate(&payload.nameplate)
+        .await
+        .map_err(rendezvous_err)?;
+
+    state.push.notify(&initiator_mailbox_id, join_json).await;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 #[instrument(skip(state, payload))]
 async fn mailbox_send(
     State(state): State<AppState>,
-    Json(payload): Json<MailboxSendRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<MailboxSendRequest>,
+) -> Result<impl IntoResponse, ApiError> {
     let (peer_mailbox_id, msg_json) = state
         .rendezvous_service
-        .send_message(payload.mailbox_id, payload.ciphertext_b64)
+        .send_message(
+            payload.mailbox_id,
+            &payload.mailbox_token,
+            payload.ciphertext_b64,
+        )
         .await
         .map_err(rendezvous_err)?;
 
@@ -278,11 +836,15 @@ async fn mailbox_send(
 #[instrument(skip(state, payload))]
 async fn mailbox_recv(
     State(state): State<AppState>,
-    Json(payload): Json<MailboxSendRequest>,
-) -> Result<(StatusCode, Json<MailboxRecvResponse>), (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<MailboxRecvRequest>,
+) -> Result<(StatusCode, Json<MailboxRecvResponse>), ApiError> {
     let response = state
         .rendezvous_service
-        .recv_messages(payload.mailbox_id)
+        .recv_messages(
+            payload.mailbox_id,
+            &payload.mailbox_token,
+            payload.since_sequence,
+        )
         .await
         .map_err(rendezvous_err)?;
 
@@ -290,39 +852,441 @@ async fn mailbox_recv(
 }
 
 #[instrument(skip(state, payload))]
-async fn connection_close(
+async fn mailbox_ack(
     State(state): State<AppState>,
-    Json(payload): Json<ConnectionCloseRequest>,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    JsonBody(payload): JsonBody<MailboxAckRequest>,
+) -> Result<impl IntoResponse, ApiError> {
     state
         .rendezvous_service
-        .close_connection(payload.mailbox_id)
+        .ack_messages(
+            payload.mailbox_id,
+            &payload.mailbox_token,
+            payload.up_to_sequence,
+        )
         .await
         .map_err(rendezvous_err)?;
 
     Ok(StatusCode::ACCEPTED)
 }
 
+#[instrument(skip(state, payload))]
+async fn connection_close(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<ConnectionCloseRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let closed = state
+        .rendezvous_service
+        .close_connection(payload.mailbox_id, &payload.mailbox_token)
+        .await
+        .map_err(rendezvous_err)?;
+
+    // Ends local sockets on both mailboxes; sockets on other instances notice on
+    // their next keepalive tick
+    for mailbox_id in &closed {
+        state.push.close_mailbox(mailbox_id);
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+// -------- Unattended Access Handlers --------
+
+#[instrument(skip(state, payload))]
+async fn host_register(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<HostRegisterRequest>,
+) -> Result<(StatusCode, Json<HostRegisterResponse>), ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+
+    let host_id = state
+        .hosts
+        .register_host(payload.client_id, payload.password)
+        .await
+        .map_err(host_err)?;
+
+    Ok((StatusCode::OK, Json(HostRegisterResponse { host_id })))
+}
+
+#[instrument(skip(state, payload))]
+async fn host_listen(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<HostListenRequest>,
+) -> Result<(StatusCode, Json<ConnectionInitResponse>), ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+
+    let response = state
+        .hosts
+        .listen(&payload.client_id)
+        .await
+        .map_err(host_err)?;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[instrument(skip(state, payload))]
+async fn host_unregister(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<HostUnregisterRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .registry
+        .verify_session(&payload.client_id, &payload.session_token)
+        .await
+        .map_err(registry_err)?;
+
+    state
+        .hosts
+        .unregister_host(&payload.client_id)
+        .await
+        .map_err(host_err)?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[instrument(skip(state, payload))]
+async fn host_connect(
+    State(state): State<AppState>,
+    JsonBody(payload): JsonBody<HostConnectRequest>,
+) -> Result<(StatusCode, Json<ConnectionJoinResponse>), ApiError> {
+    let (response, host_mailbox_id, join_json) = state
+        .hosts
+        .connect(&payload.host_id, payload.password)
+        .await
+        .map_err(host_err)?;
+
+    // The host learns about the viewer the same way as with a link join
+    state.push.notify(&host_mailbox_id, join_json).await;
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
 // Root handler for "/"
 async fn root() -> impl IntoResponse {
     (StatusCode::OK, "Server OK!")
 }
 
-// WebSocket endpoint: subscribe to mailbox events
+#[derive(Debug, Deserialize)]
+struct WsConnectQuery {
+    since: Option<u64>,
+}
+
+// WebSocket endpoint: subscribe to mailbox events. The mailbox token is only taken
+// from `Authorization: Bearer`; query strings end up in proxy and access logs.
+// With `?since=N` the stored messages after sequence N are replayed before live push.
 async fn ws_upgrade(
     State(state): State<AppState>,
-    Path(mailbox_id): Path<String>,
+    PathParam(mailbox_id): PathParam<String>,
+    QueryParams(query): QueryParams<WsConnectQuery>,
+    headers: HeaderMap,
     ws: WebSocketUpgrade,
-) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws(socket, state, mailbox_id))
+) -> Result<impl IntoResponse, ApiError> {
+    if *state.shutdown.borrow() {
+        return Err(ApiError::shutting_down());
+    }
+    let Some(mailbox_token) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+    else {
+        return Err(rendezvous_err(RendezvousError::InvalidMailboxToken));
+    };
+    state
+        .rendezvous_service
+        .authorize(&mailbox_id, &mailbox_token)
+        .await
+        .map_err(rendezvous_err)?;
+
+    // Subscribe before upgrading so a full mailbox is refused with a plain HTTP error,
+    // and before reading the backlog so nothing falls between the two; the overlap
+    // is dropped in `handle_ws` by only forwarding sequences past the cursor
+    let subscription = state.push.subscribe(&mailbox_id).map_err(push_err)?;
+
+    let since = query.since;
+    let aborted = wait_for_shutdown(state.abort.clone());
+    Ok(ws.on_upgrade(move |socket| async move {
+        tokio::select! {
+            () = handle_ws(socket, state, subscription, mailbox_id, mailbox_token, since) => {}
+            // Past the grace period the socket is dropped wherever it is stuck
+            () = aborted => {}
+        }
+    }))
+}
+
+fn push_err(err: PushError) -> ApiError {
+    let (status, code) = match err {
+        PushError::SubscriberLimit => {
+            (StatusCode::TOO_MANY_REQUESTS, ErrorCode::TooManySubscribers)
+        }
+    };
+    ApiError::new(status, code, err.to_string())
+}
+
+async fn handle_ws(
+    mut socket: WebSocket,
+    state: AppState,
+    mut rx: PushSubscription,
+    mailbox_id: String,
+    mailbox_token: String,
+    since: Option<u64>,
+) {
+    metrics().ws_connections.inc();
+    let mut cursor = since.unwrap_or(0);
+    // Whether `cursor` is the last sequence this client has, so a jump past it
+    // means a push is missing; without `since` that is only known after the
+    // first message
+    let mut synced = since.is_some();
+    if since.is_some()
+        && replay_backlog(
+            &mut socket,
+            &state,
+            &mailbox_id,
+            &mailbox_token,
+            &mut cursor,
+        )
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let mut ping = tokio::time::interval(state.config.ws_ping_interval);
+    ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // Any inbound frame, pongs included, proves the socket is alive
+    let dead_after = state.config.ws_ping_interval * 2;
+    let mut last_seen = Instant::now();
+    // Pushes and client commands count as activity; keepalive traffic does not
+    let mut last_activity = Instant::now();
+    let stopping = wait_for_shutdown(state.shutdown.clone());
+    tokio::pin!(stopping);
+
+    loop {
+        tokio::select! {
+            () = &mut stopping => {
+                let _ = socket
+                    .send(close_message(close_code::AWAY, "server going away, reconnect"))
+                    .await;
+                break;
+            }
+            pushed = rx.recv() => {
+                match pushed {
+                    Ok(msg) => {
+                        if let Ok(pushed) = serde_json::from_str::<MailboxMessage>(&msg) {
+                            if pushed.sequence <= cursor {
+                                continue;
+                            }
+                            // Pushes from concurrent sends can overtake each other;
+                            // take the gap from the store so nothing is skipped
+                            if synced && pushed.sequence > cursor + 1 {
+                                let replayed = replay_backlog(
+                                    &mut socket,
+                                    &state,
+                                    &mailbox_id,
+                                    &mailbox_token,
+                                    &mut cursor,
+                                )
+                                .await;
+                                if replayed.is_err() {
+                                    break;
+                                }
+                                last_activity = Instant::now();
+                                continue;
+                            }
+                            cursor = pushed.sequence;
+                            synced = true;
+                        }
+                        if socket.send(Message::Text(msg)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        metrics().ws_lagged.inc();
+                        debug!(
+                            mailbox_id = %mailbox_id,
+                            skipped,
+                            "WebSocket lagged, resyncing from store"
+                        );
+                        let replayed = replay_backlog(
+                            &mut socket,
+                            &state,
+                            &mailbox_id,
+                            &mailbox_token,
+                            &mut cursor,
+                        )
+                        .await;
+                        if replayed.is_err() {
+                            break;
+                        }
+                        synced = true;
+                    }
+                    // The mailbox was closed on this instance
+                    Err(RecvError::Closed) => {
+                        let _ = socket
+                            .send(close_message(close_code::NORMAL, "mailbox closed"))
+                            .await;
+                        break;
+                    }
+                }
+                last_activity = Instant::now();
+            }
+            inbound = socket.recv() => {
+                let Some(Ok(frame)) = inbound else { break };
+                last_seen = Instant::now();
+                match frame {
+                    Message::Text(text) => {
+                        last_activity = last_seen;
+                        let (reply, close) =
+                            handle_ws_frame(&state, &mailbox_id, &mailbox_token, &text).await;
+                        let reply = serde_json::to_string(&reply).unwrap_or_default();
+                        if socket.send(Message::Text(reply)).await.is_err() {
+                            break;
+                        }
+                        if close {
+                            let _ = socket
+                                .send(close_message(close_code::NORMAL, "session closed"))
+                                .await;
+                            break;
+                        }
+                    }
+                    Message::Close(_) => break,
+                    // Pings are answered by the WebSocket layer itself
+                    Message::Ping(_) | Message::Pong(_) | Message::Binary(_) => {}
+                }
+            }
+            _ = ping.tick() => {
+                let now = Instant::now();
+                if now.duration_since(last_seen) > dead_after {
+                    debug!(mailbox_id = %mailbox_id, "Closing unresponsive WebSocket");
+                    break;
+                }
+                if now.duration_since(last_activity) > state.config.ws_idle_timeout {
+                    debug!(mailbox_id = %mailbox_id, "Closing idle WebSocket");
+                    let _ = socket.send(close_message(close_code::NORMAL, "idle")).await;
+                    break;
+                }
+                // Catches mailboxes that expired or were closed via another instance
+                if let Ok(false) = state
+                    .rendezvous_service
+                    .verify_mailbox(&mailbox_id, &mailbox_token)
+                    .await
+                {
+                    debug!(mailbox_id = %mailbox_id, "Closing WebSocket for closed mailbox");
+                    let _ = socket
+                        .send(close_message(close_code::NORMAL, "mailbox closed"))
+                        .await;
+                    break;
+                }
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
 }
 
-async fn handle_ws(mut socket: WebSocket, state: AppState, mailbox_id: String) {
-    let mut rx = state.push.subscribe(&mailbox_id).await;
-    // Forward broadcast events to WebSocket client
-    while let Ok(msg) = rx.recv().await {
-        if socket.send(Message::Text(msg)).await.is_err() {
-            break;
+/// Sends every stored message after `cursor` and advances it. Storage errors are
+/// reported to the client as an error frame; only a failed send is returned.
+async fn replay_backlog(
+    socket: &mut WebSocket,
+    state: &AppState,
+    mailbox_id: &str,
+    mailbox_token: &str,
+    cursor: &mut u64,
+) -> Result<(), axum::Error> {
+    let backlog = match state
+        .rendezvous_service
+        .recv_messages(mailbox_id.to_string(), mailbox_token, *cursor)
+        .await
+    {
+        Ok(backlog) => backlog,
+        Err(e) => {
+            let reply = serde_json::to_string(&ws_error(e)).unwrap_or_default();
+            return socket.send(Message::Text(reply)).await;
         }
+    };
+    for msg in backlog.messages {
+        let sequence = msg.sequence;
+        socket
+            .send(Message::Text(
+                serde_json::to_string(&msg).unwrap_or_default(),
+            ))
+            .await?;
+        *cursor = sequence;
     }
+    Ok(())
+}
+
+/// Runs one client command and returns the reply, plus whether the socket should close.
+async fn handle_ws_frame(
+    state: &AppState,
+    mailbox_id: &str,
+    mailbox_token: &str,
+    text: &str,
+) -> (MailboxWsServerFrame, bool) {
+    let frame = match serde_json::from_str::<MailboxWsClientFrame>(text) {
+        Ok(frame) => frame,
+        Err(e) => {
+            return (
+                MailboxWsServerFrame::Error {
+                    code: ErrorCode::InvalidRequest,
+                    message: format!("Malformed frame: {e}"),
+                },
+                false,
+            )
+        }
+    };
+
+    let service = &state.rendezvous_service;
+    match frame {
+        MailboxWsClientFrame::Send { ciphertext_b64 } => match service
+            .send_message(mailbox_id.to_string(), mailbox_token, ciphertext_b64)
+            .await
+        {
+            Ok((peer_mailbox_id, msg_json)) => {
+                state.push.notify(&peer_mailbox_id, msg_json).await;
+                (MailboxWsServerFrame::Sent, false)
+            }
+            Err(e) => (ws_error(e), false),
+        },
+        MailboxWsClientFrame::Ack { up_to_sequence } => match service
+            .ack_messages(mailbox_id.to_string(), mailbox_token, up_to_sequence)
+            .await
+        {
+            Ok(()) => (MailboxWsServerFrame::Acked { up_to_sequence }, false),
+            Err(e) => (ws_error(e), false),
+        },
+        MailboxWsClientFrame::Close => match service
+            .close_connection(mailbox_id.to_string(), mailbox_token)
+            .await
+        {
+            Ok(closed) => {
+                for closed_mailbox_id in &closed {
+                    state.push.close_mailbox(closed_mailbox_id);
+                }
+                (MailboxWsServerFrame::Closed, true)
+            }
+            Err(e) => (ws_error(e), false),
+        },
+    }
+}
+
+fn ws_error(err: RendezvousError) -> MailboxWsServerFrame {
+    MailboxWsServerFrame::Error {
+        code: rendezvous_code(&err),
+        message: err.to_string(),
+    }
+}
+
+fn close_message(code: u16, reason: &'static str) -> Message {
+    Message::Close(Some(CloseFrame {
+        code,
+        reason: reason.into(),
+    }))
 }
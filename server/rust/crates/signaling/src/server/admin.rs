@@ -0,0 +1,206 @@
+use super::{registry_err, rendezvous_err, ApiError, AppState, PathParam};
+use crate::config::AdminToken;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get},
+    Json, Router,
+};
+use serde::Serialize;
+use shared::models::{ClientId, ErrorCode};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+/// Operator endpoints, nested under `/admin`. Every request must carry
+/// `Authorization: Bearer <SIGNALING_ADMIN_TOKEN>`.
+pub(super) fn router(token: AdminToken) -> Router<AppState> {
+    Router::new()
+        .route("/stats", get(stats))
+        .route("/clients", get(list_clients))
+        .route("/clients/:client_id", delete(remove_client))
+        .route("/mailboxes", get(list_mailboxes))
+        .route("/mailboxes/:mailbox_id", delete(close_mailbox))
+        .route("/rendezvous/:token", delete(revoke_rendezvous))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(token),
+            require_admin_token,
+        ))
+}
+
+async fn require_admin_token(
+    State(token): State<Arc<AdminToken>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if presented.is_some_and(|presented| token.matches(presented)) {
+        return next.run(request).await;
+    }
+    warn!(path = %request.uri().path(), "Rejected admin request");
+    ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        ErrorCode::Unauthorized,
+        "Admin token rejected",
+    )
+    .into_response()
+}
+
+type AdminResult<T> = Result<T, ApiError>;
+
+#[derive(Serialize)]
+struct AdminStats {
+    clients: usize,
+    mailboxes: usize,
+    paired_mailboxes: usize,
+    /// WebSockets connected to this instance.
+    websockets: usize,
+    /// Mailboxes with at least one WebSocket on this instance.
+    push_channels: usize,
+}
+
+#[derive(Serialize)]
+struct AdminClient {
+    client_id: ClientId,
+    device_label: String,
+    public_key_b64: String,
+    registered_at_epoch_ms: u128,
+    last_heartbeat_epoch_ms: u128,
+}
+
+#[derive(Serialize)]
+struct AdminMailbox {
+    mailbox_id: String,
+    peer_mailbox_id: Option<String>,
+    paired: bool,
+    created_at_epoch_ms: u128,
+    expires_at_epoch_ms: u128,
+    /// WebSockets subscribed on this instance.
+    websockets: usize,
+}
+
+#[derive(Serialize)]
+struct ClosedMailboxes {
+    closed: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct RevokedRendezvous {
+    mailbox_id: String,
+}
+
+async fn stats(State(state): State<AppState>) -> AdminResult<Json<AdminStats>> {
+    let clients = state
+        .registry
+        .active_client_count()
+        .await
+        .map_err(registry_err)?;
+    let mailboxes = state
+        .rendezvous_service
+        .list_mailboxes()
+        .await
+        .map_err(rendezvous_err)?;
+    let push = state.push.stats();
+    Ok(Json(AdminStats {
+        clients,
+        mailboxes: mailboxes.len(),
+        paired_mailboxes: mailboxes
+            .iter()
+            .filter(|mailbox| mailbox.peer_mailbox_id.is_some())
+            .count(),
+        websockets: push.subscribers,
+        push_channels: push.mailboxes,
+    }))
+}
+
+async fn list_clients(State(state): State<AppState>) -> AdminResult<Json<Vec<AdminClient>>> {
+    let mut clients: Vec<AdminClient> = state
+        .registry
+        .list_clients()
+        .await
+        .map_err(registry_err)?
+        .into_iter()
+        // Session tokens stay out of the listing
+        .map(|(client_id, record)| AdminClient {
+            client_id,
+            device_label: record.device_label,
+            public_key_b64: record.public_key_b64,
+            registered_at_epoch_ms: record.registered_at_epoch_ms,
+            last_heartbeat_epoch_ms: record.last_heartbeat_epoch_ms,
+        })
+        .collect();
+    clients.sort_by_key(|client| client.registered_at_epoch_ms);
+    Ok(Json(clients))
+}
+
+#[instrument(skip(state))]
+async fn remove_client(
+    State(state): State<AppState>,
+    PathParam(client_id): PathParam<ClientId>,
+) -> AdminResult<StatusCode> {
+    state
+        .registry
+        .remove_client(&client_id)
+        .await
+        .map_err(registry_err)?;
+    info!(%client_id, "Client removed by admin");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_mailboxes(State(state): State<AppState>) -> AdminResult<Json<Vec<AdminMailbox>>> {
+    let mut mailboxes: Vec<AdminMailbox> = state
+        .rendezvous_service
+        .list_mailboxes()
+        .await
+        .map_err(rendezvous_err)?
+        .into_iter()
+        .map(|mailbox| AdminMailbox {
+            websockets: state.push.subscriber_count(&mailbox.mailbox_id),
+            paired: mailbox.peer_mailbox_id.is_some(),
+            mailbox_id: mailbox.mailbox_id,
+            peer_mailbox_id: mailbox.peer_mailbox_id,
+            created_at_epoch_ms: mailbox.created_at_epoch_ms,
+            expires_at_epoch_ms: mailbox.expires_at_epoch_ms,
+        })
+        .collect();
+    mailboxes.sort_by_key(|mailbox| mailbox.created_at_epoch_ms);
+    Ok(Json(mailboxes))
+}
+
+#[instrument(skip(state))]
+async fn close_mailbox(
+    State(state): State<AppState>,
+    PathParam(mailbox_id): PathParam<String>,
+) -> AdminResult<Json<ClosedMailboxes>> {
+    let closed = state
+        .rendezvous_service
+        .force_close(&mailbox_id)
+        .await
+        .map_err(rendezvous_err)?;
+    // Same as a client-initiated close: local sockets end now, sockets on other
+    // instances on their next keepalive tick
+    for mailbox_id in &closed {
+        state.push.close_mailbox(mailbox_id);
+    }
+    info!(?closed, "Mailbox pair closed by admin");
+    Ok(Json(ClosedMailboxes { closed }))
+}
+
+#[instrument(skip_all)]
+async fn revoke_rendezvous(
+    State(state): State<AppState>,
+    PathParam(token): PathParam<String>,
+) -> AdminResult<Json<RevokedRendezvous>> {
+    let mailbox_id = state
+        .rendezvous_service
+        .revoke_rendezvous(&token)
+        .await
+        .map_err(rendezvous_err)?;
+    info!(%mailbox_id, "Rendezvous token revoked by admin");
+    Ok(Json(RevokedRendezvous { mailbox_id }))
+}
@@ -0,0 +1,323 @@
+use crate::metrics::metrics;
+use crate::repository::host_store::{AttemptReservation, HostRecord, HostStore};
+use crate::services::rendezvous_service::{RendezvousError, RendezvousService};
+use argon2::Argon2;
+use password_hash::rand_core::OsRng;
+use password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use shared::models::{ClientId, ConnectionInitResponse, ConnectionJoinResponse};
+use shared::{host, token};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+/// Shortest access password a host may set.
+pub const MIN_HOST_PASSWORD_LEN: usize = 8;
+
+/// Longest access password a host may set, which also bounds the work a single
+/// connect attempt can cause.
+pub const MAX_HOST_PASSWORD_LEN: usize = 128;
+
+/// How often a fresh random host ID may collide before registration gives up.
+const HOST_ID_ATTEMPTS: usize = 8;
+
+/// Checked against when the host ID is unknown, so a miss costs as much as a
+/// wrong password and response times do not reveal which IDs exist.
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    hash_password("not-a-real-host-password").expect("hashing a constant password")
+});
+
+/// How many wrong passwords lock a host ID, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostLockoutPolicy {
+    pub max_failed_attempts: u32,
+    pub lockout: Duration,
+}
+
+/// Unattended access: hosts keep a persistent ID and password, and viewers
+/// join whichever mailbox the host is currently listening on.
+pub struct HostService {
+    repo: Arc<dyn HostStore>,
+    rendezvous: Arc<RendezvousService>,
+    /// How long a listening mailbox waits for a viewer.
+    listen_ttl: Duration,
+    lockout: HostLockoutPolicy,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HostError {
+    #[error("Storage error: {0}")]
+    Storage(#[from] anyhow::Error),
+    #[error("Password must be {MIN_HOST_PASSWORD_LEN} to {MAX_HOST_PASSWORD_LEN} characters")]
+    InvalidPassword,
+    #[error("This device has no host ID")]
+    HostNotFound,
+    #[error("Unknown host ID or wrong password")]
+    InvalidCredentials,
+    #[error("Too many failed attempts; try again later")]
+    Locked { retry_after: Duration },
+    #[error("Host is not accepting connections")]
+    HostOffline,
+    #[error(transparent)]
+    Rendezvous(RendezvousError),
+}
+
+impl HostError {
+    /// Short, stable name used as a metrics label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Storage(_) => "storage",
+            Self::InvalidPassword => "invalid_password",
+            Self::HostNotFound => "host_not_found",
+            Self::InvalidCredentials => "invalid_credentials",
+            Self::Locked { .. } => "locked",
+            Self::HostOffline => "host_offline",
+            Self::Rendezvous(err) => err.label(),
+        }
+    }
+}
+
+impl HostService {
+    pub fn new(
+        repo: Arc<dyn HostStore>,
+        rendezvous: Arc<RendezvousService>,
+        listen_ttl: Duration,
+        lockout: HostLockoutPolicy,
+    ) -> Self {
+        Self {
+            repo,
+            rendezvous,
+            listen_ttl,
+            lockout,
+        }
+    }
+
+    /// Sets the access password of `owner`'s host ID and returns the ID,
+    /// assigning one the first time. Changing the password lifts any lockout.
+    pub async fn register_host(
+        &self,
+        owner: ClientId,
+        password: String,
+    ) -> Result<String, HostError> {
+        let result = self.save_host(owner, password).await;
+        metrics().record_host("register", &result);
+        result
+    }
+
+    async fn save_host(&self, owner: ClientId, password: String) -> Result<String, HostError> {
+        let length = password.chars().count();
+        if !(MIN_HOST_PASSWORD_LEN..=MAX_HOST_PASSWORD_LEN).contains(&length) {
+            return Err(HostError::InvalidPassword);
+        }
+        let password_hash = hash_password_blocking(password).await?;
+
+        if let Some(mut record) = self.existing_host(&owner).await? {
+            record.password_hash = password_hash;
+            self.repo.update_host(&record).await?;
+            self.repo.clear_failures(&record.host_id).await?;
+            return Ok(record.host_id);
+        }
+
+        for _ in 0..HOST_ID_ATTEMPTS {
+            let record = HostRecord {
+                host_id: host::gen_host_id(),
+                owner,
+                password_hash: password_hash.clone(),
+                created_at_epoch_ms: now_epoch_ms(),
+            };
+            if self.repo.create_host(&record).await? {
+                info!(host_id = %record.host_id, client_id = %owner, "Host ID assigned");
+                return Ok(record.host_id);
+            }
+        }
+        Err(HostError::Storage(anyhow::anyhow!(
+            "no free host ID after {HOST_ID_ATTEMPTS} attempts"
+        )))
+    }
+
+    /// Opens the mailbox `owner`'s next viewer joins. Any mailbox the host was
+    /// listening on before can no longer be joined.
+    pub async fn listen(&self, owner: &ClientId) -> Result<ConnectionInitResponse, HostError> {
+        let result = self.open_listening_mailbox(owner).await;
+        metrics().record_host("listen", &result);
+        result
+    }
+
+    async fn open_listening_mailbox(
+        &self,
+        owner: &ClientId,
+    ) -> Result<ConnectionInitResponse, HostError> {
+        let record = self
+            .existing_host(owner)
+            .await?
+            .ok_or(HostError::HostNotFound)?;
+        // The rendezvous ID never leaves the server; viewers reach it by host ID
+        let rendezvous_id = token::gen_token();
+        let response = self
+            .rendezvous
+            .init_connection(rendezvous_id.clone())
+            .await
+            .map_err(HostError::Rendezvous)?;
+        let replaced = self
+            .repo
+            .set_listening(&record.host_id, &rendezvous_id, self.listen_ttl)
+            .await?;
+        if let Some(replaced) = replaced {
+            match self.rendezvous.revoke_rendezvous(&replaced).await {
+                Ok(_) | Err(RendezvousError::InvalidToken) => {}
+                Err(err) => return Err(HostError::Rendezvous(err)),
+            }
+        }
+        Ok(response)
+    }
+
+    /// Checks the password and joins the host's listening mailbox. Returns what
+    /// `RendezvousService::join_connection` does.
+    pub async fn connect(
+        &self,
+        host_id: &str,
+        password: String,
+    ) -> Result<(ConnectionJoinResponse, String, String), HostError> {
+        let result = self.join_host(host_id, password).await;
+        metrics().record_host("connect", &result);
+        result
+    }
+
+    async fn join_host(
+        &self,
+        host_id: &str,
+        password: String,
+    ) -> Result<(ConnectionJoinResponse, String, String), HostError> {
+        let Some(host_id) = host::normalize_host_id(host_id) else {
+            return Err(HostError::InvalidCredentials);
+        };
+        if password.chars().count() > MAX_HOST_PASSWORD_LEN {
+            return Err(HostError::InvalidCredentials);
+        }
+
+        // The attempt is counted before the password is looked at, so a locked
+        // ID reveals nothing and concurrent guesses cannot overrun the limit
+        let attempt = match self
+            .repo
+            .reserve_attempt(
+                &host_id,
+                self.lockout.max_failed_attempts,
+                self.lockout.lockout,
+            )
+            .await?
+        {
+            AttemptReservation::Reserved(attempt) => attempt,
+            AttemptReservation::Locked { resets_in } => {
+                return Err(HostError::Locked {
+                    retry_after: resets_in,
+                })
+            }
+        };
+
+        match self.check_password(&host_id, password).await {
+            // Success forgets earlier failures along with this attempt
+            Ok(true) => self.repo.clear_failures(&host_id).await?,
+            Ok(false) if attempt.count >= self.lockout.max_failed_attempts => {
+                warn!(host_id = %host_id, "Host locked after repeated wrong passwords");
+                return Err(HostError::Locked {
+                    retry_after: attempt.resets_in,
+                });
+            }
+            Ok(false) => return Err(HostError::InvalidCredentials),
+            Err(err) => {
+                self.repo.refund_attempt(&host_id).await?;
+                return Err(err);
+            }
+        }
+
+        let rendezvous_id = self
+            .repo
+            .take_listening(&host_id)
+            .await?
+            .ok_or(HostError::HostOffline)?;
+        self.rendezvous
+            .join_connection(rendezvous_id)
+            .await
+            .map_err(|err| match err {
+                // The listening mailbox expired before the host renewed it
+                RendezvousError::InvalidToken | RendezvousError::MailboxNotFound => {
+                    HostError::HostOffline
+                }
+                err => HostError::Rendezvous(err),
+            })
+    }
+
+    /// Whether `password` opens `host_id`. An unknown ID costs the same as a
+    /// wrong password and is reported as one.
+    async fn check_password(&self, host_id: &str, password: String) -> Result<bool, HostError> {
+        let record = self.repo.get_host(host_id).await?;
+        let password_hash = record
+            .as_ref()
+            .map_or_else(|| DUMMY_PASSWORD_HASH.clone(), |r| r.password_hash.clone());
+        let verified = verify_password_blocking(password, password_hash).await?;
+        Ok(record.is_some() && verified)
+    }
+
+    /// Gives up `owner`'s host ID; it stops accepting viewers immediately.
+    pub async fn unregister_host(&self, owner: &ClientId) -> Result<(), HostError> {
+        let result = async {
+            let record = self
+                .existing_host(owner)
+                .await?
+                .ok_or(HostError::HostNotFound)?;
+            if let Some(rendezvous_id) = self.repo.take_listening(&record.host_id).await? {
+                match self.rendezvous.revoke_rendezvous(&rendezvous_id).await {
+                    Ok(_) | Err(RendezvousError::InvalidToken) => {}
+                    Err(err) => return Err(HostError::Rendezvous(err)),
+                }
+            }
+            self.repo.delete_host(&record).await?;
+            Ok(())
+        }
+        .await;
+        metrics().record_host("unregister", &result);
+        result
+    }
+
+    async fn existing_host(&self, owner: &ClientId) -> Result<Option<HostRecord>, HostError> {
+        let Some(host_id) = self.repo.host_id_for_owner(owner).await? else {
+            return Ok(None);
+        };
+        Ok(self.repo.get_host(&host_id).await?)
+    }
+}
+
+fn hash_password(password: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("password hashing failed: {e}"))
+}
+
+/// Argon2 is deliberately slow, so it runs off the async workers.
+async fn hash_password_blocking(password: String) -> Result<String, HostError> {
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(anyhow::Error::new)?
+        .map_err(HostError::Storage)
+}
+
+async fn verify_password_blocking(password: String, phc: String) -> Result<bool, HostError> {
+    tokio::task::spawn_blocking(move || {
+        let hash = PasswordHash::new(&phc)
+            .map_err(|e| anyhow::anyhow!("stored password hash is invalid: {e}"))?;
+        Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await
+    .map_err(anyhow::Error::new)?
+    .map_err(HostError::Storage)
+}
+
+fn now_epoch_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
@@ -1 +1,2 @@
+pub mod host_service;
 pub mod rendezvous_service;
@@ -1,23 +1,40 @@
-use crate::repository::redis_repository::{MailboxMessageStored, MailboxState, RedisRepository};
-use shared::connection;
+use crate::metrics::metrics;
+use crate::repository::mailbox_store::{
+    MailboxAppend, MailboxQuota, MailboxState, MailboxStore, RendezvousRedemption,
+};
 use shared::models::{
     ConnectionInitResponse, ConnectionJoinResponse, MailboxMessage, MailboxRecvResponse,
+    NameplateOpenResponse,
 };
+use shared::{code, connection, token};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+/// Nameplates are drawn below each limit in turn, so codes stay short while few
+/// are in use and only grow once the short ones are taken.
+const NAMEPLATE_LIMITS: [u32; 3] = [100, 10_000, 100_000];
+
+/// Random picks per limit before moving on to the next.
+const NAMEPLATE_ATTEMPTS: usize = 8;
+
 #[derive(Clone)]
 pub struct RendezvousService {
-    repo: RedisRepository,
+    repo: Arc<dyn MailboxStore>,
     mailbox_ttl: Duration,
     rendezvous_ttl: Duration,
+    /// How long a redeemed token keeps answering `SessionAlreadyPaired`.
+    joined_flag_ttl: Duration,
+    quota: MailboxQuota,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum RendezvousError {
-    #[error("Redis error: {0}")]
-    Redis(#[from] anyhow::Error),
+    #[error("Storage error: {0}")]
+    Storage(#[from] anyhow::Error),
     #[error("Mailbox not found")]
     MailboxNotFound,
+    #[error("Invalid mailbox token")]
+    InvalidMailboxToken,
     #[error("Session expired")]
     SessionExpired,
     #[error("Invalid or expired token")]
@@ -26,14 +43,46 @@ pub enum RendezvousError {
     SessionAlreadyPaired,
     #[error("No peer connected")]
     NoPeerConnected,
+    #[error("Message exceeds {max_bytes} bytes")]
+    MessageTooLarge { max_bytes: u64 },
+    #[error("Mailbox is full; acknowledge delivered messages first")]
+    MailboxQuotaExceeded,
+    #[error("No free nameplate; try again shortly")]
+    NameplatesExhausted,
+}
+
+impl RendezvousError {
+    /// Short, stable name used as a metrics label.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Storage(_) => "storage",
+            Self::MailboxNotFound => "mailbox_not_found",
+            Self::InvalidMailboxToken => "invalid_mailbox_token",
+            Self::SessionExpired => "session_expired",
+            Self::InvalidToken => "invalid_token",
+            Self::SessionAlreadyPaired => "session_already_paired",
+            Self::NoPeerConnected => "no_peer_connected",
+            Self::MessageTooLarge { .. } => "message_too_large",
+            Self::MailboxQuotaExceeded => "mailbox_quota_exceeded",
+            Self::NameplatesExhausted => "nameplates_exhausted",
+        }
+    }
 }
 
 impl RendezvousService {
-    pub fn new(repo: RedisRepository, mailbox_ttl: Duration, rendezvous_ttl: Duration) -> Self {
+    pub fn new(
+        repo: Arc<dyn MailboxStore>,
+        mailbox_ttl: Duration,
+        rendezvous_ttl: Duration,
+        joined_flag_ttl: Duration,
+        quota: MailboxQuota,
+    ) -> Self {
         Self {
             repo,
             mailbox_ttl,
             rendezvous_ttl,
+            joined_flag_ttl,
+            quota,
         }
     }
 
@@ -41,17 +90,83 @@ impl RendezvousService {
         &self,
         rendezvous_id_b64: String,
     ) -> Result<ConnectionInitResponse, RendezvousError> {
+        let result = self.open_mailbox(rendezvous_id_b64).await;
+        metrics().record_rendezvous("init", &result);
+        result
+    }
+
+    async fn open_mailbox(
+        &self,
+        rendezvous_id_b64: String,
+    ) -> Result<ConnectionInitResponse, RendezvousError> {
+        let response = self.create_mailbox().await?;
+
+        // Store rendezvous mapping
+        self.repo
+            .save_rendezvous(
+                &rendezvous_id_b64,
+                &response.mailbox_id,
+                self.rendezvous_ttl.as_secs(),
+            )
+            .await
+            .map_err(RendezvousError::Storage)?;
+
+        Ok(response)
+    }
+
+    /// Opens a mailbox behind a fresh nameplate, the short number that starts a
+    /// connection code. The nameplate lives as long as a rendezvous token.
+    pub async fn open_nameplate(&self) -> Result<NameplateOpenResponse, RendezvousError> {
+        let result = self.claim_nameplate().await;
+        metrics().record_rendezvous("nameplate_open", &result);
+        result
+    }
+
+    async fn claim_nameplate(&self) -> Result<NameplateOpenResponse, RendezvousError> {
+        let response = self.create_mailbox().await?;
+        for limit in NAMEPLATE_LIMITS {
+            for _ in 0..NAMEPLATE_ATTEMPTS {
+                let nameplate = code::gen_nameplate(limit);
+                let claimed = self
+                    .repo
+                    .claim_nameplate(
+                        &nameplate,
+                        &response.mailbox_id,
+                        self.rendezvous_ttl.as_secs(),
+                    )
+                    .await
+                    .map_err(RendezvousError::Storage)?;
+                if claimed {
+                    return Ok(NameplateOpenResponse {
+                        nameplate,
+                        mailbox_id: response.mailbox_id,
+                        mailbox_token: response.mailbox_token,
+                        expires_at_epoch_ms: response.expires_at_epoch_ms,
+                    });
+                }
+            }
+        }
+        self.repo
+            .delete_mailbox(&response.mailbox_id)
+            .await
+            .map_err(RendezvousError::Storage)?;
+        Err(RendezvousError::NameplatesExhausted)
+    }
+
+    async fn create_mailbox(&self) -> Result<ConnectionInitResponse, RendezvousError> {
         let mailbox_id = connection::gen_mailbox_id();
+        let mailbox_token = token::gen_token();
 
         let now_ms = SystemTime::now()
             .duration_since(UNIX_EPOCH)
-            .map_err(|e| RendezvousError::Redis(anyhow::Error::new(e)))?
+            .map_err(|e| RendezvousError::Storage(anyhow::Error::new(e)))?
             .as_millis();
         let expires_ms = now_ms + self.mailbox_ttl.as_millis();
 
         let mailbox_state = MailboxState {
             mailbox_id: mailbox_id.clone(),
             peer_mailbox_id: None,
+            access_token_hash: token::hash_token(&mailbox_token),
             created_at_epoch_ms: now_ms,
             expires_at_epoch_ms: expires_ms,
         };
@@ -59,24 +174,15 @@ impl RendezvousService {
         self.repo
             .save_mailbox_meta(&mailbox_state, self.mailbox_ttl.as_secs())
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
         self.repo
             .clear_mailbox_messages(&mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?;
-
-        // Store rendezvous mapping
-        self.repo
-            .save_rendezvous(
-                &rendezvous_id_b64,
-                &mailbox_id,
-                self.rendezvous_ttl.as_secs(),
-            )
-            .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
 
         Ok(ConnectionInitResponse {
             mailbox_id,
+            mailbox_token,
             expires_at_epoch_ms: expires_ms,
         })
     }
@@ -84,21 +190,60 @@ impl RendezvousService {
     pub async fn join_connection(
         &self,
         token_b64: String,
+    ) -> Result<(ConnectionJoinResponse, String, String), RendezvousError> {
+        let result = async {
+            let redemption = self
+                .repo
+                .redeem_rendezvous(&token_b64, self.joined_flag_ttl.as_secs())
+                .await
+                .map_err(RendezvousError::Storage)?;
+            self.pair_mailbox(redemption).await
+        }
+        .await;
+        metrics().record_rendezvous("join", &result);
+        result
+    }
+
+    /// Joins the mailbox behind a nameplate. Like a link token it works once, so
+    /// whoever holds a code gets a single guess at its words.
+    pub async fn join_nameplate(
+        &self,
+        nameplate: &str,
+    ) -> Result<(ConnectionJoinResponse, String, String), RendezvousError> {
+        let result = async {
+            let nameplate =
+                code::normalize_nameplate(nameplate).ok_or(RendezvousError::InvalidToken)?;
+            let redemption = self
+                .repo
+                .redeem_nameplate(&nameplate, self.joined_flag_ttl.as_secs())
+                .await
+                .map_err(RendezvousError::Storage)?;
+            self.pair_mailbox(redemption).await
+        }
+        .await;
+        metrics().record_rendezvous("nameplate_join", &result);
+        result
+    }
+
+    async fn pair_mailbox(
+        &self,
+        redemption: RendezvousRedemption,
     ) -> Result<(ConnectionJoinResponse, String, String), RendezvousError> {
         // Returns (Response, InitiatorMailboxId, JoinMessageJson)
 
-        let initiator_mailbox_id = self
-            .repo
-            .get_and_delete_rendezvous(&token_b64)
-            .await
-            .map_err(RendezvousError::Redis)?
-            .ok_or(RendezvousError::InvalidToken)?;
+        let initiator_mailbox_id = match redemption {
+            RendezvousRedemption::Redeemed(mailbox_id) => mailbox_id,
+            RendezvousRedemption::AlreadyRedeemed => {
+                return Err(RendezvousError::SessionAlreadyPaired)
+            }
+            RendezvousRedemption::Unknown => return Err(RendezvousError::InvalidToken),
+        };
 
         let mut initiator_state = self
             .repo
             .get_mailbox_meta(&initiator_mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?
+            .map_err(RendezvousError::Storage)?
             .ok_or(RendezvousError::MailboxNotFound)?;
 
         if initiator_state.peer_mailbox_id.is_some() {
@@ -106,50 +251,51 @@ impl RendezvousService {
         }
 
         let responder_mailbox_id = connection::gen_mailbox_id();
+        let responder_mailbox_token = token::gen_token();
 
         // Link them
         initiator_state.peer_mailbox_id = Some(responder_mailbox_id.clone());
         self.repo
             .save_mailbox_meta(&initiator_state, self.mailbox_ttl.as_secs())
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
 
         let responder_state = MailboxState {
             mailbox_id: responder_mailbox_id.clone(),
             peer_mailbox_id: Some(initiator_mailbox_id.clone()),
+            access_token_hash: token::hash_token(&responder_mailbox_token),
             created_at_epoch_ms: initiator_state.created_at_epoch_ms,
             expires_at_epoch_ms: initiator_state.expires_at_epoch_ms,
         };
         self.repo
             .save_mailbox_meta(&responder_state, self.mailbox_ttl.as_secs())
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
         self.repo
             .clear_mailbox_messages(&responder_mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
 
         // Create join message for initiator
-        let seq = self
+        let join_msg = self
             .repo
-            .get_message_count(&initiator_mailbox_id)
+            .append_message(
+                &initiator_mailbox_id,
+                &responder_mailbox_id,
+                "",
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("SystemTime is before UNIX_EPOCH when creating join message timestamp")
+                    .as_millis(),
+                self.mailbox_ttl.as_secs(),
+                &self.quota,
+            )
             .await
-            .map_err(RendezvousError::Redis)?;
-        let join_msg = MailboxMessageStored {
-            from_mailbox_id: responder_mailbox_id.clone(),
-            ciphertext_b64: "".to_string(),
-            sequence: seq,
-            timestamp_epoch_ms: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("SystemTime is before UNIX_EPOCH when creating join message timestamp")
-                .as_millis(),
+            .map_err(RendezvousError::Storage)?;
+        let MailboxAppend::Appended(join_msg) = join_msg else {
+            return Err(RendezvousError::MailboxQuotaExceeded);
         };
 
-        self.repo
-            .push_message(&initiator_mailbox_id, &join_msg, self.mailbox_ttl.as_secs())
-            .await
-            .map_err(RendezvousError::Redis)?;
-
         let join_json = match serde_json::to_string(&join_msg) {
             Ok(json) => json,
             Err(err) => {
@@ -164,6 +310,7 @@ impl RendezvousService {
         Ok((
             ConnectionJoinResponse {
                 mailbox_id: responder_mailbox_id,
+                mailbox_token: responder_mailbox_token,
                 expires_at_epoch_ms: responder_state.expires_at_epoch_ms,
             },
             initiator_mailbox_id,
@@ -174,16 +321,31 @@ impl RendezvousService {
     pub async fn send_message(
         &self,
         mailbox_id: String,
+        mailbox_token: &str,
+        ciphertext_b64: String,
+    ) -> Result<(String, String), RendezvousError> {
+        let result = self
+            .append_to_peer(mailbox_id, mailbox_token, ciphertext_b64)
+            .await;
+        metrics().record_mailbox("send", &result);
+        result
+    }
+
+    async fn append_to_peer(
+        &self,
+        mailbox_id: String,
+        mailbox_token: &str,
         ciphertext_b64: String,
     ) -> Result<(String, String), RendezvousError> {
         // Returns (PeerMailboxId, MessageJson)
 
-        let mailbox_state = self
-            .repo
-            .get_mailbox_meta(&mailbox_id)
-            .await
-            .map_err(RendezvousError::Redis)?
-            .ok_or(RendezvousError::MailboxNotFound)?;
+        if ciphertext_b64.len() as u64 > self.quota.max_message_bytes {
+            return Err(RendezvousError::MessageTooLarge {
+                max_bytes: self.quota.max_message_bytes,
+            });
+        }
+
+        let mailbox_state = self.authorize(&mailbox_id, mailbox_token).await?;
 
         let peer_mailbox_id = mailbox_state
             .peer_mailbox_id
@@ -197,45 +359,66 @@ impl RendezvousService {
             return Err(RendezvousError::SessionExpired);
         }
 
-        let seq = self
+        let msg = match self
             .repo
-            .get_message_count(&peer_mailbox_id)
+            .append_message(
+                &peer_mailbox_id,
+                &mailbox_id,
+                &ciphertext_b64,
+                now_ms,
+                self.mailbox_ttl.as_secs(),
+                &self.quota,
+            )
             .await
-            .map_err(RendezvousError::Redis)?;
-
-        let msg = MailboxMessageStored {
-            from_mailbox_id: mailbox_id,
-            ciphertext_b64,
-            sequence: seq,
-            timestamp_epoch_ms: now_ms,
+            .map_err(RendezvousError::Storage)?
+        {
+            MailboxAppend::Appended(msg) => msg,
+            MailboxAppend::QuotaExceeded => return Err(RendezvousError::MailboxQuotaExceeded),
         };
-        self.repo
-            .push_message(&peer_mailbox_id, &msg, self.mailbox_ttl.as_secs())
-            .await
-            .map_err(RendezvousError::Redis)?;
 
         let msg_json = serde_json::to_string(&msg).unwrap_or_default();
         Ok((peer_mailbox_id, msg_json))
     }
 
+    /// Returns messages newer than `since_sequence` along with the cursor to use next.
     pub async fn recv_messages(
         &self,
         mailbox_id: String,
+        mailbox_token: &str,
+        since_sequence: u64,
+    ) -> Result<MailboxRecvResponse, RendezvousError> {
+        let result = self
+            .read_messages(mailbox_id, mailbox_token, since_sequence)
+            .await;
+        metrics().record_mailbox("recv", &result);
+        result
+    }
+
+    async fn read_messages(
+        &self,
+        mailbox_id: String,
+        mailbox_token: &str,
+        since_sequence: u64,
     ) -> Result<MailboxRecvResponse, RendezvousError> {
-        let _ = self
+        self.authorize(&mailbox_id, mailbox_token).await?;
+
+        // Read the counter before the log: a message appended in between then shows
+        // up in the log instead of being covered by a cursor it was never part of.
+        let allocated_sequence = self
             .repo
-            .get_mailbox_meta(&mailbox_id)
+            .get_last_sequence(&mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?
-            .ok_or(RendezvousError::MailboxNotFound)?;
+            .map_err(RendezvousError::Storage)?;
 
         let stored_msgs = self
             .repo
-            .get_messages(&mailbox_id)
+            .get_messages(&mailbox_id, since_sequence)
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
 
-        let last_sequence = stored_msgs.last().map(|m| m.sequence).unwrap_or(0);
+        let last_sequence = stored_msgs
+            .last()
+            .map_or(allocated_sequence, |m| m.sequence.max(allocated_sequence));
 
         let messages: Vec<MailboxMessage> = stored_msgs
             .into_iter()
@@ -253,15 +436,52 @@ impl RendezvousService {
         })
     }
 
-    pub async fn verify_mailbox(&self, mailbox_id: &str) -> Result<bool, RendezvousError> {
-        let mailbox_state = self
+    /// Trims delivered messages so later receives and the log itself stay small.
+    pub async fn ack_messages(
+        &self,
+        mailbox_id: String,
+        mailbox_token: &str,
+        up_to_sequence: u64,
+    ) -> Result<(), RendezvousError> {
+        self.authorize(&mailbox_id, mailbox_token).await?;
+
+        self.repo
+            .trim_messages(&mailbox_id, up_to_sequence)
+            .await
+            .map_err(RendezvousError::Storage)
+    }
+
+    /// Loads a mailbox after checking that `mailbox_token` is the one it was issued with.
+    pub async fn authorize(
+        &self,
+        mailbox_id: &str,
+        mailbox_token: &str,
+    ) -> Result<MailboxState, RendezvousError> {
+        let state = self
             .repo
             .get_mailbox_meta(mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?
+            .ok_or(RendezvousError::MailboxNotFound)?;
 
-        let Some(state) = mailbox_state else {
-            return Ok(false);
+        let presented = token::hash_token(mailbox_token);
+        if !connection::constant_time_eq(presented.as_bytes(), state.access_token_hash.as_bytes()) {
+            return Err(RendezvousError::InvalidMailboxToken);
+        }
+        Ok(state)
+    }
+
+    pub async fn verify_mailbox(
+        &self,
+        mailbox_id: &str,
+        mailbox_token: &str,
+    ) -> Result<bool, RendezvousError> {
+        let state = match self.authorize(mailbox_id, mailbox_token).await {
+            Ok(state) => state,
+            Err(RendezvousError::MailboxNotFound | RendezvousError::InvalidMailboxToken) => {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
         };
 
         let now_ms = SystemTime::now()
@@ -272,29 +492,80 @@ impl RendezvousService {
         Ok(now_ms < state.expires_at_epoch_ms)
     }
 
-    pub async fn close_connection(&self, mailbox_id: String) -> Result<(), RendezvousError> {
-        let mailbox_state = self
-            .repo
-            .get_mailbox_meta(&mailbox_id)
-            .await
-            .map_err(RendezvousError::Redis)?;
+    /// Deletes the mailbox and its peer, returning the IDs that were removed.
+    pub async fn close_connection(
+        &self,
+        mailbox_id: String,
+        mailbox_token: &str,
+    ) -> Result<Vec<String>, RendezvousError> {
+        let result = self.delete_mailboxes(mailbox_id, mailbox_token).await;
+        metrics().record_rendezvous("close", &result);
+        result
+    }
 
-        let Some(state) = mailbox_state else {
-            return Ok(());
+    async fn delete_mailboxes(
+        &self,
+        mailbox_id: String,
+        mailbox_token: &str,
+    ) -> Result<Vec<String>, RendezvousError> {
+        let state = match self.authorize(&mailbox_id, mailbox_token).await {
+            Ok(state) => state,
+            // Already gone (closed by the peer or expired)
+            Err(RendezvousError::MailboxNotFound) => return Ok(Vec::new()),
+            Err(e) => return Err(e),
         };
+        self.delete_pair(state).await
+    }
+
+    /// Closes a mailbox and its peer without the mailbox token, for operators.
+    pub async fn force_close(&self, mailbox_id: &str) -> Result<Vec<String>, RendezvousError> {
+        let result = async {
+            let state = self
+                .repo
+                .get_mailbox_meta(mailbox_id)
+                .await
+                .map_err(RendezvousError::Storage)?
+                .ok_or(RendezvousError::MailboxNotFound)?;
+            self.delete_pair(state).await
+        }
+        .await;
+        metrics().record_rendezvous("admin_close", &result);
+        result
+    }
+
+    /// Every live mailbox, paired or still waiting for a responder.
+    pub async fn list_mailboxes(&self) -> Result<Vec<MailboxState>, RendezvousError> {
+        self.repo
+            .list_mailboxes()
+            .await
+            .map_err(RendezvousError::Storage)
+    }
+
+    /// Invalidates a rendezvous token nobody has joined with yet and returns the
+    /// mailbox it pointed at. The mailbox itself stays until closed or expired.
+    pub async fn revoke_rendezvous(&self, token_b64: &str) -> Result<String, RendezvousError> {
+        self.repo
+            .get_and_delete_rendezvous(token_b64)
+            .await
+            .map_err(RendezvousError::Storage)?
+            .ok_or(RendezvousError::InvalidToken)
+    }
 
+    async fn delete_pair(&self, state: MailboxState) -> Result<Vec<String>, RendezvousError> {
         self.repo
             .delete_mailbox(&state.mailbox_id)
             .await
-            .map_err(RendezvousError::Redis)?;
+            .map_err(RendezvousError::Storage)?;
 
         if let Some(peer_id) = state.peer_mailbox_id.as_ref() {
             self.repo
                 .delete_mailbox(peer_id)
                 .await
-                .map_err(RendezvousError::Redis)?;
+                .map_err(RendezvousError::Storage)?;
         }
 
-        Ok(())
+        Ok(std::iter::once(state.mailbox_id)
+            .chain(state.peer_mailbox_id)
+            .collect())
     }
 }
@@ -0,0 +1,114 @@
+pub mod message;
+
+use crate::metrics::metrics;
+use message::{
+    Class, Message, MessageBuilder, ATTR_ERROR_CODE, ATTR_MESSAGE_INTEGRITY,
+    ATTR_MESSAGE_INTEGRITY_SHA256, ATTR_NONCE, ATTR_REALM, ATTR_UNKNOWN_ATTRIBUTES, ATTR_USERNAME,
+    ATTR_XOR_MAPPED_ADDRESS, METHOD_BINDING,
+};
+use std::net::SocketAddr;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tracing::{debug, info};
+
+/// Large enough for any STUN message worth answering; longer datagrams are
+/// truncated and then fail the length check.
+const MAX_DATAGRAM: usize = 1500;
+
+/// Comprehension-required attributes the responder accepts (and ignores) in a
+/// Binding request. Any other one below 0x8000 gets a 420 reply.
+const UNDERSTOOD_ATTRIBUTES: &[u16] = &[
+    ATTR_USERNAME,
+    ATTR_MESSAGE_INTEGRITY,
+    ATTR_MESSAGE_INTEGRITY_SHA256,
+    ATTR_ERROR_CODE,
+    ATTR_UNKNOWN_ATTRIBUTES,
+    ATTR_REALM,
+    ATTR_NONCE,
+    ATTR_XOR_MAPPED_ADDRESS,
+];
+
+/// Answers STUN Binding requests on `socket` until shutdown starts.
+///
+/// This is the minimal server from RFC 8489 §12: no authentication and no
+/// state, just the reflexive address the request came from. The reply is at
+/// most a few dozen bytes larger than the request, so it is a poor amplifier.
+pub async fn serve(socket: UdpSocket, mut shutdown: watch::Receiver<bool>) {
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    loop {
+        let (len, from) = tokio::select! {
+            received = socket.recv_from(&mut buf) => match received {
+                Ok(received) => received,
+                Err(err) => {
+                    // ICMP port unreachable from an earlier reply surfaces here on
+                    // some platforms; it is not fatal for the socket
+                    debug!(error = %err, "STUN receive failed");
+                    continue;
+                }
+            },
+            _ = shutdown.wait_for(|stopping| *stopping) => break,
+        };
+        let Some(reply) = respond(&buf[..len], from) else {
+            continue;
+        };
+        if let Err(err) = socket.send_to(&reply, from).await {
+            debug!(error = %err, peer = %from, "STUN reply failed");
+        }
+    }
+    info!("STUN responder stopped");
+}
+
+/// Binds `addr` and runs [`serve`] in the background.
+pub async fn spawn(addr: SocketAddr, shutdown: watch::Receiver<bool>) -> anyhow::Result<()> {
+    let socket = UdpSocket::bind(addr).await?;
+    info!(address = %socket.local_addr()?, "Serving STUN");
+    tokio::spawn(serve(socket, shutdown));
+    Ok(())
+}
+
+/// The reply to one datagram from `from`, or `None` when it must be ignored:
+/// anything that is not a well-formed Binding request.
+pub fn respond(datagram: &[u8], from: SocketAddr) -> Option<Vec<u8>> {
+    let request = match Message::decode(datagram) {
+        Ok(request) => request,
+        Err(err) => {
+            debug!(error = %err, peer = %from, "Ignoring datagram");
+            record("invalid");
+            return None;
+        }
+    };
+    if request.class != Class::Request || request.method != METHOD_BINDING {
+        // Indications are never answered; other methods belong to TURN
+        record("unsupported");
+        return None;
+    }
+
+    let unknown: Vec<u16> = request
+        .attributes
+        .iter()
+        .map(|(kind, _)| *kind)
+        .filter(|kind| *kind < 0x8000 && !UNDERSTOOD_ATTRIBUTES.contains(kind))
+        .collect();
+    if !unknown.is_empty() {
+        debug!(peer = %from, ?unknown, "STUN request with unknown attributes");
+        record("unknown_attribute");
+        let listed: Vec<u8> = unknown.iter().flat_map(|kind| kind.to_be_bytes()).collect();
+        return Some(
+            MessageBuilder::new(Class::Error, METHOD_BINDING, request.transaction_id)
+                .error_code(420, "Unknown Attribute")
+                .attribute(ATTR_UNKNOWN_ATTRIBUTES, &listed)
+                .build_with_fingerprint(),
+        );
+    }
+
+    record("ok");
+    Some(
+        MessageBuilder::new(Class::Success, METHOD_BINDING, request.transaction_id)
+            .xor_address(ATTR_XOR_MAPPED_ADDRESS, from)
+            .build_with_fingerprint(),
+    )
+}
+
+fn record(outcome: &str) {
+    metrics().stun_requests.with_label_values(&[outcome]).inc();
+}
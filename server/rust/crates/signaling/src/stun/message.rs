@@ -0,0 +1,341 @@
+//! STUN message encoding and decoding (RFC 8489), limited to what the built-in
+//! STUN responder and TURN relay need.
+
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::net::{IpAddr, SocketAddr};
+
+pub const MAGIC_COOKIE: u32 = 0x2112_A442;
+pub const HEADER_LEN: usize = 20;
+
+pub const METHOD_BINDING: u16 = 0x001;
+pub const METHOD_ALLOCATE: u16 = 0x003;
+pub const METHOD_REFRESH: u16 = 0x004;
+pub const METHOD_SEND: u16 = 0x006;
+pub const METHOD_DATA: u16 = 0x007;
+pub const METHOD_CREATE_PERMISSION: u16 = 0x008;
+pub const METHOD_CHANNEL_BIND: u16 = 0x009;
+
+pub const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+pub const ATTR_USERNAME: u16 = 0x0006;
+pub const ATTR_MESSAGE_INTEGRITY: u16 = 0x0008;
+pub const ATTR_ERROR_CODE: u16 = 0x0009;
+pub const ATTR_UNKNOWN_ATTRIBUTES: u16 = 0x000A;
+pub const ATTR_CHANNEL_NUMBER: u16 = 0x000C;
+pub const ATTR_LIFETIME: u16 = 0x000D;
+pub const ATTR_XOR_PEER_ADDRESS: u16 = 0x0012;
+pub const ATTR_DATA: u16 = 0x0013;
+pub const ATTR_REALM: u16 = 0x0014;
+pub const ATTR_NONCE: u16 = 0x0015;
+pub const ATTR_XOR_RELAYED_ADDRESS: u16 = 0x0016;
+pub const ATTR_EVEN_PORT: u16 = 0x0018;
+pub const ATTR_REQUESTED_TRANSPORT: u16 = 0x0019;
+pub const ATTR_DONT_FRAGMENT: u16 = 0x001A;
+pub const ATTR_MESSAGE_INTEGRITY_SHA256: u16 = 0x001C;
+pub const ATTR_RESERVATION_TOKEN: u16 = 0x0022;
+pub const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+pub const ATTR_SOFTWARE: u16 = 0x8022;
+pub const ATTR_FINGERPRINT: u16 = 0x8028;
+
+/// XORed into the CRC-32 so FINGERPRINT tells STUN apart from other protocols
+/// multiplexed on the same port.
+const FINGERPRINT_XOR: u32 = 0x5354_554E;
+
+/// Size of a MESSAGE-INTEGRITY attribute: header plus an HMAC-SHA1.
+const INTEGRITY_ATTR_LEN: usize = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Request,
+    Indication,
+    Success,
+    Error,
+}
+
+impl Class {
+    fn bits(self) -> u16 {
+        match self {
+            Self::Request => 0b00,
+            Self::Indication => 0b01,
+            Self::Success => 0b10,
+            Self::Error => 0b11,
+        }
+    }
+
+    fn from_bits(bits: u16) -> Self {
+        match bits {
+            0b00 => Self::Request,
+            0b01 => Self::Indication,
+            0b10 => Self::Success,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// Why a datagram was not accepted as a STUN message. Such datagrams are
+/// dropped without a reply.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum DecodeError {
+    #[error("shorter than a STUN header")]
+    TooShort,
+    #[error("not a STUN message")]
+    NotStun,
+    #[error("length does not match the datagram")]
+    BadLength,
+    #[error("malformed attribute")]
+    BadAttribute,
+    #[error("FINGERPRINT mismatch")]
+    BadFingerprint,
+}
+
+/// A decoded STUN message. Attribute values are kept raw; padding is stripped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub class: Class,
+    pub method: u16,
+    pub transaction_id: [u8; 12],
+    pub attributes: Vec<(u16, Vec<u8>)>,
+    /// The bytes MESSAGE-INTEGRITY covers, with the length field already
+    /// adjusted, and the HMAC it carried.
+    integrity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl Message {
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        if buf.len() < HEADER_LEN {
+            return Err(DecodeError::TooShort);
+        }
+        let message_type = u16::from_be_bytes([buf[0], buf[1]]);
+        let length = usize::from(u16::from_be_bytes([buf[2], buf[3]]));
+        let cookie = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+        // The top two bits are zero for STUN, which separates it from RTP/DTLS
+        if message_type & 0xC000 != 0 || cookie != MAGIC_COOKIE {
+            return Err(DecodeError::NotStun);
+        }
+        if length % 4 != 0 || HEADER_LEN + length != buf.len() {
+            return Err(DecodeError::BadLength);
+        }
+        let mut transaction_id = [0u8; 12];
+        transaction_id.copy_from_slice(&buf[8..HEADER_LEN]);
+
+        let mut attributes = Vec::new();
+        let mut integrity = None;
+        let mut offset = HEADER_LEN;
+        while offset < buf.len() {
+            if offset + 4 > buf.len() {
+                return Err(DecodeError::BadAttribute);
+            }
+            let kind = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+            let value_len = usize::from(u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]));
+            let value_start = offset + 4;
+            let value_end = value_start + value_len;
+            if value_end > buf.len() {
+                return Err(DecodeError::BadAttribute);
+            }
+            if kind == ATTR_FINGERPRINT {
+                // Must be the last attribute and cover everything before it
+                if value_len != 4 || value_end != buf.len() {
+                    return Err(DecodeError::BadAttribute);
+                }
+                let expected = u32::from_be_bytes([
+                    buf[value_start],
+                    buf[value_start + 1],
+                    buf[value_start + 2],
+                    buf[value_start + 3],
+                ]);
+                if fingerprint(&buf[..offset]) != expected {
+                    return Err(DecodeError::BadFingerprint);
+                }
+            }
+            if kind == ATTR_MESSAGE_INTEGRITY && integrity.is_none() {
+                if value_len != 20 {
+                    return Err(DecodeError::BadAttribute);
+                }
+                let mut covered = buf[..offset].to_vec();
+                let length = (offset - HEADER_LEN + INTEGRITY_ATTR_LEN) as u16;
+                covered[2..4].copy_from_slice(&length.to_be_bytes());
+                integrity = Some((covered, buf[value_start..value_end].to_vec()));
+            }
+            attributes.push((kind, buf[value_start..value_end].to_vec()));
+            offset = value_start + padded(value_len);
+        }
+
+        Ok(Self {
+            class: Class::from_bits(((message_type >> 4) & 0b01) | ((message_type >> 7) & 0b10)),
+            method: (message_type & 0x000F)
+                | ((message_type >> 1) & 0x0070)
+                | ((message_type >> 2) & 0x0F80),
+            transaction_id,
+            attributes,
+            integrity,
+        })
+    }
+
+    pub fn attribute(&self, kind: u16) -> Option<&[u8]> {
+        self.attributes
+            .iter()
+            .find(|(attr, _)| *attr == kind)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// The address carried in an XOR-MAPPED-ADDRESS style attribute.
+    pub fn xor_address(&self, kind: u16) -> Option<SocketAddr> {
+        decode_xor_address(self.attribute(kind)?, &self.transaction_id)
+    }
+
+    /// Every address of a repeatable XOR address attribute, in message order;
+    /// `None` if any of them is malformed.
+    pub fn xor_addresses(&self, kind: u16) -> Option<Vec<SocketAddr>> {
+        self.attributes
+            .iter()
+            .filter(|(attr, _)| *attr == kind)
+            .map(|(_, value)| decode_xor_address(value, &self.transaction_id))
+            .collect()
+    }
+
+    /// Checks MESSAGE-INTEGRITY against `key`; `false` when the attribute is missing.
+    pub fn verify_integrity(&self, key: &[u8]) -> bool {
+        let Some((covered, expected)) = &self.integrity else {
+            return false;
+        };
+        let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(covered);
+        mac.verify_slice(expected).is_ok()
+    }
+}
+
+/// Builds one outgoing message; attributes are written in the order added.
+pub struct MessageBuilder {
+    buf: Vec<u8>,
+    transaction_id: [u8; 12],
+}
+
+impl MessageBuilder {
+    pub fn new(class: Class, method: u16, transaction_id: [u8; 12]) -> Self {
+        let class = class.bits();
+        let message_type = (method & 0x000F)
+            | ((method & 0x0070) << 1)
+            | ((method & 0x0F80) << 2)
+            | ((class & 0b01) << 4)
+            | ((class & 0b10) << 7);
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(&message_type.to_be_bytes());
+        buf.extend_from_slice(&0u16.to_be_bytes());
+        buf.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        buf.extend_from_slice(&transaction_id);
+        Self {
+            buf,
+            transaction_id,
+        }
+    }
+
+    pub fn attribute(mut self, kind: u16, value: &[u8]) -> Self {
+        let value_len = u16::try_from(value.len()).expect("attribute fits in a datagram");
+        self.buf.extend_from_slice(&kind.to_be_bytes());
+        self.buf.extend_from_slice(&value_len.to_be_bytes());
+        self.buf.extend_from_slice(value);
+        self.buf
+            .resize(self.buf.len() + padded(value.len()) - value.len(), 0);
+        self.set_length(self.buf.len() - HEADER_LEN);
+        self
+    }
+
+    pub fn xor_address(self, kind: u16, addr: SocketAddr) -> Self {
+        let value = encode_xor_address(addr, &self.transaction_id);
+        self.attribute(kind, &value)
+    }
+
+    /// ERROR-CODE with a reason phrase, e.g. `(420, "Unknown Attribute")`.
+    pub fn error_code(self, code: u16, reason: &str) -> Self {
+        let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+        value.extend_from_slice(reason.as_bytes());
+        self.attribute(ATTR_ERROR_CODE, &value)
+    }
+
+    /// Appends MESSAGE-INTEGRITY over everything written so far.
+    pub fn message_integrity(mut self, key: &[u8]) -> Self {
+        self.set_length(self.buf.len() - HEADER_LEN + INTEGRITY_ATTR_LEN);
+        let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(&self.buf);
+        let digest = mac.finalize().into_bytes();
+        self.attribute(ATTR_MESSAGE_INTEGRITY, &digest)
+    }
+
+    /// Ends the message without a FINGERPRINT.
+    pub fn build(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Ends the message with a FINGERPRINT over everything before it.
+    pub fn build_with_fingerprint(mut self) -> Vec<u8> {
+        // The length field must already count the FINGERPRINT attribute
+        self.set_length(self.buf.len() - HEADER_LEN + 8);
+        let crc = fingerprint(&self.buf);
+        self.attribute(ATTR_FINGERPRINT, &crc.to_be_bytes()).buf
+    }
+
+    fn set_length(&mut self, length: usize) {
+        let length = u16::try_from(length).expect("message fits in a datagram");
+        self.buf[2..4].copy_from_slice(&length.to_be_bytes());
+    }
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+fn fingerprint(bytes: &[u8]) -> u32 {
+    crc32fast::hash(bytes) ^ FINGERPRINT_XOR
+}
+
+fn encode_xor_address(addr: SocketAddr, transaction_id: &[u8; 12]) -> Vec<u8> {
+    let port = addr.port() ^ (MAGIC_COOKIE >> 16) as u16;
+    // Dual-stack sockets report IPv4 peers as ::ffff:a.b.c.d
+    match addr.ip().to_canonical() {
+        IpAddr::V4(ip) => {
+            let mut value = vec![0, 0x01];
+            value.extend_from_slice(&port.to_be_bytes());
+            value.extend_from_slice(&(u32::from(ip) ^ MAGIC_COOKIE).to_be_bytes());
+            value
+        }
+        IpAddr::V6(ip) => {
+            let mut value = vec![0, 0x02];
+            value.extend_from_slice(&port.to_be_bytes());
+            let key = xor_key(transaction_id);
+            value.extend(ip.octets().iter().zip(key).map(|(byte, k)| byte ^ k));
+            value
+        }
+    }
+}
+
+fn decode_xor_address(value: &[u8], transaction_id: &[u8; 12]) -> Option<SocketAddr> {
+    if value.len() < 4 {
+        return None;
+    }
+    let port = u16::from_be_bytes([value[2], value[3]]) ^ (MAGIC_COOKIE >> 16) as u16;
+    let ip = match (value[1], value.len()) {
+        (0x01, 8) => {
+            let raw = u32::from_be_bytes([value[4], value[5], value[6], value[7]]);
+            IpAddr::from((raw ^ MAGIC_COOKIE).to_be_bytes())
+        }
+        (0x02, 20) => {
+            let key = xor_key(transaction_id);
+            let mut octets = [0u8; 16];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = value[4 + i] ^ key[i];
+            }
+            IpAddr::from(octets)
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip, port))
+}
+
+/// The magic cookie followed by the transaction ID, which IPv6 addresses are
+/// XORed with.
+fn xor_key(transaction_id: &[u8; 12]) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    key[4..].copy_from_slice(transaction_id);
+    key
+}
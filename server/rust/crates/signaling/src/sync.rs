@@ -0,0 +1,10 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks a std mutex that is only held for short, non-async sections. A poisoned
+/// lock just means another thread panicked mid-update of plain maps or counters;
+/// keep using it rather than taking the whole server down.
+pub(crate) fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
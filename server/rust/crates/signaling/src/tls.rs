@@ -0,0 +1,143 @@
+use crate::config::TlsSettings;
+use anyhow::Context;
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::ServerConfig;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// Builds the rustls configuration from the PEM files in `settings`.
+///
+/// The handshake uses ring explicitly rather than the process-wide default
+/// provider, so it does not depend on which rustls features other crates enable.
+pub fn load_server_config(settings: &TlsSettings) -> anyhow::Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(&settings.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("reading TLS certificate {}", settings.cert_path.display()))?;
+    if certs.is_empty() {
+        anyhow::bail!("no certificate found in {}", settings.cert_path.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(&settings.key_path)
+        .with_context(|| format!("reading TLS private key {}", settings.key_path.display()))?;
+
+    let mut config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .context("TLS certificate and private key do not match")?;
+    // The upgrade to a WebSocket is an HTTP/1.1 exchange; h2 is fine for the rest
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Re-reads the certificate and key and swaps them in for new handshakes.
+///
+/// Established connections, WebSockets included, keep the session they
+/// negotiated. On error the previous certificate stays in use.
+pub fn reload(tls: &RustlsConfig, settings: &TlsSettings) -> anyhow::Result<()> {
+    tls.reload_from_config(load_server_config(settings)?);
+    Ok(())
+}
+
+/// Reloads the certificate whenever either file changes on disk (checked every
+/// `poll_interval`) or, on Unix, when the process receives SIGHUP.
+///
+/// Polling file metadata rather than using inotify also catches the symlink
+/// swaps that Kubernetes secrets and certbot's `live/` directory rely on.
+pub fn spawn_reloader(tls: RustlsConfig, settings: TlsSettings, poll_interval: Duration) {
+    // Taken before spawning so a change that lands before the task first runs
+    // is not mistaken for the baseline
+    let mut last_seen = fingerprint(&settings);
+    tokio::spawn(async move {
+        let mut poll = tokio::time::interval(poll_interval);
+        poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut hangup = hangup_signal();
+
+        loop {
+            let trigger = tokio::select! {
+                _ = poll.tick() => {
+                    let current = fingerprint(&settings);
+                    if current == last_seen {
+                        continue;
+                    }
+                    last_seen = current;
+                    "file change"
+                }
+                _ = hangup.recv() => "SIGHUP",
+            };
+            match reload(&tls, &settings) {
+                Ok(()) => info!(
+                    %trigger,
+                    cert = %settings.cert_path.display(),
+                    "Reloaded TLS certificate"
+                ),
+                Err(err) => warn!(
+                    %trigger,
+                    error = %format!("{err:#}"),
+                    "TLS reload failed, keeping the current certificate"
+                ),
+            }
+        }
+    });
+}
+
+type Fingerprint = [Option<(SystemTime, u64)>; 2];
+
+fn fingerprint(settings: &TlsSettings) -> Fingerprint {
+    [
+        file_stamp(&settings.cert_path),
+        file_stamp(&settings.key_path),
+    ]
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    // `metadata` follows symlinks, so a swapped link target counts as a change
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(unix)]
+struct HangupSignal(Option<tokio::signal::unix::Signal>);
+
+#[cfg(unix)]
+impl HangupSignal {
+    async fn recv(&mut self) {
+        match &mut self.0 {
+            Some(signal) => {
+                signal.recv().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn hangup_signal() -> HangupSignal {
+    use tokio::signal::unix::{signal, SignalKind};
+    match signal(SignalKind::hangup()) {
+        Ok(signal) => HangupSignal(Some(signal)),
+        Err(err) => {
+            warn!(error = %err, "Failed to listen for SIGHUP, relying on file changes only");
+            HangupSignal(None)
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct HangupSignal;
+
+#[cfg(not(unix))]
+impl HangupSignal {
+    async fn recv(&mut self) {
+        std::future::pending().await
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> HangupSignal {
+    HangupSignal
+}
@@ -0,0 +1,729 @@
+mod allocation;
+mod auth;
+
+pub use auth::long_term_key;
+
+use crate::config::{TurnRelaySettings, TurnSecret};
+use crate::metrics::metrics;
+use crate::registry::SessionRegistry;
+use crate::stun::{
+    self,
+    message::{
+        Class, Message, MessageBuilder, ATTR_CHANNEL_NUMBER, ATTR_DATA, ATTR_LIFETIME,
+        ATTR_MESSAGE_INTEGRITY, ATTR_NONCE, ATTR_REALM, ATTR_REQUESTED_TRANSPORT,
+        ATTR_UNKNOWN_ATTRIBUTES, ATTR_USERNAME, ATTR_XOR_MAPPED_ADDRESS, ATTR_XOR_PEER_ADDRESS,
+        ATTR_XOR_RELAYED_ADDRESS, METHOD_ALLOCATE, METHOD_BINDING, METHOD_CHANNEL_BIND,
+        METHOD_CREATE_PERMISSION, METHOD_REFRESH, METHOD_SEND,
+    },
+};
+use crate::sync::lock;
+use allocation::{drop_datagram, Allocation, Bandwidth, Grant};
+use auth::{AuthError, Authenticator, Credentials};
+use futures_util::future::join_all;
+use shared::models::ClientId;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+/// Allocation lifetime when the client does not ask for one, and the most it
+/// may ask for (RFC 5766 §2.2).
+const DEFAULT_LIFETIME: Duration = Duration::from_secs(600);
+const MAX_LIFETIME: Duration = Duration::from_secs(3600);
+const PERMISSION_LIFETIME: Duration = Duration::from_secs(300);
+const CHANNEL_LIFETIME: Duration = Duration::from_secs(600);
+
+/// How often expired allocations are dropped and owners are checked to still
+/// be registered.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Far larger than anything WebRTC sends over UDP; longer datagrams are truncated.
+const MAX_DATAGRAM: usize = 16 * 1024;
+
+/// REQUESTED-TRANSPORT value for UDP, the only one relayed.
+const TRANSPORT_UDP: u8 = 17;
+
+/// Channel numbers a client may bind (RFC 5766 §11).
+const CHANNEL_NUMBERS: std::ops::RangeInclusive<u16> = 0x4000..=0x7FFE;
+
+/// Comprehension-required attributes the relay handles. Requests with any
+/// other one get a 420, which is how DONT-FRAGMENT, EVEN-PORT and
+/// RESERVATION-TOKEN are declined.
+const UNDERSTOOD_ATTRIBUTES: &[u16] = &[
+    ATTR_USERNAME,
+    ATTR_MESSAGE_INTEGRITY,
+    ATTR_REALM,
+    ATTR_NONCE,
+    ATTR_LIFETIME,
+    ATTR_REQUESTED_TRANSPORT,
+    ATTR_XOR_PEER_ADDRESS,
+    ATTR_CHANNEL_NUMBER,
+    ATTR_DATA,
+];
+
+/// An error response: STUN error code and reason phrase.
+type Reject = (u16, &'static str);
+
+const BAD_REQUEST: Reject = (400, "Bad Request");
+const FORBIDDEN: Reject = (403, "Forbidden");
+const ALLOCATION_MISMATCH: Reject = (437, "Allocation Mismatch");
+const WRONG_CREDENTIALS: Reject = (441, "Wrong Credentials");
+const UNSUPPORTED_TRANSPORT: Reject = (442, "Unsupported Transport Protocol");
+const PEER_FAMILY_MISMATCH: Reject = (443, "Peer Address Family Mismatch");
+const QUOTA_REACHED: Reject = (486, "Allocation Quota Reached");
+const SERVER_ERROR: Reject = (500, "Server Error");
+const INSUFFICIENT_CAPACITY: Reject = (508, "Insufficient Capacity");
+
+/// A TURN relay (RFC 5766) for UDP, authenticating clients with the REST
+/// credentials handed out on `/ice-servers` and only for clients that are
+/// still registered.
+///
+/// Each allocation gets its own relay socket. Requests and ChannelData from
+/// clients are handled on one task, except Allocate requests, which wait on
+/// the session store and are answered from a task of their own; the periodic
+/// sweep runs on another. Each relay socket has a task forwarding peer
+/// datagrams back to its client.
+pub struct TurnServer {
+    socket: Arc<UdpSocket>,
+    relay: Arc<Relay>,
+}
+
+struct Relay {
+    settings: TurnRelaySettings,
+    auth: Authenticator,
+    registry: Arc<SessionRegistry>,
+    /// Keyed by the client's address: UDP is the only client transport.
+    allocations: Mutex<HashMap<SocketAddr, Arc<Allocation>>>,
+    bandwidth: Mutex<HashMap<ClientId, Arc<Bandwidth>>>,
+    /// Where the search for a free relay port starts next, if a range is set.
+    next_port: Mutex<u16>,
+}
+
+impl TurnServer {
+    pub async fn bind(
+        settings: TurnRelaySettings,
+        secret: TurnSecret,
+        registry: Arc<SessionRegistry>,
+    ) -> std::io::Result<Self> {
+        let socket = Arc::new(UdpSocket::bind(settings.listen_addr).await?);
+        let next_port = settings
+            .relay_ports
+            .as_ref()
+            .map_or(0, |ports| *ports.start());
+        Ok(Self {
+            socket,
+            relay: Arc::new(Relay {
+                auth: Authenticator::new(settings.realm.clone(), secret),
+                settings,
+                registry,
+                allocations: Mutex::new(HashMap::new()),
+                bandwidth: Mutex::new(HashMap::new()),
+                next_port: Mutex::new(next_port),
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Handles clients until shutdown starts, then drops every allocation.
+    pub async fn serve(self, mut shutdown: watch::Receiver<bool>) {
+        let sweeper = tokio::spawn(self.relay.clone().sweep_periodically());
+        let mut allocating = JoinSet::new();
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let received = tokio::select! {
+                received = self.socket.recv_from(&mut buf) => received,
+                Some(_) = allocating.join_next() => continue,
+                _ = shutdown.wait_for(|stopping| *stopping) => break,
+            };
+            let (len, from) = match received {
+                Ok(received) => received,
+                Err(err) => {
+                    debug!(error = %err, "TURN receive failed");
+                    continue;
+                }
+            };
+            let reply = self
+                .relay
+                .handle(&buf[..len], from, &self.socket, &mut allocating)
+                .await;
+            if let Some(reply) = reply {
+                send_reply(&self.socket, &reply, from).await;
+            }
+        }
+        sweeper.abort();
+        // Waits for the aborted allocations so none is added after the clear
+        allocating.shutdown().await;
+        lock(&self.relay.allocations).clear();
+        info!("TURN relay stopped");
+    }
+}
+
+/// Binds the relay's listener and runs [`TurnServer::serve`] in the background.
+pub async fn spawn(
+    settings: TurnRelaySettings,
+    secret: TurnSecret,
+    registry: Arc<SessionRegistry>,
+    shutdown: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let server = TurnServer::bind(settings, secret, registry).await?;
+    info!(address = %server.local_addr()?, "Serving TURN");
+    tokio::spawn(server.serve(shutdown));
+    Ok(())
+}
+
+async fn send_reply(listener: &UdpSocket, reply: &[u8], to: SocketAddr) {
+    if let Err(err) = listener.send_to(reply, to).await {
+        debug!(error = %err, peer = %to, "TURN reply failed");
+    }
+}
+
+impl Relay {
+    /// The reply to one datagram from `from`, if it gets one. ChannelData and
+    /// Send indications are relayed as a side effect, and Allocate requests
+    /// are answered from a task spawned on `allocating`.
+    async fn handle(
+        self: &Arc<Self>,
+        datagram: &[u8],
+        from: SocketAddr,
+        listener: &Arc<UdpSocket>,
+        allocating: &mut JoinSet<()>,
+    ) -> Option<Vec<u8>> {
+        // ChannelData starts with 0b01; STUN messages start with 0b00
+        if datagram.first().is_some_and(|byte| byte & 0xC0 == 0x40) {
+            self.relay_channel_data(datagram, from).await;
+            return None;
+        }
+        let message = match Message::decode(datagram) {
+            Ok(message) => message,
+            Err(err) => {
+                debug!(error = %err, peer = %from, "Ignoring datagram");
+                drop_datagram("invalid");
+                return None;
+            }
+        };
+        let label = match (message.class, message.method) {
+            (Class::Request, METHOD_BINDING) => return stun::respond(datagram, from),
+            (Class::Indication, METHOD_SEND) => {
+                self.relay_send_indication(&message, from).await;
+                return None;
+            }
+            (Class::Request, METHOD_ALLOCATE) => "allocate",
+            (Class::Request, METHOD_REFRESH) => "refresh",
+            (Class::Request, METHOD_CREATE_PERMISSION) => "create_permission",
+            (Class::Request, METHOD_CHANNEL_BIND) => "channel_bind",
+            _ => {
+                drop_datagram("unsupported");
+                return None;
+            }
+        };
+        if message.method == METHOD_ALLOCATE {
+            let (relay, listener) = (self.clone(), listener.clone());
+            allocating.spawn(async move {
+                let reply = relay.respond(&message, label, from, &listener).await;
+                send_reply(&listener, &reply, from).await;
+            });
+            return None;
+        }
+        Some(self.respond(&message, label, from, listener).await)
+    }
+
+    /// Answers a request and counts the outcome under `label`.
+    async fn respond(
+        &self,
+        request: &Message,
+        label: &'static str,
+        from: SocketAddr,
+        listener: &Arc<UdpSocket>,
+    ) -> Vec<u8> {
+        let (outcome, reply) = self.answer(request, from, listener).await;
+        metrics()
+            .turn_requests
+            .with_label_values(&[label, outcome])
+            .inc();
+        reply
+    }
+
+    /// Authenticates a request and carries it out, returning the metrics
+    /// outcome and the response.
+    async fn answer(
+        &self,
+        request: &Message,
+        from: SocketAddr,
+        listener: &Arc<UdpSocket>,
+    ) -> (&'static str, Vec<u8>) {
+        let method = request.method;
+        let reply = |class| MessageBuilder::new(class, method, request.transaction_id);
+
+        let unknown: Vec<u16> = request
+            .attributes
+            .iter()
+            .map(|(kind, _)| *kind)
+            .filter(|kind| *kind < 0x8000 && !UNDERSTOOD_ATTRIBUTES.contains(kind))
+            .collect();
+        if !unknown.is_empty() {
+            let listed: Vec<u8> = unknown.iter().flat_map(|kind| kind.to_be_bytes()).collect();
+            let response = reply(Class::Error)
+                .error_code(420, "Unknown Attribute")
+                .attribute(ATTR_UNKNOWN_ATTRIBUTES, &listed)
+                .build_with_fingerprint();
+            return ("unknown_attribute", response);
+        }
+
+        let now = SystemTime::now();
+        let credentials = match self.auth.authenticate(request, from, now) {
+            Ok(credentials) => credentials,
+            Err(err) => {
+                let (outcome, (code, reason)) = match err {
+                    AuthError::Challenge => ("challenge", (401, "Unauthorized")),
+                    AuthError::Rejected => ("unauthorized", (401, "Unauthorized")),
+                    AuthError::StaleNonce => ("stale_nonce", (438, "Stale Nonce")),
+                    AuthError::Incomplete => {
+                        return (
+                            "bad_request",
+                            reply(Class::Error)
+                                .error_code(BAD_REQUEST.0, BAD_REQUEST.1)
+                                .build_with_fingerprint(),
+                        );
+                    }
+                };
+                let response = reply(Class::Error)
+                    .error_code(code, reason)
+                    .attribute(ATTR_REALM, self.auth.realm().as_bytes())
+                    .attribute(ATTR_NONCE, self.auth.nonce(from, now).as_bytes())
+                    .build_with_fingerprint();
+                return (outcome, response);
+            }
+        };
+
+        let now = Instant::now();
+        let success = reply(Class::Success);
+        let result = match method {
+            METHOD_ALLOCATE => {
+                self.allocate(request, from, &credentials, listener, success, now)
+                    .await
+            }
+            METHOD_REFRESH => self.refresh(request, from, &credentials, success, now),
+            METHOD_CREATE_PERMISSION => {
+                self.create_permission(request, from, &credentials, success, now)
+            }
+            _ => self.channel_bind(request, from, &credentials, success, now),
+        };
+        let (outcome, builder) = match result {
+            Ok(builder) => ("ok", builder),
+            Err((code, reason)) => (
+                reject_label(code),
+                reply(Class::Error).error_code(code, reason),
+            ),
+        };
+        let response = builder
+            .message_integrity(&credentials.key)
+            .build_with_fingerprint();
+        (outcome, response)
+    }
+
+    async fn allocate(
+        &self,
+        request: &Message,
+        from: SocketAddr,
+        credentials: &Credentials,
+        listener: &Arc<UdpSocket>,
+        success: MessageBuilder,
+        now: Instant,
+    ) -> Result<MessageBuilder, Reject> {
+        let existing = lock(&self.allocations).get(&from).cloned();
+        if let Some(existing) = existing {
+            return reallocate(&existing, request, from, credentials, success, now);
+        }
+
+        match request.attribute(ATTR_REQUESTED_TRANSPORT) {
+            Some([TRANSPORT_UDP, _, _, _]) => {}
+            Some([_, _, _, _]) => return Err(UNSUPPORTED_TRANSPORT),
+            _ => return Err(BAD_REQUEST),
+        }
+        let lifetime = requested_lifetime(request)?;
+        if lifetime.is_zero() {
+            return Err(BAD_REQUEST);
+        }
+
+        match self.registry.is_registered(&credentials.client_id).await {
+            Ok(true) => {}
+            Ok(false) => return Err(FORBIDDEN),
+            Err(err) => {
+                warn!(error = %err, "TURN could not check the client's session");
+                return Err(SERVER_ERROR);
+            }
+        }
+        if held_by(&lock(&self.allocations), credentials.client_id)
+            >= self.settings.max_allocations_per_session
+        {
+            return Err(QUOTA_REACHED);
+        }
+
+        let (socket, relayed_addr) = self.bind_relay_socket().await.map_err(|err| {
+            warn!(error = %err, "TURN could not bind a relay socket");
+            INSUFFICIENT_CAPACITY
+        })?;
+        let bandwidth = lock(&self.bandwidth)
+            .entry(credentials.client_id)
+            .or_insert_with(|| Arc::new(Bandwidth::new(self.settings.bandwidth_bytes_per_sec)))
+            .clone();
+        let grant = Grant {
+            owner: credentials.client_id,
+            username: credentials.username.clone(),
+            transaction_id: request.transaction_id,
+            lifetime,
+            bandwidth,
+        };
+        let allocation = Allocation::start(grant, from, socket, relayed_addr, listener.clone());
+        // Other Allocate requests ran while this one waited: a retransmission
+        // may have made the allocation already, or others used up the quota
+        let mut allocations = lock(&self.allocations);
+        if let Some(existing) = allocations.get(&from) {
+            return reallocate(existing, request, from, credentials, success, now);
+        }
+        if held_by(&allocations, credentials.client_id) >= self.settings.max_allocations_per_session
+        {
+            return Err(QUOTA_REACHED);
+        }
+        debug!(client = %from, relayed = %relayed_addr, owner = %credentials.client_id, "TURN allocation created");
+        let response = allocate_success(success, &allocation, from, lifetime);
+        allocations.insert(from, allocation);
+        Ok(response)
+    }
+
+    fn refresh(
+        &self,
+        request: &Message,
+        from: SocketAddr,
+        credentials: &Credentials,
+        success: MessageBuilder,
+        now: Instant,
+    ) -> Result<MessageBuilder, Reject> {
+        let allocation = self.allocation_for(from, credentials)?;
+        let lifetime = requested_lifetime(request)?;
+        if lifetime.is_zero() {
+            lock(&self.allocations).remove(&from);
+            self.forget_idle_bandwidth();
+        } else {
+            allocation.refresh(lifetime, now);
+        }
+        Ok(success.attribute(ATTR_LIFETIME, &lifetime_value(lifetime)))
+    }
+
+    fn create_permission(
+        &self,
+        request: &Message,
+        from: SocketAddr,
+        credentials: &Credentials,
+        success: MessageBuilder,
+        now: Instant,
+    ) -> Result<MessageBuilder, Reject> {
+        let allocation = self.allocation_for(from, credentials)?;
+        let peers = request
+            .xor_addresses(ATTR_XOR_PEER_ADDRESS)
+            .filter(|peers| !peers.is_empty())
+            .ok_or(BAD_REQUEST)?;
+        if peers
+            .iter()
+            .any(|peer| !same_family(peer.ip(), allocation.relayed_addr.ip()))
+        {
+            return Err(PEER_FAMILY_MISMATCH);
+        }
+        if peers.iter().any(|peer| !self.peer_allowed(peer.ip())) {
+            return Err(FORBIDDEN);
+        }
+        for peer in peers {
+            allocation.permit(peer.ip(), now);
+        }
+        Ok(success)
+    }
+
+    fn channel_bind(
+        &self,
+        request: &Message,
+        from: SocketAddr,
+        credentials: &Credentials,
+        success: MessageBuilder,
+        now: Instant,
+    ) -> Result<MessageBuilder, Reject> {
+        let allocation = self.allocation_for(from, credentials)?;
+        let channel = match request.attribute(ATTR_CHANNEL_NUMBER) {
+            Some([high, low, _, _]) => u16::from_be_bytes([*high, *low]),
+            _ => return Err(BAD_REQUEST),
+        };
+        let peer = request
+            .xor_address(ATTR_XOR_PEER_ADDRESS)
+            .ok_or(BAD_REQUEST)?;
+        if !CHANNEL_NUMBERS.contains(&channel) {
+            return Err(BAD_REQUEST);
+        }
+        if !same_family(peer.ip(), allocation.relayed_addr.ip()) {
+            return Err(PEER_FAMILY_MISMATCH);
+        }
+        if !self.peer_allowed(peer.ip()) {
+            return Err(FORBIDDEN);
+        }
+        if !allocation.bind_channel(channel, peer, now) {
+            return Err(BAD_REQUEST);
+        }
+        Ok(success)
+    }
+
+    /// Whether clients may relay to `peer`. Loopback, unspecified, multicast and
+    /// link-local addresses are never allowed, nor are the configured ranges;
+    /// loopback is only let through when the relay itself listens on loopback,
+    /// as in local development, where it cannot reach anything else anyway.
+    fn peer_allowed(&self, peer: IpAddr) -> bool {
+        let peer = peer.to_canonical();
+        let special = match peer {
+            IpAddr::V4(ip) => {
+                ip.is_unspecified() || ip.is_multicast() || ip.is_broadcast() || ip.is_link_local()
+            }
+            IpAddr::V6(ip) => {
+                ip.is_unspecified() || ip.is_multicast() || ip.is_unicast_link_local()
+            }
+        };
+        let loopback = peer.is_loopback() && !self.settings.listen_addr.ip().is_loopback();
+        !special
+            && !loopback
+            && !self
+                .settings
+                .denied_peer_ranges
+                .iter()
+                .any(|range| range.contains(&peer))
+    }
+
+    /// The allocation a Refresh, CreatePermission or ChannelBind from `from`
+    /// applies to; it must have been made with the same credentials.
+    fn allocation_for(
+        &self,
+        from: SocketAddr,
+        credentials: &Credentials,
+    ) -> Result<Arc<Allocation>, Reject> {
+        let allocation = lock(&self.allocations)
+            .get(&from)
+            .cloned()
+            .ok_or(ALLOCATION_MISMATCH)?;
+        if allocation.username != credentials.username {
+            return Err(WRONG_CREDENTIALS);
+        }
+        Ok(allocation)
+    }
+
+    async fn relay_send_indication(&self, indication: &Message, from: SocketAddr) {
+        let Some(allocation) = lock(&self.allocations).get(&from).cloned() else {
+            drop_datagram("no_allocation");
+            return;
+        };
+        let (Some(peer), Some(data)) = (
+            indication.xor_address(ATTR_XOR_PEER_ADDRESS),
+            indication.attribute(ATTR_DATA),
+        ) else {
+            drop_datagram("invalid");
+            return;
+        };
+        allocation.send_to_peer(data, peer, Instant::now()).await;
+    }
+
+    async fn relay_channel_data(&self, datagram: &[u8], from: SocketAddr) {
+        let Some(allocation) = lock(&self.allocations).get(&from).cloned() else {
+            drop_datagram("no_allocation");
+            return;
+        };
+        let (Some(header), Some(body)) = (datagram.get(..4), datagram.get(4..)) else {
+            drop_datagram("invalid");
+            return;
+        };
+        let channel = u16::from_be_bytes([header[0], header[1]]);
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+        // Over UDP the padding to a multiple of four is optional
+        let Some(data) = body.get(..length) else {
+            drop_datagram("invalid");
+            return;
+        };
+        let now = Instant::now();
+        let Some(peer) = allocation.channel_peer(channel, now) else {
+            drop_datagram("no_channel");
+            return;
+        };
+        allocation.send_to_peer(data, peer, now).await;
+    }
+
+    /// Runs [`Relay::sweep`] every [`SWEEP_INTERVAL`], away from the receive
+    /// loop so that session lookups do not hold up relaying.
+    async fn sweep_periodically(self: Arc<Self>) {
+        let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+        sweep.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            sweep.tick().await;
+            self.sweep().await;
+        }
+    }
+
+    /// Drops expired allocations and those whose owner is no longer registered.
+    async fn sweep(&self) {
+        let now = Instant::now();
+        let owners: HashSet<ClientId> = {
+            let mut allocations = lock(&self.allocations);
+            allocations.retain(|_, allocation| !allocation.is_expired(now));
+            for allocation in allocations.values() {
+                allocation.prune(now);
+            }
+            allocations
+                .values()
+                .map(|allocation| allocation.owner)
+                .collect()
+        };
+        let checks = owners
+            .into_iter()
+            .map(|owner| async move { (owner, self.registry.is_registered(&owner).await) });
+        let mut expired = HashSet::new();
+        for (owner, registered) in join_all(checks).await {
+            match registered {
+                Ok(true) => {}
+                Ok(false) => {
+                    debug!(%owner, "Dropping TURN allocations of an expired session");
+                    expired.insert(owner);
+                }
+                Err(err) => warn!(error = %err, "TURN could not check a session"),
+            }
+        }
+        if !expired.is_empty() {
+            lock(&self.allocations).retain(|_, allocation| !expired.contains(&allocation.owner));
+        }
+        self.forget_idle_bandwidth();
+    }
+
+    fn forget_idle_bandwidth(&self) {
+        let allocations = lock(&self.allocations);
+        lock(&self.bandwidth).retain(|owner, _| {
+            allocations
+                .values()
+                .any(|allocation| allocation.owner == *owner)
+        });
+    }
+
+    /// Binds a relay socket, from the configured port range if there is one.
+    async fn bind_relay_socket(&self) -> std::io::Result<(UdpSocket, SocketAddr)> {
+        let listen_ip = self.settings.listen_addr.ip();
+        let bind_ip = match (listen_ip.is_unspecified(), self.settings.relay_ip) {
+            (false, _) => listen_ip,
+            (true, IpAddr::V4(_)) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            (true, IpAddr::V6(_)) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        let Some(ports) = self.settings.relay_ports.clone() else {
+            let socket = UdpSocket::bind((bind_ip, 0)).await?;
+            let port = socket.local_addr()?.port();
+            return Ok((socket, SocketAddr::new(self.settings.relay_ip, port)));
+        };
+
+        let count = usize::from(ports.end() - ports.start()) + 1;
+        let mut last_err = None;
+        for _ in 0..count {
+            let port = {
+                let mut next = lock(&self.next_port);
+                let port = *next;
+                *next = if port >= *ports.end() {
+                    *ports.start()
+                } else {
+                    port + 1
+                };
+                port
+            };
+            match UdpSocket::bind((bind_ip, port)).await {
+                Ok(socket) => return Ok((socket, SocketAddr::new(self.settings.relay_ip, port))),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| std::io::ErrorKind::AddrInUse.into()))
+    }
+}
+
+/// The answer to an Allocate from a client that already has an allocation: a
+/// retransmission gets the original answer; anything else is a second
+/// allocation on the same 5-tuple.
+fn reallocate(
+    existing: &Allocation,
+    request: &Message,
+    from: SocketAddr,
+    credentials: &Credentials,
+    success: MessageBuilder,
+    now: Instant,
+) -> Result<MessageBuilder, Reject> {
+    if existing.transaction_id != request.transaction_id
+        || existing.username != credentials.username
+    {
+        return Err(ALLOCATION_MISMATCH);
+    }
+    Ok(allocate_success(
+        success,
+        existing,
+        from,
+        existing.remaining_lifetime(now),
+    ))
+}
+
+fn held_by(allocations: &HashMap<SocketAddr, Arc<Allocation>>, owner: ClientId) -> usize {
+    allocations
+        .values()
+        .filter(|allocation| allocation.owner == owner)
+        .count()
+}
+
+fn allocate_success(
+    success: MessageBuilder,
+    allocation: &Allocation,
+    from: SocketAddr,
+    lifetime: Duration,
+) -> MessageBuilder {
+    success
+        .xor_address(ATTR_XOR_RELAYED_ADDRESS, allocation.relayed_addr)
+        .attribute(ATTR_LIFETIME, &lifetime_value(lifetime))
+        .xor_address(ATTR_XOR_MAPPED_ADDRESS, from)
+}
+
+/// The LIFETIME a request asks for, defaulted and capped; zero means delete.
+fn requested_lifetime(request: &Message) -> Result<Duration, Reject> {
+    match request.attribute(ATTR_LIFETIME) {
+        None => Ok(DEFAULT_LIFETIME),
+        Some([a, b, c, d]) => {
+            let secs = u64::from(u32::from_be_bytes([*a, *b, *c, *d]));
+            Ok(match secs {
+                0 => Duration::ZERO,
+                secs => Duration::from_secs(secs).clamp(DEFAULT_LIFETIME, MAX_LIFETIME),
+            })
+        }
+        Some(_) => Err(BAD_REQUEST),
+    }
+}
+
+fn lifetime_value(lifetime: Duration) -> [u8; 4] {
+    (lifetime.as_secs() as u32).to_be_bytes()
+}
+
+fn same_family(a: IpAddr, b: IpAddr) -> bool {
+    a.to_canonical().is_ipv4() == b.to_canonical().is_ipv4()
+}
+
+/// Metrics outcome for an error response after authentication.
+fn reject_label(code: u16) -> &'static str {
+    match code {
+        400 => "bad_request",
+        403 => "forbidden",
+        437 => "allocation_mismatch",
+        441 => "wrong_credentials",
+        442 => "unsupported_transport",
+        443 => "peer_family_mismatch",
+        486 => "quota_reached",
+        508 => "insufficient_capacity",
+        _ => "server_error",
+    }
+}
@@ -0,0 +1,282 @@
+//! One relayed transport address and the permissions and channels installed on it.
+
+use super::{CHANNEL_LIFETIME, MAX_DATAGRAM, PERMISSION_LIFETIME};
+use crate::metrics::metrics;
+use crate::stun::message::{Class, MessageBuilder, ATTR_DATA, ATTR_XOR_PEER_ADDRESS, METHOD_DATA};
+use crate::sync::lock;
+use shared::models::ClientId;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::task::AbortHandle;
+use tracing::debug;
+use uuid::Uuid;
+
+/// A token bucket shared by all of one client's allocations, holding at most
+/// one second's worth of bytes.
+pub(super) struct Bandwidth {
+    bytes_per_sec: f64,
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl Bandwidth {
+    pub(super) fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec as f64;
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new((bytes_per_sec, Instant::now())),
+        }
+    }
+
+    /// Takes `bytes` from the bucket, or returns `false` if the datagram must
+    /// be dropped.
+    pub(super) fn take(&self, bytes: usize, now: Instant) -> bool {
+        let mut bucket = lock(&self.bucket);
+        let (tokens, refilled_at) = &mut *bucket;
+        let elapsed = now.saturating_duration_since(*refilled_at).as_secs_f64();
+        *tokens = (*tokens + elapsed * self.bytes_per_sec).min(self.bytes_per_sec);
+        *refilled_at = now;
+        if *tokens < bytes as f64 {
+            return false;
+        }
+        *tokens -= bytes as f64;
+        true
+    }
+}
+
+struct ChannelBinding {
+    peer: SocketAddr,
+    expires_at: Instant,
+}
+
+struct State {
+    expires_at: Instant,
+    permissions: HashMap<IpAddr, Instant>,
+    channels: HashMap<u16, ChannelBinding>,
+}
+
+pub(super) struct Allocation {
+    pub(super) owner: ClientId,
+    /// Later requests on this allocation must use the same credentials.
+    pub(super) username: String,
+    /// Lets a retransmitted Allocate get the original success reply.
+    pub(super) transaction_id: [u8; 12],
+    pub(super) relayed_addr: SocketAddr,
+    relay: Arc<UdpSocket>,
+    bandwidth: Arc<Bandwidth>,
+    state: Mutex<State>,
+    task: AbortHandle,
+}
+
+/// Everything an allocation is created from besides its sockets.
+pub(super) struct Grant {
+    pub(super) owner: ClientId,
+    pub(super) username: String,
+    pub(super) transaction_id: [u8; 12],
+    pub(super) lifetime: Duration,
+    pub(super) bandwidth: Arc<Bandwidth>,
+}
+
+impl Allocation {
+    /// Starts relaying datagrams from `relay` back to `client` through `listener`.
+    /// The relay task ends when the allocation is dropped.
+    pub(super) fn start(
+        grant: Grant,
+        client: SocketAddr,
+        relay: UdpSocket,
+        relayed_addr: SocketAddr,
+        listener: Arc<UdpSocket>,
+    ) -> Arc<Self> {
+        let relay = Arc::new(relay);
+        metrics().turn_allocations.inc();
+        Arc::new_cyclic(|this| {
+            let task = tokio::spawn(relay_to_client(
+                this.clone(),
+                relay.clone(),
+                listener,
+                client,
+            ));
+            Self {
+                owner: grant.owner,
+                username: grant.username,
+                transaction_id: grant.transaction_id,
+                relayed_addr,
+                relay,
+                bandwidth: grant.bandwidth,
+                state: Mutex::new(State {
+                    expires_at: Instant::now() + grant.lifetime,
+                    permissions: HashMap::new(),
+                    channels: HashMap::new(),
+                }),
+                task: task.abort_handle(),
+            }
+        })
+    }
+
+    pub(super) fn refresh(&self, lifetime: Duration, now: Instant) {
+        lock(&self.state).expires_at = now + lifetime;
+    }
+
+    pub(super) fn remaining_lifetime(&self, now: Instant) -> Duration {
+        lock(&self.state).expires_at.saturating_duration_since(now)
+    }
+
+    pub(super) fn is_expired(&self, now: Instant) -> bool {
+        lock(&self.state).expires_at <= now
+    }
+
+    /// Drops permissions and channel bindings that have lapsed.
+    pub(super) fn prune(&self, now: Instant) {
+        let mut state = lock(&self.state);
+        state.permissions.retain(|_, expires_at| *expires_at > now);
+        state.channels.retain(|_, binding| binding.expires_at > now);
+    }
+
+    pub(super) fn permit(&self, peer: IpAddr, now: Instant) {
+        lock(&self.state)
+            .permissions
+            .insert(peer.to_canonical(), now + PERMISSION_LIFETIME);
+    }
+
+    /// Binds `channel` to `peer`, or refreshes an existing binding. Fails if
+    /// either is already bound to something else.
+    pub(super) fn bind_channel(&self, channel: u16, peer: SocketAddr, now: Instant) -> bool {
+        let mut state = lock(&self.state);
+        let conflict = state.channels.iter().any(|(number, binding)| {
+            binding.expires_at > now && ((*number == channel) != (binding.peer == peer))
+        });
+        if conflict {
+            return false;
+        }
+        state.channels.insert(
+            channel,
+            ChannelBinding {
+                peer,
+                expires_at: now + CHANNEL_LIFETIME,
+            },
+        );
+        state
+            .permissions
+            .insert(peer.ip().to_canonical(), now + PERMISSION_LIFETIME);
+        true
+    }
+
+    pub(super) fn channel_peer(&self, channel: u16, now: Instant) -> Option<SocketAddr> {
+        let state = lock(&self.state);
+        state
+            .channels
+            .get(&channel)
+            .filter(|binding| binding.expires_at > now)
+            .map(|binding| binding.peer)
+    }
+
+    /// Sends `data` from the client out of the relayed address to `peer`.
+    pub(super) async fn send_to_peer(&self, data: &[u8], peer: SocketAddr, now: Instant) {
+        if !self.is_permitted(peer.ip(), now) {
+            drop_datagram("no_permission");
+            return;
+        }
+        if !self.bandwidth.take(data.len(), now) {
+            drop_datagram("bandwidth");
+            return;
+        }
+        match self.relay.send_to(data, peer).await {
+            Ok(_) => metrics()
+                .turn_relayed_bytes
+                .with_label_values(&["to_peer"])
+                .inc_by(data.len() as u64),
+            Err(err) => debug!(error = %err, %peer, "TURN relay send failed"),
+        }
+    }
+
+    /// How `data` from `peer` is framed for the client: ChannelData when a
+    /// channel is bound to the peer, a Data indication otherwise. `None` when
+    /// the peer has no permission or the client is over its bandwidth.
+    fn frame_for_client(&self, data: &[u8], peer: SocketAddr, now: Instant) -> Option<Vec<u8>> {
+        if !self.is_permitted(peer.ip(), now) {
+            drop_datagram("no_permission");
+            return None;
+        }
+        if !self.bandwidth.take(data.len(), now) {
+            drop_datagram("bandwidth");
+            return None;
+        }
+        let channel = {
+            let state = lock(&self.state);
+            state
+                .channels
+                .iter()
+                .find(|(_, binding)| binding.peer == peer && binding.expires_at > now)
+                .map(|(number, _)| *number)
+        };
+        let frame = match channel {
+            Some(number) => {
+                let mut frame = Vec::with_capacity(4 + data.len());
+                frame.extend_from_slice(&number.to_be_bytes());
+                frame.extend_from_slice(&(data.len() as u16).to_be_bytes());
+                frame.extend_from_slice(data);
+                frame
+            }
+            None => {
+                let mut transaction_id = [0u8; 12];
+                transaction_id.copy_from_slice(&Uuid::new_v4().as_bytes()[..12]);
+                MessageBuilder::new(Class::Indication, METHOD_DATA, transaction_id)
+                    .xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+                    .attribute(ATTR_DATA, data)
+                    .build()
+            }
+        };
+        metrics()
+            .turn_relayed_bytes
+            .with_label_values(&["to_client"])
+            .inc_by(data.len() as u64);
+        Some(frame)
+    }
+
+    fn is_permitted(&self, peer: IpAddr, now: Instant) -> bool {
+        lock(&self.state)
+            .permissions
+            .get(&peer.to_canonical())
+            .is_some_and(|expires_at| *expires_at > now)
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        self.task.abort();
+        metrics().turn_allocations.dec();
+    }
+}
+
+async fn relay_to_client(
+    allocation: Weak<Allocation>,
+    relay: Arc<UdpSocket>,
+    listener: Arc<UdpSocket>,
+    client: SocketAddr,
+) {
+    let mut buf = vec![0u8; MAX_DATAGRAM];
+    loop {
+        let (len, peer) = match relay.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(err) => {
+                debug!(error = %err, "TURN relay receive failed");
+                continue;
+            }
+        };
+        let Some(allocation) = allocation.upgrade() else {
+            break;
+        };
+        let Some(frame) = allocation.frame_for_client(&buf[..len], peer, Instant::now()) else {
+            continue;
+        };
+        if let Err(err) = listener.send_to(&frame, client).await {
+            debug!(error = %err, %client, "TURN relay reply failed");
+        }
+    }
+}
+
+pub(super) fn drop_datagram(reason: &str) {
+    metrics().turn_dropped.with_label_values(&[reason]).inc();
+}
@@ -0,0 +1,149 @@
+//! Long-term credentials (RFC 8489 §9.2) where the password is a TURN REST
+//! credential minted by [`crate::ice`], so the relay needs no user database.
+
+use crate::config::TurnSecret;
+use crate::ice::turn_rest_password;
+use crate::stun::message::{
+    Message, ATTR_MESSAGE_INTEGRITY, ATTR_NONCE, ATTR_REALM, ATTR_USERNAME,
+};
+use hmac::{Hmac, Mac};
+use md5::{Digest, Md5};
+use rand::RngCore;
+use sha1::Sha1;
+use shared::models::ClientId;
+use std::net::SocketAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a nonce is accepted before the client is told to pick up a fresh one.
+const NONCE_LIFETIME: Duration = Duration::from_secs(600);
+
+/// Who sent an authenticated request, and the key its reply is signed with.
+pub(super) struct Credentials {
+    pub(super) username: String,
+    pub(super) client_id: ClientId,
+    pub(super) key: [u8; 16],
+}
+
+/// Why a request was not authenticated; each maps to one error response.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) enum AuthError {
+    /// No MESSAGE-INTEGRITY yet: the first leg of the challenge.
+    Challenge,
+    /// MESSAGE-INTEGRITY without USERNAME, REALM or NONCE.
+    Incomplete,
+    StaleNonce,
+    /// Unknown realm, malformed or expired username, or a bad HMAC.
+    Rejected,
+}
+
+pub(super) struct Authenticator {
+    realm: String,
+    secret: TurnSecret,
+    /// Signs nonces so they can be checked without remembering them.
+    nonce_key: [u8; 32],
+}
+
+impl Authenticator {
+    pub(super) fn new(realm: String, secret: TurnSecret) -> Self {
+        let mut nonce_key = [0u8; 32];
+        rand::rng().fill_bytes(&mut nonce_key);
+        Self {
+            realm,
+            secret,
+            nonce_key,
+        }
+    }
+
+    pub(super) fn realm(&self) -> &str {
+        &self.realm
+    }
+
+    /// A nonce for `client`: its expiry in hex followed by a truncated HMAC
+    /// over the expiry and the client's address.
+    pub(super) fn nonce(&self, client: SocketAddr, now: SystemTime) -> String {
+        let expires_at = (now + NONCE_LIFETIME)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        format!("{expires_at:016x}{}", self.nonce_tag(expires_at, client))
+    }
+
+    fn nonce_is_fresh(&self, nonce: &str, client: SocketAddr, now: SystemTime) -> bool {
+        let Some((expiry, tag)) = nonce.split_at_checked(16) else {
+            return false;
+        };
+        let Ok(expires_at) = u64::from_str_radix(expiry, 16) else {
+            return false;
+        };
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        expires_at > now
+            && shared::connection::constant_time_eq(
+                tag.as_bytes(),
+                self.nonce_tag(expires_at, client).as_bytes(),
+            )
+    }
+
+    fn nonce_tag(&self, expires_at: u64, client: SocketAddr) -> String {
+        let mut mac =
+            Hmac::<Sha1>::new_from_slice(&self.nonce_key).expect("HMAC accepts any key length");
+        mac.update(&expires_at.to_be_bytes());
+        mac.update(client.to_string().as_bytes());
+        mac.finalize().into_bytes()[..8]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Checks `request` from `client` against the REST credential its
+    /// USERNAME (`<unix expiry>:<client id>`) implies.
+    pub(super) fn authenticate(
+        &self,
+        request: &Message,
+        client: SocketAddr,
+        now: SystemTime,
+    ) -> Result<Credentials, AuthError> {
+        if request.attribute(ATTR_MESSAGE_INTEGRITY).is_none() {
+            return Err(AuthError::Challenge);
+        }
+        let text = |kind| {
+            request
+                .attribute(kind)
+                .and_then(|value| std::str::from_utf8(value).ok())
+        };
+        let (Some(username), Some(realm), Some(nonce)) =
+            (text(ATTR_USERNAME), text(ATTR_REALM), text(ATTR_NONCE))
+        else {
+            return Err(AuthError::Incomplete);
+        };
+        if !self.nonce_is_fresh(nonce, client, now) {
+            return Err(AuthError::StaleNonce);
+        }
+        if realm != self.realm {
+            return Err(AuthError::Rejected);
+        }
+
+        let (expiry, user) = username.split_once(':').ok_or(AuthError::Rejected)?;
+        let expires_at: u64 = expiry.parse().map_err(|_| AuthError::Rejected)?;
+        let client_id: ClientId = user.parse().map_err(|_| AuthError::Rejected)?;
+        let now_secs = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if expires_at <= now_secs {
+            return Err(AuthError::Rejected);
+        }
+
+        let password = turn_rest_password(self.secret.as_bytes(), username);
+        let key = long_term_key(username, &self.realm, &password);
+        if !request.verify_integrity(&key) {
+            return Err(AuthError::Rejected);
+        }
+        Ok(Credentials {
+            username: username.to_string(),
+            client_id,
+            key,
+        })
+    }
+}
+
+/// The long-term credential key, MD5(username ":" realm ":" password).
+pub fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    Md5::digest(format!("{username}:{realm}:{password}")).into()
+}
@@ -0,0 +1,99 @@
+//! Helpers shared by the integration tests.
+
+// Each test binary uses only some of these
+#![allow(dead_code)]
+
+use shared::identity::DeviceIdentity;
+use shared::models::{RegisterChallengeResponse, RegisterRequest, RegisterResponse};
+use signaling_server::config::ConfigSources;
+use signaling_server::{run_server, SignalingServerConfig};
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// A connection to the Redis server at `REDIS_URL` and a key prefix no other
+/// test run uses, or `None` when the variable is unset so Redis-backed tests
+/// can skip themselves.
+pub async fn redis_connection() -> Option<(redis::aio::ConnectionManager, String)> {
+    let Ok(url) = std::env::var("REDIS_URL") else {
+        eprintln!("REDIS_URL is not set; skipping Redis-backed test");
+        return None;
+    };
+    let client = redis::Client::open(url).expect("valid REDIS_URL");
+    let conn = client
+        .get_connection_manager()
+        .await
+        .expect("connect to REDIS_URL");
+    Some((conn, format!("test-{}", uuid::Uuid::new_v4())))
+}
+
+/// A configuration with in-memory stores on a free local port, with `env`
+/// applied on top as `SIGNALING_*` variables.
+pub fn server_config(env: &[(&str, &str)]) -> SignalingServerConfig {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let sources = [
+        ("SIGNALING_PORT", port.to_string().as_str()),
+        ("SIGNALING_MAILBOX_STORE", "memory"),
+        ("SIGNALING_SESSION_STORE", "memory"),
+        ("SIGNALING_PUSH_BACKEND", "memory"),
+        ("SIGNALING_RATE_LIMIT_STORE", "memory"),
+    ]
+    .iter()
+    .chain(env)
+    .fold(ConfigSources::default(), |sources, (name, value)| {
+        sources.with_env(*name, *value)
+    });
+    SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config
+}
+
+/// Starts a server built by `server_config` and returns its base URL once it
+/// accepts connections.
+pub async fn start_server(env: &[(&str, &str)]) -> String {
+    let config = server_config(env);
+    let base_url = format!("http://127.0.0.1:{}", config.listen_addr.port());
+    tokio::spawn(run_server(config));
+    wait_until_listening(&base_url).await;
+    base_url
+}
+
+pub async fn wait_until_listening(base_url: &str) {
+    for _ in 0..100 {
+        if reqwest::get(format!("{base_url}/health")).await.is_ok() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("server did not start");
+}
+
+/// Registers a fresh device with the server at `base_url`.
+pub async fn register_device(base_url: &str) -> RegisterResponse {
+    let client = reqwest::Client::new();
+    let identity = DeviceIdentity::generate();
+    let challenge: RegisterChallengeResponse = client
+        .post(format!("{base_url}/register/challenge"))
+        .send()
+        .await
+        .expect("challenge")
+        .json()
+        .await
+        .expect("challenge body");
+    client
+        .post(format!("{base_url}/register"))
+        .json(&RegisterRequest {
+            device_label: "laptop".to_string(),
+            public_key_b64: identity.public_key_b64(),
+            signature_b64: identity.sign_registration(&challenge.nonce),
+            nonce: challenge.nonce,
+        })
+        .send()
+        .await
+        .expect("register")
+        .json()
+        .await
+        .expect("register body")
+}
@@ -0,0 +1,155 @@
+use base64::Engine as _;
+use signaling_server::config::{ConfigSources, StoreBackend};
+use signaling_server::SignalingServerConfig;
+use std::time::Duration;
+
+#[test]
+fn environment_overrides_the_config_file() {
+    let sources = ConfigSources::default()
+        .with_file(
+            "signaling.toml",
+            r#"
+            port = 9000
+            mailbox_store = "memory"
+
+            [redis]
+            url = "redis://file-host/"
+            "#,
+        )
+        .expect("parse file")
+        .with_env("SIGNALING_REDIS_URL", "redis://env-host/");
+
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    assert_eq!(config.listen_addr.port(), 9000);
+    assert_eq!(config.mailbox_store, StoreBackend::Memory);
+    assert_eq!(config.redis_url, "redis://env-host/");
+}
+
+#[test]
+fn yaml_tables_and_lists_map_to_settings() {
+    let sources = ConfigSources::default()
+        .with_file(
+            "signaling.yaml",
+            "rate_limit:\n  join: 5/30\ntrusted_proxies:\n  - 10.0.0.0/8\n  - 192.0.2.7\nws:\n  ping_secs: 15\n",
+        )
+        .expect("parse file");
+
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    assert_eq!(config.rate_limit_join.burst, 5);
+    assert_eq!(config.trusted_proxies.len(), 2);
+    assert_eq!(config.ws_ping_interval, Duration::from_secs(15));
+}
+
+#[test]
+fn every_invalid_setting_is_reported() {
+    let sources = ConfigSources::default()
+        .with_file(
+            "signaling.toml",
+            "heartbeat_secs = 0\nunknown_knob = true\n",
+        )
+        .expect("parse file")
+        .with_env("SIGNALING_PORT", "80x")
+        .with_env("SIGNALING_REDIS_ENC_KEY_B64", "not-a-key")
+        .with_env("SIGNALING_TLS_CERT_PATH", "/etc/cert.pem");
+
+    let err = SignalingServerConfig::from_sources(&sources).expect_err("invalid config");
+    let reported = |needle: &str| err.problems.iter().any(|problem| problem.contains(needle));
+    assert!(reported("SIGNALING_PORT"), "{err}");
+    assert!(reported("SIGNALING_REDIS_ENC_KEY_B64"), "{err}");
+    assert!(reported("`heartbeat_secs`"), "{err}");
+    assert!(reported("`unknown_knob`"), "{err}");
+    assert!(reported("SIGNALING_TLS_KEY_PATH"), "{err}");
+    assert_eq!(err.problems.len(), 5, "{err}");
+}
+
+#[test]
+fn ignored_variables_produce_warnings() {
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_ROOM_TTL_SECS", "30")
+        .with_env("SIGNALING_HEARTBEAT_SEC", "10")
+        // Compose passes `${VAR:-}` through as an empty value
+        .with_env("SIGNALING_REDIS_ENC_KEY_B64", "");
+
+    let loaded = SignalingServerConfig::from_sources(&sources).expect("valid config");
+    assert_eq!(loaded.warnings.len(), 2, "{:?}", loaded.warnings);
+    assert!(loaded.warnings[0].contains("SIGNALING_HEARTBEAT_SEC"));
+    assert!(loaded.warnings[1].contains("SIGNALING_MAILBOX_TTL_SECS"));
+    assert!(loaded.config.redis_encryption_key.is_none());
+}
+
+#[test]
+fn shipped_example_config_is_valid() {
+    let example = include_str!("../signaling.example.toml");
+    let sources = ConfigSources::default()
+        .with_file("signaling.example.toml", example)
+        .expect("parse example");
+    let loaded = SignalingServerConfig::from_sources(&sources).expect("valid example");
+    assert!(loaded.warnings.is_empty());
+}
+
+#[test]
+fn admin_token_is_validated_without_echoing_it() {
+    let short = ConfigSources::default().with_env("SIGNALING_ADMIN_TOKEN", "hunter2");
+    let err = SignalingServerConfig::from_sources(&short).expect_err("short token");
+    assert!(err.problems[0].contains("SIGNALING_ADMIN_TOKEN"), "{err}");
+    assert!(!err.to_string().contains("hunter2"), "{err}");
+
+    let token = "a".repeat(64);
+    let sources = ConfigSources::default().with_env("SIGNALING_ADMIN_TOKEN", token.clone());
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    let admin_token = config.admin_token.as_ref().expect("admin token");
+    assert!(admin_token.matches(&token));
+    assert!(!admin_token.matches("a"));
+    assert!(!format!("{config:?}").contains(&token));
+}
+
+#[test]
+fn encryption_keys_are_kept_out_of_debug_output() {
+    let key = base64::engine::general_purpose::STANDARD.encode([0xaa; 32]);
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_REDIS_ENCRYPT", "true")
+        .with_env("SIGNALING_REDIS_ENC_KEY_B64", key.clone())
+        .with_env("SIGNALING_REDIS_ENC_PREVIOUS_KEYS", format!("old:{key}"));
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    assert!(config.redis_encryption_key.is_some());
+    assert_eq!(config.redis_decryption_keys.len(), 1);
+    assert!(!config.redis_encrypt_allow_plaintext);
+
+    let debug = format!("{config:?}");
+    assert!(debug.contains("EncryptionKey(<redacted>)"), "{debug}");
+    // The key decodes to 0xaa bytes
+    assert!(!debug.contains("170, 170"), "{debug}");
+}
+
+#[test]
+fn invalid_encryption_keys_are_reported_without_echoing_them() {
+    let sources = ConfigSources::default()
+        .with_env(
+            "SIGNALING_REDIS_ENC_KEY_B64",
+            "c2VjcmV0LWtleS1tYXRlcmlhbA==",
+        )
+        .with_env(
+            "SIGNALING_REDIS_ENC_PREVIOUS_KEYS",
+            "old:bm90LWJhc2U2NA==,broken-entry-without-id",
+        );
+
+    let err = SignalingServerConfig::from_sources(&sources).expect_err("invalid keys");
+    assert_eq!(err.problems.len(), 2, "{err}");
+    assert!(
+        err.problems[0].contains("SIGNALING_REDIS_ENC_KEY_B64"),
+        "{err}"
+    );
+    assert!(err.problems[1].contains("entry 1"), "{err}");
+    let reported = err.to_string();
+    for leaked in ["c2VjcmV0", "bm90LWJh", "broken-entry"] {
+        assert!(!reported.contains(leaked), "{reported}");
+    }
+}
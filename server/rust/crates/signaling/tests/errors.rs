@@ -0,0 +1,103 @@
+mod common;
+
+use reqwest::StatusCode;
+use shared::models::{ErrorCode, ErrorResponse};
+
+/// Starts a server with in-memory stores and a tight join rate limit.
+async fn start_server() -> String {
+    common::start_server(&[("SIGNALING_RATE_LIMIT_JOIN", "2/60")]).await
+}
+
+async fn error_body(response: reqwest::Response) -> (StatusCode, Option<String>, ErrorResponse) {
+    let status = response.status();
+    let header = response
+        .headers()
+        .get("x-request-id")
+        .map(|value| value.to_str().expect("ascii").to_string());
+    let body = response.json().await.expect("error body");
+    (status, header, body)
+}
+
+#[tokio::test]
+async fn errors_carry_code_request_id_and_retry_hint() {
+    let base_url = start_server().await;
+    let client = reqwest::Client::new();
+    let join = format!("{base_url}/connection/join");
+
+    let (status, header, body) = error_body(
+        client
+            .post(&join)
+            .json(&serde_json::json!({ "token_b64": "never-issued" }))
+            .send()
+            .await
+            .expect("join"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body.code, ErrorCode::InvalidRendezvousToken);
+    assert_eq!(body.retry_after_ms, None);
+    assert!(!body.request_id.is_empty());
+    assert_eq!(header.as_deref(), Some(body.request_id.as_str()));
+
+    let (status, header, body) = error_body(
+        client
+            .post(&join)
+            .header("x-request-id", "trace-42")
+            .header("content-type", "application/json")
+            .body("{not json")
+            .send()
+            .await
+            .expect("join"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body.code, ErrorCode::InvalidRequest);
+    assert_eq!(body.request_id, "trace-42");
+    assert_eq!(header.as_deref(), Some("trace-42"));
+
+    let response = client
+        .post(&join)
+        .json(&serde_json::json!({ "token_b64": "never-issued" }))
+        .send()
+        .await
+        .expect("join");
+    assert_eq!(
+        response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|secs| secs > 0),
+        Some(true)
+    );
+    let (status, _, body) = error_body(response).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(body.code, ErrorCode::RateLimited);
+    assert!(body.retry_after_ms.is_some_and(|ms| ms > 0));
+}
+
+#[tokio::test]
+async fn unknown_paths_and_unsafe_request_ids_are_handled() {
+    let base_url = start_server().await;
+    let (status, header, body) = error_body(
+        reqwest::Client::new()
+            .get(format!("{base_url}/no-such-endpoint"))
+            .header("x-request-id", "has spaces and \"quotes\"")
+            .send()
+            .await
+            .expect("request"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body.code, ErrorCode::InvalidRequest);
+    assert_ne!(body.request_id, "has spaces and \"quotes\"");
+    assert_eq!(header.as_deref(), Some(body.request_id.as_str()));
+}
+
+#[test]
+fn unknown_codes_decode_as_unknown() {
+    let body: ErrorResponse =
+        serde_json::from_str(r#"{"code":"added_later","message":"?"}"#).expect("decode");
+    assert_eq!(body.code, ErrorCode::Unknown);
+    assert_eq!(body.request_id, "");
+}
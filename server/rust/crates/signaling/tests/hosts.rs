@@ -0,0 +1,199 @@
+use shared::host;
+use signaling_server::repository::host_store::InMemoryHostRepository;
+use signaling_server::repository::mailbox_store::MailboxQuota;
+use signaling_server::repository::memory_repository::InMemoryMailboxRepository;
+use signaling_server::services::host_service::{HostError, HostLockoutPolicy, HostService};
+use signaling_server::services::rendezvous_service::RendezvousService;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const PASSWORD: &str = "correct horse battery";
+
+fn host_service(max_failed_attempts: u32) -> (HostService, Arc<RendezvousService>) {
+    let rendezvous = Arc::new(RendezvousService::new(
+        Arc::new(InMemoryMailboxRepository::new()),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        MailboxQuota {
+            max_message_bytes: 1024,
+            max_messages: 16,
+            max_bytes: 16 * 1024,
+        },
+    ));
+    let hosts = HostService::new(
+        Arc::new(InMemoryHostRepository::new()),
+        rendezvous.clone(),
+        Duration::from_secs(60),
+        HostLockoutPolicy {
+            max_failed_attempts,
+            lockout: Duration::from_secs(600),
+        },
+    );
+    (hosts, rendezvous)
+}
+
+#[tokio::test]
+async fn host_id_is_kept_across_password_changes() {
+    let (hosts, _) = host_service(5);
+    let device = Uuid::new_v4();
+
+    let host_id = hosts
+        .register_host(device, PASSWORD.to_string())
+        .await
+        .expect("register");
+    assert_eq!(host::normalize_host_id(&host_id), Some(host_id.clone()));
+    let again = hosts
+        .register_host(device, "a different password".to_string())
+        .await
+        .expect("change password");
+    assert_eq!(again, host_id);
+
+    hosts.listen(&device).await.expect("listen");
+    assert!(matches!(
+        hosts.connect(&host_id, PASSWORD.to_string()).await,
+        Err(HostError::InvalidCredentials)
+    ));
+    // Grouped the way it is displayed
+    hosts
+        .connect(
+            &host::format_host_id(&host_id),
+            "a different password".to_string(),
+        )
+        .await
+        .expect("connect with the new password");
+
+    assert!(matches!(
+        hosts.register_host(device, "short".to_string()).await,
+        Err(HostError::InvalidPassword)
+    ));
+}
+
+#[tokio::test]
+async fn viewer_joins_the_listening_mailbox_once() {
+    let (hosts, rendezvous) = host_service(5);
+    let device = Uuid::new_v4();
+    let host_id = hosts
+        .register_host(device, PASSWORD.to_string())
+        .await
+        .expect("register");
+
+    assert!(matches!(
+        hosts.connect(&host_id, PASSWORD.to_string()).await,
+        Err(HostError::HostOffline)
+    ));
+
+    // Listening again retires the earlier mailbox
+    hosts.listen(&device).await.expect("listen");
+    let listening = hosts.listen(&device).await.expect("listen again");
+
+    let (joined, host_mailbox_id, _) = hosts
+        .connect(&host_id, PASSWORD.to_string())
+        .await
+        .expect("connect");
+    assert_eq!(host_mailbox_id, listening.mailbox_id);
+    rendezvous
+        .send_message(
+            joined.mailbox_id,
+            &joined.mailbox_token,
+            "ciphertext".to_string(),
+        )
+        .await
+        .expect("viewer reaches the host");
+
+    assert!(matches!(
+        hosts.connect(&host_id, PASSWORD.to_string()).await,
+        Err(HostError::HostOffline)
+    ));
+}
+
+#[tokio::test]
+async fn wrong_passwords_lock_the_host_id() {
+    let (hosts, _) = host_service(3);
+    let device = Uuid::new_v4();
+    let host_id = hosts
+        .register_host(device, PASSWORD.to_string())
+        .await
+        .expect("register");
+    hosts.listen(&device).await.expect("listen");
+
+    for _ in 0..2 {
+        assert!(matches!(
+            hosts.connect(&host_id, "guess-guess".to_string()).await,
+            Err(HostError::InvalidCredentials)
+        ));
+    }
+    assert!(matches!(
+        hosts.connect(&host_id, "guess-guess".to_string()).await,
+        Err(HostError::Locked { .. })
+    ));
+    // Even the right password is refused until the lockout ends
+    match hosts.connect(&host_id, PASSWORD.to_string()).await {
+        Err(HostError::Locked { retry_after }) => {
+            assert!(retry_after > Duration::from_secs(590));
+        }
+        other => panic!("expected lockout, got {other:?}"),
+    }
+
+    // The owner setting a new password lifts it
+    hosts
+        .register_host(device, "a fresh password".to_string())
+        .await
+        .expect("change password");
+    hosts
+        .connect(&host_id, "a fresh password".to_string())
+        .await
+        .expect("connect after reset");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_guesses_cannot_overrun_the_lockout() {
+    let (hosts, _) = host_service(3);
+    let device = Uuid::new_v4();
+    let host_id = hosts
+        .register_host(device, PASSWORD.to_string())
+        .await
+        .expect("register");
+    hosts.listen(&device).await.expect("listen");
+
+    // All of them are in flight before the first password check finishes
+    let guesses = (0..16).map(|i| hosts.connect(&host_id, format!("guess-{i:04}")));
+    let results = futures_util::future::join_all(guesses).await;
+    let checked = results
+        .iter()
+        .filter(|result| matches!(result, Err(HostError::InvalidCredentials)))
+        .count();
+    assert_eq!(checked, 2, "{results:?}");
+    assert!(results.iter().all(|result| matches!(
+        result,
+        Err(HostError::InvalidCredentials | HostError::Locked { .. })
+    )));
+    assert!(matches!(
+        hosts.connect(&host_id, PASSWORD.to_string()).await,
+        Err(HostError::Locked { .. })
+    ));
+}
+
+#[tokio::test]
+async fn unknown_and_unregistered_hosts_look_alike() {
+    let (hosts, _) = host_service(5);
+    let device = Uuid::new_v4();
+    let host_id = hosts
+        .register_host(device, PASSWORD.to_string())
+        .await
+        .expect("register");
+    hosts.listen(&device).await.expect("listen");
+    hosts.unregister_host(&device).await.expect("unregister");
+
+    for id in [host_id.as_str(), "123456789", "not-an-id"] {
+        assert!(matches!(
+            hosts.connect(id, PASSWORD.to_string()).await,
+            Err(HostError::InvalidCredentials)
+        ));
+    }
+    assert!(matches!(
+        hosts.listen(&device).await,
+        Err(HostError::HostNotFound)
+    ));
+}
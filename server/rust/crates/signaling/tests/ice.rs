@@ -0,0 +1,61 @@
+use signaling_server::config::ConfigSources;
+use signaling_server::ice::{turn_rest_credentials, IceServerProvider};
+use signaling_server::SignalingServerConfig;
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+#[test]
+fn turn_credentials_match_coturn_format() {
+    let (username, credential) =
+        turn_rest_credentials(b"turn-shared-secret", "alice", 1_700_000_000);
+    assert_eq!(username, "1700000000:alice");
+    assert_eq!(credential, "RXM8cmVrcU98snNLpKm+uJgLUBU=");
+}
+
+#[test]
+fn turn_servers_get_per_client_credentials() {
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_STUN_URLS", "stun:stun.example.com:3478")
+        .with_env(
+            "SIGNALING_TURN_URLS",
+            "turn:turn.example.com:3478?transport=udp,turns:turn.example.com:5349",
+        )
+        .with_env("SIGNALING_TURN_SECRET", "turn-shared-secret")
+        .with_env("SIGNALING_TURN_CREDENTIAL_TTL_SECS", "600");
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    let provider = IceServerProvider::new(&config);
+
+    let client_id = Uuid::new_v4();
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let response = provider.servers_for(&client_id, now);
+
+    assert_eq!(response.ice_servers.len(), 2);
+    assert_eq!(response.ice_servers[0].urls, ["stun:stun.example.com:3478"]);
+    assert!(response.ice_servers[0].credential.is_none());
+    let turn = &response.ice_servers[1];
+    assert_eq!(turn.urls.len(), 2);
+    assert_eq!(
+        turn.username.as_deref(),
+        Some(format!("1700000600:{client_id}").as_str())
+    );
+    assert!(turn.credential.is_some());
+    assert_eq!(response.expires_at_epoch_ms, Some(1_700_000_600_000));
+
+    // Only credential-free entries go into the published client config
+    let client_config = config.client_config();
+    assert_eq!(client_config.ice_servers.len(), 1);
+    assert!(client_config.ice_servers[0].username.is_none());
+}
+
+#[test]
+fn turn_urls_require_a_secret() {
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_STUN_URLS", "turn:wrong-scheme.example.com")
+        .with_env("SIGNALING_TURN_URLS", "turn:turn.example.com");
+    let err = SignalingServerConfig::from_sources(&sources).expect_err("invalid config");
+    assert_eq!(err.problems.len(), 2, "{err}");
+    assert!(err.problems[0].contains("SIGNALING_STUN_URLS"), "{err}");
+    assert!(err.problems[1].contains("SIGNALING_TURN_SECRET"), "{err}");
+}
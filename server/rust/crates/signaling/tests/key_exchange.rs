@@ -0,0 +1,81 @@
+use shared::connection::{self, ExchangeKey};
+
+#[test]
+fn both_ends_agree_on_keys_and_sas() {
+    let initiator = ExchangeKey::generate();
+    let responder = ExchangeKey::generate();
+    let initiator_public = connection::decode_public_key(&initiator.public_key_b64()).expect("key");
+    let responder_public = connection::decode_public_key(&responder.public_key_b64()).expect("key");
+
+    let secret = initiator
+        .agree_as_initiator(&responder_public)
+        .expect("initiator");
+    assert_eq!(
+        responder
+            .agree_as_responder(&initiator_public)
+            .expect("responder"),
+        secret
+    );
+    // A resumed key agrees like the original
+    assert_eq!(
+        ExchangeKey::from_secret(&initiator.secret())
+            .agree_as_initiator(&responder_public)
+            .expect("resumed"),
+        secret
+    );
+
+    let sas = connection::format_sas(&connection::derive_keys(&secret).expect("keys").sas);
+    let digits: Vec<&str> = sas.split(' ').collect();
+    assert_eq!(digits.len(), 2, "{sas}");
+    assert!(digits
+        .iter()
+        .all(|group| group.len() == 3 && group.bytes().all(|b| b.is_ascii_digit())));
+    assert_eq!(connection::format_sas(&[0; 32]), "000 000");
+}
+
+#[test]
+fn a_substituted_key_shows_up_in_the_sas() {
+    let initiator = ExchangeKey::generate();
+    let responder = ExchangeKey::generate();
+    let attacker = ExchangeKey::generate();
+
+    // The attacker answers the link in the responder's place and relays
+    let initiator_side = initiator
+        .agree_as_initiator(&attacker.public_key())
+        .expect("initiator");
+    let responder_side = responder
+        .agree_as_responder(&attacker.public_key())
+        .expect("responder");
+    assert_ne!(initiator_side, responder_side);
+    assert_ne!(
+        connection::derive_keys(&initiator_side).expect("keys").sas,
+        connection::derive_keys(&responder_side).expect("keys").sas
+    );
+
+    // The roles are part of the secret, so a reflected key does not agree
+    assert_ne!(
+        initiator
+            .agree_as_responder(&responder.public_key())
+            .expect("swapped"),
+        initiator
+            .agree_as_initiator(&responder.public_key())
+            .expect("initiator")
+    );
+}
+
+#[test]
+fn bad_public_keys_are_rejected() {
+    let key = ExchangeKey::generate();
+    let mut one = [0u8; 32];
+    one[0] = 1;
+    for low_order in [[0u8; 32], one] {
+        assert!(key.agree_as_initiator(&low_order).is_err());
+        assert!(key.agree_as_responder(&low_order).is_err());
+    }
+
+    // Links from before the exchange carried a hex secret in the fragment
+    let old_fragment = "07".repeat(32);
+    assert!(connection::decode_public_key(&old_fragment).is_err());
+    assert!(connection::decode_public_key("not base64!").is_err());
+    assert!(connection::decode_public_key("").is_err());
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use reqwest::StatusCode;
+use std::net::TcpListener;
+
+/// The value of `series` (name plus labels, as rendered) in a scrape.
+fn sample(scrape: &str, series: &str) -> Option<f64> {
+    scrape.lines().find_map(|line| {
+        let value = line.strip_prefix(series)?.strip_prefix(' ')?;
+        Some(value.parse().expect("numeric sample"))
+    })
+}
+
+async fn scrape(url: &str) -> String {
+    let response = reqwest::get(url).await.expect("scrape");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok()),
+        Some("text/plain; version=0.0.4")
+    );
+    response.text().await.expect("scrape body")
+}
+
+#[tokio::test]
+async fn rendezvous_outcomes_are_counted_by_label() {
+    let base_url = common::start_server(&[("SIGNALING_METRICS_ENABLED", "true")]).await;
+    let metrics_url = format!("{base_url}/metrics");
+    let failed_joins =
+        "signaling_rendezvous_operations_total{operation=\"join\",outcome=\"invalid_token\"}";
+
+    let before = scrape(&metrics_url).await;
+    assert!(before.contains("# TYPE signaling_websocket_active gauge"));
+    let client = reqwest::Client::new();
+    for _ in 0..2 {
+        let response = client
+            .post(format!("{base_url}/connection/join"))
+            .json(&serde_json::json!({ "token_b64": "never-issued" }))
+            .send()
+            .await
+            .expect("join");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    let after = scrape(&metrics_url).await;
+    assert!(after.contains("# TYPE signaling_rendezvous_operations_total counter"));
+    assert_eq!(
+        sample(&after, failed_joins).expect("failed joins are counted")
+            - sample(&before, failed_joins).unwrap_or(0.0),
+        2.0
+    );
+    assert_eq!(sample(&after, "signaling_active_clients"), Some(0.0));
+}
+
+#[tokio::test]
+async fn metrics_can_move_to_their_own_address() {
+    let metrics_port = TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("free port")
+        .port();
+    let metrics_addr = format!("127.0.0.1:{metrics_port}");
+    let base_url = common::start_server(&[
+        ("SIGNALING_METRICS_ENABLED", "true"),
+        ("SIGNALING_METRICS_ADDR", &metrics_addr),
+    ])
+    .await;
+
+    let scraped = scrape(&format!("http://{metrics_addr}/metrics")).await;
+    assert!(scraped.contains("signaling_websocket_connections_total"));
+    let public = reqwest::get(format!("{base_url}/metrics"))
+        .await
+        .expect("request");
+    assert_eq!(public.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,82 @@
+use signaling_server::repository::payload_cipher::PayloadCipher;
+
+const OLD_KEY: [u8; 32] = [1; 32];
+const NEW_KEY: [u8; 32] = [2; 32];
+
+#[test]
+fn sealed_values_round_trip_and_name_their_key() {
+    let cipher = PayloadCipher::new("primary", NEW_KEY, []).expect("cipher");
+    let sealed = cipher.seal(r#"{"mailbox_id":"m1"}"#).expect("seal");
+    assert!(sealed.starts_with("enc1:primary:"), "{sealed}");
+    assert!(!sealed.contains("m1"));
+    assert_eq!(
+        cipher.open(&sealed).expect("open"),
+        r#"{"mailbox_id":"m1"}"#
+    );
+
+    // Fresh nonce per value
+    assert_ne!(
+        cipher.seal("same").expect("seal"),
+        cipher.seal("same").expect("seal")
+    );
+}
+
+#[test]
+fn rotated_keys_still_decrypt_but_never_encrypt() {
+    let before = PayloadCipher::new("v1", OLD_KEY, []).expect("cipher");
+    let sealed_before = before.seal("in flight").expect("seal");
+
+    let after = PayloadCipher::new("v2", NEW_KEY, [("v1".to_string(), OLD_KEY)]).expect("cipher");
+    assert_eq!(after.open(&sealed_before).expect("open"), "in flight");
+    let sealed_after = after.seal("new").expect("seal");
+    assert!(sealed_after.starts_with("enc1:v2:"), "{sealed_after}");
+    assert!(before.open(&sealed_after).is_err());
+}
+
+#[test]
+fn unknown_key_ids_are_reported() {
+    let retired = PayloadCipher::new("v1", OLD_KEY, []).expect("cipher");
+    let sealed = retired.seal("orphaned").expect("seal");
+
+    let current = PayloadCipher::new("v2", NEW_KEY, []).expect("cipher");
+    let err = current.open(&sealed).expect_err("unknown key");
+    assert!(err.to_string().contains("v1"), "{err}");
+
+    assert!(PayloadCipher::new("", NEW_KEY, []).is_err());
+    assert!(PayloadCipher::new("a:b", NEW_KEY, []).is_err());
+}
+
+#[test]
+fn tampered_and_relabelled_values_are_rejected() {
+    let cipher = PayloadCipher::new("v2", NEW_KEY, [("v1".to_string(), OLD_KEY)]).expect("cipher");
+    let sealed = cipher.seal("payload").expect("seal");
+
+    let mut tampered = sealed.clone().into_bytes();
+    let last = tampered.len() - 2;
+    tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+    let err = cipher
+        .open(&String::from_utf8(tampered).expect("utf8"))
+        .expect_err("tampered");
+    assert!(err.to_string().contains("v2"), "{err}");
+
+    // Claiming another configured key does not help either
+    let relabelled = sealed.replacen("enc1:v2:", "enc1:v1:", 1);
+    assert!(cipher.open(&relabelled).is_err());
+    assert!(cipher.open("enc1:v2").is_err());
+}
+
+#[test]
+fn plaintext_is_only_read_during_migration() {
+    let strict = PayloadCipher::new("v1", NEW_KEY, []).expect("cipher");
+    assert!(strict.open(r#"{"mailbox_id":"m1"}"#).is_err());
+
+    let migrating = PayloadCipher::new("v1", NEW_KEY, [])
+        .expect("cipher")
+        .allow_plaintext(true);
+    assert_eq!(
+        migrating.open(r#"{"mailbox_id":"m1"}"#).expect("plaintext"),
+        r#"{"mailbox_id":"m1"}"#
+    );
+    // New writes are sealed regardless
+    assert!(migrating.seal("x").expect("seal").starts_with("enc1:v1:"));
+}
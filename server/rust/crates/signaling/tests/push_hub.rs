@@ -0,0 +1,55 @@
+use signaling_server::push_hub::{PushError, PushHub};
+use tokio::sync::broadcast::error::RecvError;
+
+const BUFFER_CAPACITY: usize = 8;
+const MAX_SUBSCRIBERS: usize = 2;
+
+#[tokio::test]
+async fn channel_is_removed_with_its_last_subscriber() {
+    let hub = PushHub::new(BUFFER_CAPACITY, MAX_SUBSCRIBERS);
+
+    let first = hub.subscribe("mailbox").expect("subscribe");
+    let second = hub.subscribe("mailbox").expect("subscribe");
+    let stats = hub.stats();
+    assert_eq!((stats.mailboxes, stats.subscribers), (1, 2));
+
+    drop(first);
+    let stats = hub.stats();
+    assert_eq!((stats.mailboxes, stats.subscribers), (1, 1));
+
+    drop(second);
+    let stats = hub.stats();
+    assert_eq!((stats.mailboxes, stats.subscribers), (0, 0));
+}
+
+#[tokio::test]
+async fn subscribers_per_mailbox_are_capped() {
+    let hub = PushHub::new(BUFFER_CAPACITY, MAX_SUBSCRIBERS);
+
+    let _held: Vec<_> = (0..MAX_SUBSCRIBERS)
+        .map(|_| hub.subscribe("mailbox").expect("subscribe"))
+        .collect();
+    assert!(matches!(
+        hub.subscribe("mailbox"),
+        Err(PushError::SubscriberLimit)
+    ));
+
+    // The cap is per mailbox, not global.
+    let _other = hub.subscribe("other-mailbox").expect("subscribe");
+}
+
+#[tokio::test]
+async fn closing_a_mailbox_ends_its_subscriptions() {
+    let hub = PushHub::new(BUFFER_CAPACITY, MAX_SUBSCRIBERS);
+    let mut subscription = hub.subscribe("mailbox").expect("subscribe");
+
+    hub.notify("mailbox", "hello".to_string()).await;
+    hub.close_mailbox("mailbox");
+
+    assert_eq!(subscription.recv().await.expect("buffered"), "hello");
+    assert!(matches!(subscription.recv().await, Err(RecvError::Closed)));
+    assert_eq!(hub.stats().mailboxes, 0);
+
+    drop(subscription);
+    assert_eq!(hub.stats().subscribers, 0);
+}
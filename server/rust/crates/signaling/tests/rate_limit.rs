@@ -0,0 +1,227 @@
+mod common;
+
+use axum::http::{HeaderMap, HeaderValue};
+use signaling_server::rate_limit::{RateLimitPolicies, RateLimitScope, RateLimiter};
+use signaling_server::repository::rate_limit_store::{
+    InMemoryRateLimitRepository, RateDecision, RateLimitPolicy, RateLimitStore,
+    RedisRateLimitRepository,
+};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+const TWO_PER_SECOND: RateLimitPolicy = RateLimitPolicy {
+    burst: 2,
+    period: Duration::from_secs(1),
+};
+
+fn limiter(trusted_proxies: &[&str]) -> RateLimiter {
+    RateLimiter::new(
+        Arc::new(InMemoryRateLimitRepository::new()),
+        RateLimitPolicies {
+            register: TWO_PER_SECOND,
+            connection_init: TWO_PER_SECOND,
+            connection_join: TWO_PER_SECOND,
+            nameplate_join: TWO_PER_SECOND,
+            host_connect: TWO_PER_SECOND,
+        },
+        trusted_proxies
+            .iter()
+            .map(|net| net.parse().expect("net"))
+            .collect(),
+    )
+}
+
+fn ip(raw: &str) -> IpAddr {
+    raw.parse().expect("ip")
+}
+
+fn forwarded_for(hops: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "x-forwarded-for",
+        HeaderValue::from_str(hops).expect("header"),
+    );
+    headers
+}
+
+/// Takes tokens until the bucket refuses one.
+async fn assert_bucket(store: &dyn RateLimitStore, key: &str) {
+    for _ in 0..TWO_PER_SECOND.burst {
+        assert_eq!(
+            store
+                .try_acquire(key, &TWO_PER_SECOND)
+                .await
+                .expect("acquire"),
+            RateDecision::Allowed
+        );
+    }
+    let RateDecision::Limited { retry_after } = store
+        .try_acquire(key, &TWO_PER_SECOND)
+        .await
+        .expect("acquire")
+    else {
+        panic!("third request within the period must be limited");
+    };
+    // One token comes back every 500ms
+    assert!(retry_after > Duration::ZERO, "{retry_after:?}");
+    assert!(retry_after <= Duration::from_millis(500), "{retry_after:?}");
+
+    tokio::time::sleep(retry_after + Duration::from_millis(20)).await;
+    assert_eq!(
+        store
+            .try_acquire(key, &TWO_PER_SECOND)
+            .await
+            .expect("acquire"),
+        RateDecision::Allowed,
+        "a token is back after retry_after"
+    );
+}
+
+#[tokio::test]
+async fn token_bucket_allows_a_burst_then_refills() {
+    let store = InMemoryRateLimitRepository::new();
+    assert_bucket(&store, "join:192.0.2.1").await;
+    // Other keys have their own bucket
+    assert_eq!(
+        store
+            .try_acquire("join:192.0.2.2", &TWO_PER_SECOND)
+            .await
+            .expect("acquire"),
+        RateDecision::Allowed
+    );
+}
+
+#[tokio::test]
+async fn sweeps_keep_buckets_of_slower_scopes() {
+    let store = InMemoryRateLimitRepository::new();
+    let slow = RateLimitPolicy {
+        burst: 1,
+        period: Duration::from_secs(300),
+    };
+    let fast = RateLimitPolicy {
+        burst: 1,
+        period: Duration::from_millis(50),
+    };
+    let acquire = |key: String, policy: RateLimitPolicy| {
+        let store = &store;
+        async move { store.try_acquire(&key, &policy).await.expect("acquire") }
+    };
+    assert_eq!(
+        acquire("nameplate_join:192.0.2.1".into(), slow).await,
+        RateDecision::Allowed
+    );
+
+    // Enough fast buckets to trigger a sweep, the next one of which runs once
+    // they have refilled and the sweep interval has passed
+    for i in 0..5000 {
+        acquire(format!("join:{i}"), fast).await;
+    }
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    acquire("join:sweep".into(), fast).await;
+
+    // The slow bucket is still empty, not forgotten and refilled
+    assert!(matches!(
+        acquire("nameplate_join:192.0.2.1".into(), slow).await,
+        RateDecision::Limited { .. }
+    ));
+}
+
+#[tokio::test]
+async fn redis_token_bucket_allows_a_burst_then_refills() {
+    let Some((conn, key_prefix)) = common::redis_connection().await else {
+        return;
+    };
+    let store = RedisRateLimitRepository::new(conn, key_prefix);
+    assert_bucket(&store, "join:192.0.2.1").await;
+}
+
+#[test]
+fn forwarded_for_is_only_read_from_trusted_proxies() {
+    let limiter = limiter(&["10.0.0.0/8"]);
+    let headers = forwarded_for("203.0.113.9");
+
+    // A direct client cannot choose its bucket
+    assert_eq!(
+        limiter.client_ip(ip("198.51.100.7"), &headers),
+        ip("198.51.100.7")
+    );
+    assert_eq!(
+        limiter.client_ip(ip("10.0.0.2"), &headers),
+        ip("203.0.113.9")
+    );
+    assert_eq!(
+        limiter.client_ip(ip("10.0.0.2"), &HeaderMap::new()),
+        ip("10.0.0.2")
+    );
+}
+
+#[test]
+fn forwarded_for_is_walked_from_the_right() {
+    let limiter = limiter(&["10.0.0.0/8"]);
+    let proxy = ip("10.0.0.2");
+
+    // The client prepends a fake hop; the proxy appends the real one
+    assert_eq!(
+        limiter.client_ip(proxy, &forwarded_for("1.1.1.1, 203.0.113.9, 10.0.0.3")),
+        ip("203.0.113.9")
+    );
+    let mut split = forwarded_for("1.1.1.1");
+    split.append("x-forwarded-for", HeaderValue::from_static("203.0.113.9"));
+    assert_eq!(limiter.client_ip(proxy, &split), ip("203.0.113.9"));
+
+    // Nothing but trusted hops, or garbage before an untrusted one: the peer
+    // is all that can be attributed, never the client-supplied leftmost hop
+    assert_eq!(
+        limiter.client_ip(proxy, &forwarded_for("10.9.9.9, 10.0.0.3")),
+        proxy
+    );
+    assert_eq!(
+        limiter.client_ip(proxy, &forwarded_for("203.0.113.9, not-an-ip")),
+        proxy
+    );
+}
+
+#[tokio::test]
+async fn ipv6_clients_share_a_bucket_per_64() {
+    let limiter = limiter(&[]);
+    let scope = RateLimitScope::ConnectionJoin;
+
+    assert_eq!(
+        limiter.check(scope, ip("2001:db8:1:2::1")).await,
+        RateDecision::Allowed
+    );
+    assert_eq!(
+        limiter.check(scope, ip("2001:db8:1:2:ffff::9")).await,
+        RateDecision::Allowed
+    );
+    assert!(matches!(
+        limiter.check(scope, ip("2001:db8:1:2:abcd::1")).await,
+        RateDecision::Limited { .. }
+    ));
+    // The next /64 and other scopes are separate
+    assert_eq!(
+        limiter.check(scope, ip("2001:db8:1:3::1")).await,
+        RateDecision::Allowed
+    );
+    assert_eq!(
+        limiter
+            .check(RateLimitScope::Register, ip("2001:db8:1:2::1"))
+            .await,
+        RateDecision::Allowed
+    );
+
+    // IPv4-mapped addresses count as the IPv4 client
+    assert_eq!(
+        limiter.check(scope, ip("192.0.2.1")).await,
+        RateDecision::Allowed
+    );
+    assert_eq!(
+        limiter.check(scope, ip("::ffff:192.0.2.1")).await,
+        RateDecision::Allowed
+    );
+    assert!(matches!(
+        limiter.check(scope, ip("192.0.2.1")).await,
+        RateDecision::Limited { .. }
+    ));
+}
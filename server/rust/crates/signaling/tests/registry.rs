@@ -0,0 +1,244 @@
+mod common;
+
+use shared::identity::DeviceIdentity;
+use shared::models::{HeartbeatRequest, RegisterRequest};
+use shared::token;
+use signaling_server::registry::{RegistryError, SessionRegistry};
+use signaling_server::repository::redis_session_repository::RedisSessionRepository;
+use signaling_server::repository::session_repository::InMemorySessionRepository;
+use signaling_server::repository::session_store::{ClientRecord, SessionStore};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn registry_with(
+    store: Arc<InMemorySessionRepository>,
+    session_token_ttl: Duration,
+) -> SessionRegistry {
+    SessionRegistry::new(
+        store,
+        Duration::from_secs(60),
+        session_token_ttl,
+        Duration::from_secs(30),
+    )
+}
+
+async fn signed_request(registry: &SessionRegistry, identity: &DeviceIdentity) -> RegisterRequest {
+    let nonce = registry.issue_challenge().await.expect("challenge").nonce;
+    RegisterRequest {
+        device_label: "laptop".to_string(),
+        public_key_b64: identity.public_key_b64(),
+        signature_b64: identity.sign_registration(&nonce),
+        nonce,
+    }
+}
+
+#[tokio::test]
+async fn device_id_is_derived_from_the_key_and_stable() {
+    let store = Arc::new(InMemorySessionRepository::new());
+    let registry = registry_with(store.clone(), Duration::from_secs(3600));
+    let identity = DeviceIdentity::generate();
+
+    let first = registry
+        .register(signed_request(&registry, &identity).await)
+        .await
+        .expect("register");
+    let second = registry
+        .register(signed_request(&registry, &identity).await)
+        .await
+        .expect("register again");
+    assert_eq!(first.client_id, identity.device_id());
+    assert_eq!(second.client_id, first.client_id);
+    assert_eq!(
+        registry.active_client_count().await.expect("count"),
+        1,
+        "re-registering must not add a second client"
+    );
+
+    // Only the newest token works, and the store never sees it in the clear
+    assert!(matches!(
+        registry
+            .verify_session(&first.client_id, &first.session_token)
+            .await,
+        Err(RegistryError::InvalidToken)
+    ));
+    registry
+        .verify_session(&second.client_id, &second.session_token)
+        .await
+        .expect("current token");
+    let record = store
+        .get_client(&second.client_id)
+        .await
+        .expect("get")
+        .expect("record");
+    assert_eq!(
+        record.session_token_hash,
+        token::hash_token(&second.session_token)
+    );
+    assert_eq!(record.public_key_b64, identity.public_key_b64());
+}
+
+#[tokio::test]
+async fn session_token_is_bound_to_its_device() {
+    let registry = registry_with(
+        Arc::new(InMemorySessionRepository::new()),
+        Duration::from_secs(3600),
+    );
+    let alice = registry
+        .register(signed_request(&registry, &DeviceIdentity::generate()).await)
+        .await
+        .expect("register");
+    let bob = registry
+        .register(signed_request(&registry, &DeviceIdentity::generate()).await)
+        .await
+        .expect("register");
+
+    assert!(matches!(
+        registry
+            .heartbeat(HeartbeatRequest {
+                client_id: bob.client_id,
+                session_token: alice.session_token,
+            })
+            .await,
+        Err(RegistryError::InvalidToken)
+    ));
+}
+
+#[tokio::test]
+async fn challenges_are_single_use_and_signatures_checked() {
+    let registry = registry_with(
+        Arc::new(InMemorySessionRepository::new()),
+        Duration::from_secs(3600),
+    );
+    let identity = DeviceIdentity::generate();
+
+    let request = signed_request(&registry, &identity).await;
+    registry.register(request.clone()).await.expect("register");
+    assert!(matches!(
+        registry.register(request).await,
+        Err(RegistryError::InvalidChallenge)
+    ));
+
+    let never_issued = RegisterRequest {
+        nonce: "never-issued".to_string(),
+        signature_b64: identity.sign_registration("never-issued"),
+        ..signed_request(&registry, &identity).await
+    };
+    assert!(matches!(
+        registry.register(never_issued).await,
+        Err(RegistryError::InvalidChallenge)
+    ));
+
+    // A signature from another key over the right nonce must not pass
+    let mut forged = signed_request(&registry, &identity).await;
+    forged.signature_b64 = DeviceIdentity::generate().sign_registration(&forged.nonce);
+    assert!(matches!(
+        registry.register(forged).await,
+        Err(RegistryError::InvalidSignature)
+    ));
+}
+
+#[tokio::test]
+async fn expired_session_token_is_rejected() {
+    let registry = registry_with(
+        Arc::new(InMemorySessionRepository::new()),
+        Duration::from_millis(50),
+    );
+    let response = registry
+        .register(signed_request(&registry, &DeviceIdentity::generate()).await)
+        .await
+        .expect("register");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(matches!(
+        registry
+            .verify_session(&response.client_id, &response.session_token)
+            .await,
+        Err(RegistryError::SessionExpired)
+    ));
+}
+
+#[tokio::test]
+async fn heartbeat_does_not_bring_back_a_removed_client_in_redis() {
+    let Some((conn, key_prefix)) = common::redis_connection().await else {
+        return;
+    };
+    let store = RedisSessionRepository::new(conn, key_prefix);
+    let client_id = DeviceIdentity::generate().device_id();
+    let record = ClientRecord {
+        device_label: "laptop".to_string(),
+        public_key_b64: String::new(),
+        session_token_hash: String::new(),
+        session_expires_at_epoch_ms: 0,
+        registered_at_epoch_ms: 1,
+        last_heartbeat_epoch_ms: 1,
+    };
+    let ttl = Duration::from_secs(60);
+
+    store
+        .insert_client(client_id, record, ttl)
+        .await
+        .expect("insert");
+    assert!(store
+        .update_client_heartbeat(&client_id, 2, ttl)
+        .await
+        .expect("heartbeat"));
+    let stored = store
+        .get_client(&client_id)
+        .await
+        .expect("get")
+        .expect("client");
+    assert_eq!(stored.last_heartbeat_epoch_ms, 2);
+    assert_eq!(stored.registered_at_epoch_ms, 1);
+
+    store.remove_client(&client_id).await.expect("remove");
+    assert!(!store
+        .update_client_heartbeat(&client_id, 3, ttl)
+        .await
+        .expect("heartbeat"));
+    assert!(store.get_client(&client_id).await.expect("get").is_none());
+    assert_eq!(store.get_client_count().await.expect("count"), 0);
+}
+
+#[tokio::test]
+async fn redis_prune_removes_only_stale_clients() {
+    let Some((conn, key_prefix)) = common::redis_connection().await else {
+        return;
+    };
+    let store = RedisSessionRepository::new(conn, key_prefix);
+    let ttl = Duration::from_secs(60);
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("clock")
+        .as_millis();
+    let record = |last_heartbeat_epoch_ms| ClientRecord {
+        device_label: "laptop".to_string(),
+        public_key_b64: String::new(),
+        session_token_hash: String::new(),
+        session_expires_at_epoch_ms: 0,
+        registered_at_epoch_ms: 1,
+        last_heartbeat_epoch_ms,
+    };
+    let stale = DeviceIdentity::generate().device_id();
+    let revived = DeviceIdentity::generate().device_id();
+    store
+        .insert_client(stale, record(1), ttl)
+        .await
+        .expect("insert");
+    store
+        .insert_client(revived, record(1), ttl)
+        .await
+        .expect("insert");
+    assert!(store
+        .update_client_heartbeat(&revived, now_ms, ttl)
+        .await
+        .expect("heartbeat"));
+
+    let pruned = store
+        .prune_stale_clients(Duration::from_secs(30))
+        .await
+        .expect("prune");
+    assert_eq!(pruned, vec![stale]);
+    assert!(store.get_client(&stale).await.expect("get").is_none());
+    assert!(store.get_client(&revived).await.expect("get").is_some());
+    assert_eq!(store.get_client_count().await.expect("count"), 1);
+}
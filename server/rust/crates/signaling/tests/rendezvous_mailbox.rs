@@ -0,0 +1,401 @@
+mod common;
+
+use shared::models::MailboxMessage;
+use signaling_server::repository::mailbox_store::{
+    MailboxAppend, MailboxQuota, MailboxState, MailboxStore, RendezvousRedemption,
+};
+use signaling_server::repository::memory_repository::InMemoryMailboxRepository;
+use signaling_server::repository::redis_repository::RedisRepository;
+use signaling_server::services::rendezvous_service::{RendezvousError, RendezvousService};
+use std::sync::Arc;
+use std::time::Duration;
+
+const CONCURRENT_SENDERS: u64 = 64;
+
+const TEST_QUOTA: MailboxQuota = MailboxQuota {
+    max_message_bytes: 1024,
+    max_messages: 128,
+    max_bytes: 64 * 1024,
+};
+
+/// A mailbox ID together with the access token issued for it.
+#[derive(Clone)]
+struct Mailbox {
+    id: String,
+    token: String,
+}
+
+async fn paired_service() -> (Arc<RendezvousService>, Mailbox, Mailbox) {
+    paired_service_with_quota(TEST_QUOTA).await
+}
+
+async fn paired_service_with_quota(
+    quota: MailboxQuota,
+) -> (Arc<RendezvousService>, Mailbox, Mailbox) {
+    paired_service_on(Arc::new(InMemoryMailboxRepository::new()), quota).await
+}
+
+async fn paired_service_on(
+    store: Arc<dyn MailboxStore>,
+    quota: MailboxQuota,
+) -> (Arc<RendezvousService>, Mailbox, Mailbox) {
+    let service = Arc::new(RendezvousService::new(
+        store,
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        quota,
+    ));
+    let init = service
+        .init_connection("rendezvous-token".to_string())
+        .await
+        .expect("init");
+    let (join, initiator_mailbox_id, _) = service
+        .join_connection("rendezvous-token".to_string())
+        .await
+        .expect("join");
+    assert_eq!(init.mailbox_id, initiator_mailbox_id);
+    (
+        service,
+        Mailbox {
+            id: init.mailbox_id,
+            token: init.mailbox_token,
+        },
+        Mailbox {
+            id: join.mailbox_id,
+            token: join.mailbox_token,
+        },
+    )
+}
+
+#[tokio::test]
+async fn empty_mailbox_reports_sequence_zero() {
+    let (service, _, responder) = paired_service().await;
+
+    let recv = service
+        .recv_messages(responder.id.clone(), &responder.token, 0)
+        .await
+        .expect("recv");
+    assert!(recv.messages.is_empty());
+    assert_eq!(recv.last_sequence, 0);
+}
+
+#[tokio::test]
+async fn first_message_is_sequence_one() {
+    let (service, initiator, responder) = paired_service().await;
+
+    // The join notification is the first message in the initiator's mailbox.
+    let recv = service
+        .recv_messages(initiator.id.clone(), &initiator.token, 0)
+        .await
+        .expect("recv");
+    assert_eq!(recv.messages.len(), 1);
+    assert_eq!(recv.messages[0].sequence, 1);
+    assert_eq!(recv.last_sequence, 1);
+
+    service
+        .send_message(initiator.id.clone(), &initiator.token, "offer".to_string())
+        .await
+        .expect("send");
+    let recv = service
+        .recv_messages(responder.id.clone(), &responder.token, 0)
+        .await
+        .expect("recv");
+    assert_eq!(recv.messages[0].sequence, 1);
+    assert_eq!(recv.last_sequence, 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_senders_get_unique_ordered_sequences() {
+    let (service, initiator, responder) = paired_service().await;
+    assert_concurrent_sends_are_sequenced(service, initiator, responder).await;
+}
+
+/// Same as above against Redis, where the sequence comes from the append script.
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_senders_get_unique_ordered_sequences_in_redis() {
+    let Some((conn, key_prefix)) = common::redis_connection().await else {
+        return;
+    };
+    let (service, initiator, responder) =
+        paired_service_on(Arc::new(RedisRepository::new(conn, key_prefix)), TEST_QUOTA).await;
+    assert_concurrent_sends_are_sequenced(service, initiator, responder).await;
+}
+
+async fn assert_concurrent_sends_are_sequenced(
+    service: Arc<RendezvousService>,
+    initiator: Mailbox,
+    responder: Mailbox,
+) {
+    let sends = (0..CONCURRENT_SENDERS).map(|i| {
+        let service = Arc::clone(&service);
+        let responder = responder.clone();
+        tokio::spawn(async move {
+            let (_, json) = service
+                .send_message(responder.id, &responder.token, format!("candidate-{i}"))
+                .await
+                .expect("send");
+            serde_json::from_str::<MailboxMessage>(&json)
+                .expect("message json")
+                .sequence
+        })
+    });
+    let mut sequences = Vec::new();
+    for send in sends {
+        sequences.push(send.await.expect("task"));
+    }
+    sequences.sort_unstable();
+
+    // Sequence 1 is the join notification; the sends must fill 2..=N+1 exactly.
+    let expected: Vec<u64> = (2..=CONCURRENT_SENDERS + 1).collect();
+    assert_eq!(sequences, expected);
+
+    let recv = service
+        .recv_messages(initiator.id.clone(), &initiator.token, 0)
+        .await
+        .expect("recv");
+    let stored: Vec<u64> = recv.messages.iter().map(|m| m.sequence).collect();
+    let expected: Vec<u64> = (1..=CONCURRENT_SENDERS + 1).collect();
+    assert_eq!(stored, expected, "log order must match sequence order");
+    assert_eq!(recv.last_sequence, CONCURRENT_SENDERS + 1);
+}
+
+#[tokio::test]
+async fn cursor_and_ack_skip_delivered_messages() {
+    let (service, initiator, responder) = paired_service().await;
+    for i in 0..3 {
+        service
+            .send_message(initiator.id.clone(), &initiator.token, format!("ice-{i}"))
+            .await
+            .expect("send");
+    }
+
+    let first = service
+        .recv_messages(responder.id.clone(), &responder.token, 0)
+        .await
+        .expect("recv");
+    assert_eq!(first.messages.len(), 3);
+    assert_eq!(first.last_sequence, 3);
+
+    // Nothing new past the cursor, and the cursor does not move.
+    let again = service
+        .recv_messages(responder.id.clone(), &responder.token, first.last_sequence)
+        .await
+        .expect("recv");
+    assert!(again.messages.is_empty());
+    assert_eq!(again.last_sequence, 3);
+
+    service
+        .ack_messages(responder.id.clone(), &responder.token, 2)
+        .await
+        .expect("ack");
+    service
+        .send_message(
+            initiator.id.clone(),
+            &initiator.token,
+            "renegotiate".to_string(),
+        )
+        .await
+        .expect("send");
+
+    let after_ack = service
+        .recv_messages(responder.id.clone(), &responder.token, 0)
+        .await
+        .expect("recv");
+    let sequences: Vec<u64> = after_ack.messages.iter().map(|m| m.sequence).collect();
+    assert_eq!(sequences, vec![3, 4]);
+    assert_eq!(after_ack.last_sequence, 4);
+}
+
+#[tokio::test]
+async fn quotas_reject_oversized_and_overflowing_messages() {
+    let (service, initiator, responder) = paired_service_with_quota(MailboxQuota {
+        max_message_bytes: 8,
+        max_messages: 2,
+        max_bytes: 12,
+    })
+    .await;
+
+    let oversized = service
+        .send_message(initiator.id.clone(), &initiator.token, "x".repeat(9))
+        .await;
+    assert!(matches!(
+        oversized,
+        Err(RendezvousError::MessageTooLarge { max_bytes: 8 })
+    ));
+
+    service
+        .send_message(initiator.id.clone(), &initiator.token, "x".repeat(8))
+        .await
+        .expect("first message fits");
+    let over_bytes = service
+        .send_message(initiator.id.clone(), &initiator.token, "x".repeat(5))
+        .await;
+    assert!(matches!(
+        over_bytes,
+        Err(RendezvousError::MailboxQuotaExceeded)
+    ));
+    service
+        .send_message(initiator.id.clone(), &initiator.token, "x".repeat(4))
+        .await
+        .expect("second message fits exactly");
+    let over_count = service
+        .send_message(initiator.id.clone(), &initiator.token, String::new())
+        .await;
+    assert!(matches!(
+        over_count,
+        Err(RendezvousError::MailboxQuotaExceeded)
+    ));
+
+    // Acknowledging frees both slots and bytes.
+    service
+        .ack_messages(responder.id.clone(), &responder.token, 2)
+        .await
+        .expect("ack");
+    service
+        .send_message(initiator.id.clone(), &initiator.token, "x".repeat(8))
+        .await
+        .expect("fits after ack");
+}
+
+#[tokio::test]
+async fn mailbox_operations_require_the_issued_token() {
+    let (service, initiator, responder) = paired_service().await;
+    assert_ne!(initiator.token, responder.token);
+
+    // Knowing a mailbox ID is not enough, and one side's token does not open the other.
+    for token in ["", "guessed", responder.token.as_str()] {
+        assert!(matches!(
+            service
+                .send_message(initiator.id.clone(), token, "offer".to_string())
+                .await,
+            Err(RendezvousError::InvalidMailboxToken)
+        ));
+        assert!(matches!(
+            service.recv_messages(initiator.id.clone(), token, 0).await,
+            Err(RendezvousError::InvalidMailboxToken)
+        ));
+        assert!(matches!(
+            service.ack_messages(initiator.id.clone(), token, 1).await,
+            Err(RendezvousError::InvalidMailboxToken)
+        ));
+        assert!(matches!(
+            service.close_connection(initiator.id.clone(), token).await,
+            Err(RendezvousError::InvalidMailboxToken)
+        ));
+        assert!(!service
+            .verify_mailbox(&initiator.id, token)
+            .await
+            .expect("verify"));
+    }
+
+    assert!(service
+        .verify_mailbox(&initiator.id, &initiator.token)
+        .await
+        .expect("verify"));
+    service
+        .close_connection(initiator.id.clone(), &initiator.token)
+        .await
+        .expect("close");
+    assert!(matches!(
+        service
+            .recv_messages(responder.id, &responder.token, 0)
+            .await,
+        Err(RendezvousError::MailboxNotFound)
+    ));
+}
+
+#[tokio::test]
+async fn reused_invitation_is_told_apart_from_unknown_one() {
+    let (service, _, _) = paired_service().await;
+
+    assert!(matches!(
+        service
+            .join_connection("rendezvous-token".to_string())
+            .await,
+        Err(RendezvousError::SessionAlreadyPaired)
+    ));
+    assert!(matches!(
+        service.join_connection("never-issued".to_string()).await,
+        Err(RendezvousError::InvalidToken)
+    ));
+}
+
+#[tokio::test]
+async fn in_memory_entries_expire_with_their_ttl() {
+    let store = InMemoryMailboxRepository::new();
+    let state = |mailbox_id: &str| MailboxState {
+        mailbox_id: mailbox_id.to_string(),
+        peer_mailbox_id: None,
+        access_token_hash: String::new(),
+        created_at_epoch_ms: 0,
+        expires_at_epoch_ms: 0,
+    };
+    for (mailbox_id, ttl_secs) in [("short", 1), ("long", 60)] {
+        store
+            .save_mailbox_meta(&state(mailbox_id), ttl_secs)
+            .await
+            .expect("meta");
+        store
+            .save_rendezvous(&format!("{mailbox_id}-token"), mailbox_id, ttl_secs)
+            .await
+            .expect("rendezvous");
+        assert!(store
+            .claim_nameplate(&format!("{mailbox_id}-nameplate"), mailbox_id, ttl_secs)
+            .await
+            .expect("nameplate"));
+        let appended = store
+            .append_message(mailbox_id, "peer", "aGk", 0, ttl_secs, &TEST_QUOTA)
+            .await
+            .expect("append");
+        assert!(matches!(appended, MailboxAppend::Appended(_)));
+    }
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert!(store
+        .get_mailbox_meta("short")
+        .await
+        .expect("meta")
+        .is_none());
+    assert_eq!(
+        store
+            .redeem_rendezvous("short-token", 60)
+            .await
+            .expect("redeem"),
+        RendezvousRedemption::Unknown
+    );
+    assert!(store
+        .claim_nameplate("short-nameplate", "other", 60)
+        .await
+        .expect("nameplate is free again"));
+    assert!(store
+        .get_messages("short", 0)
+        .await
+        .expect("messages")
+        .is_empty());
+    assert_eq!(store.get_last_sequence("short").await.expect("sequence"), 0);
+
+    // Entries with time left are untouched
+    assert!(store
+        .get_mailbox_meta("long")
+        .await
+        .expect("meta")
+        .is_some());
+    assert_eq!(
+        store
+            .redeem_rendezvous("long-token", 60)
+            .await
+            .expect("redeem"),
+        RendezvousRedemption::Redeemed("long".to_string())
+    );
+    assert!(!store
+        .claim_nameplate("long-nameplate", "other", 60)
+        .await
+        .expect("nameplate still taken"));
+    assert_eq!(
+        store.get_messages("long", 0).await.expect("messages").len(),
+        1
+    );
+    assert_eq!(store.get_last_sequence("long").await.expect("sequence"), 1);
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use shared::code;
+use shared::connection;
+use shared::models::{ErrorCode, ErrorResponse};
+use shared::pake::Spake2;
+use signaling_server::repository::mailbox_store::MailboxQuota;
+use signaling_server::repository::memory_repository::InMemoryMailboxRepository;
+use signaling_server::services::rendezvous_service::{RendezvousError, RendezvousService};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+fn rendezvous_service() -> RendezvousService {
+    RendezvousService::new(
+        Arc::new(InMemoryMailboxRepository::new()),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        Duration::from_secs(60),
+        MailboxQuota {
+            max_message_bytes: 1024,
+            max_messages: 16,
+            max_bytes: 16 * 1024,
+        },
+    )
+}
+
+#[test]
+fn codes_are_read_back_in_canonical_form() {
+    let code = code::gen_code("7").expect("code");
+    let words: Vec<&str> = code.split('-').collect();
+    assert_eq!(words.len(), 1 + code::CODE_WORDS);
+    assert_eq!(words[0], "7");
+
+    let typed = format!("  7 {}  {} ", words[1].to_uppercase(), words[2]);
+    assert_eq!(code::normalize_code(&typed), Some(code.clone()));
+    assert_eq!(code::code_nameplate(&typed), Some("7".to_string()));
+
+    for bad in [
+        "07-guitar-orbit",
+        "7-guitar",
+        "7-guitar-notaword",
+        "guitar-orbit-7",
+    ] {
+        assert_eq!(code::normalize_code(bad), None, "{bad}");
+    }
+}
+
+#[test]
+fn spake2_agrees_only_on_the_same_code() {
+    let code = "7-guitar-orbit";
+    let exchange = |creator: Spake2, joiner: Spake2| {
+        let (creator_message, joiner_message) =
+            (creator.message().to_vec(), joiner.message().to_vec());
+        (
+            creator.finish(&joiner_message).expect("creator"),
+            joiner.finish(&creator_message).expect("joiner"),
+        )
+    };
+
+    let (secret, joined) = exchange(Spake2::start(code), Spake2::start(code));
+    assert_eq!(joined, secret);
+    let (creator, guesser) = exchange(Spake2::start(code), Spake2::start("7-guitar-otter"));
+    assert_ne!(creator, guesser);
+
+    // Keys for the mailbox come out the same on both ends
+    let keys = connection::derive_keys(&secret).expect("keys");
+    let ciphertext = connection::encrypt_payload(&keys.k_sig, b"offer").expect("encrypt");
+    assert_eq!(
+        connection::decrypt_payload(
+            &connection::derive_keys(&joined).expect("keys").k_sig,
+            &ciphertext
+        )
+        .expect("decrypt"),
+        b"offer"
+    );
+    let wrong = connection::derive_keys(&guesser).expect("keys");
+    assert!(connection::decrypt_payload(&wrong.k_sig, &ciphertext).is_err());
+
+    assert!(Spake2::start(code).finish(&[0u8; 33]).is_err());
+    assert!(Spake2::start(code).finish(&[1u8; 16]).is_err());
+}
+
+#[tokio::test]
+async fn a_nameplate_can_be_joined_once() {
+    let service = rendezvous_service();
+    let opened = service.open_nameplate().await.expect("open");
+    assert!(code::normalize_nameplate(&opened.nameplate).is_some());
+
+    let (joined, initiator_mailbox_id, _) = service
+        .join_nameplate(&format!(" {} ", opened.nameplate))
+        .await
+        .expect("join");
+    assert_eq!(initiator_mailbox_id, opened.mailbox_id);
+    service
+        .send_message(
+            joined.mailbox_id,
+            &joined.mailbox_token,
+            "spake2 message".to_string(),
+        )
+        .await
+        .expect("joiner reaches the creator");
+
+    assert!(matches!(
+        service.join_nameplate(&opened.nameplate).await,
+        Err(RendezvousError::SessionAlreadyPaired)
+    ));
+    for unknown in ["99999", "0", "seven", ""] {
+        assert!(matches!(
+            service.join_nameplate(unknown).await,
+            Err(RendezvousError::InvalidToken)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn nameplates_in_use_are_not_handed_out_again() {
+    let service = rendezvous_service();
+    let mut nameplates = HashSet::new();
+    // More than fit below the first limit, so later ones need more digits
+    for _ in 0..150 {
+        let opened = service.open_nameplate().await.expect("open");
+        assert!(nameplates.insert(opened.nameplate));
+    }
+
+    // A joined nameplate stays reserved, so a late joiner cannot reach a newer
+    // mailbox by mistake
+    let first = nameplates.iter().next().expect("nameplate").clone();
+    service.join_nameplate(&first).await.expect("join");
+    for _ in 0..50 {
+        let opened = service.open_nameplate().await.expect("open");
+        assert_ne!(opened.nameplate, first);
+    }
+}
+
+#[tokio::test]
+async fn nameplate_joins_need_a_session_and_have_their_own_budget() {
+    let base_url = common::start_server(&[("SIGNALING_RATE_LIMIT_NAMEPLATE_JOIN", "2/60")]).await;
+    let joiner = common::register_device(&base_url).await;
+    let client = reqwest::Client::new();
+    let join = |body: serde_json::Value| {
+        client
+            .post(format!("{base_url}/connection/nameplate/join"))
+            .json(&body)
+            .send()
+    };
+
+    let anonymous = join(serde_json::json!({ "nameplate": "7" }))
+        .await
+        .expect("join");
+    assert_eq!(
+        anonymous.status(),
+        reqwest::StatusCode::UNPROCESSABLE_ENTITY
+    );
+    let forged = join(serde_json::json!({
+        "client_id": joiner.client_id,
+        "session_token": "not-the-token",
+        "nameplate": "7",
+    }))
+    .await
+    .expect("join");
+    assert_eq!(forged.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let body: ErrorResponse = forged.json().await.expect("error body");
+    assert_eq!(body.code, ErrorCode::InvalidSessionToken);
+
+    // Both attempts counted against the nameplate budget, not the link one
+    let limited = join(serde_json::json!({
+        "client_id": joiner.client_id,
+        "session_token": joiner.session_token,
+        "nameplate": "7",
+    }))
+    .await
+    .expect("join");
+    assert_eq!(limited.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let link_join = client
+        .post(format!("{base_url}/connection/join"))
+        .json(&serde_json::json!({ "token_b64": "never-issued" }))
+        .send()
+        .await
+        .expect("join");
+    assert_eq!(link_join.status(), reqwest::StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,160 @@
+use signaling_server::config::ConfigSources;
+use signaling_server::stun::message::{
+    Class, DecodeError, Message, MessageBuilder, ATTR_ERROR_CODE, ATTR_FINGERPRINT, ATTR_SOFTWARE,
+    ATTR_UNKNOWN_ATTRIBUTES, ATTR_XOR_MAPPED_ADDRESS, METHOD_BINDING,
+};
+use signaling_server::stun::{respond, serve};
+use signaling_server::SignalingServerConfig;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+
+const TRANSACTION_ID: [u8; 12] = *b"abcdefghijkl";
+
+fn binding_request() -> Vec<u8> {
+    MessageBuilder::new(Class::Request, METHOD_BINDING, TRANSACTION_ID).build_with_fingerprint()
+}
+
+/// RFC 5769 §2.2: sample IPv4 Binding response.
+const RFC5769_IPV4_RESPONSE: [u8; 80] = [
+    0x01, 0x01, 0x00, 0x3c, 0x21, 0x12, 0xa4, 0x42, 0xb7, 0xe7, 0xa7, 0x01, 0xbc, 0x34, 0xd6, 0x86,
+    0xfa, 0x87, 0xdf, 0xae, 0x80, 0x22, 0x00, 0x0b, 0x74, 0x65, 0x73, 0x74, 0x20, 0x76, 0x65, 0x63,
+    0x74, 0x6f, 0x72, 0x20, 0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0xa1, 0x47, 0xe1, 0x12, 0xa6, 0x43,
+    0x00, 0x08, 0x00, 0x14, 0x2b, 0x91, 0xf5, 0x99, 0xfd, 0x9e, 0x90, 0xc3, 0x8c, 0x74, 0x89, 0xf9,
+    0x2a, 0xf9, 0xba, 0x53, 0xf0, 0x6b, 0xe7, 0xd7, 0x80, 0x28, 0x00, 0x04, 0xc0, 0x7d, 0x4c, 0x96,
+];
+
+#[test]
+fn decodes_the_rfc5769_sample_response() {
+    let response = Message::decode(&RFC5769_IPV4_RESPONSE).expect("valid sample");
+    assert_eq!(response.class, Class::Success);
+    assert_eq!(response.method, METHOD_BINDING);
+    assert_eq!(
+        response.xor_address(ATTR_XOR_MAPPED_ADDRESS),
+        Some("192.0.2.1:32853".parse().unwrap())
+    );
+}
+
+#[test]
+fn checks_message_integrity_of_the_rfc5769_sample() {
+    let response = Message::decode(&RFC5769_IPV4_RESPONSE).expect("valid sample");
+    assert!(response.verify_integrity(b"VOkJxbRl1RmTxUk/WvJxBt"));
+    assert!(!response.verify_integrity(b"wrong password"));
+}
+
+#[test]
+fn signed_messages_verify_with_the_same_key() {
+    let signed = MessageBuilder::new(Class::Request, METHOD_BINDING, TRANSACTION_ID)
+        .attribute(ATTR_SOFTWARE, b"test")
+        .message_integrity(b"key")
+        .build_with_fingerprint();
+    let message = Message::decode(&signed).expect("valid message");
+    assert!(message.verify_integrity(b"key"));
+    assert!(!message.verify_integrity(b"other key"));
+}
+
+#[test]
+fn binding_response_reflects_the_source_address() {
+    for from in ["203.0.113.7:54321", "[2001:db8::1]:3478"] {
+        let from: SocketAddr = from.parse().unwrap();
+        let reply = respond(&binding_request(), from).expect("reply");
+        let response = Message::decode(&reply).expect("valid response");
+        assert_eq!(response.class, Class::Success);
+        assert_eq!(response.method, METHOD_BINDING);
+        assert_eq!(response.transaction_id, TRANSACTION_ID);
+        assert_eq!(response.xor_address(ATTR_XOR_MAPPED_ADDRESS), Some(from));
+        assert!(response.attribute(ATTR_FINGERPRINT).is_some());
+    }
+}
+
+#[test]
+fn ipv4_mapped_sources_are_reported_as_ipv4() {
+    let from: SocketAddr = "[::ffff:198.51.100.4]:40000".parse().unwrap();
+    let reply = respond(&binding_request(), from).expect("reply");
+    let response = Message::decode(&reply).unwrap();
+    assert_eq!(
+        response.xor_address(ATTR_XOR_MAPPED_ADDRESS),
+        Some("198.51.100.4:40000".parse().unwrap())
+    );
+}
+
+#[test]
+fn corrupted_fingerprint_is_rejected() {
+    let mut request = binding_request();
+    let last = request.len() - 1;
+    request[last] ^= 0xFF;
+    assert_eq!(Message::decode(&request), Err(DecodeError::BadFingerprint));
+    assert!(respond(&request, "127.0.0.1:1".parse().unwrap()).is_none());
+}
+
+#[test]
+fn non_stun_and_non_binding_datagrams_are_ignored() {
+    let from: SocketAddr = "127.0.0.1:1".parse().unwrap();
+    assert!(respond(b"GET / HTTP/1.1\r\n\r\n", from).is_none());
+    let indication = MessageBuilder::new(Class::Indication, METHOD_BINDING, TRANSACTION_ID).build();
+    assert!(respond(&indication, from).is_none());
+}
+
+#[test]
+fn unknown_required_attributes_get_a_420() {
+    let request = MessageBuilder::new(Class::Request, METHOD_BINDING, TRANSACTION_ID)
+        .attribute(0x7F00, b"mystery")
+        .build();
+    let reply = respond(&request, "127.0.0.1:1".parse().unwrap()).expect("reply");
+    let response = Message::decode(&reply).unwrap();
+    assert_eq!(response.class, Class::Error);
+    assert_eq!(response.attribute(ATTR_ERROR_CODE).unwrap()[2..4], [4, 20]);
+    assert_eq!(
+        response.attribute(ATTR_UNKNOWN_ATTRIBUTES),
+        Some(&[0x7F, 0x00][..])
+    );
+}
+
+#[tokio::test]
+async fn responder_answers_over_loopback_udp() {
+    let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = server.local_addr().unwrap();
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let task = tokio::spawn(serve(server, shutdown_rx));
+
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client
+        .send_to(&binding_request(), server_addr)
+        .await
+        .unwrap();
+    let mut buf = [0u8; 1500];
+    let (len, _) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+        .await
+        .expect("reply in time")
+        .unwrap();
+    let response = Message::decode(&buf[..len]).unwrap();
+    assert_eq!(
+        response.xor_address(ATTR_XOR_MAPPED_ADDRESS),
+        Some(client.local_addr().unwrap())
+    );
+
+    shutdown_tx.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("responder stops on shutdown")
+        .unwrap();
+}
+
+#[test]
+fn stun_addr_is_advertised_at_the_public_host() {
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_PUBLIC_URL", "https://signal.example.com/base")
+        .with_env("SIGNALING_STUN_ADDR", "0.0.0.0:3478")
+        .with_env("SIGNALING_STUN_URLS", "stun:backup.example.com:3478");
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    assert_eq!(
+        config.stun_urls,
+        [
+            "stun:signal.example.com:3478",
+            "stun:backup.example.com:3478"
+        ]
+    );
+}
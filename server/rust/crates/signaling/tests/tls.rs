@@ -0,0 +1,161 @@
+use axum::{routing::get, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::{CertificateDer, ServerName};
+use signaling_server::config::TlsSettings;
+use signaling_server::tls;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::TlsConnector;
+
+struct CertDir {
+    settings: TlsSettings,
+}
+
+impl CertDir {
+    fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("signaling-tls-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        Self {
+            settings: TlsSettings {
+                cert_path: dir.join("cert.pem"),
+                key_path: dir.join("key.pem"),
+            },
+        }
+    }
+
+    /// Writes a fresh self-signed certificate for `localhost` and returns it.
+    fn issue(&self) -> CertificateDer<'static> {
+        let issued = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("generate certificate");
+        std::fs::write(&self.settings.cert_path, issued.cert.pem()).expect("write cert");
+        std::fs::write(&self.settings.key_path, issued.key_pair.serialize_pem())
+            .expect("write key");
+        issued.cert.der().clone()
+    }
+
+    /// Replaces only the certificate, leaving a key that does not match it.
+    fn issue_cert_only(&self) {
+        let issued = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("generate certificate");
+        std::fs::write(&self.settings.cert_path, issued.cert.pem()).expect("write cert");
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.settings.cert_path.parent().unwrap().to_path_buf()
+    }
+}
+
+impl Drop for CertDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.dir());
+    }
+}
+
+async fn serve(tls: RustlsConfig) -> SocketAddr {
+    let handle = axum_server::Handle::new();
+    let app = Router::new().route("/", get(|| async { "ok" }));
+    let server =
+        axum_server::bind_rustls("127.0.0.1:0".parse().unwrap(), tls).handle(handle.clone());
+    tokio::spawn(async move { server.serve(app.into_make_service()).await });
+    handle.listening().await.expect("server listening")
+}
+
+/// Connects trusting only `cert`, so success proves which certificate was served.
+async fn connect(
+    addr: SocketAddr,
+    cert: &CertificateDer<'static>,
+) -> std::io::Result<TlsStream<TcpStream>> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert.clone()).expect("add root");
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+
+    let stream = TcpStream::connect(addr).await?;
+    TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await
+}
+
+async fn get_root(stream: &mut TlsStream<TcpStream>) -> String {
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .expect("write request");
+    let mut response = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !response.ends_with(b"\r\n\r\nok") {
+        let read = stream.read(&mut chunk).await.expect("read response");
+        assert!(read > 0, "connection closed mid-response");
+        response.extend_from_slice(&chunk[..read]);
+    }
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+#[tokio::test]
+async fn reload_serves_new_certificate_without_dropping_open_connections() {
+    let certs = CertDir::new();
+    let first = certs.issue();
+    let tls = RustlsConfig::from_config(tls::load_server_config(&certs.settings).unwrap());
+    let addr = serve(tls.clone()).await;
+
+    let mut open = connect(addr, &first)
+        .await
+        .expect("handshake with first cert");
+    assert!(get_root(&mut open).await.starts_with("HTTP/1.1 200"));
+
+    let second = certs.issue();
+    tls::reload(&tls, &certs.settings).expect("reload");
+
+    assert!(connect(addr, &first).await.is_err());
+    let mut fresh = connect(addr, &second)
+        .await
+        .expect("handshake with second cert");
+    assert!(get_root(&mut fresh).await.starts_with("HTTP/1.1 200"));
+
+    // The connection negotiated before the reload keeps working
+    assert!(get_root(&mut open).await.starts_with("HTTP/1.1 200"));
+}
+
+#[tokio::test]
+async fn reloader_picks_up_changed_files() {
+    let certs = CertDir::new();
+    certs.issue();
+    let tls = RustlsConfig::from_config(tls::load_server_config(&certs.settings).unwrap());
+    let addr = serve(tls.clone()).await;
+    tls::spawn_reloader(tls, certs.settings.clone(), Duration::from_millis(50));
+
+    let renewed = certs.issue();
+    let reloaded = tokio::time::timeout(Duration::from_secs(5), async {
+        while connect(addr, &renewed).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    assert!(reloaded.is_ok(), "renewed certificate was never served");
+}
+
+#[tokio::test]
+async fn failed_reload_keeps_the_current_certificate() {
+    let certs = CertDir::new();
+    let current = certs.issue();
+    let tls = RustlsConfig::from_config(tls::load_server_config(&certs.settings).unwrap());
+    let addr = serve(tls.clone()).await;
+
+    certs.issue_cert_only();
+    assert!(tls::reload(&tls, &certs.settings).is_err());
+
+    let mut stream = connect(addr, &current)
+        .await
+        .expect("handshake with current cert");
+    assert!(get_root(&mut stream).await.starts_with("HTTP/1.1 200"));
+}
@@ -0,0 +1,523 @@
+use shared::identity::DeviceIdentity;
+use shared::models::{ClientId, RegisterRequest};
+use signaling_server::config::{ConfigSources, TurnRelaySettings, TurnSecret};
+use signaling_server::ice::turn_rest_credentials;
+use signaling_server::registry::SessionRegistry;
+use signaling_server::repository::session_repository::InMemorySessionRepository;
+use signaling_server::stun::message::{
+    Class, Message, MessageBuilder, ATTR_CHANNEL_NUMBER, ATTR_DATA, ATTR_ERROR_CODE, ATTR_LIFETIME,
+    ATTR_NONCE, ATTR_REALM, ATTR_REQUESTED_TRANSPORT, ATTR_USERNAME, ATTR_XOR_MAPPED_ADDRESS,
+    ATTR_XOR_PEER_ADDRESS, ATTR_XOR_RELAYED_ADDRESS, METHOD_ALLOCATE, METHOD_CHANNEL_BIND,
+    METHOD_CREATE_PERMISSION, METHOD_DATA, METHOD_REFRESH, METHOD_SEND,
+};
+use signaling_server::turn::{long_term_key, TurnServer};
+use signaling_server::SignalingServerConfig;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::time::timeout;
+
+const SECRET: &str = "turn-shared-secret";
+const REALM: &str = "test.example";
+
+struct Relay {
+    addr: SocketAddr,
+    registry: Arc<SessionRegistry>,
+    _shutdown: watch::Sender<bool>,
+}
+
+fn relay_settings() -> TurnRelaySettings {
+    TurnRelaySettings {
+        listen_addr: "127.0.0.1:0".parse().unwrap(),
+        relay_ip: "127.0.0.1".parse().unwrap(),
+        relay_ports: None,
+        realm: REALM.to_string(),
+        max_allocations_per_session: 4,
+        bandwidth_bytes_per_sec: 1024 * 1024,
+        denied_peer_ranges: ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"]
+            .iter()
+            .map(|range| range.parse().unwrap())
+            .collect(),
+    }
+}
+
+async fn start_relay(max_allocations_per_session: usize, bandwidth_bytes_per_sec: u64) -> Relay {
+    start_relay_with(TurnRelaySettings {
+        max_allocations_per_session,
+        bandwidth_bytes_per_sec,
+        ..relay_settings()
+    })
+    .await
+}
+
+async fn start_relay_with(settings: TurnRelaySettings) -> Relay {
+    let registry = Arc::new(SessionRegistry::new(
+        Arc::new(InMemorySessionRepository::new()),
+        Duration::from_secs(60),
+        Duration::from_secs(3600),
+        Duration::from_secs(30),
+    ));
+    // Clients reach a relay listening on all interfaces over loopback too
+    let relay_ip = settings.relay_ip;
+    let server = TurnServer::bind(settings, TurnSecret::new(SECRET).unwrap(), registry.clone())
+        .await
+        .expect("bind relay");
+    let addr = SocketAddr::new(relay_ip, server.local_addr().unwrap().port());
+    let (shutdown, shutdown_rx) = watch::channel(false);
+    tokio::spawn(server.serve(shutdown_rx));
+    Relay {
+        addr,
+        registry,
+        _shutdown: shutdown,
+    }
+}
+
+async fn register(registry: &SessionRegistry) -> ClientId {
+    let identity = DeviceIdentity::generate();
+    let nonce = registry.issue_challenge().await.expect("challenge").nonce;
+    registry
+        .register(RegisterRequest {
+            device_label: "test".to_string(),
+            public_key_b64: identity.public_key_b64(),
+            signature_b64: identity.sign_registration(&nonce),
+            nonce,
+        })
+        .await
+        .expect("register")
+        .client_id
+}
+
+/// A TURN client on a loopback socket holding REST credentials for one client ID.
+struct Client {
+    socket: UdpSocket,
+    relay: SocketAddr,
+    username: String,
+    key: [u8; 16],
+    nonce: Option<String>,
+    next_transaction: u8,
+}
+
+impl Client {
+    async fn new(relay: SocketAddr, client_id: ClientId) -> Self {
+        let expires_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 3600;
+        let (username, password) =
+            turn_rest_credentials(SECRET.as_bytes(), &client_id.to_string(), expires_at);
+        Self {
+            socket: UdpSocket::bind("127.0.0.1:0").await.unwrap(),
+            relay,
+            key: long_term_key(&username, REALM, &password),
+            username,
+            nonce: None,
+            next_transaction: 0,
+        }
+    }
+
+    async fn exchange(&self, request: &[u8]) -> Message {
+        self.socket.send_to(request, self.relay).await.unwrap();
+        let reply = self.receive().await;
+        Message::decode(&reply).expect("valid reply")
+    }
+
+    async fn receive(&self) -> Vec<u8> {
+        let mut buf = vec![0u8; 2048];
+        let (len, _) = timeout(Duration::from_secs(5), self.socket.recv_from(&mut buf))
+            .await
+            .expect("reply in time")
+            .unwrap();
+        buf.truncate(len);
+        buf
+    }
+
+    /// Sends a signed request, first picking up a nonce from the 401 challenge.
+    async fn request(
+        &mut self,
+        method: u16,
+        attributes: impl Fn(MessageBuilder) -> MessageBuilder,
+    ) -> Message {
+        if self.nonce.is_none() {
+            let unsigned = attributes(self.builder(method)).build_with_fingerprint();
+            let challenge = self.exchange(&unsigned).await;
+            assert_eq!(error_code(&challenge), Some(401));
+            assert_eq!(challenge.attribute(ATTR_REALM), Some(REALM.as_bytes()));
+            self.nonce =
+                Some(String::from_utf8(challenge.attribute(ATTR_NONCE).unwrap().to_vec()).unwrap());
+        }
+        let request = attributes(self.builder(method))
+            .attribute(ATTR_USERNAME, self.username.as_bytes())
+            .attribute(ATTR_REALM, REALM.as_bytes())
+            .attribute(ATTR_NONCE, self.nonce.as_ref().unwrap().as_bytes())
+            .message_integrity(&self.key)
+            .build_with_fingerprint();
+        let response = self.exchange(&request).await;
+        assert!(response.verify_integrity(&self.key), "reply is signed");
+        response
+    }
+
+    fn builder(&mut self, method: u16) -> MessageBuilder {
+        self.next_transaction += 1;
+        MessageBuilder::new(Class::Request, method, [self.next_transaction; 12])
+    }
+
+    async fn allocate(&mut self) -> Message {
+        self.request(METHOD_ALLOCATE, |request| {
+            request.attribute(ATTR_REQUESTED_TRANSPORT, &[17, 0, 0, 0])
+        })
+        .await
+    }
+}
+
+fn send_indication(peer: SocketAddr, data: &[u8]) -> Vec<u8> {
+    MessageBuilder::new(Class::Indication, METHOD_SEND, [0xEE; 12])
+        .xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+        .attribute(ATTR_DATA, data)
+        .build()
+}
+
+fn error_code(message: &Message) -> Option<u16> {
+    let value = message.attribute(ATTR_ERROR_CODE)?;
+    Some(u16::from(value[2]) * 100 + u16::from(value[3]))
+}
+
+/// Asks for a permission and a channel to `peer` and returns their error codes.
+async fn permit(client: &mut Client, peer: &str) -> (Option<u16>, Option<u16>) {
+    let peer: SocketAddr = peer.parse().unwrap();
+    let permission = client
+        .request(METHOD_CREATE_PERMISSION, |request| {
+            request.xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+        })
+        .await;
+    let channel = client
+        .request(METHOD_CHANNEL_BIND, |request| {
+            request
+                .attribute(ATTR_CHANNEL_NUMBER, &[0x40, 0x00, 0, 0])
+                .xor_address(ATTR_XOR_PEER_ADDRESS, peer)
+        })
+        .await;
+    (error_code(&permission), error_code(&channel))
+}
+
+async fn receive_from(socket: &UdpSocket) -> (Vec<u8>, SocketAddr) {
+    let mut buf = vec![0u8; 2048];
+    let (len, from) = timeout(Duration::from_secs(5), socket.recv_from(&mut buf))
+        .await
+        .expect("datagram in time")
+        .unwrap();
+    buf.truncate(len);
+    (buf, from)
+}
+
+#[tokio::test]
+async fn relays_between_a_client_and_a_permitted_peer() {
+    let relay = start_relay(4, 1024 * 1024).await;
+    let client_id = register(&relay.registry).await;
+    let mut client = Client::new(relay.addr, client_id).await;
+
+    let allocated = client.allocate().await;
+    assert_eq!(allocated.class, Class::Success);
+    assert_eq!(
+        allocated.xor_address(ATTR_XOR_MAPPED_ADDRESS),
+        Some(client.socket.local_addr().unwrap())
+    );
+    assert_eq!(
+        allocated.attribute(ATTR_LIFETIME),
+        Some(600u32.to_be_bytes().as_slice())
+    );
+    let relayed = allocated
+        .xor_address(ATTR_XOR_RELAYED_ADDRESS)
+        .expect("relayed address");
+
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let peer_addr = peer.local_addr().unwrap();
+
+    // Nothing reaches the peer before a permission is installed
+    let early = send_indication(peer_addr, b"too early");
+    client.socket.send_to(&early, relay.addr).await.unwrap();
+
+    let permitted = client
+        .request(METHOD_CREATE_PERMISSION, |request| {
+            request.xor_address(ATTR_XOR_PEER_ADDRESS, peer_addr)
+        })
+        .await;
+    assert_eq!(permitted.class, Class::Success);
+
+    let hello = send_indication(peer_addr, b"hello peer");
+    client.socket.send_to(&hello, relay.addr).await.unwrap();
+    let (data, from) = receive_from(&peer).await;
+    assert_eq!(data, b"hello peer");
+    assert_eq!(from, relayed);
+
+    peer.send_to(b"hello client", relayed).await.unwrap();
+    let indication = Message::decode(&client.receive().await).expect("Data indication");
+    assert_eq!(
+        (indication.class, indication.method),
+        (Class::Indication, METHOD_DATA)
+    );
+    assert_eq!(
+        indication.xor_address(ATTR_XOR_PEER_ADDRESS),
+        Some(peer_addr)
+    );
+    assert_eq!(
+        indication.attribute(ATTR_DATA),
+        Some(b"hello client".as_slice())
+    );
+
+    let bound = client
+        .request(METHOD_CHANNEL_BIND, |request| {
+            request
+                .attribute(ATTR_CHANNEL_NUMBER, &[0x40, 0x00, 0, 0])
+                .xor_address(ATTR_XOR_PEER_ADDRESS, peer_addr)
+        })
+        .await;
+    assert_eq!(bound.class, Class::Success);
+
+    client
+        .socket
+        .send_to(&[0x40, 0x00, 0x00, 0x03, b'a', b'b', b'c', 0], relay.addr)
+        .await
+        .unwrap();
+    let (data, _) = receive_from(&peer).await;
+    assert_eq!(data, b"abc");
+
+    peer.send_to(b"xyz", relayed).await.unwrap();
+    assert_eq!(
+        client.receive().await,
+        [0x40, 0x00, 0x00, 0x03, b'x', b'y', b'z']
+    );
+
+    let released = client
+        .request(METHOD_REFRESH, |request| {
+            request.attribute(ATTR_LIFETIME, &0u32.to_be_bytes())
+        })
+        .await;
+    assert_eq!(released.class, Class::Success);
+    let refreshed = client.request(METHOD_REFRESH, |request| request).await;
+    assert_eq!(error_code(&refreshed), Some(437));
+}
+
+#[tokio::test]
+async fn allocations_are_limited_per_session() {
+    let relay = start_relay(1, 1024 * 1024).await;
+    let client_id = register(&relay.registry).await;
+
+    let mut first = Client::new(relay.addr, client_id).await;
+    assert_eq!(first.allocate().await.class, Class::Success);
+
+    let mut second = Client::new(relay.addr, client_id).await;
+    assert_eq!(error_code(&second.allocate().await), Some(486));
+
+    let mut other = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(other.allocate().await.class, Class::Success);
+}
+
+#[tokio::test]
+async fn unregistered_clients_and_bad_passwords_are_refused() {
+    let relay = start_relay(4, 1024 * 1024).await;
+
+    let mut stranger = Client::new(relay.addr, ClientId::new_v4()).await;
+    assert_eq!(error_code(&stranger.allocate().await), Some(403));
+
+    let mut forger = Client::new(relay.addr, register(&relay.registry).await).await;
+    forger.key = long_term_key(&forger.username, REALM, "guessed");
+    let unsigned = forger
+        .builder(METHOD_ALLOCATE)
+        .attribute(ATTR_REQUESTED_TRANSPORT, &[17, 0, 0, 0])
+        .build_with_fingerprint();
+    let challenge = forger.exchange(&unsigned).await;
+    let nonce = challenge.attribute(ATTR_NONCE).unwrap().to_vec();
+    let request = forger
+        .builder(METHOD_ALLOCATE)
+        .attribute(ATTR_REQUESTED_TRANSPORT, &[17, 0, 0, 0])
+        .attribute(ATTR_USERNAME, forger.username.as_bytes())
+        .attribute(ATTR_REALM, REALM.as_bytes())
+        .attribute(ATTR_NONCE, &nonce)
+        .message_integrity(&forger.key)
+        .build_with_fingerprint();
+    assert_eq!(error_code(&forger.exchange(&request).await), Some(401));
+}
+
+#[tokio::test]
+async fn only_udp_relaying_is_offered() {
+    let relay = start_relay(4, 1024 * 1024).await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    let tcp = client
+        .request(METHOD_ALLOCATE, |request| {
+            request.attribute(ATTR_REQUESTED_TRANSPORT, &[6, 0, 0, 0])
+        })
+        .await;
+    assert_eq!(error_code(&tcp), Some(442));
+}
+
+#[tokio::test]
+async fn traffic_over_the_bandwidth_limit_is_dropped() {
+    let relay = start_relay(4, 1000).await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(client.allocate().await.class, Class::Success);
+    let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let peer_addr = peer.local_addr().unwrap();
+    client
+        .request(METHOD_CHANNEL_BIND, |request| {
+            request
+                .attribute(ATTR_CHANNEL_NUMBER, &[0x40, 0x01, 0, 0])
+                .xor_address(ATTR_XOR_PEER_ADDRESS, peer_addr)
+        })
+        .await;
+
+    // One second's worth of bytes fits; the next datagram right after does not
+    let mut frame = vec![0x40, 0x01, 0x02, 0x58];
+    frame.extend_from_slice(&[7u8; 600]);
+    for _ in 0..2 {
+        client.socket.send_to(&frame, relay.addr).await.unwrap();
+    }
+    let (data, _) = receive_from(&peer).await;
+    assert_eq!(data.len(), 600);
+    assert!(
+        timeout(Duration::from_millis(200), peer.recv_from(&mut [0u8; 1024]))
+            .await
+            .is_err(),
+        "second datagram should be dropped"
+    );
+}
+
+#[tokio::test]
+async fn special_peer_addresses_are_forbidden() {
+    let relay = start_relay(4, 1024 * 1024).await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(client.allocate().await.class, Class::Success);
+
+    for peer in [
+        "0.0.0.0:9",
+        "224.0.0.1:9",
+        "255.255.255.255:9",
+        "169.254.10.1:9",
+        // IPv4-mapped addresses are checked as the IPv4 address they carry
+        "[::ffff:169.254.10.1]:9",
+    ] {
+        assert_eq!(
+            permit(&mut client, peer).await,
+            (Some(403), Some(403)),
+            "{peer}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn loopback_peers_need_a_loopback_relay() {
+    let relay = start_relay_with(TurnRelaySettings {
+        listen_addr: "0.0.0.0:0".parse().unwrap(),
+        ..relay_settings()
+    })
+    .await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(client.allocate().await.class, Class::Success);
+
+    assert_eq!(
+        permit(&mut client, "127.0.0.1:9").await,
+        (Some(403), Some(403))
+    );
+}
+
+#[tokio::test]
+async fn private_peer_ranges_are_denied_unless_configured_otherwise() {
+    let relay = start_relay(4, 1024 * 1024).await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(client.allocate().await.class, Class::Success);
+    for peer in ["10.1.2.3:9", "172.16.5.5:9", "192.168.1.1:9"] {
+        assert_eq!(
+            permit(&mut client, peer).await,
+            (Some(403), Some(403)),
+            "{peer}"
+        );
+    }
+    assert_eq!(permit(&mut client, "203.0.113.5:9").await, (None, None));
+
+    let relay = start_relay_with(TurnRelaySettings {
+        denied_peer_ranges: vec!["203.0.113.0/24".parse().unwrap()],
+        ..relay_settings()
+    })
+    .await;
+    let mut client = Client::new(relay.addr, register(&relay.registry).await).await;
+    assert_eq!(client.allocate().await.class, Class::Success);
+    assert_eq!(permit(&mut client, "10.1.2.3:9").await, (None, None));
+    assert_eq!(
+        permit(&mut client, "203.0.113.5:9").await,
+        (Some(403), Some(403))
+    );
+}
+
+#[test]
+fn denied_peer_ranges_default_to_private_networks() {
+    let relay_config = |ranges: Option<&str>| {
+        let mut sources = ConfigSources::default()
+            .with_env("SIGNALING_TURN_ADDR", "127.0.0.1:3479")
+            .with_env("SIGNALING_TURN_SECRET", SECRET);
+        if let Some(ranges) = ranges {
+            sources = sources.with_env("SIGNALING_TURN_DENIED_PEER_RANGES", ranges);
+        }
+        SignalingServerConfig::from_sources(&sources).map(|loaded| {
+            loaded
+                .config
+                .turn_relay
+                .expect("relay enabled")
+                .denied_peer_ranges
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        })
+    };
+
+    assert_eq!(
+        relay_config(None).expect("defaults"),
+        ["10.0.0.0/8", "172.16.0.0/12", "192.168.0.0/16", "fc00::/7"]
+    );
+    assert_eq!(
+        relay_config(Some("100.64.0.0/10, 192.0.2.7")).expect("custom"),
+        ["100.64.0.0/10", "192.0.2.7/32"]
+    );
+    assert!(relay_config(Some("none")).expect("none").is_empty());
+    let err = relay_config(Some("10.0.0.0/33"))
+        .expect_err("bad range")
+        .to_string();
+    assert!(err.contains("SIGNALING_TURN_DENIED_PEER_RANGES"), "{err}");
+}
+
+#[test]
+fn relay_is_advertised_and_needs_the_shared_secret() {
+    let sources = ConfigSources::default()
+        .with_env("SIGNALING_PUBLIC_URL", "https://signal.example.com")
+        .with_env("SIGNALING_TURN_ADDR", "0.0.0.0:3479")
+        .with_env("SIGNALING_TURN_EXTERNAL_IP", "203.0.113.9")
+        .with_env("SIGNALING_TURN_RELAY_PORTS", "50000-50100")
+        .with_env("SIGNALING_TURN_SECRET", SECRET);
+    let config = SignalingServerConfig::from_sources(&sources)
+        .expect("valid config")
+        .config;
+    assert_eq!(
+        config.turn_urls,
+        ["turn:signal.example.com:3479?transport=udp"]
+    );
+    let relay = config.turn_relay.expect("relay enabled");
+    assert_eq!(
+        relay.relay_ip,
+        "203.0.113.9".parse::<std::net::IpAddr>().unwrap()
+    );
+    assert_eq!(relay.relay_ports, Some(50000..=50100));
+
+    let missing = ConfigSources::default()
+        .with_env("SIGNALING_TURN_ADDR", "0.0.0.0:3479")
+        .with_env("SIGNALING_TURN_RELAY_PORTS", "60000-50000");
+    let err = SignalingServerConfig::from_sources(&missing)
+        .expect_err("invalid config")
+        .to_string();
+    assert!(err.contains("SIGNALING_TURN_SECRET is not"), "{err}");
+    assert!(
+        err.contains("SIGNALING_TURN_EXTERNAL_IP is required"),
+        "{err}"
+    );
+    assert!(err.contains("SIGNALING_TURN_RELAY_PORTS"), "{err}");
+}
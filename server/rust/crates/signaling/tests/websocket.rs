@@ -0,0 +1,362 @@
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use shared::models::{
+    ConnectionInitRequest, ConnectionInitResponse, ConnectionJoinRequest, ConnectionJoinResponse,
+    ErrorCode, MailboxMessage, MailboxSendRequest, MailboxWsClientFrame, MailboxWsServerFrame,
+};
+use shared::token;
+use signaling_server::metrics::metrics;
+use signaling_server::run_server_until;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+struct Mailbox {
+    id: String,
+    token: String,
+}
+
+/// Registers a device, opens a link and joins it, returning the initiator's and
+/// the responder's mailbox.
+async fn paired_mailboxes(base_url: &str) -> (Mailbox, Mailbox) {
+    let client = reqwest::Client::new();
+    let registered = common::register_device(base_url).await;
+
+    let rendezvous_id = token::gen_token();
+    let initiator: ConnectionInitResponse = client
+        .post(format!("{base_url}/connection/init"))
+        .json(&ConnectionInitRequest {
+            client_id: registered.client_id,
+            session_token: registered.session_token,
+            rendezvous_id_b64: rendezvous_id.clone(),
+        })
+        .send()
+        .await
+        .expect("init")
+        .json()
+        .await
+        .expect("init body");
+    let responder: ConnectionJoinResponse = client
+        .post(format!("{base_url}/connection/join"))
+        .json(&ConnectionJoinRequest {
+            token_b64: rendezvous_id,
+        })
+        .send()
+        .await
+        .expect("join")
+        .json()
+        .await
+        .expect("join body");
+
+    (
+        Mailbox {
+            id: initiator.mailbox_id,
+            token: initiator.mailbox_token,
+        },
+        Mailbox {
+            id: responder.mailbox_id,
+            token: responder.mailbox_token,
+        },
+    )
+}
+
+async fn connect(base_url: &str, mailbox: &Mailbox, query: &str) -> Socket {
+    let url = format!(
+        "{}/ws/{}{query}",
+        base_url.replace("http", "ws"),
+        mailbox.id
+    );
+    let mut request = url.into_client_request().expect("request");
+    request.headers_mut().insert(
+        "authorization",
+        format!("Bearer {}", mailbox.token).parse().expect("header"),
+    );
+    tokio_tungstenite::connect_async(request)
+        .await
+        .expect("upgrade")
+        .0
+}
+
+/// The next frame other than a ping or pong.
+async fn next_frame(socket: &mut Socket) -> Message {
+    loop {
+        let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("frame in time")
+            .expect("socket open")
+            .expect("frame");
+        if !matches!(frame, Message::Ping(_) | Message::Pong(_)) {
+            return frame;
+        }
+    }
+}
+
+async fn next_json<T: serde::de::DeserializeOwned>(socket: &mut Socket) -> T {
+    match next_frame(socket).await {
+        Message::Text(text) => serde_json::from_str(&text).expect("json frame"),
+        other => panic!("expected a text frame, got {other:?}"),
+    }
+}
+
+async fn send_frame(socket: &mut Socket, frame: &MailboxWsClientFrame) {
+    socket
+        .send(Message::Text(serde_json::to_string(frame).expect("json")))
+        .await
+        .expect("send");
+}
+
+/// Sends `ciphertext_b64` from `from` to its peer over HTTP.
+async fn post_message(
+    client: &reqwest::Client,
+    base_url: &str,
+    from: &Mailbox,
+    ciphertext_b64: &str,
+) {
+    let response = client
+        .post(format!("{base_url}/connection/send"))
+        .json(&MailboxSendRequest {
+            mailbox_id: from.id.clone(),
+            mailbox_token: from.token.clone(),
+            ciphertext_b64: ciphertext_b64.to_string(),
+        })
+        .send()
+        .await
+        .expect("send");
+    assert!(response.status().is_success(), "{}", response.status());
+}
+
+fn assert_closed_with(frame: Message, code: CloseCode, reason: &str) {
+    match frame {
+        Message::Close(Some(close)) => {
+            assert_eq!(close.code, code);
+            assert_eq!(close.reason, reason);
+        }
+        other => panic!("expected a close frame, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn typed_frames_send_ack_and_close_the_session() {
+    let base_url = common::start_server(&[]).await;
+    let (initiator, responder) = paired_mailboxes(&base_url).await;
+    let mut initiator_ws = connect(&base_url, &initiator, "").await;
+    let mut responder_ws = connect(&base_url, &responder, "").await;
+
+    send_frame(
+        &mut initiator_ws,
+        &MailboxWsClientFrame::Send {
+            ciphertext_b64: "b2ZmZXI".to_string(),
+        },
+    )
+    .await;
+    assert!(matches!(
+        next_json(&mut initiator_ws).await,
+        MailboxWsServerFrame::Sent
+    ));
+    let pushed: MailboxMessage = next_json(&mut responder_ws).await;
+    assert_eq!(pushed.from_mailbox_id, initiator.id);
+    assert_eq!(pushed.ciphertext_b64, "b2ZmZXI");
+    assert_eq!(pushed.sequence, 1);
+
+    send_frame(
+        &mut responder_ws,
+        &MailboxWsClientFrame::Ack { up_to_sequence: 1 },
+    )
+    .await;
+    assert!(matches!(
+        next_json(&mut responder_ws).await,
+        MailboxWsServerFrame::Acked { up_to_sequence: 1 }
+    ));
+
+    // A bad frame is answered, not fatal
+    responder_ws
+        .send(Message::Text("{\"type\":\"shout\"}".to_string()))
+        .await
+        .expect("send");
+    match next_json(&mut responder_ws).await {
+        MailboxWsServerFrame::Error { code, .. } => assert_eq!(code, ErrorCode::InvalidRequest),
+        other => panic!("expected an error frame, got {other:?}"),
+    }
+
+    send_frame(&mut responder_ws, &MailboxWsClientFrame::Close).await;
+    assert!(matches!(
+        next_json(&mut responder_ws).await,
+        MailboxWsServerFrame::Closed
+    ));
+    assert_closed_with(
+        next_frame(&mut responder_ws).await,
+        CloseCode::Normal,
+        "session closed",
+    );
+    // Closing the session closes the peer's socket too
+    assert_closed_with(
+        next_frame(&mut initiator_ws).await,
+        CloseCode::Normal,
+        "mailbox closed",
+    );
+}
+
+#[tokio::test]
+async fn idle_sockets_are_pinged_then_closed() {
+    let base_url = common::start_server(&[
+        ("SIGNALING_WS_PING_SECS", "1"),
+        ("SIGNALING_WS_IDLE_TIMEOUT_SECS", "2"),
+    ])
+    .await;
+    let (initiator, _responder) = paired_mailboxes(&base_url).await;
+    let mut socket = connect(&base_url, &initiator, "").await;
+
+    let first = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("frame in time")
+        .expect("socket open")
+        .expect("frame");
+    assert!(matches!(first, Message::Ping(_)), "{first:?}");
+    // Answering pings keeps the socket alive but does not count as activity
+    assert_closed_with(next_frame(&mut socket).await, CloseCode::Normal, "idle");
+}
+
+#[tokio::test]
+async fn unresponsive_sockets_are_dropped() {
+    let base_url = common::start_server(&[
+        ("SIGNALING_WS_PING_SECS", "1"),
+        ("SIGNALING_WS_IDLE_TIMEOUT_SECS", "60"),
+    ])
+    .await;
+    let (initiator, _responder) = paired_mailboxes(&base_url).await;
+    let mut socket = connect(&base_url, &initiator, "").await;
+
+    // Not reading means no pongs go back, so the server gives up on the socket
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+    loop {
+        match tokio::time::timeout(Duration::from_secs(5), socket.next())
+            .await
+            .expect("socket dropped in time")
+        {
+            Some(Ok(Message::Ping(_) | Message::Pong(_))) => continue,
+            Some(Ok(other)) => panic!("unexpected frame {other:?}"),
+            Some(Err(_)) | None => break,
+        }
+    }
+}
+
+#[tokio::test]
+async fn since_replays_the_backlog_before_live_messages() {
+    let base_url = common::start_server(&[]).await;
+    let (initiator, responder) = paired_mailboxes(&base_url).await;
+    let client = reqwest::Client::new();
+    for ciphertext in ["b25l", "dHdv", "dGhyZWU"] {
+        post_message(&client, &base_url, &initiator, ciphertext).await;
+    }
+
+    // The client already has the first message
+    let mut socket = connect(&base_url, &responder, "?since=1").await;
+    for (sequence, ciphertext) in [(2, "dHdv"), (3, "dGhyZWU")] {
+        let replayed: MailboxMessage = next_json(&mut socket).await;
+        assert_eq!(replayed.sequence, sequence);
+        assert_eq!(replayed.ciphertext_b64, ciphertext);
+    }
+    post_message(&client, &base_url, &initiator, "Zm91cg").await;
+    let live: MailboxMessage = next_json(&mut socket).await;
+    assert_eq!(live.sequence, 4);
+    assert_eq!(live.ciphertext_b64, "Zm91cg");
+}
+
+#[tokio::test]
+async fn lagging_sockets_resync_from_the_store() {
+    // A one-slot push channel overflows as soon as sends outpace the socket
+    let base_url = common::start_server(&[
+        ("SIGNALING_WS_PUSH_BUFFER_CAPACITY", "1"),
+        ("SIGNALING_MAILBOX_MAX_MESSAGES", "10000"),
+        ("SIGNALING_MAILBOX_MAX_BYTES", "10000000"),
+    ])
+    .await;
+    let (initiator, responder) = paired_mailboxes(&base_url).await;
+    let client = reqwest::Client::new();
+    let mut socket = connect(&base_url, &responder, "?since=0").await;
+
+    let lagged_before = metrics().ws_lagged.get();
+    let mut received = 0;
+    for _ in 0..20 {
+        let burst = (0..32).map(|_| post_message(&client, &base_url, &initiator, "YnVyc3Q"));
+        futures_util::future::join_all(burst).await;
+        // Every message arrives once and in order, whether pushed or replayed
+        for _ in 0..32 {
+            let message: MailboxMessage = next_json(&mut socket).await;
+            received += 1;
+            assert_eq!(message.sequence, received);
+        }
+        if metrics().ws_lagged.get() > lagged_before {
+            return;
+        }
+    }
+    panic!("the push channel never overflowed");
+}
+
+#[tokio::test]
+async fn shutdown_tells_sockets_to_reconnect_and_drains_them() {
+    let config = common::server_config(&[("SIGNALING_SHUTDOWN_GRACE_SECS", "30")]);
+    let base_url = format!("http://127.0.0.1:{}", config.listen_addr.port());
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(run_server_until(config, async {
+        let _ = stopped.await;
+    }));
+    common::wait_until_listening(&base_url).await;
+    let (initiator, _responder) = paired_mailboxes(&base_url).await;
+    let mut socket = connect(&base_url, &initiator, "").await;
+
+    stop.send(()).expect("server running");
+    assert_closed_with(
+        next_frame(&mut socket).await,
+        CloseCode::Away,
+        "server going away, reconnect",
+    );
+    // Finishing the close handshake releases the socket, so the server stops
+    // well inside the grace period. It may hang up before our reply to its
+    // close frame is written, so the stream ends either cleanly or in an error.
+    let after = socket.next().await;
+    assert!(!matches!(after, Some(Ok(_))), "{after:?}");
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("drained before the grace period")
+        .expect("server task")
+        .expect("clean stop");
+}
+
+#[tokio::test]
+async fn connections_left_after_the_grace_period_are_cut() {
+    let config = common::server_config(&[("SIGNALING_SHUTDOWN_GRACE_SECS", "1")]);
+    let addr = format!("127.0.0.1:{}", config.listen_addr.port());
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(run_server_until(config, async {
+        let _ = stopped.await;
+    }));
+    common::wait_until_listening(&format!("http://{addr}")).await;
+
+    // A request that never finishes keeps its connection busy through the drain
+    let mut stalled = TcpStream::connect(&addr).await.expect("connect");
+    stalled
+        .write_all(b"GET /health HTTP/1.1\r\nHost: test\r\n")
+        .await
+        .expect("write");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    stop.send(()).expect("server running");
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .expect("stopped after the grace period")
+        .expect("server task")
+        .expect("clean stop");
+    let mut buf = [0u8; 64];
+    let read = tokio::time::timeout(Duration::from_secs(1), stalled.read(&mut buf))
+        .await
+        .expect("connection cut once the server stopped");
+    assert!(matches!(read, Ok(0) | Err(_)), "{read:?}");
+}
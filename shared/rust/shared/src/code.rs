@@ -0,0 +1,128 @@
+//! Short connection codes such as `7-guitar-orbit`, easy to read out over the
+//! phone. The number is a nameplate the server maps to a mailbox; the words
+//! never reach the server. Both ends run [`crate::pake::Spake2`] with the whole
+//! code to agree on the mailbox secret.
+
+use rand::Rng;
+
+/// Words after the nameplate. Each picks one of 256, so a wrong guess is
+/// caught with probability 1 - 2^-16.
+pub const CODE_WORDS: usize = 2;
+
+/// Longest nameplate, in digits.
+pub const MAX_NAMEPLATE_DIGITS: usize = 5;
+
+const WORDLIST: [&str; 256] = [
+    "acid", "acorn", "actor", "adult", "agent", "alarm", "album", "alley", "amber", "angle",
+    "ankle", "apple", "apron", "arena", "arrow", "atlas", "attic", "autumn", "badge", "bagel",
+    "baker", "bamboo", "banjo", "barn", "basil", "basket", "beach", "beacon", "beard", "beaver",
+    "bench", "berry", "bicycle", "bishop", "blanket", "blossom", "boat", "bonnet", "bottle",
+    "bridge", "broom", "bucket", "buffalo", "bugle", "butter", "cabin", "cactus", "camel",
+    "candle", "canoe", "canyon", "carpet", "carrot", "castle", "cattle", "cello", "chalk",
+    "cherry", "chess", "circle", "citrus", "clover", "cobra", "coffee", "comet", "copper", "coral",
+    "cotton", "cousin", "coyote", "crater", "cricket", "crystal", "cupboard", "dagger", "daisy",
+    "dancer", "denim", "desert", "dolphin", "donkey", "dragon", "drawer", "dune", "eagle", "easel",
+    "echo", "elbow", "ember", "engine", "falcon", "fennel", "ferry", "fiddle", "figure", "flannel",
+    "flute", "forest", "fossil", "fountain", "fox", "galaxy", "garden", "garlic", "gazelle",
+    "geyser", "giant", "ginger", "glacier", "goblet", "gopher", "granite", "grape", "guitar",
+    "hammer", "harbor", "harvest", "hazel", "helmet", "hermit", "hickory", "honey", "hornet",
+    "husky", "igloo", "island", "ivory", "jacket", "jaguar", "jasmine", "jelly", "jigsaw",
+    "jungle", "kayak", "kernel", "kettle", "kitten", "koala", "ladder", "lagoon", "lantern",
+    "lava", "lemon", "lentil", "lettuce", "lizard", "lobster", "locket", "lotus", "magnet",
+    "mango", "maple", "marble", "meadow", "melon", "meteor", "mirror", "mitten", "monkey",
+    "mosaic", "muffin", "mural", "napkin", "nectar", "needle", "nickel", "noodle", "nutmeg",
+    "oasis", "oatmeal", "ocean", "olive", "onion", "orbit", "orchid", "otter", "oyster", "paddle",
+    "panda", "parrot", "peanut", "pebble", "pelican", "pepper", "piano", "pigeon", "pillow",
+    "pilot", "pine", "planet", "plum", "pony", "poppy", "potato", "pretzel", "pumpkin", "puzzle",
+    "quartz", "quilt", "rabbit", "raccoon", "radish", "rainbow", "raven", "rhubarb", "ribbon",
+    "river", "robin", "rocket", "saddle", "salmon", "sandal", "scarf", "shadow", "shovel",
+    "silver", "sketch", "sled", "sparrow", "spider", "spruce", "squid", "statue", "stove",
+    "sunset", "swan", "teapot", "temple", "thistle", "thunder", "tiger", "toast", "tomato",
+    "tractor", "trumpet", "tulip", "tundra", "tunnel", "turtle", "umbrella", "unicorn", "valley",
+    "velvet", "violin", "volcano", "wagon", "walnut", "walrus", "whale", "whistle", "willow",
+    "window", "wizard", "yogurt", "zebra", "zipper",
+];
+
+/// Generate a random nameplate below `limit`, which is capped to
+/// [`MAX_NAMEPLATE_DIGITS`] digits.
+pub fn gen_nameplate(limit: u32) -> String {
+    let limit = limit.clamp(2, 10u32.pow(MAX_NAMEPLATE_DIGITS as u32));
+    rand::rng().random_range(1..limit).to_string()
+}
+
+/// Generate a code for a nameplate issued by the server.
+pub fn gen_code(nameplate: &str) -> anyhow::Result<String> {
+    let nameplate = normalize_nameplate(nameplate)
+        .ok_or_else(|| anyhow::anyhow!("Invalid nameplate: {}", nameplate))?;
+    let mut rng = rand::rng();
+    let mut parts = vec![nameplate];
+    parts
+        .extend((0..CODE_WORDS).map(|_| WORDLIST[rng.random_range(0..WORDLIST.len())].to_string()));
+    Ok(parts.join("-"))
+}
+
+/// Canonical form of a nameplate: decimal digits without a leading zero.
+pub fn normalize_nameplate(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    let well_formed = (1..=MAX_NAMEPLATE_DIGITS).contains(&raw.len())
+        && raw.bytes().all(|b| b.is_ascii_digit())
+        && !raw.starts_with('0');
+    well_formed.then(|| raw.to_string())
+}
+
+/// Canonical form of a code as typed by a person: lowercase, with the parts
+/// separated by single dashes whether they were typed with dashes or spaces.
+/// Returns `None` unless it has a nameplate and known words.
+pub fn normalize_code(raw: &str) -> Option<String> {
+    let raw = raw.to_lowercase();
+    let parts: Vec<&str> = raw
+        .split(|c: char| c == '-' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    let (nameplate, words) = parts.split_first()?;
+    if words.len() != CODE_WORDS || !words.iter().all(|word| WORDLIST.contains(word)) {
+        return None;
+    }
+    let mut canonical = vec![normalize_nameplate(nameplate)?];
+    canonical.extend(words.iter().map(|word| word.to_string()));
+    Some(canonical.join("-"))
+}
+
+/// Nameplate of a code, the only part the server is told.
+pub fn code_nameplate(raw: &str) -> Option<String> {
+    let code = normalize_code(raw)?;
+    code.split('-').next().map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_codes_normalize_to_themselves() {
+        let nameplate = gen_nameplate(100);
+        assert!((1..100).contains(&nameplate.parse::<u32>().expect("number")));
+        let code = gen_code(&nameplate).expect("code");
+        assert_eq!(normalize_code(&code).as_deref(), Some(code.as_str()));
+        assert_eq!(code_nameplate(&code), Some(nameplate));
+        assert!(gen_code("007").is_err());
+    }
+
+    #[test]
+    fn typed_codes_are_normalized() {
+        assert_eq!(
+            normalize_code(" 7 Guitar  orbit ").as_deref(),
+            Some("7-guitar-orbit")
+        );
+        assert_eq!(
+            normalize_code("7-guitar-orbit").as_deref(),
+            Some("7-guitar-orbit")
+        );
+        assert_eq!(normalize_code("7-guitar"), None);
+        assert_eq!(normalize_code("7-guitar-orbit-orbit"), None);
+        assert_eq!(normalize_code("7-guitar-xylophone"), None);
+        assert_eq!(normalize_code("07-guitar-orbit"), None);
+        assert_eq!(normalize_nameplate("123456"), None);
+        assert_eq!(normalize_nameplate(" 42 ").as_deref(), Some("42"));
+    }
+}
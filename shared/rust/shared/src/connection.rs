@@ -3,12 +3,16 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
 };
 use base64::Engine as _;
+use curve25519_dalek::montgomery::MontgomeryPoint;
 use hmac::{Hmac, Mac};
 use rand::RngCore;
-use sha2::Sha256;
+use sha2::{Digest, Sha256};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Prefix of the hash the link key exchange secret is derived with.
+const EXCHANGE_CONTEXT: &[u8] = b"signaling/x25519/v1\0";
+
 /// Derived keys from a shared secret for end-to-end encrypted signaling
 #[derive(Debug, Clone)]
 pub struct DerivedKeys {
@@ -41,13 +45,88 @@ pub fn derive_keys(secret: &[u8; 32]) -> anyhow::Result<DerivedKeys> {
     Ok(DerivedKeys { k_sig, k_mac, sas })
 }
 
-/// Generate a high-entropy non-guessable rendezvous ID
-/// Uses 32 bytes of random data, encoded in URL-safe base64
-pub fn gen_rendezvous_id() -> String {
-    let mut bytes = [0u8; 32];
-    let mut rng = rand::rng();
-    rng.fill_bytes(&mut bytes);
-    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+/// Ephemeral X25519 key for one connection link. The link carries only the
+/// initiator's public key and the responder answers with its own through the
+/// mailbox, so a leaked link does not reveal the session keys. Whoever gets
+/// the link first could still join in the peer's place, which is why both
+/// users compare [`format_sas`] of the result before going on.
+pub struct ExchangeKey {
+    secret: [u8; 32],
+}
+
+impl ExchangeKey {
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        rand::rng().fill_bytes(&mut secret);
+        Self { secret }
+    }
+
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Self { secret: *secret }
+    }
+
+    /// Raw secret, for keeping the key until the peer answers.
+    pub fn secret(&self) -> [u8; 32] {
+        self.secret
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        MontgomeryPoint::mul_base_clamped(self.secret).to_bytes()
+    }
+
+    /// Public key as carried in links and mailbox messages, URL-safe base64.
+    pub fn public_key_b64(&self) -> String {
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(self.public_key())
+    }
+
+    /// Shared secret for `derive_keys`, on the side that created the link.
+    pub fn agree_as_initiator(&self, responder_public: &[u8; 32]) -> anyhow::Result<[u8; 32]> {
+        self.agree(responder_public, &self.public_key(), responder_public)
+    }
+
+    /// Shared secret for `derive_keys`, on the side that joined with the link.
+    pub fn agree_as_responder(&self, initiator_public: &[u8; 32]) -> anyhow::Result<[u8; 32]> {
+        self.agree(initiator_public, initiator_public, &self.public_key())
+    }
+
+    fn agree(
+        &self,
+        peer_public: &[u8; 32],
+        initiator_public: &[u8; 32],
+        responder_public: &[u8; 32],
+    ) -> anyhow::Result<[u8; 32]> {
+        let shared = MontgomeryPoint(*peer_public)
+            .mul_clamped(self.secret)
+            .to_bytes();
+        // Low-order public keys force an all-zero result
+        if constant_time_eq(&shared, &[0u8; 32]) {
+            anyhow::bail!("Invalid peer public key");
+        }
+        // Both public keys go into the secret, so the SAS also covers them
+        Ok(Sha256::new()
+            .chain_update(EXCHANGE_CONTEXT)
+            .chain_update(initiator_public)
+            .chain_update(responder_public)
+            .chain_update(shared)
+            .finalize()
+            .into())
+    }
+}
+
+/// Decode a public key as produced by [`ExchangeKey::public_key_b64`].
+pub fn decode_public_key(public_key_b64: &str) -> anyhow::Result<[u8; 32]> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(public_key_b64)
+        .map_err(|e| anyhow::anyhow!("Invalid public key: {}", e))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid public key length"))
+}
+
+/// Six digits derived from [`DerivedKeys::sas`], grouped like `123 456`, for
+/// users to read to each other. Matching digits mean nobody sits in between.
+pub fn format_sas(sas: &[u8; 32]) -> String {
+    let value = u32::from_be_bytes([sas[0], sas[1], sas[2], sas[3]]) % 1_000_000;
+    format!("{:03} {:03}", value / 1000, value % 1000)
 }
 
 /// Generate an opaque mailbox ID for blind storage
@@ -58,6 +137,12 @@ pub fn gen_mailbox_id() -> String {
     hex::encode(bytes)
 }
 
+/// Compares two secrets without short-circuiting on the first mismatching byte.
+/// Only the length can leak, so compare digests or fixed-length tokens.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Encrypts a payload using AES-256-GCM.
 /// Returns base64-encoded string containing [nonce + ciphertext + tag].
 pub fn encrypt_payload(key: &[u8; 32], plaintext: &[u8]) -> anyhow::Result<String> {
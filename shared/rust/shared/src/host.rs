@@ -0,0 +1,106 @@
+//! Unattended access: a host keeps a persistent, human-dialable ID and an
+//! access password, and viewers connect with both instead of a one-shot link.
+
+use crate::pake::Spake2;
+use rand::Rng;
+
+/// Number of digits in a host ID.
+pub const HOST_ID_DIGITS: usize = 9;
+
+/// Prefix of the SPAKE2 password of a host session. Short codes never contain
+/// a NUL, so the two kinds of exchange cannot be confused.
+const HOST_PAKE_CONTEXT: &str = "signaling/host/v1\0";
+
+/// Generate a random host ID: nine digits, never starting with zero so it
+/// reads the same when typed into a number field.
+pub fn gen_host_id() -> String {
+    let mut rng = rand::rng();
+    rng.random_range(100_000_000u32..1_000_000_000).to_string()
+}
+
+/// Canonical form of a host ID as typed by a person: spaces and dashes are
+/// dropped. Returns `None` unless what remains is a well-formed ID.
+pub fn normalize_host_id(raw: &str) -> Option<String> {
+    let digits: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    let well_formed = digits.len() == HOST_ID_DIGITS
+        && digits.bytes().all(|b| b.is_ascii_digit())
+        && !digits.starts_with('0');
+    well_formed.then_some(digits)
+}
+
+/// Host ID grouped in threes for display, e.g. `123 456 789`.
+pub fn format_host_id(host_id: &str) -> String {
+    host_id
+        .as_bytes()
+        .chunks(3)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One side of the SPAKE2 exchange that opens a host session; host and viewer
+/// both run it, and [`Spake2::finish`] yields the secret for
+/// `connection::derive_keys`.
+///
+/// The password only authenticates the exchange, so each session gets fresh
+/// keys and recorded mailbox traffic is no help in guessing the password.
+pub fn host_pake(host_id: &str, password: &str) -> Spake2 {
+    Spake2::start(&format!("{HOST_PAKE_CONTEXT}{host_id}\0{password}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_ids_are_normalized_and_grouped() {
+        let host_id = gen_host_id();
+        assert_eq!(
+            normalize_host_id(&host_id).as_deref(),
+            Some(host_id.as_str())
+        );
+        assert_eq!(
+            normalize_host_id(" 123-456 789 ").as_deref(),
+            Some("123456789")
+        );
+        assert_eq!(normalize_host_id("012345678"), None);
+        assert_eq!(normalize_host_id("12345678"), None);
+        assert_eq!(format_host_id("123456789"), "123 456 789");
+    }
+
+    /// The secrets both ends of an exchange end up with.
+    fn exchange(host: Spake2, viewer: Spake2) -> ([u8; 32], [u8; 32]) {
+        let (host_message, viewer_message) = (host.message().to_vec(), viewer.message().to_vec());
+        (
+            host.finish(&viewer_message).expect("host"),
+            viewer.finish(&host_message).expect("viewer"),
+        )
+    }
+
+    #[test]
+    fn sessions_agree_only_with_the_right_password() {
+        let (host, viewer) = exchange(
+            host_pake("123456789", "hunter22"),
+            host_pake("123456789", "hunter22"),
+        );
+        assert_eq!(host, viewer);
+
+        // Every exchange gives the session different keys
+        let (again, _) = exchange(
+            host_pake("123456789", "hunter22"),
+            host_pake("123456789", "hunter22"),
+        );
+        assert_ne!(again, host);
+
+        for (host_id, password) in [("123456789", "hunter23"), ("123456780", "hunter22")] {
+            let (host, guess) = exchange(
+                host_pake("123456789", "hunter22"),
+                host_pake(host_id, password),
+            );
+            assert_ne!(host, guess);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Device identity: a long-lived Ed25519 key pair that a device registers with
+//! the signaling server. The server never sees the secret key; it learns the
+//! public key, derives a stable device ID from it, and checks a signature over
+//! a single-use challenge nonce.
+
+use crate::models::ClientId;
+use base64::Engine as _;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::{Builder, Uuid};
+
+/// Prefix of every registration signature, so a signature made for anything
+/// else can never be replayed as a registration.
+const REGISTRATION_CONTEXT: &[u8] = b"signaling/register/v1\0";
+
+/// Prefix of the hash a device ID is cut from.
+const DEVICE_ID_CONTEXT: &[u8] = b"signaling/device-id/v1\0";
+
+/// An Ed25519 signing key identifying one device.
+pub struct DeviceIdentity {
+    signing_key: SigningKey,
+}
+
+impl DeviceIdentity {
+    /// A fresh random identity. Persist [`Self::secret`] to keep the device ID.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        rand::rng().fill_bytes(&mut secret);
+        Self::from_secret(&secret)
+    }
+
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Self {
+            signing_key: SigningKey::from_bytes(secret),
+        }
+    }
+
+    /// The 32-byte seed the identity can be restored from.
+    pub fn secret(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    /// Public key as sent in `RegisterRequest::public_key_b64`.
+    pub fn public_key_b64(&self) -> String {
+        encode_b64(self.signing_key.verifying_key().as_bytes())
+    }
+
+    /// The ID the server will assign to this device.
+    pub fn device_id(&self) -> ClientId {
+        device_id(&self.signing_key.verifying_key())
+    }
+
+    /// Signature for `RegisterRequest::signature_b64` over a server nonce.
+    pub fn sign_registration(&self, nonce: &str) -> String {
+        let message = registration_message(nonce, &self.signing_key.verifying_key());
+        encode_b64(&self.signing_key.sign(&message).to_bytes())
+    }
+}
+
+/// Stable ID of the device holding `public_key`: the first 128 bits of a
+/// SHA-256 over the key, laid out as a version 8 UUID.
+pub fn device_id(public_key: &VerifyingKey) -> ClientId {
+    let digest = Sha256::new()
+        .chain_update(DEVICE_ID_CONTEXT)
+        .chain_update(public_key.as_bytes())
+        .finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Builder::from_custom_bytes(bytes).into_uuid()
+}
+
+/// Checks that `signature_b64` signs `nonce` with the key in `public_key_b64`
+/// and returns the device ID that key maps to.
+pub fn verify_registration(
+    public_key_b64: &str,
+    nonce: &str,
+    signature_b64: &str,
+) -> anyhow::Result<Uuid> {
+    let public_key: [u8; 32] = decode_b64(public_key_b64)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("public key must be 32 bytes"))?;
+    let public_key = VerifyingKey::from_bytes(&public_key)?;
+    let signature: [u8; 64] = decode_b64(signature_b64)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("signature must be 64 bytes"))?;
+    public_key.verify_strict(
+        &registration_message(nonce, &public_key),
+        &Signature::from_bytes(&signature),
+    )?;
+    Ok(device_id(&public_key))
+}
+
+/// The signed bytes bind the nonce to the key, so a captured signature is
+/// useless with any other key or challenge.
+fn registration_message(nonce: &str, public_key: &VerifyingKey) -> Vec<u8> {
+    let mut message = Vec::with_capacity(REGISTRATION_CONTEXT.len() + nonce.len() + 33);
+    message.extend_from_slice(REGISTRATION_CONTEXT);
+    message.extend_from_slice(nonce.as_bytes());
+    message.push(0);
+    message.extend_from_slice(public_key.as_bytes());
+    message
+}
+
+fn encode_b64(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn decode_b64(encoded: &str) -> anyhow::Result<Vec<u8>> {
+    Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(encoded)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registration_signatures_verify_to_the_device_id() {
+        let identity = DeviceIdentity::generate();
+        let signature = identity.sign_registration("nonce");
+        let device = verify_registration(&identity.public_key_b64(), "nonce", &signature)
+            .expect("valid signature");
+        assert_eq!(device, identity.device_id());
+        assert_eq!(
+            DeviceIdentity::from_secret(&identity.secret()).device_id(),
+            device
+        );
+    }
+
+    #[test]
+    fn signatures_are_bound_to_nonce_and_key() {
+        let identity = DeviceIdentity::generate();
+        let other = DeviceIdentity::generate();
+        let signature = identity.sign_registration("nonce");
+        assert!(verify_registration(&identity.public_key_b64(), "other", &signature).is_err());
+        assert!(verify_registration(&other.public_key_b64(), "nonce", &signature).is_err());
+        assert!(verify_registration(&identity.public_key_b64(), "nonce", "c2hvcnQ").is_err());
+        assert!(verify_registration("not base64!", "nonce", &signature).is_err());
+    }
+}
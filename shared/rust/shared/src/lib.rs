@@ -1,2 +1,7 @@
+pub mod code;
 pub mod connection;
+pub mod host;
+pub mod identity;
 pub mod models;
+pub mod pake;
+pub mod token;
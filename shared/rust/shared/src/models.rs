@@ -4,17 +4,38 @@ use uuid::Uuid;
 
 pub type ClientId = Uuid;
 
+/// Single-use nonce a device signs to prove it holds its identity key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterChallengeResponse {
+    pub nonce: String,
+    pub expires_at_epoch_ms: u128,
+}
+
+/// See `identity::DeviceIdentity` for producing the key and signature.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegisterRequest {
     pub device_label: String,
+    /// Ed25519 public key, URL-safe base64 without padding.
+    pub public_key_b64: String,
+    /// Nonce from `/register/challenge`.
+    pub nonce: String,
+    /// Signature over the nonce, URL-safe base64 without padding.
+    pub signature_b64: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RegisterResponse {
+    /// Derived from the public key, so it is the same on every registration.
     pub client_id: ClientId,
     pub session_token: String,
+    /// After this the session token is rejected and the device must register again.
+    pub session_expires_at_epoch_ms: u128,
     pub heartbeat_interval_secs: u64,
     pub display_name: String,
+    /// STUN and TURN servers for this client; TURN entries carry credentials
+    /// bound to `client_id`.
+    #[serde(default)]
+    pub ice_servers: Vec<IceServerDto>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -53,10 +74,46 @@ pub struct SignalFetchResponse {
     pub messages: Vec<SignalEnvelope>,
 }
 
+/// One entry of a WebRTC `iceServers` list, in the shape `RTCPeerConnection` expects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IceServerDto {
+    pub urls: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+}
+
+impl IceServerDto {
+    pub fn without_credentials(urls: Vec<String>) -> Self {
+        Self {
+            urls,
+            username: None,
+            credential: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceServersRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceServersResponse {
+    pub ice_servers: Vec<IceServerDto>,
+    /// When the TURN credentials stop working; `None` if no TURN server is configured.
+    pub expires_at_epoch_ms: Option<u128>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalingClientConfigDto {
     pub base_url: String,
     pub heartbeat_interval_secs: u64,
+    /// Servers usable without credentials; TURN entries come with `/register`.
+    #[serde(default)]
+    pub ice_servers: Vec<IceServerDto>,
 }
 
 impl SignalingClientConfigDto {
@@ -64,8 +121,14 @@ impl SignalingClientConfigDto {
         Self {
             base_url: base_url.into(),
             heartbeat_interval_secs: heartbeat_interval.as_secs(),
+            ice_servers: Vec::new(),
         }
     }
+
+    pub fn with_ice_servers(mut self, ice_servers: Vec<IceServerDto>) -> Self {
+        self.ice_servers = ice_servers;
+        self
+    }
 }
 // ---------- Connection Link Models (Blind Rendezvous) ----------
 
@@ -78,7 +141,8 @@ pub struct ConnectionInitRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionInitResponse {
-    pub mailbox_id: String, // opaque ID for initiator
+    pub mailbox_id: String,    // opaque ID for initiator
+    pub mailbox_token: String, // secret required for every operation on the mailbox
     pub expires_at_epoch_ms: u128,
 }
 
@@ -89,19 +153,63 @@ pub struct ConnectionJoinRequest {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionJoinResponse {
-    pub mailbox_id: String, // opaque ID for responder
+    pub mailbox_id: String,    // opaque ID for responder
+    pub mailbox_token: String, // secret required for every operation on the mailbox
+    pub expires_at_epoch_ms: u128,
+}
+
+/// Opens a mailbox reachable by a short nameplate instead of a link token; see
+/// `code::gen_code` for turning the nameplate into a code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameplateOpenRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameplateOpenResponse {
+    pub nameplate: String,     // a few digits, the first part of the code
+    pub mailbox_id: String,    // opaque ID for initiator
+    pub mailbox_token: String, // secret required for every operation on the mailbox
     pub expires_at_epoch_ms: u128,
 }
 
+/// Joins the mailbox behind a nameplate; answered with a
+/// `ConnectionJoinResponse`. Each nameplate can be joined once. Nameplates are
+/// short enough to guess, so unlike a link join this needs a registered device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameplateJoinRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+    pub nameplate: String, // only the number, never the words of the code
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MailboxSendRequest {
     pub mailbox_id: String,
+    pub mailbox_token: String,
     pub ciphertext_b64: String, // opaque encrypted blob from client
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailboxRecvRequest {
+    pub mailbox_id: String,
+    pub mailbox_token: String,
+    #[serde(default)]
+    pub since_sequence: u64, // only messages with a higher sequence are returned
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailboxAckRequest {
+    pub mailbox_id: String,
+    pub mailbox_token: String,
+    pub up_to_sequence: u64, // messages up to and including this sequence are trimmed
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionCloseRequest {
     pub mailbox_id: String,
+    pub mailbox_token: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,5 +223,137 @@ pub struct MailboxMessage {
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MailboxRecvResponse {
     pub messages: Vec<MailboxMessage>,
-    pub last_sequence: u64,
+    pub last_sequence: u64, // cursor to pass as since_sequence on the next recv
+}
+
+// ---------- Unattended Access (Host ID + Password) ----------
+
+/// Sets the access password of the calling device's host ID, assigning the ID
+/// on first use. Later calls keep the ID and only change the password.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostRegisterRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostRegisterResponse {
+    pub host_id: String, // nine digits, see `host::format_host_id` for display
+}
+
+/// Opens the mailbox the next viewer joins; answered with a
+/// `ConnectionInitResponse`. Call again once it is joined or expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostListenRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostUnregisterRequest {
+    pub client_id: ClientId,
+    pub session_token: String,
+}
+
+/// Joins the mailbox a host is listening on; answered with a
+/// `ConnectionJoinResponse`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostConnectRequest {
+    pub host_id: String, // spaces and dashes are ignored
+    pub password: String,
+}
+
+// ---------- Mailbox WebSocket Frames ----------
+//
+// Pushed mailbox messages are sent as plain `MailboxMessage` JSON; the frames below
+// are the client's commands and the server's replies to them.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MailboxWsClientFrame {
+    Send { ciphertext_b64: String },
+    Ack { up_to_sequence: u64 },
+    Close,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MailboxWsServerFrame {
+    Sent,
+    Acked {
+        up_to_sequence: u64,
+    },
+    Closed,
+    Error {
+        #[serde(default)]
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+// ---------- Errors ----------
+
+/// Why a request failed, stable across releases so clients can branch on it
+/// instead of on the status code or message. Codes the client does not know
+/// yet decode as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The body or path could not be parsed.
+    InvalidRequest,
+    ClientNotFound,
+    InvalidSessionToken,
+    MailboxNotFound,
+    InvalidMailboxToken,
+    /// The mailbox pair outlived its TTL, or the session token its lifetime.
+    SessionExpired,
+    /// The registration nonce is unknown, expired or already used.
+    InvalidChallenge,
+    /// The registration signature does not verify against the public key.
+    InvalidSignature,
+    /// The invitation (link token or nameplate) was never issued, was revoked,
+    /// or expired unused.
+    InvalidRendezvousToken,
+    /// The invitation was already used by another peer.
+    SessionAlreadyPaired,
+    NoPeerConnected,
+    MessageTooLarge,
+    /// Too many unacknowledged messages; ack before sending more.
+    MailboxFull,
+    TooManySubscribers,
+    /// The calling device has not registered a host ID.
+    HostNotFound,
+    /// Unknown host ID or wrong password; the two are not told apart.
+    InvalidHostCredentials,
+    /// Too many wrong passwords for this host ID; see `retry_after_ms`.
+    HostLocked,
+    /// The password is right but the host is not listening for viewers.
+    HostOffline,
+    RateLimited,
+    /// This instance is draining; retry, possibly against another one.
+    ShuttingDown,
+    Unauthorized,
+    /// Every nameplate is in use; retry shortly or fall back to a link.
+    NameplatesExhausted,
+    /// Backing storage failed; usually transient.
+    StorageUnavailable,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+/// The JSON body of every non-2xx response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: ErrorCode,
+    /// Human-readable detail; not meant for branching.
+    pub message: String,
+    /// Wait at least this long before retrying; absent when retrying as-is will
+    /// not help.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+    /// Matches the `X-Request-Id` response header and the server's logs.
+    #[serde(default)]
+    pub request_id: String,
 }
@@ -0,0 +1,106 @@
+//! SPAKE2 over Ed25519, in its symmetric form: both ends play the same role,
+//! so neither has to know whether it created the code or typed it in.
+//!
+//! The exchange itself is the `spake2` crate's; this only fixes the identity
+//! both ends use. Seeing both messages tells an eavesdropper (the server
+//! included) nothing it could test guesses against offline; a peer with the
+//! wrong code ends up with a different secret, and only learns that its single
+//! guess was wrong.
+
+use spake2::{Ed25519Group, Identity, Password};
+
+/// Length of a SPAKE2 message: a side marker and a compressed Edwards point.
+pub const MESSAGE_LEN: usize = 33;
+
+/// Symmetric identity of every exchange, so messages from other protocols
+/// using SPAKE2 with the same password never combine into the same secret.
+const IDENTITY: &[u8] = b"signaling/spake2/v1";
+
+/// One side of a SPAKE2 exchange. The random scalar behind its message never
+/// leaves this value, and [`Spake2::finish`] consumes it, so no exchange can be
+/// resumed or finished twice.
+pub struct Spake2 {
+    state: spake2::Spake2<Ed25519Group>,
+    message: Vec<u8>,
+}
+
+impl Spake2 {
+    /// Start an exchange with a fresh random secret.
+    pub fn start(code: &str) -> Self {
+        let (state, message) = spake2::Spake2::<Ed25519Group>::start_symmetric(
+            &Password::new(code),
+            &Identity::new(IDENTITY),
+        );
+        Self { state, message }
+    }
+
+    /// Message to send to the peer.
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// Combine the peer's message into the shared secret for
+    /// `connection::derive_keys`. Both ends get the same secret only if they
+    /// used the same code; a mismatch shows up as messages that fail to decrypt.
+    pub fn finish(self, peer_message: &[u8]) -> anyhow::Result<[u8; 32]> {
+        let secret = self
+            .state
+            .finish(peer_message)
+            .map_err(|err| anyhow::anyhow!("Invalid SPAKE2 message: {err}"))?;
+        secret
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid SPAKE2 secret length"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_codes_agree() {
+        let a = Spake2::start("7-guitar-orbit");
+        let b = Spake2::start("7-guitar-orbit");
+        assert_eq!(a.message().len(), MESSAGE_LEN);
+        let (a_message, b_message) = (a.message().to_vec(), b.message().to_vec());
+        assert_eq!(
+            a.finish(&b_message).expect("a"),
+            b.finish(&a_message).expect("b")
+        );
+    }
+
+    #[test]
+    fn different_codes_disagree() {
+        let a = Spake2::start("7-guitar-orbit");
+        let b = Spake2::start("7-guitar-otter");
+        let (a_message, b_message) = (a.message().to_vec(), b.message().to_vec());
+        assert_ne!(
+            a.finish(&b_message).expect("a"),
+            b.finish(&a_message).expect("b")
+        );
+    }
+
+    #[test]
+    fn speaks_plain_symmetric_spake2() {
+        // The wire format is the crate's, with nothing added on top
+        let ours = Spake2::start("7-guitar-orbit");
+        let (theirs, their_message) = spake2::Spake2::<Ed25519Group>::start_symmetric(
+            &Password::new("7-guitar-orbit"),
+            &Identity::new(b"signaling/spake2/v1"),
+        );
+        let our_message = ours.message().to_vec();
+        assert_eq!(
+            ours.finish(&their_message).expect("ours").to_vec(),
+            theirs.finish(&our_message).expect("theirs")
+        );
+    }
+
+    #[test]
+    fn bad_messages_are_rejected() {
+        assert!(Spake2::start("7-guitar-orbit").finish(&[0x53; 16]).is_err());
+        // Asymmetric side markers are not accepted
+        let mut wrong_side = Spake2::start("7-guitar-orbit").message().to_vec();
+        wrong_side[0] = b'A';
+        assert!(Spake2::start("7-guitar-orbit").finish(&wrong_side).is_err());
+    }
+}
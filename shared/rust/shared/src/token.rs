@@ -0,0 +1,44 @@
+//! Bearer tokens: session tokens, mailbox tokens, rendezvous IDs and challenge
+//! nonces are all full-entropy random strings, and the server keeps only their
+//! hash.
+
+use base64::Engine as _;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+/// A fresh token: 32 random bytes in URL-safe base64 without padding.
+pub fn gen_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// What the server stores in place of a token (hex-encoded SHA-256). Tokens
+/// from [`gen_token`] cannot be guessed, so a fast hash is enough.
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_are_fresh_url_safe_and_full_length() {
+        let token = gen_token();
+        assert_eq!(token.len(), 43);
+        assert!(token
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        assert_ne!(gen_token(), token);
+    }
+
+    #[test]
+    fn hashes_are_stable_hex_sha256() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_ne!(hash_token("abd"), hash_token("abc"));
+    }
+}